@@ -0,0 +1,62 @@
+//! Spoken announcements, so that applications which draw everything
+//! onto a canvas can still tell users what's happening.
+//!
+//! Announcements are made with text-to-speech. On Windows, they're handed
+//! to the running screen reader when there is one, and spoken with SAPI
+//! otherwise. Elsewhere, they're spoken by the platform's speech service
+//! (Speech Dispatcher on Linux, AVFoundation on macOS), alongside any
+//! screen reader rather than through it. They're only made when the
+//! `accessibility` feature is enabled, and ignored otherwise.
+//!
+//! The window's contents aren't exposed to the platform's accessibility
+//! tree, so screen readers can't inspect them. A description of the
+//! application can be given with `UIBlueprint::accessible_description()`,
+//! which is announced along with the title when the window opens.
+
+/// How urgently an announcement should be made, following the
+/// politeness levels of ARIA live regions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Politeness {
+    /// Wait for anything already being spoken to finish.
+    Polite,
+    /// Interrupt anything already being spoken.
+    Assertive,
+}
+
+/// Speak the given text, returning whether the announcement could
+/// be made. Announcements can be made from any thread.
+#[allow(unused_variables)]
+pub fn announce(text: &str, politeness: Politeness) -> bool {
+    #[cfg(feature = "accessibility")]
+    {
+        // Keeping the speech service open between announcements,
+        // since closing it can cut off the one being spoken
+        thread_local! {
+            static SPEECH: std::cell::RefCell<Option<tts::Tts>> = std::cell::RefCell::new(None);
+        }
+
+        SPEECH.with(|speech| {
+            let mut speech = speech.borrow_mut();
+            if speech.is_none() {
+                *speech = tts::Tts::default()
+                    .map_err(|error| log_error!("unable to make announcements: {}", error))
+                    .ok();
+            }
+
+            match speech.as_mut() {
+                Some(speech) => speech.speak(text, politeness == Politeness::Assertive).is_ok(),
+                None => false,
+            }
+        })
+    }
+
+    #[cfg(not(feature = "accessibility"))]
+    false
+}
+
+#[test]
+#[cfg(not(feature = "accessibility"))]
+fn _announce_without_feature() {
+    assert!(!announce("Level complete", Politeness::Polite));
+    assert!(!announce("Game over", Politeness::Assertive));
+}
@@ -0,0 +1,259 @@
+//! Playing animations made of sprite sheet frames, such as a character's
+//! walk cycle, and switching between them as the character's state changes.
+//!
+//! Each `Animation` is a named list of areas of a sprite sheet, each shown
+//! for its own duration. An `AnimationController` plays one animation at a
+//! time, moved forward every frame like a `Tween`, and reports the area of
+//! the sheet to draw. Animations can loop, or hand over to another animation
+//! when they finish, such as returning to "idle" after "attack".
+
+use crate::Rect;
+use std::time::Duration;
+
+/// A named sequence of frames from a sprite sheet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    pub name: String,
+    /// Areas of the sprite sheet, and how long each is shown.
+    pub frames: Vec<(Rect, Duration)>,
+    pub looping: bool,
+    /// The animation to play once this one finishes.
+    /// Ignored while `looping` is set.
+    pub next: Option<String>,
+}
+
+impl Animation {
+    /// An animation that shows each area of the sheet for the same
+    /// duration, and stops on its last frame.
+    pub fn new(name: &str, frames: Vec<Rect>, frame_duration: Duration) -> Animation {
+        Animation {
+            name: name.to_string(),
+            frames: frames.into_iter().map(|frame| (frame, frame_duration)).collect(),
+            looping: false,
+            next: None,
+        }
+    }
+
+    /// An animation of the first `count` cells in one `row` of a sprite
+    /// sheet that's laid out as a grid of `cell_size` cells.
+    pub fn from_row(name: &str, cell_size: (u32, u32), row: u32, count: u32, frame_duration: Duration) -> Animation {
+        let (width, height) = cell_size;
+        let frames = (0..count).map(|column| Rect::new(column * width, row * height, width, height)).collect();
+        Animation::new(name, frames, frame_duration)
+    }
+
+    /// Start over from the first frame whenever the animation finishes.
+    pub fn looping(self, looping: bool) -> Animation {
+        Animation { looping, ..self }
+    }
+
+    /// Play the animation called `next` once this one finishes.
+    pub fn then(self, next: &str) -> Animation {
+        Animation { next: Some(next.to_string()), ..self }
+    }
+
+    /// How long it takes to play every frame once.
+    pub fn duration(&self) -> Duration {
+        self.frames.iter().map(|(_, duration)| *duration).sum()
+    }
+}
+
+/// Something that happened while an `AnimationController` advanced.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnimationEvent {
+    /// The animation with this name played its last frame to the end,
+    /// either stopping there or handing over to its `next` animation.
+    Finished(String),
+}
+
+/// Plays one of a set of animations at a time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationController {
+    animations: Vec<Animation>,
+    // The animation and frame being shown, and for how long it has been
+    current: usize,
+    frame: usize,
+    frame_elapsed: Duration,
+    finished: bool,
+    events: Vec<AnimationEvent>,
+}
+
+impl AnimationController {
+    /// Start playing the first of `animations`, which mustn't be empty.
+    pub fn new(animations: Vec<Animation>) -> AnimationController {
+        assert!(!animations.is_empty(), "an AnimationController needs at least one animation");
+
+        AnimationController {
+            animations,
+            current: 0,
+            frame: 0,
+            frame_elapsed: Duration::ZERO,
+            finished: false,
+            events: vec![],
+        }
+    }
+
+    /// Switch to the animation called `name`, from its first frame, unless
+    /// it's already playing. Returns false if there's no such animation.
+    pub fn play(&mut self, name: &str) -> bool {
+        match self.animations.iter().position(|animation| animation.name == name) {
+            Some(index) if index == self.current && !self.finished => true,
+            Some(index) => {
+                self.current = index;
+                self.restart();
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Start the current animation over from its first frame.
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.frame_elapsed = Duration::ZERO;
+        self.finished = false;
+    }
+
+    /// Move the animation forward in time, usually by the length of a
+    /// frame, carrying any time left over at the end of one animation
+    /// into the next.
+    pub fn advance(&mut self, elapsed: Duration) {
+        if self.finished {
+            return;
+        }
+        self.frame_elapsed += elapsed;
+
+        // Limiting how many animations can be passed through without any
+        // time passing, so that animations without any duration can't
+        // hand over to each other forever
+        let mut handovers = 0;
+
+        loop {
+            let animation = &self.animations[self.current];
+            let total = animation.duration();
+
+            // Skipping whole loops at once, so that long frames can't loop forever
+            if animation.looping && self.frame == 0 && !total.is_zero() && self.frame_elapsed >= total {
+                let loops = self.frame_elapsed.as_nanos() / total.as_nanos();
+                self.frame_elapsed -= Duration::from_nanos((total.as_nanos() * loops) as u64);
+            }
+
+            let frame_duration = match animation.frames.get(self.frame) {
+                Some((_, duration)) => *duration,
+                None => Duration::ZERO,
+            };
+            if self.frame_elapsed < frame_duration {
+                return;
+            }
+            self.frame_elapsed -= frame_duration;
+            if !frame_duration.is_zero() {
+                handovers = 0;
+            }
+
+            if self.frame + 1 < animation.frames.len() {
+                self.frame += 1;
+                continue;
+            }
+
+            if animation.looping {
+                self.frame = 0;
+                if total.is_zero() {
+                    self.frame_elapsed = Duration::ZERO;
+                    return;
+                }
+                continue;
+            }
+
+            self.events.push(AnimationEvent::Finished(animation.name.clone()));
+            let next = animation.next.as_ref()
+                .and_then(|next| self.animations.iter().position(|animation| &animation.name == next));
+
+            match next {
+                Some(next) if handovers < self.animations.len() => {
+                    handovers += 1;
+                    self.current = next;
+                    self.frame = 0;
+                },
+                _ => {
+                    self.finished = true;
+                    self.frame_elapsed = Duration::ZERO;
+                    return;
+                },
+            }
+        }
+    }
+
+    pub fn current(&self) -> &Animation {
+        &self.animations[self.current]
+    }
+
+    /// The index of the frame being shown, within the current animation.
+    pub fn frame_index(&self) -> usize {
+        self.frame
+    }
+
+    /// The area of the sprite sheet to draw.
+    pub fn frame(&self) -> Rect {
+        match self.current().frames.get(self.frame) {
+            Some((rect, _)) => *rect,
+            None => Rect::new(0, 0, 0, 0),
+        }
+    }
+
+    /// Whether the current animation has stopped on its last frame.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// The events since the last call, oldest first.
+    pub fn take_events(&mut self) -> Vec<AnimationEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+#[test]
+fn _animation_controller() {
+    let ms = Duration::from_millis;
+
+    let mut controller = AnimationController::new(vec![
+        Animation::from_row("idle", (16, 16), 0, 2, ms(100)).looping(true),
+        Animation::from_row("attack", (16, 16), 1, 3, ms(50)).then("idle"),
+        Animation::from_row("die", (16, 16), 2, 2, ms(50)),
+    ]);
+
+    // Looping, even when a single step spans several loops
+    controller.advance(ms(150));
+    assert_eq!(controller.frame(), Rect::new(16, 0, 16, 16));
+    controller.advance(ms(1000));
+    assert_eq!(controller.frame_index(), 1);
+    assert!(controller.play("idle"));
+    assert_eq!(controller.frame_index(), 1);
+
+    // Handing over to the next animation, with the leftover time
+    assert!(controller.play("attack"));
+    assert_eq!(controller.frame(), Rect::new(0, 16, 16, 16));
+    controller.advance(ms(260));
+    assert_eq!(controller.current().name, "idle");
+    assert_eq!(controller.frame_index(), 1);
+    assert_eq!(controller.take_events(), vec![AnimationEvent::Finished("attack".to_string())]);
+    assert!(controller.take_events().is_empty());
+
+    // Stopping on the last frame
+    controller.play("die");
+    controller.advance(ms(500));
+    assert!(controller.is_finished());
+    assert_eq!(controller.frame(), Rect::new(16, 32, 16, 16));
+    assert!(!controller.play("fly"));
+
+    // Handing over several times in one long step, such as after a hitch
+    let mut controller = AnimationController::new(vec![
+        Animation::from_row("a", (16, 16), 0, 1, ms(50)).then("b"),
+        Animation::from_row("b", (16, 16), 1, 1, ms(50)).then("a"),
+    ]);
+    controller.advance(ms(160));
+    assert!(!controller.is_finished());
+    assert_eq!(controller.current().name, "b");
+    assert_eq!(controller.take_events().len(), 3);
+    controller.advance(ms(40));
+    assert_eq!(controller.current().name, "a");
+}
@@ -0,0 +1,457 @@
+//! Loading images from disk or from embedded bytes, and caching them by name,
+//! and playing animated GIFs.
+
+use crate::{RgbaImage, RgbaImageRegion};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// The reasons that an asset can fail to load.
+#[derive(Debug)]
+pub enum AssetError {
+    /// The file couldn't be read.
+    Io(std::io::Error),
+    /// The file was read, but isn't a valid image.
+    Decode(String),
+}
+
+impl std::fmt::Display for AssetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AssetError::Io(error) => write!(f, "couldn't read asset: {}", error),
+            AssetError::Decode(reason) => write!(f, "couldn't decode asset: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+impl From<std::io::Error> for AssetError {
+    fn from(error: std::io::Error) -> AssetError {
+        AssetError::Io(error)
+    }
+}
+
+/// A cheap reference to an image owned by an `Assets` manager.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ImageHandle(usize);
+
+/// Loads images once, and hands out `ImageHandle`s that can be used
+/// to look them up again every frame.
+///
+/// Images can be read from files relative to a root directory, or
+/// embedded into the executable at compile time with `include_bytes!()`
+/// and passed to `load_image_bytes()`.
+///
+/// During development, calling `reload_changed()` every frame swaps in
+/// new versions of image files as soon as they're saved. It polls each
+/// file's modification time rather than subscribing to filesystem events,
+/// which keeps it dependency-free and is cheap for the handful of files a
+/// UI loads, but a crate like `notify` would scale better to large asset
+/// directories.
+pub struct Assets {
+    root: PathBuf,
+    images: Vec<RgbaImage>,
+    keys: HashMap<String, ImageHandle>,
+    // The file that each image was loaded from, and when it was last modified
+    sources: Vec<Option<(PathBuf, SystemTime)>>,
+}
+
+impl Assets {
+    /// Create an asset manager that reads files relative to `root`.
+    pub fn new(root: impl AsRef<Path>) -> Assets {
+        Assets {
+            root: root.as_ref().to_path_buf(),
+            images: vec![],
+            keys: HashMap::new(),
+            sources: vec![],
+        }
+    }
+
+    /// Load a PNG file relative to the root directory, using its path as
+    /// its key. Files that have already been loaded aren't read again.
+    pub fn load_image(&mut self, path: &str) -> Result<ImageHandle, AssetError> {
+        if let Some(handle) = self.keys.get(path) {
+            return Ok(*handle);
+        }
+
+        let full_path = self.root.join(path);
+        let modified = std::fs::metadata(&full_path)?.modified()?;
+        let image = decode_png(&std::fs::read(&full_path)?)?;
+
+        let handle = self.insert_image(path, image);
+        self.sources[handle.0] = Some((full_path, modified));
+        Ok(handle)
+    }
+
+    /// Decode a PNG that has already been read into memory, usually with
+    /// `include_bytes!()`, and store it under `key`. Keys that have already
+    /// been loaded aren't decoded again.
+    pub fn load_image_bytes(&mut self, key: &str, bytes: &[u8]) -> Result<ImageHandle, AssetError> {
+        if let Some(handle) = self.keys.get(key) {
+            return Ok(*handle);
+        }
+
+        let image = decode_png(bytes)?;
+        Ok(self.insert_image(key, image))
+    }
+
+    /// Store an image that was created in code under `key`,
+    /// replacing any image that was previously stored there.
+    pub fn insert_image(&mut self, key: &str, image: RgbaImage) -> ImageHandle {
+        if let Some(handle) = self.keys.get(key) {
+            self.images[handle.0] = image;
+            self.sources[handle.0] = None;
+            return *handle;
+        }
+
+        let handle = ImageHandle(self.images.len());
+        self.images.push(image);
+        self.sources.push(None);
+        self.keys.insert(key.to_string(), handle);
+        handle
+    }
+
+    /// Read image files again if they've been modified since they were
+    /// loaded, returning the handles of the images that changed. Files
+    /// that can't be decoded, perhaps because they're still being
+    /// written, keep their previous image and are retried next time.
+    pub fn reload_changed(&mut self) -> Vec<ImageHandle> {
+        let mut reloaded = vec![];
+
+        for (index, source) in self.sources.iter_mut().enumerate() {
+            let (path, last_modified) = match source {
+                Some(source) => source,
+                None => continue,
+            };
+
+            let modified = match std::fs::metadata(&*path).and_then(|m| m.modified()) {
+                Ok(modified) if modified != *last_modified => modified,
+                _ => continue,
+            };
+
+            let image = std::fs::read(&*path).map_err(AssetError::from).and_then(|b| decode_png(&b));
+            if let Ok(image) = image {
+                self.images[index] = image;
+                *last_modified = modified;
+                reloaded.push(ImageHandle(index));
+            }
+        }
+
+        reloaded
+    }
+
+    /// The handle of an image that has already been loaded.
+    pub fn handle(&self, key: &str) -> Option<ImageHandle> {
+        self.keys.get(key).copied()
+    }
+
+    /// The image that a handle refers to.
+    pub fn image(&self, handle: ImageHandle) -> &RgbaImage {
+        &self.images[handle.0]
+    }
+}
+
+/// Decode a PNG file of any color type or bit depth into an `RgbaImage`.
+pub fn decode_png(bytes: &[u8]) -> Result<RgbaImage, AssetError> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+
+    let decode_error = |error: png::DecodingError| AssetError::Decode(error.to_string());
+    let mut reader = decoder.read_info().map_err(decode_error)?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(decode_error)?;
+
+    // Converting every color type to RGBA
+    let mut image = RgbaImage::new(info.width, info.height);
+    let pixels = buffer[..info.buffer_size()].chunks_exact(info.color_type.samples());
+
+    for (pixel, samples) in image.bytes.chunks_exact_mut(4).zip(pixels) {
+        let rgba = match *samples {
+            [gray] => [gray, gray, gray, 255],
+            [gray, alpha] => [gray, gray, gray, alpha],
+            [r, g, b] => [r, g, b, 255],
+            [r, g, b, a] => [r, g, b, a],
+            _ => return Err(AssetError::Decode("unsupported color type".to_string())),
+        };
+        pixel.copy_from_slice(&rgba);
+    }
+
+    Ok(image)
+}
+
+/// An image made of frames that are shown one after another,
+/// such as an animated GIF. It always has at least one frame,
+/// and a delay for each frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimatedImage {
+    frames: Vec<RgbaImage>,
+    delays: Vec<Duration>,
+    repeat: Option<u32>,
+}
+
+impl AnimatedImage {
+    /// Combine frames with how long each one is shown, and how many more
+    /// times the animation plays after playing once, or `None` to play it
+    /// forever. Returns `None` if there are no frames, or if there isn't
+    /// exactly one delay per frame.
+    pub fn new(frames: Vec<RgbaImage>, delays: Vec<Duration>, repeat: Option<u32>) -> Option<AnimatedImage> {
+        if frames.is_empty() || frames.len() != delays.len() {
+            return None;
+        }
+        Some(AnimatedImage { frames, delays, repeat })
+    }
+
+    pub fn frames(&self) -> &[RgbaImage] {
+        &self.frames
+    }
+
+    /// How long each frame is shown.
+    pub fn delays(&self) -> &[Duration] {
+        &self.delays
+    }
+
+    /// How many more times the animation plays after playing once,
+    /// or `None` if it plays forever.
+    pub fn repeat(&self) -> Option<u32> {
+        self.repeat
+    }
+
+    /// How long it takes to play the animation once.
+    pub fn duration(&self) -> Duration {
+        self.delays.iter().sum()
+    }
+
+    /// The frame that's shown at `elapsed` into the animation. Once it's
+    /// done repeating, the last frame is shown from then on.
+    pub fn frame_at(&self, elapsed: Duration) -> &RgbaImage {
+        &self.frames[self.frame_index(elapsed)]
+    }
+
+    fn frame_index(&self, elapsed: Duration) -> usize {
+        let duration = self.duration();
+        if duration.is_zero() {
+            return 0;
+        }
+
+        let plays = elapsed.as_nanos() / duration.as_nanos();
+        if matches!(self.repeat, Some(repeat) if plays > repeat as u128) {
+            return self.frames.len() - 1;
+        }
+
+        // Finding the frame that the time into the current play falls in
+        let mut remaining = Duration::from_nanos((elapsed.as_nanos() % duration.as_nanos()) as u64);
+        for (index, delay) in self.delays.iter().enumerate() {
+            if remaining < *delay {
+                return index;
+            }
+            remaining -= *delay;
+        }
+        self.frames.len() - 1
+    }
+}
+
+/// Plays an `AnimatedImage` in real time. `current_frame().as_region()`
+/// can be returned straight from `UIController::next_frame()`.
+pub struct AnimationPlayer {
+    image: AnimatedImage,
+    started: Instant,
+}
+
+impl AnimationPlayer {
+    /// Start playing an animation from its first frame.
+    pub fn new(image: AnimatedImage) -> AnimationPlayer {
+        AnimationPlayer { image, started: Instant::now() }
+    }
+
+    pub fn image(&self) -> &AnimatedImage {
+        &self.image
+    }
+
+    /// Play the animation again from its first frame.
+    pub fn restart(&mut self) {
+        self.started = Instant::now();
+    }
+
+    /// The frame that should be on screen now.
+    pub fn current_frame(&self) -> &RgbaImage {
+        self.image.frame_at(self.started.elapsed())
+    }
+}
+
+/// Decode every frame of a GIF file, composited the way browsers show them.
+pub fn decode_gif(bytes: &[u8]) -> Result<AnimatedImage, AssetError> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+
+    let decode_error = |error: gif::DecodingError| AssetError::Decode(error.to_string());
+    let mut decoder = options.read_info(bytes).map_err(decode_error)?;
+    let (width, height) = (decoder.width() as u32, decoder.height() as u32);
+    let repeat = match decoder.repeat() {
+        gif::Repeat::Finite(repeat) => Some(repeat as u32),
+        gif::Repeat::Infinite => None,
+    };
+
+    let mut canvas = RgbaImage::new(width, height);
+    let mut frames = vec![];
+    let mut delays = vec![];
+
+    while let Some(frame) = decoder.read_next_frame().map_err(decode_error)? {
+        let previous = (frame.dispose == gif::DisposalMethod::Previous).then(|| canvas.clone());
+        let area = (frame.left as u32, frame.top as u32, frame.width as u32, frame.height as u32);
+
+        // Drawing the frame's opaque pixels over what came before
+        for_each_gif_pixel(&mut canvas, area, |pixel, index| {
+            let source = &frame.buffer[index * 4..index * 4 + 4];
+            if source[3] != 0 {
+                pixel.copy_from_slice(source);
+            }
+        });
+        frames.push(canvas.clone());
+
+        // Browsers stretch very short delays, which were never meant literally
+        delays.push(match frame.delay {
+            0 | 1 => Duration::from_millis(100),
+            delay => Duration::from_millis(delay as u64 * 10),
+        });
+
+        match frame.dispose {
+            gif::DisposalMethod::Background => for_each_gif_pixel(&mut canvas, area, |pixel, _| pixel.fill(0)),
+            gif::DisposalMethod::Previous => canvas = previous.unwrap(),
+            gif::DisposalMethod::Any | gif::DisposalMethod::Keep => {},
+        }
+    }
+
+    if frames.is_empty() {
+        return Err(AssetError::Decode("GIF has no frames".to_string()));
+    }
+
+    Ok(AnimatedImage { frames, delays, repeat })
+}
+
+// Visiting each pixel of the canvas that a frame covers, along with the
+// pixel's index within the frame, ignoring parts beyond the canvas
+fn for_each_gif_pixel(
+    canvas: &mut RgbaImage,
+    (left, top, width, height): (u32, u32, u32, u32),
+    mut f: impl FnMut(&mut [u8], usize),
+) {
+    for y in 0..height.min(canvas.height.saturating_sub(top)) {
+        for x in 0..width.min(canvas.width.saturating_sub(left)) {
+            let offset = (((top + y) * canvas.width + left + x) * 4) as usize;
+            f(&mut canvas.bytes[offset..offset + 4], (y * width + x) as usize);
+        }
+    }
+}
+
+/// Encode pixels as an 8-bit RGBA PNG file.
+pub fn encode_png(pixels: &RgbaImageRegion) -> Result<Vec<u8>, AssetError> {
+    let mut bytes = vec![];
+    let mut encoder = png::Encoder::new(&mut bytes, pixels.width(), pixels.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().map_err(std::io::Error::from)?;
+    writer.write_image_data(&pixels.contiguous_bytes()).map_err(std::io::Error::from)?;
+    writer.finish().map_err(std::io::Error::from)?;
+    Ok(bytes)
+}
+
+#[test]
+fn _encode_png() {
+    let mut image = RgbaImage::new(3, 2);
+    image.set_pixel(2, 1, (10, 20, 30, 40));
+
+    let bytes = encode_png(&image.as_region()).unwrap();
+    assert!(decode_png(&bytes).unwrap() == image);
+}
+
+#[test]
+fn _decode_gif() {
+    // Encoding a 2x1 GIF that turns its left pixel from red to blue,
+    // with a transparent right pixel that shows the frame below
+    let palette = [255, 0, 0, 0, 0, 255, 0, 0, 0];
+    let mut bytes = vec![];
+    {
+        let mut encoder = gif::Encoder::new(&mut bytes, 2, 1, &palette).unwrap();
+        encoder.set_repeat(gif::Repeat::Finite(1)).unwrap();
+        for (indices, delay) in [([0, 0], 5), ([1, 2], 0)] {
+            let frame = gif::Frame {
+                width: 2,
+                height: 1,
+                delay,
+                transparent: Some(2),
+                buffer: std::borrow::Cow::Borrowed(&indices),
+                ..gif::Frame::default()
+            };
+            encoder.write_frame(&frame).unwrap();
+        }
+    }
+
+    let animation = decode_gif(&bytes).unwrap();
+    assert_eq!(animation.delays(), [Duration::from_millis(50), Duration::from_millis(100)]);
+    assert_eq!(animation.repeat(), Some(1));
+    assert_eq!(animation.frames()[0].get_pixel(1, 0), Some((255, 0, 0, 255)));
+    assert_eq!(animation.frames()[1].get_pixel(0, 0), Some((0, 0, 255, 255)));
+    assert_eq!(animation.frames()[1].get_pixel(1, 0), Some((255, 0, 0, 255)));
+
+    // Playing twice, then stopping on the last frame
+    assert_eq!(animation.frame_index(Duration::from_millis(40)), 0);
+    assert_eq!(animation.frame_index(Duration::from_millis(60)), 1);
+    assert_eq!(animation.frame_index(Duration::from_millis(190)), 0);
+    assert_eq!(animation.frame_index(Duration::from_millis(400)), 1);
+    assert!(decode_gif(&[1, 2, 3]).is_err());
+
+    // Rejecting animations without a delay for every frame
+    let frames = animation.frames().to_vec();
+    assert!(AnimatedImage::new(vec![], vec![], None).is_none());
+    assert!(AnimatedImage::new(frames.clone(), vec![Duration::ZERO], None).is_none());
+    assert_eq!(AnimatedImage::new(frames, animation.delays().to_vec(), Some(1)), Some(animation));
+}
+
+#[test]
+fn _load_image_bytes() {
+    // Encoding a 2x1 grayscale PNG
+    let mut bytes = vec![];
+    let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.write_header().unwrap().write_image_data(&[0, 200]).unwrap();
+
+    let mut assets = Assets::new(".");
+    let handle = assets.load_image_bytes("gradient", &bytes).unwrap();
+
+    assert_eq!(assets.handle("gradient"), Some(handle));
+    assert_eq!(assets.load_image_bytes("gradient", &[]).unwrap(), handle);
+    assert_eq!(assets.image(handle).get_pixel(1, 0), Some((200, 200, 200, 255)));
+    assert!(assets.load_image_bytes("broken", &[1, 2, 3]).is_err());
+}
+
+#[test]
+fn _reload_changed() {
+    let encode = |gray: u8| {
+        let mut bytes = vec![];
+        let mut encoder = png::Encoder::new(&mut bytes, 1, 1);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.write_header().unwrap().write_image_data(&[gray]).unwrap();
+        bytes
+    };
+
+    let root = std::env::temp_dir().join(format!("open_ui_assets_{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("sprite.png"), encode(10)).unwrap();
+
+    let mut assets = Assets::new(&root);
+    let handle = assets.load_image("sprite.png").unwrap();
+    assert_eq!(assets.reload_changed(), vec![]);
+
+    // Saving a new version of the file. Filesystem timestamps can be too
+    // coarse to differ within a test, so the recorded time is rewound instead
+    std::fs::write(root.join("sprite.png"), encode(20)).unwrap();
+    if let Some((_, last_modified)) = &mut assets.sources[handle.0] {
+        *last_modified = SystemTime::UNIX_EPOCH;
+    }
+
+    assert_eq!(assets.reload_changed(), vec![handle]);
+    assert_eq!(assets.image(handle).get_pixel(0, 0), Some((20, 20, 20, 255)));
+    std::fs::remove_dir_all(&root).unwrap();
+}
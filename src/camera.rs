@@ -0,0 +1,180 @@
+//! Live video from webcams, delivered as `RgbaImage` frames that can be
+//! drawn onto the canvas like any other image, for vision demos and
+//! photo booths.
+//!
+//! Cameras are opened with the `camera` feature, through Video4Linux on
+//! Linux. Elsewhere, or without the feature, there are no cameras, and
+//! opening one fails with `CameraError::Unsupported`. Frames are captured
+//! on a thread of their own, so waiting for the camera never holds up the
+//! application, and frames that aren't collected in time are dropped.
+
+use crate::RgbaImage;
+use std::sync::mpsc;
+
+/// A camera that can be opened with `Camera::open()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraInfo {
+    pub index: usize,
+    pub name: String,
+}
+
+#[derive(Debug)]
+pub enum CameraError {
+    /// The `camera` feature is disabled, or cameras aren't
+    /// supported on this platform.
+    Unsupported,
+    /// The camera can't produce frames in a format that can be
+    /// converted to RGBA.
+    UnsupportedFormat,
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for CameraError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CameraError::Unsupported => write!(f, "cameras require the `camera` feature, on Linux"),
+            CameraError::UnsupportedFormat => write!(f, "the camera doesn't support YUYV frames"),
+            CameraError::Io(error) => write!(f, "couldn't use camera: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for CameraError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CameraError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for CameraError {
+    fn from(error: std::io::Error) -> CameraError {
+        CameraError::Io(error)
+    }
+}
+
+/// Every connected camera.
+pub fn cameras() -> Vec<CameraInfo> {
+    #[cfg(all(feature = "camera", target_os = "linux"))]
+    {
+        v4l::context::enum_devices().iter()
+            .map(|node| CameraInfo {
+                index: node.index(),
+                name: node.name().unwrap_or_else(|| format!("Camera {}", node.index())),
+            })
+            .collect()
+    }
+
+    #[cfg(not(all(feature = "camera", target_os = "linux")))]
+    vec![]
+}
+
+/// An open camera, capturing frames until it's dropped.
+pub struct Camera {
+    size: (u32, u32),
+    frames: mpsc::Receiver<RgbaImage>,
+}
+
+impl Camera {
+    /// Start capturing from a camera listed by `cameras()`, at the supported
+    /// resolution closest to `size`.
+    #[allow(unused_variables)]
+    pub fn open(index: usize, size: (u32, u32)) -> Result<Camera, CameraError> {
+        #[cfg(all(feature = "camera", target_os = "linux"))]
+        {
+            use v4l::video::Capture;
+
+            let device = v4l::Device::new(index)?;
+            let mut format = device.format()?;
+            format.width = size.0;
+            format.height = size.1;
+            format.fourcc = v4l::FourCC::new(b"YUYV");
+
+            // The driver adjusts the format to the closest one it supports
+            let format = device.set_format(&format)?;
+            if format.fourcc != v4l::FourCC::new(b"YUYV") {
+                return Err(CameraError::UnsupportedFormat);
+            }
+
+            // Holding on to at most one frame that hasn't been collected yet
+            let (sender, frames) = mpsc::sync_channel(1);
+            std::thread::spawn(move || {
+                use v4l::io::traits::CaptureStream;
+
+                let mut stream = match v4l::prelude::MmapStream::with_buffers(&device, v4l::buffer::Type::VideoCapture, 4) {
+                    Ok(stream) => stream,
+                    Err(error) => return log_error!("couldn't start camera: {}", error),
+                };
+
+                while let Ok((buffer, _)) = stream.next() {
+                    let mut frame = RgbaImage::new(format.width, format.height);
+                    yuyv_to_rgba(buffer, format.stride as usize, &mut frame);
+
+                    match sender.try_send(frame) {
+                        Ok(()) | Err(mpsc::TrySendError::Full(_)) => {},
+                        Err(mpsc::TrySendError::Disconnected(_)) => break,
+                    }
+                }
+            });
+
+            Ok(Camera { size: (format.width, format.height), frames })
+        }
+
+        #[cfg(not(all(feature = "camera", target_os = "linux")))]
+        Err(CameraError::Unsupported)
+    }
+
+    /// The resolution of the camera's frames.
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// The newest frame, if one has arrived since the last call.
+    pub fn latest_frame(&mut self) -> Option<RgbaImage> {
+        self.frames.try_iter().last()
+    }
+}
+
+// Converting YUYV, where each four bytes hold two pixels that share their
+// color, into RGBA, using the BT.601 coefficients that webcams use.
+// Strides shorter than a row, which some drivers report as zero, are
+// treated as unpadded rows.
+#[cfg_attr(not(all(feature = "camera", target_os = "linux")), allow(dead_code))]
+fn yuyv_to_rgba(yuyv: &[u8], stride: usize, frame: &mut RgbaImage) {
+    let width = frame.width as usize;
+
+    for (source, row) in yuyv.chunks(stride.max(width * 2)).zip(frame.bytes.chunks_mut(width * 4)) {
+        for (pair, pixels) in source.chunks_exact(4).zip(row.chunks_mut(8)) {
+            let (u, v) = (pair[1] as i32 - 128, pair[3] as i32 - 128);
+
+            for (y, pixel) in [pair[0], pair[2]].iter().zip(pixels.chunks_exact_mut(4)) {
+                let y = *y as i32;
+                pixel[0] = (y + ((359 * v) >> 8)).clamp(0, 255) as u8;
+                pixel[1] = (y - ((88 * u + 183 * v) >> 8)).clamp(0, 255) as u8;
+                pixel[2] = (y + ((454 * u) >> 8)).clamp(0, 255) as u8;
+                pixel[3] = 255;
+            }
+        }
+    }
+}
+
+#[test]
+fn _yuyv_to_rgba() {
+    // A gray pixel and a white one, then a red pixel twice, with
+    // rows padded to seven bytes
+    let yuyv = [128, 128, 255, 128, 0, 0, 0, 76, 85, 76, 255, 0, 0, 0];
+    let mut frame = RgbaImage::new(2, 2);
+    yuyv_to_rgba(&yuyv, 7, &mut frame);
+
+    assert_eq!(&frame.bytes[..8], &[128, 128, 128, 255, 255, 255, 255, 255]);
+    assert_eq!(&frame.bytes[8..], &[254, 0, 0, 255, 254, 0, 0, 255]);
+
+    // The same frame without padding, with no stride reported
+    let yuyv = [128, 128, 255, 128, 76, 85, 76, 255];
+    let mut frame = RgbaImage::new(2, 2);
+    yuyv_to_rgba(&yuyv, 0, &mut frame);
+
+    assert_eq!(&frame.bytes[..8], &[128, 128, 128, 255, 255, 255, 255, 255]);
+    assert_eq!(&frame.bytes[8..], &[254, 0, 0, 255, 254, 0, 0, 255]);
+}
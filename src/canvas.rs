@@ -0,0 +1,130 @@
+//! Controllers that draw onto canvases owned by OpenUI, instead of
+//! owning a canvas and returning a region of it from `next_frame()`.
+//!
+//! Implement `CanvasController`, then wrap the controller in
+//! `DoubleBuffered` to launch it like any other `UIController`.
+//! Two canvases are kept, and each frame the controller draws onto the
+//! one that isn't being displayed, before the two are swapped.
+
+use crate::{FrameReport, RedrawHandle, RgbaImage, RgbaImageRegion, UIBlueprint, UIContext, UIControlFlow, UIController, UIEvent};
+
+/// Like `UIController`, but drawing each frame onto a canvas
+/// that is provided by `DoubleBuffered`.
+pub trait CanvasController {
+    /// Equivalent to `UIController::blueprint()`.
+    fn blueprint(&self) -> UIBlueprint {
+        UIBlueprint::default()
+    }
+
+    /// The dimensions of the canvases, which are the dimensions of
+    /// the blueprint by default. Both canvases are replaced when this
+    /// changes, so the next frame is drawn onto a blank canvas.
+    fn canvas_size(&self) -> (u32, u32) {
+        self.blueprint().dimensions
+    }
+
+    /// This function will be called every frame, with the canvas that
+    /// isn't being displayed. The canvas still contains the frame drawn
+    /// two frames earlier, so most controllers should clear it first.
+    fn draw(&mut self, canvas: &mut RgbaImage, context: &mut UIContext);
+
+    /// Equivalent to `UIController::process_events()`.
+    fn process_events(&mut self, _events: &Vec<UIEvent>, _context: &mut UIContext) -> UIControlFlow {
+        UIControlFlow::Continue
+    }
+
+    /// Equivalent to `UIController::redraw_handle()`.
+    fn redraw_handle(&mut self, _handle: RedrawHandle) {}
+
+    /// Equivalent to `UIController::run_command()`.
+    fn run_command(&mut self, _name: &str, _args: &[&str]) -> String {
+        String::new()
+    }
+
+    /// Equivalent to `UIController::frame_report()`.
+    fn frame_report(&mut self, _report: &FrameReport) {}
+}
+
+/// Adapts a `CanvasController` to the `UIController` trait,
+/// by owning the two canvases that it draws onto.
+pub struct DoubleBuffered<T: CanvasController> {
+    controller: T,
+    front: RgbaImage,
+    back: RgbaImage,
+}
+
+impl<T: CanvasController> DoubleBuffered<T> {
+    pub fn new(controller: T) -> DoubleBuffered<T> {
+        let (width, height) = controller.canvas_size();
+
+        DoubleBuffered {
+            controller,
+            front: RgbaImage::new(width, height),
+            back: RgbaImage::new(width, height),
+        }
+    }
+
+    pub fn controller(&self) -> &T {
+        &self.controller
+    }
+
+    pub fn controller_mut(&mut self) -> &mut T {
+        &mut self.controller
+    }
+}
+
+impl<T: CanvasController> UIController for DoubleBuffered<T> {
+    fn blueprint(&self) -> UIBlueprint {
+        self.controller.blueprint()
+    }
+
+    fn next_frame(&mut self, context: &mut UIContext) -> Option<RgbaImageRegion<'_>> {
+        let (width, height) = self.controller.canvas_size();
+        if (self.back.width(), self.back.height()) != (width, height) {
+            self.front = RgbaImage::new(width, height);
+            self.back = RgbaImage::new(width, height);
+        }
+
+        // Drawing onto the back canvas, then displaying it
+        self.controller.draw(&mut self.back, context);
+        std::mem::swap(&mut self.front, &mut self.back);
+        Some(self.front.as_region())
+    }
+
+    fn process_events(&mut self, events: &Vec<UIEvent>, context: &mut UIContext) -> UIControlFlow {
+        self.controller.process_events(events, context)
+    }
+
+    fn redraw_handle(&mut self, handle: RedrawHandle) {
+        self.controller.redraw_handle(handle);
+    }
+
+    fn run_command(&mut self, name: &str, args: &[&str]) -> String {
+        self.controller.run_command(name, args)
+    }
+
+    fn frame_report(&mut self, report: &FrameReport) {
+        self.controller.frame_report(report);
+    }
+}
+
+#[test]
+fn _double_buffered() {
+    struct Stripes { frame: u8 }
+    impl CanvasController for Stripes {
+        fn canvas_size(&self) -> (u32, u32) { (2, 1) }
+        fn draw(&mut self, canvas: &mut RgbaImage, _context: &mut UIContext) {
+            self.frame += 1;
+            canvas.set_pixel(0, 0, (self.frame, 0, 0, 255));
+        }
+    }
+
+    let mut buffered = DoubleBuffered::new(Stripes { frame: 0 });
+    let mut context = UIContext::new((2, 1), 1.0);
+    assert_eq!(buffered.next_frame(&mut context).unwrap().get_pixel(0, 0), Some((1, 0, 0, 255)));
+    assert_eq!(buffered.next_frame(&mut context).unwrap().get_pixel(0, 0), Some((2, 0, 0, 255)));
+
+    // The canvas being displayed is never the one being drawn onto
+    assert_eq!(buffered.back.get_pixel(0, 0), Some((1, 0, 0, 255)));
+    assert_eq!(buffered.controller().frame, 2);
+}
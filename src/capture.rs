@@ -0,0 +1,171 @@
+//! Capturing the contents of a display, or of another application's
+//! window, for tools such as magnifiers, color pickers, and streaming
+//! overlays.
+//!
+//! Capturing needs the `capture` feature. Without it, there are no
+//! sources, and capturing fails with `CaptureError::Unsupported`. On
+//! macOS, the user has to grant the application screen recording
+//! permission first, and on Wayland, the desktop may ask the user
+//! whether to allow each capture.
+
+use crate::{Rect, RgbaImage};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SourceKind {
+    Display,
+    Window,
+}
+
+/// A display, or another application's window, that can be captured.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureSource {
+    pub kind: SourceKind,
+    /// The identifier that the OS gives the display or window.
+    pub id: u32,
+    /// The display's name, or the window's title.
+    pub name: String,
+    /// Where the source is on the desktop, in physical pixels.
+    pub position: (i32, i32),
+    pub size: (u32, u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaptureError {
+    /// The `capture` feature is disabled.
+    Unsupported,
+    /// The display or window is gone, or nothing is at the given point.
+    NotFound,
+    /// The OS refused, for example because permission wasn't granted.
+    Failed(String),
+}
+
+impl std::fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CaptureError::Unsupported => write!(f, "screen capture requires the `capture` feature"),
+            CaptureError::NotFound => write!(f, "nothing to capture there"),
+            CaptureError::Failed(reason) => write!(f, "couldn't capture the screen: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+#[cfg(feature = "capture")]
+impl From<xcap::XCapError> for CaptureError {
+    fn from(error: xcap::XCapError) -> CaptureError {
+        CaptureError::Failed(error.to_string())
+    }
+}
+
+/// Every display, followed by every window that isn't minimized.
+pub fn sources() -> Vec<CaptureSource> {
+    #[cfg(feature = "capture")]
+    {
+        let displays = xcap::Monitor::all().unwrap_or_default().into_iter().map(|monitor| CaptureSource {
+            kind: SourceKind::Display,
+            id: monitor.id(),
+            name: monitor.name().to_string(),
+            position: (monitor.x(), monitor.y()),
+            size: (monitor.width(), monitor.height()),
+        });
+        let windows = xcap::Window::all().unwrap_or_default().into_iter()
+            .filter(|window| !window.is_minimized())
+            .map(|window| CaptureSource {
+                kind: SourceKind::Window,
+                id: window.id(),
+                name: window.title().to_string(),
+                position: (window.x(), window.y()),
+                size: (window.width(), window.height()),
+            });
+
+        displays.chain(windows).collect()
+    }
+
+    #[cfg(not(feature = "capture"))]
+    vec![]
+}
+
+/// Capture what a display or window currently shows.
+#[allow(unused_variables)]
+pub fn capture(source: &CaptureSource) -> Result<RgbaImage, CaptureError> {
+    #[cfg(feature = "capture")]
+    {
+        let captured = match source.kind {
+            SourceKind::Display => xcap::Monitor::all()?.into_iter()
+                .find(|monitor| monitor.id() == source.id)
+                .ok_or(CaptureError::NotFound)?
+                .capture_image()?,
+            SourceKind::Window => xcap::Window::all()?.into_iter()
+                .find(|window| window.id() == source.id)
+                .ok_or(CaptureError::NotFound)?
+                .capture_image()?,
+        };
+
+        Ok(from_captured(captured))
+    }
+
+    #[cfg(not(feature = "capture"))]
+    Err(CaptureError::Unsupported)
+}
+
+/// Capture an area of the desktop, in physical pixels, such as the
+/// pixels around the cursor for a magnifier. Only the display under the
+/// area's top left corner is captured, and any part of the area beyond
+/// that display is left transparent.
+#[allow(unused_variables)]
+pub fn capture_area(x: i32, y: i32, width: u32, height: u32) -> Result<RgbaImage, CaptureError> {
+    #[cfg(feature = "capture")]
+    {
+        let monitor = xcap::Monitor::from_point(x, y).map_err(|_| CaptureError::NotFound)?;
+        let display = from_captured(monitor.capture_image()?);
+        let mut area = RgbaImage::new(width, height);
+
+        let position = (x - monitor.x(), y - monitor.y());
+        if let Some((source, (left, top))) = overlap(position, (width, height), (display.width, display.height)) {
+            let row_length = source.w as usize * 4;
+            for row in 0..source.h {
+                let from = ((source.y + row) as usize * display.width as usize + source.x as usize) * 4;
+                let to = ((top + row) as usize * width as usize + left as usize) * 4;
+                area.bytes[to..to + row_length].copy_from_slice(&display.bytes[from..from + row_length]);
+            }
+        }
+
+        Ok(area)
+    }
+
+    #[cfg(not(feature = "capture"))]
+    Err(CaptureError::Unsupported)
+}
+
+#[cfg(feature = "capture")]
+fn from_captured(captured: xcap::image::RgbaImage) -> RgbaImage {
+    let mut image = RgbaImage::new(captured.width(), captured.height());
+    image.bytes = captured.into_raw();
+    image
+}
+
+// The part of a display that an area covers, given the area's position
+// relative to the display, along with where that part goes in the area
+#[cfg_attr(not(feature = "capture"), allow(dead_code))]
+fn overlap(position: (i32, i32), size: (u32, u32), display: (u32, u32)) -> Option<(Rect, (u32, u32))> {
+    let (x, y) = (position.0 as i64, position.1 as i64);
+    let left = x.max(0);
+    let top = y.max(0);
+    let right = (x + size.0 as i64).min(display.0 as i64);
+    let bottom = (y + size.1 as i64).min(display.1 as i64);
+
+    if left >= right || top >= bottom {
+        return None;
+    }
+
+    let source = Rect::new(left as u32, top as u32, (right - left) as u32, (bottom - top) as u32);
+    Some((source, ((left - x) as u32, (top - y) as u32)))
+}
+
+#[test]
+fn _overlap() {
+    assert_eq!(overlap((10, 20), (30, 40), (1920, 1080)), Some((Rect::new(10, 20, 30, 40), (0, 0))));
+    assert_eq!(overlap((-5, 1070), (30, 40), (1920, 1080)), Some((Rect::new(0, 1070, 25, 10), (5, 0))));
+    assert_eq!(overlap((1920, 0), (30, 40), (1920, 1080)), None);
+}
@@ -0,0 +1,134 @@
+//! Copying and pasting text and images.
+//!
+//! With the `clipboard` feature enabled, text and images are shared with
+//! other applications through the system clipboard. Otherwise, or if the
+//! system clipboard is unavailable, they're only shared within the
+//! application. Like the system clipboard, the clipboard holds either
+//! text or an image, and copying one replaces the other.
+
+use crate::{RgbaImage, RgbaImageRegion};
+
+/// A place to put text or images that have been copied, until they're pasted.
+pub struct Clipboard {
+    #[cfg(feature = "clipboard")]
+    system: Option<arboard::Clipboard>,
+    text: String,
+    image: Option<RgbaImage>,
+}
+
+impl Default for Clipboard {
+    fn default() -> Clipboard {
+        Clipboard::new()
+    }
+}
+
+impl Clipboard {
+    pub fn new() -> Clipboard {
+        Clipboard {
+            #[cfg(feature = "clipboard")]
+            system: arboard::Clipboard::new().ok(),
+            text: String::new(),
+            image: None,
+        }
+    }
+
+    /// The text that was most recently copied, if any.
+    pub fn get_text(&mut self) -> Option<String> {
+        #[cfg(feature = "clipboard")]
+        if let Some(system) = &mut self.system {
+            match system.get_text() {
+                Ok(text) => return Some(text),
+                // Forgetting text copied here once another application copies an image
+                Err(_) if system.get_image().is_ok() => self.text.clear(),
+                Err(_) => {},
+            }
+        }
+
+        if self.text.is_empty() { None } else { Some(self.text.clone()) }
+    }
+
+    /// Replace the contents of the clipboard.
+    pub fn set_text(&mut self, text: &str) {
+        self.text.clear();
+        self.image = None;
+
+        #[cfg(feature = "clipboard")]
+        if let Some(system) = &mut self.system {
+            if system.set_text(text.to_string()).is_ok() {
+                return;
+            }
+        }
+
+        // Keeping the text here when the system clipboard can't
+        self.text = text.to_string();
+    }
+
+    /// The image that was most recently copied, if any. Images copied by
+    /// other applications are converted to 8-bit RGBA.
+    pub fn get_image(&mut self) -> Option<RgbaImage> {
+        #[cfg(feature = "clipboard")]
+        if let Some(system) = &mut self.system {
+            match system.get_image() {
+                Ok(data) => {
+                    let mut image = RgbaImage::new(data.width as u32, data.height as u32);
+                    if image.bytes.len() == data.bytes.len() {
+                        image.bytes.copy_from_slice(&data.bytes);
+                        return Some(image);
+                    }
+                },
+                // Forgetting images copied here once another application copies text
+                Err(_) if system.get_text().is_ok() => self.image = None,
+                Err(_) => {},
+            }
+        }
+
+        self.image.clone()
+    }
+
+    /// Replace the contents of the clipboard with an image,
+    /// such as a selection from a paint tool.
+    pub fn set_image<'a>(&mut self, image: impl Into<RgbaImageRegion<'a>>) {
+        let image = image.into();
+        self.text.clear();
+        self.image = None;
+
+        #[cfg(feature = "clipboard")]
+        if let Some(system) = &mut self.system {
+            let copied = system.set_image(arboard::ImageData {
+                width: image.width as usize,
+                height: image.height as usize,
+                bytes: image.contiguous_bytes(),
+            });
+            if copied.is_ok() {
+                return;
+            }
+        }
+
+        // Keeping the image here when the system clipboard can't
+        let mut copy = RgbaImage::new(0, 0);
+        copy.copy_region(&image);
+        self.image = Some(copy);
+    }
+}
+
+#[test]
+#[cfg(not(feature = "clipboard"))]
+fn _local_clipboard() {
+    let mut clipboard = Clipboard::new();
+    assert_eq!(clipboard.get_text(), None);
+    assert!(clipboard.get_image().is_none());
+
+    clipboard.set_text("hello");
+    assert_eq!(clipboard.get_text(), Some("hello".to_string()));
+
+    // Copying an image replaces the text, and copying text replaces the image
+    let mut image = RgbaImage::new(2, 1);
+    image.fill((255, 0, 0, 255));
+    clipboard.set_image(&image);
+    assert_eq!(clipboard.get_text(), None);
+    assert_eq!(clipboard.get_image().map(|copy| copy.bytes), Some(image.bytes));
+
+    clipboard.set_text("world");
+    assert_eq!(clipboard.get_text(), Some("world".to_string()));
+    assert!(clipboard.get_image().is_none());
+}
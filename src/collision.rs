@@ -0,0 +1,151 @@
+//! Detecting when sprites touch, for games.
+//!
+//! Positions are where the top left corner of each image is drawn, as with
+//! `RgbaImage::draw()`. `bounds_overlap()` is a quick test of the images'
+//! rectangles, while a `CollisionMask` records which pixels of an image are
+//! solid, so that sprites only collide where their visible pixels meet.
+//! Masks are best made once per sprite, and reused every frame.
+
+use crate::RgbaImageRegion;
+
+/// Whether the rectangles of two images overlap, when they're drawn
+/// at the given positions.
+pub fn bounds_overlap(position: (i32, i32), size: (u32, u32), other_position: (i32, i32), other_size: (u32, u32)) -> bool {
+    overlap(position, size, other_position, other_size).is_some()
+}
+
+// The area covered by both rectangles, as its left, top, right and
+// bottom edges, if they overlap
+fn overlap(position: (i32, i32), size: (u32, u32), other_position: (i32, i32), other_size: (u32, u32)) -> Option<(i64, i64, i64, i64)> {
+    let left = (position.0 as i64).max(other_position.0 as i64);
+    let top = (position.1 as i64).max(other_position.1 as i64);
+    let right = (position.0 as i64 + size.0 as i64).min(other_position.0 as i64 + other_size.0 as i64);
+    let bottom = (position.1 as i64 + size.1 as i64).min(other_position.1 as i64 + other_size.1 as i64);
+
+    if left < right && top < bottom { Some((left, top, right, bottom)) } else { None }
+}
+
+/// Which pixels of an image are solid, packed 64 to a word so that
+/// they can be compared many at a time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollisionMask {
+    width: u32,
+    height: u32,
+    words_per_row: usize,
+    words: Vec<u64>,
+}
+
+impl CollisionMask {
+    /// Record which pixels of an image are solid, meaning that their
+    /// alpha is at least `alpha_threshold`.
+    pub fn new<'a>(image: impl Into<RgbaImageRegion<'a>>, alpha_threshold: u8) -> CollisionMask {
+        let image = image.into();
+        let (width, height) = (image.width(), image.height());
+        let words_per_row = (width as usize).div_ceil(64);
+        let mut words = vec![0; words_per_row * height as usize];
+
+        for y in 0..height {
+            for x in 0..width {
+                if matches!(image.get_pixel(x, y), Some((_, _, _, alpha)) if alpha >= alpha_threshold) {
+                    words[y as usize * words_per_row + x as usize / 64] |= 1 << (x % 64);
+                }
+            }
+        }
+
+        CollisionMask { width, height, words_per_row, words }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn is_solid(&self, x: u32, y: u32) -> bool {
+        x < self.width && y < self.height && self.bits(y, x, 1) != 0
+    }
+
+    /// Whether any solid pixels of the two masks overlap, when their
+    /// images are drawn at the given positions.
+    pub fn overlaps(&self, position: (i32, i32), other: &CollisionMask, other_position: (i32, i32)) -> bool {
+        let Some((left, top, right, bottom)) = overlap(position, (self.width, self.height), other_position, (other.width, other.height)) else {
+            return false;
+        };
+
+        // Comparing up to 64 pixels of each row at a time
+        (top..bottom).any(|y| {
+            let row = (y - position.1 as i64) as u32;
+            let other_row = (y - other_position.1 as i64) as u32;
+
+            (left..right).step_by(64).any(|x| {
+                let count = (right - x).min(64) as u32;
+                let bits = self.bits(row, (x - position.0 as i64) as u32, count);
+                let other_bits = other.bits(other_row, (x - other_position.0 as i64) as u32, count);
+                bits & other_bits != 0
+            })
+        })
+    }
+
+    // Up to 64 bits of a row, starting at column `x`, with the
+    // first column in the lowest bit
+    fn bits(&self, row: u32, x: u32, count: u32) -> u64 {
+        let start = row as usize * self.words_per_row;
+        let (word, shift) = (x as usize / 64, x % 64);
+
+        let mut bits = self.words[start + word] >> shift;
+        if shift > 0 && word + 1 < self.words_per_row {
+            bits |= self.words[start + word + 1] << (64 - shift);
+        }
+
+        if count < 64 { bits & ((1 << count) - 1) } else { bits }
+    }
+}
+
+/// Whether the solid pixels of two images overlap, when they're drawn at
+/// the given positions. For sprites that are tested every frame, make a
+/// `CollisionMask` of each once instead.
+pub fn pixels_overlap<'a, 'b>(
+    image: impl Into<RgbaImageRegion<'a>>,
+    position: (i32, i32),
+    other: impl Into<RgbaImageRegion<'b>>,
+    other_position: (i32, i32),
+    alpha_threshold: u8,
+) -> bool {
+    let (image, other) = (image.into(), other.into());
+    if !bounds_overlap(position, (image.width(), image.height()), other_position, (other.width(), other.height())) {
+        return false;
+    }
+
+    CollisionMask::new(image, alpha_threshold).overlaps(position, &CollisionMask::new(other, alpha_threshold), other_position)
+}
+
+#[test]
+fn _collision_mask() {
+    use crate::RgbaImage;
+
+    // A wide sprite with a single solid pixel near its right edge,
+    // past the first 64 columns, and a small solid square
+    let mut wide = RgbaImage::new(100, 2);
+    wide.set_pixel(90, 1, (255, 255, 255, 255));
+    wide.set_pixel(10, 0, (255, 255, 255, 100));
+    let mut square = RgbaImage::new(2, 2);
+    for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+        square.set_pixel(x, y, (0, 0, 0, 255));
+    }
+
+    let wide_mask = CollisionMask::new(&wide, 128);
+    let square_mask = CollisionMask::new(&square, 128);
+    assert!(wide_mask.is_solid(90, 1));
+    assert!(!wide_mask.is_solid(10, 0));
+
+    assert!(wide_mask.overlaps((-20, 5), &square_mask, (69, 5)));
+    assert!(wide_mask.overlaps((-20, 5), &square_mask, (70, 6)));
+    assert!(!wide_mask.overlaps((-20, 5), &square_mask, (71, 5)));
+    assert!(!wide_mask.overlaps((-20, 5), &square_mask, (-10, 5)));
+
+    assert!(bounds_overlap((-20, 5), (100, 2), (-10, 5), (2, 2)));
+    assert!(!bounds_overlap((0, 0), (2, 2), (2, 0), (2, 2)));
+    assert!(pixels_overlap(&square, (0, 0), &square, (1, 1), 1));
+}
@@ -0,0 +1,182 @@
+//! Conversions between `RgbaPixel` and other color models.
+
+use crate::hdr::{RgbaImageF32, RgbaPixelF32};
+use crate::pixel_formats::Image;
+use crate::{RgbaImage, RgbaPixel};
+use std::sync::OnceLock;
+
+/// Convert a pixel into hue (0-360), saturation (0-1), and value (0-1).
+/// The alpha channel is ignored.
+pub fn rgb_to_hsv(pixel: &RgbaPixel) -> (f32, f32, f32) {
+    let r = pixel.0 as f32 / 255.0;
+    let g = pixel.1 as f32 / 255.0;
+    let b = pixel.2 as f32 / 255.0;
+
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let chroma = max - min;
+
+    let saturation = if max > 0.0 { chroma / max } else { 0.0 };
+    (hue(r, g, b, max, chroma), saturation, max)
+}
+
+/// Convert hue (0-360), saturation (0-1), and value (0-1) into a pixel
+/// with the given alpha.
+pub fn hsv_to_rgb(hsv: (f32, f32, f32), alpha: u8) -> RgbaPixel {
+    let (h, s, v) = hsv;
+    let s = s.clamp(0.0, 1.0);
+    let v = v.clamp(0.0, 1.0);
+
+    let chroma = v * s;
+    from_hue_and_chroma(h, chroma, v - chroma, alpha)
+}
+
+/// Convert a pixel into hue (0-360), saturation (0-1), and lightness (0-1).
+/// The alpha channel is ignored.
+pub fn rgb_to_hsl(pixel: &RgbaPixel) -> (f32, f32, f32) {
+    let r = pixel.0 as f32 / 255.0;
+    let g = pixel.1 as f32 / 255.0;
+    let b = pixel.2 as f32 / 255.0;
+
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let chroma = max - min;
+    let lightness = (max + min) / 2.0;
+
+    let saturation = if lightness > 0.0 && lightness < 1.0 {
+        chroma / (1.0 - (2.0 * lightness - 1.0).abs())
+    } else {
+        0.0
+    };
+
+    (hue(r, g, b, max, chroma), saturation, lightness)
+}
+
+/// Convert hue (0-360), saturation (0-1), and lightness (0-1) into a pixel
+/// with the given alpha.
+pub fn hsl_to_rgb(hsl: (f32, f32, f32), alpha: u8) -> RgbaPixel {
+    let (h, s, l) = hsl;
+    let s = s.clamp(0.0, 1.0);
+    let l = l.clamp(0.0, 1.0);
+
+    let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    from_hue_and_chroma(h, chroma, l - chroma / 2.0, alpha)
+}
+
+/// The perceived brightness of a pixel (0-255), using Rec. 601 weights.
+/// The alpha channel is ignored.
+pub fn luminance(pixel: &RgbaPixel) -> u8 {
+    let luminance = 0.299 * pixel.0 as f32 + 0.587 * pixel.1 as f32 + 0.114 * pixel.2 as f32;
+    luminance.round().min(255.0) as u8
+}
+
+/// Convert an sRGB-encoded channel (0-1), as stored in `RgbaImage`,
+/// into linear light (0-1), where blending and averaging behave
+/// the way light does.
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+}
+
+/// Convert a linear channel (0-1) into its sRGB encoding (0-1).
+/// Values outside of that range are clamped.
+pub fn linear_to_srgb(value: f32) -> f32 {
+    let value = value.clamp(0.0, 1.0);
+    if value <= 0.0031308 { value * 12.92 } else { 1.055 * value.powf(1.0 / 2.4) - 0.055 }
+}
+
+// Decoding 8-bit channels with a lookup table, since blending
+// in linear light decodes every pixel that it touches
+fn decode_channel(channel: u8) -> f32 {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    let table = TABLE.get_or_init(|| std::array::from_fn(|index| srgb_to_linear(index as f32 / 255.0)));
+    table[channel as usize]
+}
+
+/// Convert a pixel into linear light. Alpha is scaled to 0-1,
+/// since it's already linear.
+pub fn pixel_to_linear(pixel: &RgbaPixel) -> RgbaPixelF32 {
+    (decode_channel(pixel.0), decode_channel(pixel.1), decode_channel(pixel.2), pixel.3 as f32 / 255.0)
+}
+
+/// Convert a pixel in linear light back into an sRGB-encoded pixel.
+pub fn pixel_to_srgb(pixel: &RgbaPixelF32) -> RgbaPixel {
+    let channel = |value: f32| (linear_to_srgb(value) * 255.0).round() as u8;
+    (channel(pixel.0), channel(pixel.1), channel(pixel.2), (pixel.3.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+/// Convert every pixel of an image into linear light.
+pub fn image_to_linear(image: &RgbaImage) -> RgbaImageF32 {
+    let pixels = image.bytes.chunks_exact(4)
+        .map(|pixel| pixel_to_linear(&(pixel[0], pixel[1], pixel[2], pixel[3])))
+        .collect();
+
+    Image::from_pixels(image.width, image.height, pixels).unwrap()
+}
+
+/// Convert an image in linear light into an sRGB-encoded image,
+/// clamping values brighter than 1.0. See `RgbaImageF32::tonemap()`
+/// for alternatives to clamping.
+pub fn image_to_srgb(image: &RgbaImageF32) -> RgbaImage {
+    image.tonemap(crate::hdr::Tonemap::Clamp, 1.0)
+}
+
+// The hue shared by HSV and HSL, in degrees
+fn hue(r: f32, g: f32, b: f32, max: f32, chroma: f32) -> f32 {
+    if chroma == 0.0 {
+        return 0.0;
+    }
+
+    let hue = if max == r {
+        ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    };
+
+    hue * 60.0
+}
+
+// Building a pixel from the components shared by HSV and HSL
+fn from_hue_and_chroma(hue: f32, chroma: f32, min: f32, alpha: u8) -> RgbaPixel {
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+
+    let channel = |c: f32| ((c + min) * 255.0).round().clamp(0.0, 255.0) as u8;
+    (channel(r), channel(g), channel(b), alpha)
+}
+
+#[test]
+fn _srgb_round_trip() {
+    for channel in 0..=255u8 {
+        let pixel = (channel, channel, channel, channel);
+        assert_eq!(pixel_to_srgb(&pixel_to_linear(&pixel)), pixel);
+    }
+
+    // Middle gray in sRGB is much darker than half the light
+    assert!((pixel_to_linear(&(128, 128, 128, 255)).0 - 0.216).abs() < 0.001);
+
+    let mut image = RgbaImage::new(2, 1);
+    image.set_pixel(1, 0, (10, 128, 250, 40));
+    assert_eq!(image_to_srgb(&image_to_linear(&image)), image);
+}
+
+#[test]
+fn _hsv_round_trip() {
+    let pixel = (200, 100, 50, 128);
+    let (h, s, v) = rgb_to_hsv(&pixel);
+    assert_eq!(h.round(), 20.0);
+    assert_eq!(hsv_to_rgb((h, s, v), pixel.3), pixel);
+
+    let hsl = rgb_to_hsl(&pixel);
+    assert_eq!(hsl_to_rgb(hsl, pixel.3), pixel);
+}
@@ -0,0 +1,202 @@
+//! A console that can be opened on top of the application by pressing
+//! the backtick key, for reading recent log lines and running commands
+//! while debugging.
+//!
+//! Enable it with `UIBlueprint::debug_console()`, and register commands
+//! with `UIBlueprint::console_command()`. Entering a registered command
+//! calls `UIController::run_command()`. The console is drawn by
+//! `UI::launch()`, and isn't available with the other launch methods.
+
+use crate::clipboard::Clipboard;
+use crate::font::{self, ADVANCE, GLYPH_HEIGHT, LINE_HEIGHT};
+use crate::widgets::TextInput;
+use crate::{KeyboardAction, KeyboardKey, Rect, RgbaImage, RgbaImageRegion, UIController, UIEvent};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+// The number of log lines that are kept for display
+const MAX_LINES: usize = 200;
+
+static LOG: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Add a line to the debug console's log. Lines can be logged from
+/// any thread, whether or not the console is enabled.
+pub fn log(text: &str) {
+    let mut log = LOG.lock().unwrap_or_else(|error| error.into_inner());
+    log.extend(text.lines().map(|line| line.to_string()));
+
+    while log.len() > MAX_LINES {
+        log.pop_front();
+    }
+}
+
+/// A command that can be entered into the debug console.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsoleCommand {
+    pub name: String,
+    /// A short explanation, listed by the built-in `help` command.
+    pub description: String,
+}
+
+#[cfg_attr(not(feature = "window"), allow(dead_code))]
+pub(crate) struct Console {
+    commands: Vec<ConsoleCommand>,
+    input: TextInput,
+    clipboard: Clipboard,
+    open: bool,
+    // A copy of the latest frame, with the console drawn on top
+    overlay: RgbaImage,
+}
+
+#[cfg_attr(not(feature = "window"), allow(dead_code))]
+impl Console {
+    pub(crate) fn new(commands: Vec<ConsoleCommand>) -> Console {
+        Console {
+            commands,
+            input: TextInput::new(""),
+            clipboard: Clipboard::new(),
+            open: false,
+            overlay: RgbaImage::new(0, 0),
+        }
+    }
+
+    pub(crate) fn is_open(&self) -> bool {
+        self.open
+    }
+
+    // Opening and closing the console with the backtick key, and keeping
+    // keyboard events away from the controller while it's open. Returns
+    // true if the console needs to be redrawn.
+    pub(crate) fn process_events<T: UIController + ?Sized>(&mut self, ui_events: &mut Vec<UIEvent>, controller: &mut T) -> bool {
+        let was_open = self.open;
+        let mut entered = vec![];
+
+        ui_events.retain(|event| {
+            let event = match event {
+                UIEvent::Keyboard(event) => event,
+                UIEvent::Text(event) if self.open => {
+                    self.input.handle_text(event.character);
+                    return false;
+                },
+                _ => return true,
+            };
+
+            let pressed = event.action == KeyboardAction::Press;
+            if event.key == KeyboardKey::Grave {
+                self.open ^= pressed;
+                return false;
+            }
+
+            if self.open && pressed {
+                if event.key == KeyboardKey::Return {
+                    entered.push(self.input.text().to_string());
+                    self.input.set_text("");
+                } else {
+                    self.input.handle_key(event, &mut self.clipboard);
+                }
+            }
+
+            !self.open
+        });
+
+        for line in &entered {
+            self.run(line, controller);
+        }
+
+        self.open || was_open
+    }
+
+    fn run<T: UIController + ?Sized>(&mut self, line: &str, controller: &mut T) {
+        log(&format!("> {}", line));
+
+        let mut words = line.split_whitespace();
+        let name = match words.next() {
+            Some(name) => name,
+            None => return,
+        };
+        let args: Vec<&str> = words.collect();
+
+        match name {
+            "help" => {
+                log("help - list commands");
+                log("clear - erase the log");
+                for command in &self.commands {
+                    log(&format!("{} - {}", command.name, command.description));
+                }
+            },
+            "clear" => LOG.lock().unwrap_or_else(|error| error.into_inner()).clear(),
+            _ if self.commands.iter().any(|command| command.name == name) => {
+                log(&controller.run_command(name, &args));
+            },
+            _ => log(&format!("unknown command: {}", name)),
+        }
+    }
+
+    // Drawing the console over the top half of a frame
+    pub(crate) fn overlay(&mut self, frame: &RgbaImageRegion) -> RgbaImageRegion<'_> {
+        self.overlay.copy_region(frame);
+        let width = self.overlay.width();
+        let height = (self.overlay.height() / 2).max(LINE_HEIGHT * 2).min(self.overlay.height());
+
+        let mut shade = RgbaImage::new(width, height);
+        shade.fill((0, 0, 0, 200));
+        self.overlay.draw(&shade, 0, 0);
+
+        // Drawing the command being typed along the bottom of the console
+        let input_y = height as i32 - LINE_HEIGHT as i32 + 1;
+        let prompt = format!("> {}", self.input.text());
+        font::draw_text(&mut self.overlay, &prompt, 2, input_y, (255, 255, 255, 255));
+
+        let cursor_x = 2 + (self.input.cursor() as u32 + 2) * ADVANCE - 1;
+        self.overlay.fill_rect(Rect::new(cursor_x, input_y.max(1) as u32 - 1, 1, GLYPH_HEIGHT + 2), (250, 200, 60, 255));
+
+        // Drawing the newest log lines above it, from the bottom up
+        let log = LOG.lock().unwrap_or_else(|error| error.into_inner());
+        for (index, line) in log.iter().rev().enumerate() {
+            let y = input_y - (index as i32 + 1) * LINE_HEIGHT as i32;
+            if y < 0 { break }
+            font::draw_text(&mut self.overlay, line, 2, y, (200, 200, 200, 255));
+        }
+
+        self.overlay.as_region()
+    }
+}
+
+#[cfg(feature = "window")]
+#[test]
+fn _console() {
+    use crate::{KeyboardEvent, KeyboardModifiers};
+
+    struct Game { spawned: u32 }
+    impl UIController for Game {
+        fn next_frame(&mut self, _context: &mut crate::UIContext) -> Option<RgbaImageRegion<'_>> { None }
+        fn run_command(&mut self, _name: &str, args: &[&str]) -> String {
+            self.spawned += args.len() as u32;
+            format!("spawned {}", self.spawned)
+        }
+    }
+
+    let press = |key, character| UIEvent::Keyboard(KeyboardEvent {
+        device_id: 0,
+        key,
+        action: KeyboardAction::Press,
+        modifiers: KeyboardModifiers::default(),
+        character,
+    });
+
+    let mut game = Game { spawned: 0 };
+    let mut console = Console::new(vec![ConsoleCommand { name: "spawn".into(), description: "".into() }]);
+
+    // Keys reach the controller until the console is opened
+    let mut events = vec![press(KeyboardKey::A, Some('a')), press(KeyboardKey::Grave, Some('`'))];
+    console.process_events(&mut events, &mut game);
+    assert_eq!(events.len(), 1);
+    assert!(console.is_open());
+
+    console.input.set_text("spawn x y");
+    let mut events = vec![press(KeyboardKey::Return, None)];
+    console.process_events(&mut events, &mut game);
+    assert!(events.is_empty());
+    assert_eq!(game.spawned, 2);
+    assert!(LOG.lock().unwrap().iter().any(|line| line == "spawned 2"));
+}
@@ -0,0 +1,59 @@
+//! Native message boxes, for alerting the user or asking them to
+//! confirm an action, such as discarding unsaved changes.
+//!
+//! Native dialogs are shown on Windows and macOS when the `dialogs`
+//! feature is enabled. Elsewhere, these functions return `None`, and
+//! `Gui::message_box()` can be used to draw a dialog on the canvas instead.
+//!
+//! Native dialogs block the calling thread until they're dismissed.
+
+/// How serious the message in a dialog is, which determines its icon.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DialogLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+/// Show a message with an "OK" button, returning `None` if
+/// native dialogs aren't available.
+#[allow(unused_variables)]
+pub fn alert(title: &str, message: &str, level: DialogLevel) -> Option<()> {
+    #[cfg(all(feature = "dialogs", any(target_os = "windows", target_os = "macos")))]
+    {
+        native_dialog(title, message, level, rfd::MessageButtons::Ok);
+        Some(())
+    }
+
+    #[cfg(not(all(feature = "dialogs", any(target_os = "windows", target_os = "macos"))))]
+    None
+}
+
+/// Ask a question with "OK" and "Cancel" buttons, returning whether
+/// the user chose "OK", or `None` if native dialogs aren't available.
+#[allow(unused_variables)]
+pub fn confirm(title: &str, message: &str, level: DialogLevel) -> Option<bool> {
+    #[cfg(all(feature = "dialogs", any(target_os = "windows", target_os = "macos")))]
+    {
+        Some(native_dialog(title, message, level, rfd::MessageButtons::OkCancel))
+    }
+
+    #[cfg(not(all(feature = "dialogs", any(target_os = "windows", target_os = "macos"))))]
+    None
+}
+
+#[cfg(all(feature = "dialogs", any(target_os = "windows", target_os = "macos")))]
+fn native_dialog(title: &str, message: &str, level: DialogLevel, buttons: rfd::MessageButtons) -> bool {
+    let level = match level {
+        DialogLevel::Info => rfd::MessageLevel::Info,
+        DialogLevel::Warning => rfd::MessageLevel::Warning,
+        DialogLevel::Error => rfd::MessageLevel::Error,
+    };
+
+    rfd::MessageDialog::new()
+        .set_title(title)
+        .set_description(message)
+        .set_level(level)
+        .set_buttons(buttons)
+        .show()
+}
@@ -0,0 +1,113 @@
+//! Recording frames into a video file, by piping them to an `ffmpeg`
+//! process, which must be installed and available on the `PATH`.
+//!
+//! Every frame that's written becomes exactly one frame of the video,
+//! so the video plays back at the configured frame rate regardless of
+//! how long each frame took to draw.
+
+use crate::RgbaImageRegion;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+/// A video file being encoded by `ffmpeg`. The codec is chosen based on
+/// the file extension: VP9 for `.webm`, and H.264 for anything else.
+pub struct VideoExporter {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    dimensions: (u32, u32),
+}
+
+impl VideoExporter {
+    /// Start encoding a video with the given frame dimensions, replacing
+    /// the file at `path` if it already exists.
+    pub fn new(path: &Path, dimensions: (u32, u32), frames_per_second: u32) -> io::Result<VideoExporter> {
+        let mut child = Command::new("ffmpeg")
+            .args(ffmpeg_args(path, dimensions, frames_per_second))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let stdin = child.stdin.take();
+        Ok(VideoExporter { child, stdin, dimensions })
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+
+    /// Append a frame to the video. Frames must have the
+    /// dimensions that the exporter was created with.
+    pub fn write_frame(&mut self, frame: &RgbaImageRegion) -> io::Result<()> {
+        if (frame.width(), frame.height()) != self.dimensions {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame dimensions changed during export"));
+        }
+
+        match &mut self.stdin {
+            Some(stdin) => stdin.write_all(&frame.contiguous_bytes()),
+            None => Err(io::Error::new(io::ErrorKind::BrokenPipe, "ffmpeg is not accepting frames")),
+        }
+    }
+
+    /// Finish encoding, and wait for the video file to be written.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.close()
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        // Closing the pipe lets ffmpeg know that there are no more frames
+        if self.stdin.take().is_none() {
+            return Ok(());
+        }
+
+        let status = self.child.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!("ffmpeg exited with {}", status)))
+        }
+    }
+}
+
+impl Drop for VideoExporter {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+fn ffmpeg_args(path: &Path, dimensions: (u32, u32), frames_per_second: u32) -> Vec<String> {
+    let is_webm = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("webm"));
+
+    let mut args: Vec<String> = [
+        "-y", "-loglevel", "error",
+        "-f", "rawvideo",
+        "-pix_fmt", "rgba",
+        "-s", &format!("{}x{}", dimensions.0, dimensions.1),
+        "-r", &frames_per_second.to_string(),
+        "-i", "-",
+    ].iter().map(|arg| arg.to_string()).collect();
+
+    let codec: &[&str] = if is_webm {
+        &["-c:v", "libvpx-vp9", "-pix_fmt", "yuva420p", "-b:v", "0", "-crf", "20"]
+    } else {
+        // H.264 requires even dimensions, so odd ones are padded
+        &["-c:v", "libx264", "-pix_fmt", "yuv420p", "-crf", "18", "-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2"]
+    };
+
+    args.extend(codec.iter().map(|arg| arg.to_string()));
+    args.push(path.to_string_lossy().into_owned());
+    args
+}
+
+#[test]
+fn _ffmpeg_args() {
+    let args = ffmpeg_args(Path::new("sketch.webm"), (320, 240), 30);
+    assert!(args.windows(2).any(|pair| pair == ["-s", "320x240"]));
+    assert!(args.windows(2).any(|pair| pair == ["-r", "30"]));
+    assert!(args.contains(&"libvpx-vp9".to_string()));
+    assert_eq!(args.last().unwrap(), "sketch.webm");
+
+    let args = ffmpeg_args(Path::new("sketch.mp4"), (320, 240), 30);
+    assert!(args.contains(&"libx264".to_string()));
+}
@@ -0,0 +1,261 @@
+//! Image filters based on 2D kernel convolution.
+
+use crate::{for_each_row, RgbaImage, RgbaPixel};
+
+/// A grid of weights that determines how each output pixel
+/// is calculated from its neighborhood in the input image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Kernel {
+    width: u32,
+    height: u32,
+    weights: Vec<f32>,
+}
+
+impl Kernel {
+    /// Create a kernel from row-major weights.
+    /// Returns `None` if the dimensions aren't odd, or don't match `weights`.
+    pub fn new(width: u32, height: u32, weights: Vec<f32>) -> Option<Kernel> {
+        if width % 2 == 0 || height % 2 == 0 { return None; }
+        if weights.len() != (width * height) as usize { return None; }
+        Some(Kernel { width, height, weights })
+    }
+
+    /// A kernel that averages every pixel within `radius` equally.
+    pub fn box_blur(radius: u32) -> Kernel {
+        let size = radius * 2 + 1;
+        let weight = 1.0 / (size * size) as f32;
+        Kernel::new(size, size, vec![weight; (size * size) as usize]).unwrap()
+    }
+
+    /// A kernel that averages every pixel within `radius`,
+    /// favoring those closest to the center.
+    pub fn gaussian_blur(radius: u32) -> Kernel {
+        let size = radius * 2 + 1;
+        let sigma = (radius as f32 / 2.0).max(0.5);
+
+        let mut weights = Vec::with_capacity((size * size) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                let dx = x as f32 - radius as f32;
+                let dy = y as f32 - radius as f32;
+                weights.push((-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp());
+            }
+        }
+
+        // Normalizing so that the weights add up to 1
+        let total: f32 = weights.iter().sum();
+        let weights = weights.iter().map(|w| w / total).collect();
+
+        Kernel::new(size, size, weights).unwrap()
+    }
+
+    /// A kernel that exaggerates the difference between neighboring pixels.
+    pub fn sharpen() -> Kernel {
+        Kernel::new(3, 3, vec![
+             0.0, -1.0,  0.0,
+            -1.0,  5.0, -1.0,
+             0.0, -1.0,  0.0,
+        ]).unwrap()
+    }
+
+    /// A kernel that detects horizontal changes in brightness.
+    pub fn sobel_x() -> Kernel {
+        Kernel::new(3, 3, vec![
+            -1.0, 0.0, 1.0,
+            -2.0, 0.0, 2.0,
+            -1.0, 0.0, 1.0,
+        ]).unwrap()
+    }
+
+    /// A kernel that detects vertical changes in brightness.
+    pub fn sobel_y() -> Kernel {
+        Kernel::new(3, 3, vec![
+            -1.0, -2.0, -1.0,
+             0.0,  0.0,  0.0,
+             1.0,  2.0,  1.0,
+        ]).unwrap()
+    }
+}
+
+/// Sum the weighted neighborhood of a single pixel, treating pixels
+/// beyond the edges of the image as copies of the nearest edge pixel.
+/// Color channels are premultiplied by alpha so that transparent
+/// pixels don't bleed their (invisible) color into their neighbors.
+fn convolve_pixel(img: &RgbaImage, kernel: &Kernel, x: u32, y: u32) -> (f32, f32, f32, f32) {
+    let half_w = (kernel.width / 2) as i64;
+    let half_h = (kernel.height / 2) as i64;
+    let max_x = img.width() as i64 - 1;
+    let max_y = img.height() as i64 - 1;
+
+    let mut sum = (0.0, 0.0, 0.0, 0.0);
+
+    for ky in 0..kernel.height {
+        for kx in 0..kernel.width {
+            let weight = kernel.weights[(ky * kernel.width + kx) as usize];
+
+            let src_x = (x as i64 + kx as i64 - half_w).clamp(0, max_x);
+            let src_y = (y as i64 + ky as i64 - half_h).clamp(0, max_y);
+            let (r, g, b, a) = img.get_pixel(src_x as u32, src_y as u32).unwrap();
+
+            let alpha = a as f32 / 255.0;
+            sum.0 += weight * r as f32 * alpha;
+            sum.1 += weight * g as f32 * alpha;
+            sum.2 += weight * b as f32 * alpha;
+            sum.3 += weight * a as f32;
+        }
+    }
+
+    sum
+}
+
+/// Apply a kernel to every pixel of an image, producing a new image.
+pub fn convolve(img: &RgbaImage, kernel: &Kernel) -> RgbaImage {
+    let mut new_img = RgbaImage::new(img.width(), img.height());
+    let width = new_img.width();
+
+    for_each_row(&mut new_img.bytes, width, |y, row| {
+        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+            let (r, g, b, a) = convolve_pixel(img, kernel, x as u32, y);
+            let a = a.clamp(0.0, 255.0);
+
+            // Converting back from premultiplied alpha
+            let (r, g, b, a): RgbaPixel = if a > 0.0 {
+                let alpha = a / 255.0;
+                (
+                    (r / alpha).round().clamp(0.0, 255.0) as u8,
+                    (g / alpha).round().clamp(0.0, 255.0) as u8,
+                    (b / alpha).round().clamp(0.0, 255.0) as u8,
+                    a.round() as u8,
+                )
+            } else {
+                (0, 0, 0, 0)
+            };
+
+            pixel.copy_from_slice(&[r, g, b, a]);
+        }
+    });
+
+    new_img
+}
+
+/// Blur an image by averaging every pixel within `radius` equally.
+pub fn box_blur(img: &RgbaImage, radius: u32) -> RgbaImage {
+    convolve(img, &Kernel::box_blur(radius))
+}
+
+/// Blur an image smoothly, weighting nearby pixels most heavily.
+pub fn gaussian_blur(img: &RgbaImage, radius: u32) -> RgbaImage {
+    convolve(img, &Kernel::gaussian_blur(radius))
+}
+
+/// Make the edges within an image more pronounced.
+pub fn sharpen(img: &RgbaImage) -> RgbaImage {
+    convolve(img, &Kernel::sharpen())
+}
+
+/// Produce a grayscale image whose brightness indicates the strength of
+/// the edges in `img`. The alpha channel of `img` is preserved.
+pub fn sobel(img: &RgbaImage) -> RgbaImage {
+    let horizontal = Kernel::sobel_x();
+    let vertical = Kernel::sobel_y();
+    let mut new_img = RgbaImage::new(img.width(), img.height());
+    let width = new_img.width();
+
+    for_each_row(&mut new_img.bytes, width, |y, row| {
+        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+            let (gx_r, gx_g, gx_b, _) = convolve_pixel(img, &horizontal, x as u32, y);
+            let (gy_r, gy_g, gy_b, _) = convolve_pixel(img, &vertical, x as u32, y);
+
+            // Measuring the change in brightness in both directions
+            let gx = (gx_r + gx_g + gx_b) / 3.0;
+            let gy = (gy_r + gy_g + gy_b) / 3.0;
+            let magnitude = (gx * gx + gy * gy).sqrt().round().min(255.0) as u8;
+
+            let alpha = img.get_pixel(x as u32, y).unwrap().3;
+            pixel.copy_from_slice(&[magnitude, magnitude, magnitude, alpha]);
+        }
+    });
+
+    new_img
+}
+
+#[test]
+fn _box_blur() {
+    let mut img = RgbaImage::new(3, 1);
+    img.fill((0, 0, 0, 255));
+    img.set_pixel(1, 0, (255, 255, 255, 255));
+
+    let blurred = box_blur(&img, 1);
+    assert_eq!(blurred.get_pixel(1, 0), Some((85, 85, 85, 255)));
+}
+
+#[test]
+fn _kernel_new() {
+    assert!(Kernel::new(3, 3, vec![0.0; 9]).is_some());
+    assert!(Kernel::new(1, 5, vec![0.0; 5]).is_some());
+    assert_eq!(Kernel::new(2, 3, vec![0.0; 6]), None);
+    assert_eq!(Kernel::new(3, 3, vec![0.0; 8]), None);
+}
+
+#[test]
+fn _gaussian_blur() {
+    let kernel = Kernel::gaussian_blur(2);
+    let total: f32 = kernel.weights.iter().sum();
+    assert!((total - 1.0).abs() < 1e-5);
+
+    // The center is weighted most, and the weights are symmetric
+    let center = kernel.weights[12];
+    assert!(kernel.weights.iter().all(|&weight| weight <= center));
+    assert_eq!(kernel.weights[0], kernel.weights[24]);
+    assert_eq!(kernel.weights[2], kernel.weights[10]);
+
+    // Blurring a single color leaves it unchanged
+    let mut img = RgbaImage::new(4, 4);
+    img.fill((30, 60, 90, 255));
+    assert_eq!(gaussian_blur(&img, 2), img);
+}
+
+#[test]
+fn _sharpen() {
+    let mut img = RgbaImage::new(3, 1);
+    img.fill((50, 50, 50, 255));
+    img.set_pixel(1, 0, (100, 100, 100, 255));
+
+    // The center pixel is exaggerated, and the edges beyond the image
+    // are copies of it: 5 * 100 - 50 - 50 - 100 - 100
+    let sharpened = sharpen(&img);
+    assert_eq!(sharpened.get_pixel(1, 0), Some((200, 200, 200, 255)));
+    assert_eq!(sharpened.get_pixel(0, 0), Some((0, 0, 0, 255)));
+}
+
+#[test]
+fn _sobel() {
+    let mut img = RgbaImage::new(3, 3);
+    img.fill((255, 255, 255, 200));
+    for y in 0..3 {
+        img.set_pixel(0, y, (0, 0, 0, 200));
+    }
+
+    // Only the edge between the black column and the white ones is found,
+    // and the alpha channel is kept as it is
+    let edges = sobel(&img);
+    assert_eq!(edges.get_pixel(1, 1), Some((255, 255, 255, 200)));
+    assert_eq!(edges.get_pixel(2, 1), Some((0, 0, 0, 200)));
+}
+
+#[test]
+fn _convolve() {
+    // A transparent pixel between two red ones doesn't darken them
+    // when blurred, since its color is invisible
+    let mut img = RgbaImage::new(3, 1);
+    img.fill((255, 0, 0, 255));
+    img.set_pixel(1, 0, (0, 255, 0, 0));
+
+    let blurred = convolve(&img, &Kernel::box_blur(1));
+    assert_eq!(blurred.get_pixel(1, 0), Some((255, 0, 0, 170)));
+
+    // A kernel with a single weight copies the image
+    let identity = Kernel::new(1, 1, vec![1.0]).unwrap();
+    img.set_pixel(1, 0, (10, 20, 30, 40));
+    assert_eq!(convolve(&img, &identity).get_pixel(1, 0), Some((10, 20, 30, 40)));
+}
@@ -0,0 +1,92 @@
+//! A tiny built-in bitmap font, for drawing text onto images without
+//! needing any font files.
+
+use crate::{RgbaImage, RgbaPixel};
+
+/// The width of every glyph, in pixels.
+pub const GLYPH_WIDTH: u32 = 3;
+
+/// The height of every glyph, in pixels.
+pub const GLYPH_HEIGHT: u32 = 5;
+
+/// The horizontal distance from the start of one glyph to the next.
+pub const ADVANCE: u32 = GLYPH_WIDTH + 1;
+
+/// The vertical distance from the top of one line of text to the next.
+pub const LINE_HEIGHT: u32 = GLYPH_HEIGHT + 2;
+
+// Glyphs for printable ASCII characters, starting with space. Each glyph
+// is 15 bits, read row by row from the top-left. Lowercase letters are
+// drawn the same as capitals.
+const GLYPHS: [u16; 95] = [
+    0x0000, 0x2482, 0x5A00, 0x5F7D, 0x3C9E, 0x42A1, 0x2AAB, 0x2400,  //  !"#$%&'
+    0x1491, 0x4494, 0x0AA8, 0x05D0, 0x0014, 0x01C0, 0x0002, 0x12A4,  // ()*+,-./
+    0x7B6F, 0x2C97, 0x73E7, 0x72CF, 0x5BC9, 0x79CF, 0x79EF, 0x7252,  // 01234567
+    0x7BEF, 0x7BCF, 0x0410, 0x0414, 0x1511, 0x0E38, 0x4454, 0x6282,  // 89:;<=>?
+    0x2BE3, 0x2BED, 0x6BAE, 0x3923, 0x6B6E, 0x79A7, 0x79A4, 0x396B,  // @ABCDEFG
+    0x5BED, 0x7497, 0x126A, 0x5BAD, 0x4927, 0x5FED, 0x6B6D, 0x2B6A,  // HIJKLMNO
+    0x6BA4, 0x2B7B, 0x6BAD, 0x388E, 0x7492, 0x5B6F, 0x5B6A, 0x5BFD,  // PQRSTUVW
+    0x5AAD, 0x5A92, 0x72A7, 0x6926, 0x4889, 0x324B, 0x2A00, 0x0007,  // XYZ[\]^_
+    0x4400, 0x2BED, 0x6BAE, 0x3923, 0x6B6E, 0x79A7, 0x79A4, 0x396B,  // `abcdefg
+    0x5BED, 0x7497, 0x126A, 0x5BAD, 0x4927, 0x5FED, 0x6B6D, 0x2B6A,  // hijklmno
+    0x6BA4, 0x2B7B, 0x6BAD, 0x388E, 0x7492, 0x5B6F, 0x5B6A, 0x5BFD,  // pqrstuvw
+    0x5AAD, 0x5A92, 0x72A7, 0x3593, 0x2492, 0x64D6, 0x0CC0,  // xyz{|}~
+];
+
+// The glyph for a character, with unsupported characters drawn as '?'
+fn glyph(character: char) -> u16 {
+    match character {
+        ' '..='~' => GLYPHS[character as usize - 32],
+        _ => GLYPHS['?' as usize - 32],
+    }
+}
+
+/// The size of the area that `draw_text()` would cover.
+pub fn text_size(text: &str) -> (u32, u32) {
+    let lines = text.split('\n');
+    let longest = lines.clone().map(|line| line.chars().count() as u32).max().unwrap_or(0);
+    let line_count = lines.count() as u32;
+
+    let width = (longest * ADVANCE).saturating_sub(1);
+    let height = line_count * LINE_HEIGHT - (LINE_HEIGHT - GLYPH_HEIGHT);
+    (width, height)
+}
+
+/// Draw text with its top-left corner at the given point.
+/// Newlines start a new line of text. Parts of the text that fall
+/// outside of the image or the clipping rectangle are ignored.
+pub fn draw_text(img: &mut RgbaImage, text: &str, x: i32, y: i32, color: RgbaPixel) {
+    for (line_index, line) in text.split('\n').enumerate() {
+        let top = y + (line_index as u32 * LINE_HEIGHT) as i32;
+
+        for (char_index, character) in line.chars().enumerate() {
+            let left = x + (char_index as u32 * ADVANCE) as i32;
+            let glyph = glyph(character);
+
+            for row in 0..GLYPH_HEIGHT {
+                for column in 0..GLYPH_WIDTH {
+                    let bit = 14 - (row * GLYPH_WIDTH + column);
+                    if glyph >> bit & 1 == 0 { continue }
+
+                    let (px, py) = (left + column as i32, top + row as i32);
+                    if px >= 0 && py >= 0 {
+                        img.set_pixel(px as u32, py as u32, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn _draw_text() {
+    assert_eq!(text_size("HI"), (7, 5));
+    assert_eq!(text_size("A\nBC"), (7, 12));
+
+    let white = (255, 255, 255, 255);
+    let mut img = RgbaImage::new(7, 5);
+    draw_text(&mut img, "HI", 0, 0, white);
+
+    let top_row: Vec<bool> = (0..7).map(|x| img.get_pixel(x, 0) == Some(white)).collect();
+    assert_eq!(top_row, [true, false, true, false, true, true, true]);
+}
@@ -0,0 +1,301 @@
+//! Gamepads, whose buttons and axes are reported as `UIEvent::Gamepad`.
+//!
+//! Gamepads are read when the `gamepad` feature is enabled, and ignored
+//! otherwise. Every gamepad is normalized to the layout of an Xbox-style
+//! controller, using the community SDL_GameControllerDB mappings, so
+//! `GamepadButton::A` is always the bottom face button, whatever the
+//! vendor calls it. Controllers missing from the database can be mapped
+//! with `UIBlueprint::gamepad_mapping()`, or with the
+//! `SDL_GAMECONTROLLERCONFIG` environment variable, in the same format.
+//!
+//! Joysticks that don't fit the gamepad layout, such as flight sticks,
+//! throttles and custom hardware, can be read with
+//! `UIBlueprint::raw_joysticks(true)`, which reports every button and axis
+//! as a `UIEvent::Joystick`, identified by the code its driver gives it.
+//! Hats are reported as pairs of axes, which are -1.0, 0.0 or 1.0.
+//!
+//! Gamepads don't wake a window that is rendering on demand, so their
+//! events are only delivered along with the next frame.
+//!
+//! Gamepads with force feedback can be rumbled with
+//! `UIContext::set_rumble()`. Rumbling a gamepad that can't does nothing.
+
+use crate::UIEvent;
+use std::time::Duration;
+
+/// A button on a gamepad, named after its position on an Xbox-style controller.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    /// The bottom face button.
+    A,
+    /// The right face button.
+    B,
+    /// The left face button.
+    X,
+    /// The top face button.
+    Y,
+    LeftBumper,
+    RightBumper,
+    Back,
+    Start,
+    /// The button in the middle of the controller, usually with a logo.
+    Guide,
+    /// Pressing down on the left stick.
+    LeftStick,
+    /// Pressing down on the right stick.
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl GamepadButton {
+    pub const ALL: [GamepadButton; 15] = [
+        GamepadButton::A, GamepadButton::B, GamepadButton::X, GamepadButton::Y,
+        GamepadButton::LeftBumper, GamepadButton::RightBumper,
+        GamepadButton::Back, GamepadButton::Start, GamepadButton::Guide,
+        GamepadButton::LeftStick, GamepadButton::RightStick,
+        GamepadButton::DPadUp, GamepadButton::DPadDown, GamepadButton::DPadLeft, GamepadButton::DPadRight,
+    ];
+}
+
+/// An analog input on a gamepad. Sticks range from -1.0 to 1.0, with
+/// positive values to the right and up. Triggers range from 0.0 when
+/// released to 1.0 when fully pressed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl GamepadAxis {
+    pub const ALL: [GamepadAxis; 6] = [
+        GamepadAxis::LeftStickX, GamepadAxis::LeftStickY,
+        GamepadAxis::RightStickX, GamepadAxis::RightStickY,
+        GamepadAxis::LeftTrigger, GamepadAxis::RightTrigger,
+    ];
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// Something that happened to a gamepad.
+pub enum GamepadAction {
+    Connected,
+    Disconnected,
+    Press(GamepadButton),
+    Release(GamepadButton),
+    Axis(GamepadAxis, f32),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// The identity of a gamepad, and what happened to it. Identifiers
+/// are reused when a gamepad is reconnected.
+pub struct GamepadEvent {
+    pub gamepad_id: u64,
+    pub action: GamepadAction,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// An unmapped input on a joystick, identified by its driver's code.
+pub enum JoystickAction {
+    Button { code: u32, pressed: bool },
+    Axis { code: u32, value: f32 },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// The identity of a joystick, and which of its inputs changed.
+/// Joysticks share identifiers with gamepads, and are announced
+/// by the same `GamepadAction::Connected` events.
+pub struct JoystickEvent {
+    pub joystick_id: u64,
+    pub action: JoystickAction,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// Vibration of a gamepad's motors, queued with `UIContext::set_rumble()`.
+/// Each motor's strength is between 0.0 and 1.0. A new rumble replaces
+/// the one the gamepad is already playing, if any.
+pub struct Rumble {
+    pub gamepad_id: u64,
+    /// The strength of the heavy, low frequency motor.
+    pub strong: f32,
+    /// The strength of the light, high frequency motor.
+    pub weak: f32,
+    pub duration: Duration,
+}
+
+/// Every connected gamepad, and the mappings that normalize them.
+#[cfg_attr(not(feature = "window"), allow(dead_code))]
+pub(crate) struct Gamepads {
+    #[cfg(feature = "gamepad")]
+    gilrs: Option<gilrs::Gilrs>,
+    #[cfg(feature = "gamepad")]
+    raw: bool,
+    // The rumble each gamepad is playing, which stops if it's dropped
+    #[cfg(feature = "gamepad")]
+    rumbles: Vec<(u64, gilrs::ff::Effect)>,
+}
+
+impl Gamepads {
+    /// Start listening for gamepads, with custom mappings taking
+    /// priority over those in the database, and reporting raw
+    /// joystick inputs too if `raw` is true.
+    #[cfg_attr(not(feature = "window"), allow(dead_code))]
+    #[allow(unused_variables)]
+    pub(crate) fn new(mappings: &[String], raw: bool) -> Gamepads {
+        #[cfg(feature = "gamepad")]
+        {
+            let gilrs = gilrs::GilrsBuilder::new()
+                .add_included_mappings(true)
+                .add_env_mappings(true)
+                .add_mappings(&mappings.join("\n"))
+                .build();
+
+            match gilrs {
+                Ok(gilrs) => Gamepads { gilrs: Some(gilrs), raw, rumbles: vec![] },
+                Err(error) => {
+                    log_error!("unable to read gamepads: {}", error);
+                    Gamepads { gilrs: None, raw, rumbles: vec![] }
+                },
+            }
+        }
+
+        #[cfg(not(feature = "gamepad"))]
+        Gamepads {}
+    }
+
+    /// Collect everything that has happened to gamepads since the last call.
+    #[cfg_attr(not(feature = "window"), allow(dead_code))]
+    #[allow(unused_variables, clippy::ptr_arg)]
+    pub(crate) fn apply_gamepad_events(&mut self, ui_events: &mut Vec<UIEvent>) {
+        #[cfg(feature = "gamepad")]
+        if let Some(gilrs) = &mut self.gilrs {
+            while let Some(event) = gilrs.next_event() {
+                if self.raw {
+                    apply_joystick_event(&event, ui_events);
+                }
+
+                let action = match event.event {
+                    gilrs::EventType::Connected => Some(GamepadAction::Connected),
+                    gilrs::EventType::Disconnected => Some(GamepadAction::Disconnected),
+                    gilrs::EventType::ButtonPressed(button, _) => translate_button(button).map(GamepadAction::Press),
+                    gilrs::EventType::ButtonReleased(button, _) => translate_button(button).map(GamepadAction::Release),
+                    gilrs::EventType::AxisChanged(axis, value, _) => translate_axis(axis).map(|axis| GamepadAction::Axis(axis, value)),
+
+                    // Analog triggers are reported as buttons with a value
+                    gilrs::EventType::ButtonChanged(gilrs::Button::LeftTrigger2, value, _) => {
+                        Some(GamepadAction::Axis(GamepadAxis::LeftTrigger, value))
+                    },
+                    gilrs::EventType::ButtonChanged(gilrs::Button::RightTrigger2, value, _) => {
+                        Some(GamepadAction::Axis(GamepadAxis::RightTrigger, value))
+                    },
+                    _ => None,
+                };
+
+                if let Some(action) = action {
+                    let gamepad_id = usize::from(event.id) as u64;
+                    ui_events.push(UIEvent::Gamepad(GamepadEvent { gamepad_id, action }));
+                }
+            }
+        }
+    }
+
+    /// Start vibrating a gamepad, replacing whatever rumble it's playing.
+    #[cfg_attr(not(feature = "window"), allow(dead_code))]
+    #[allow(unused_variables)]
+    pub(crate) fn set_rumble(&mut self, rumble: Rumble) {
+        #[cfg(feature = "gamepad")]
+        if let Some(gilrs) = &mut self.gilrs {
+            use gilrs::ff::{BaseEffect, BaseEffectType, EffectBuilder, Repeat, Replay, Ticks};
+
+            self.rumbles.retain(|(gamepad_id, _)| *gamepad_id != rumble.gamepad_id);
+
+            let gamepad = gilrs.gamepads().find(|(id, _)| usize::from(*id) as u64 == rumble.gamepad_id);
+            let id = match gamepad {
+                Some((id, gamepad)) if gamepad.is_ff_supported() => id,
+                _ => return,
+            };
+
+            let duration = Ticks::from_ms(rumble.duration.as_millis().min(u32::MAX as u128) as u32);
+            let scheduling = Replay { after: Ticks::from_ms(0), play_for: duration, with_delay: Ticks::from_ms(0) };
+            let magnitude = |strength: f32| (strength.clamp(0.0, 1.0) * u16::MAX as f32) as u16;
+
+            let effect = EffectBuilder::new()
+                .add_effect(BaseEffect {
+                    kind: BaseEffectType::Strong { magnitude: magnitude(rumble.strong) },
+                    scheduling,
+                    envelope: Default::default(),
+                })
+                .add_effect(BaseEffect {
+                    kind: BaseEffectType::Weak { magnitude: magnitude(rumble.weak) },
+                    scheduling,
+                    envelope: Default::default(),
+                })
+                .repeat(Repeat::For(duration))
+                .gamepads(&[id])
+                .finish(gilrs)
+                .and_then(|effect| effect.play().map(|()| effect));
+
+            match effect {
+                Ok(effect) => self.rumbles.push((rumble.gamepad_id, effect)),
+                Err(error) => log_warning!("unable to rumble gamepad: {}", error),
+            }
+        }
+    }
+}
+
+// Reporting the driver's codes, whether or not the input is mapped
+#[cfg(feature = "gamepad")]
+fn apply_joystick_event(event: &gilrs::Event, ui_events: &mut Vec<UIEvent>) {
+    let action = match event.event {
+        gilrs::EventType::ButtonPressed(_, code) => JoystickAction::Button { code: code.into_u32(), pressed: true },
+        gilrs::EventType::ButtonReleased(_, code) => JoystickAction::Button { code: code.into_u32(), pressed: false },
+        gilrs::EventType::AxisChanged(_, value, code) => JoystickAction::Axis { code: code.into_u32(), value },
+
+        // Some drivers report analog inputs as buttons with a value
+        gilrs::EventType::ButtonChanged(button, value, code) if button.is_trigger() || button == gilrs::Button::Unknown => {
+            JoystickAction::Axis { code: code.into_u32(), value }
+        },
+        _ => return,
+    };
+
+    let joystick_id = usize::from(event.id) as u64;
+    ui_events.push(UIEvent::Joystick(JoystickEvent { joystick_id, action }));
+}
+
+#[cfg(feature = "gamepad")]
+fn translate_button(button: gilrs::Button) -> Option<GamepadButton> {
+    Some(match button {
+        gilrs::Button::South => GamepadButton::A,
+        gilrs::Button::East => GamepadButton::B,
+        gilrs::Button::West => GamepadButton::X,
+        gilrs::Button::North => GamepadButton::Y,
+        gilrs::Button::LeftTrigger => GamepadButton::LeftBumper,
+        gilrs::Button::RightTrigger => GamepadButton::RightBumper,
+        gilrs::Button::Select => GamepadButton::Back,
+        gilrs::Button::Start => GamepadButton::Start,
+        gilrs::Button::Mode => GamepadButton::Guide,
+        gilrs::Button::LeftThumb => GamepadButton::LeftStick,
+        gilrs::Button::RightThumb => GamepadButton::RightStick,
+        gilrs::Button::DPadUp => GamepadButton::DPadUp,
+        gilrs::Button::DPadDown => GamepadButton::DPadDown,
+        gilrs::Button::DPadLeft => GamepadButton::DPadLeft,
+        gilrs::Button::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    })
+}
+
+#[cfg(feature = "gamepad")]
+fn translate_axis(axis: gilrs::Axis) -> Option<GamepadAxis> {
+    Some(match axis {
+        gilrs::Axis::LeftStickX => GamepadAxis::LeftStickX,
+        gilrs::Axis::LeftStickY => GamepadAxis::LeftStickY,
+        gilrs::Axis::RightStickX => GamepadAxis::RightStickX,
+        gilrs::Axis::RightStickY => GamepadAxis::RightStickY,
+        _ => return None,
+    })
+}
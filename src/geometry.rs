@@ -0,0 +1,112 @@
+// Remembering the size, position and maximized state of a window between
+// runs, for blueprints that opt in with `UIBlueprint::remember_geometry()`.
+// Geometry is saved as a small text file in the platform's configuration
+// directory, such as `~/.config/<app>/window.txt` on Linux.
+
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct WindowGeometry {
+    // The inner size of the window while it isn't maximized, in logical pixels
+    pub(crate) size: (u32, u32),
+    pub(crate) position: Option<(i32, i32)>,
+    // The monitor that the window was on, which it's only
+    // returned to if that monitor is still connected
+    pub(crate) monitor: Option<String>,
+    pub(crate) maximized: bool,
+}
+
+impl WindowGeometry {
+    pub(crate) fn encode(&self) -> String {
+        let mut text = format!("size {} {}\n", self.size.0, self.size.1);
+        if let Some((x, y)) = self.position {
+            text += &format!("position {} {}\n", x, y);
+        }
+        if let Some(monitor) = &self.monitor {
+            text += &format!("monitor {}\n", monitor);
+        }
+        text += &format!("maximized {}\n", if self.maximized { 1 } else { 0 });
+        text
+    }
+
+    // Returning `None` if the size is missing or anything is malformed,
+    // so that a damaged file is ignored rather than half applied
+    pub(crate) fn decode(text: &str) -> Option<WindowGeometry> {
+        let mut size = None;
+        let mut geometry = WindowGeometry { size: (0, 0), position: None, monitor: None, maximized: false };
+
+        fn pair<T: std::str::FromStr>(value: &str) -> Option<(T, T)> {
+            let (a, b) = value.split_once(' ')?;
+            Some((a.parse().ok()?, b.parse().ok()?))
+        }
+
+        for line in text.lines().filter(|line| !line.is_empty()) {
+            let (key, value) = line.split_once(' ')?;
+
+            match key {
+                "size" => size = Some(pair(value)?),
+                "position" => geometry.position = Some(pair(value)?),
+                "monitor" => geometry.monitor = Some(value.to_string()),
+                "maximized" => geometry.maximized = value == "1",
+                _ => return None,
+            }
+        }
+
+        geometry.size = size.filter(|&(width, height)| width > 0 && height > 0)?;
+        Some(geometry)
+    }
+}
+
+#[test]
+fn _window_geometry() {
+    let geometry = WindowGeometry {
+        size: (1024, 768),
+        position: Some((-1280, 40)),
+        monitor: Some("DELL U2720Q".to_string()),
+        maximized: true,
+    };
+    assert_eq!(WindowGeometry::decode(&geometry.encode()), Some(geometry));
+
+    let minimal = WindowGeometry::decode("size 640 480\n").unwrap();
+    assert_eq!((minimal.position, minimal.monitor, minimal.maximized), (None, None, false));
+
+    assert_eq!(WindowGeometry::decode("position 0 0\n"), None);
+    assert_eq!(WindowGeometry::decode("size 0 480\n"), None);
+    assert_eq!(WindowGeometry::decode("size 640 480\nzoom 2\n"), None);
+}
+
+// Where an application's geometry is kept, following each platform's
+// convention for configuration files
+pub(crate) fn geometry_path(app_name: &str) -> Option<PathBuf> {
+    let env_path = |name| std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+
+    let config_directory = if cfg!(target_os = "windows") {
+        env_path("APPDATA")?
+    } else if cfg!(target_os = "macos") {
+        env_path("HOME")?.join("Library").join("Application Support")
+    } else {
+        env_path("XDG_CONFIG_HOME").or_else(|| Some(env_path("HOME")?.join(".config")))?
+    };
+
+    Some(config_directory.join(app_name).join("window.txt"))
+}
+
+pub(crate) fn load(app_name: &str) -> Option<WindowGeometry> {
+    let text = std::fs::read_to_string(geometry_path(app_name)?).ok()?;
+    WindowGeometry::decode(&text)
+}
+
+pub(crate) fn save(app_name: &str, geometry: &WindowGeometry) {
+    let path = match geometry_path(app_name) {
+        Some(path) => path,
+        None => return,
+    };
+
+    let result = path.parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&path, geometry.encode()));
+
+    if let Err(error) = result {
+        log_error!("couldn't save window geometry: {}", error);
+    }
+}
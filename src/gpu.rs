@@ -0,0 +1,294 @@
+//! Image operations that run on the GPU, after a frame has been uploaded
+//! and before it's displayed. Only available with the `gpu` feature.
+//!
+//! Effects are declared with `UIContext::set_gpu_effects()`, or
+//! `UIHandle::set_gpu_effects()`, and apply to every frame displayed
+//! until they're changed. They're much faster than the equivalent CPU
+//! functions on large canvases, but only affect what's displayed; the
+//! frame returned by the controller is left unchanged, and so are
+//! screenshots, recordings and remote streams.
+
+use crate::{OpenUiError, RgbaPixel};
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::texture::{MipmapsOption, RawImage2d, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction};
+use glium::Surface;
+
+/// An operation applied to frames on the GPU. Effects are applied in
+/// the order that they're declared, each to the result of the last.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GpuEffect {
+    /// Resample the frame to new dimensions, either smoothly, or keeping
+    /// hard pixel edges. The frame is still letterboxed according to its
+    /// original dimensions.
+    Scale { width: u32, height: u32, smooth: bool },
+    /// Blur the frame, like `filters::gaussian_blur()`.
+    GaussianBlur { radius: u32 },
+    /// Replace every color with the closest one from the palette, like
+    /// `palette::quantize()` without dithering. Only the first 256
+    /// colors are used.
+    PaletteMap(Vec<RgbaPixel>),
+}
+
+const MAX_PALETTE_SIZE: usize = 256;
+
+const VERTEX_SHADER_SRC: &str = r#"
+    #version 150
+
+    in vec2 position;
+    out vec2 v_src;
+
+    void main() {
+        v_src = position * 0.5 + 0.5;
+        gl_Position = vec4(position, 0.0, 1.0);
+    }
+"#;
+
+const COPY_SHADER_SRC: &str = r#"
+    #version 150
+
+    in vec2 v_src;
+    out vec4 color;
+
+    uniform sampler2D sampler;
+
+    void main() {
+        color = texture(sampler, v_src);
+    }
+"#;
+
+// Blurring along one axis at a time, which is equivalent to
+// a two-dimensional gaussian kernel, but far cheaper
+const BLUR_SHADER_SRC: &str = r#"
+    #version 150
+
+    in vec2 v_src;
+    out vec4 color;
+
+    uniform sampler2D sampler;
+    uniform vec2 step;
+    uniform int radius;
+    uniform float sigma;
+
+    void main() {
+        vec4 sum = vec4(0.0);
+        float total = 0.0;
+
+        for (int i = -radius; i <= radius; i++) {
+            float weight = exp(-float(i * i) / (2.0 * sigma * sigma));
+            sum += texture(sampler, v_src + step * float(i)) * weight;
+            total += weight;
+        }
+
+        color = sum / total;
+    }
+"#;
+
+const PALETTE_SHADER_SRC: &str = r#"
+    #version 150
+
+    in vec2 v_src;
+    out vec4 color;
+
+    uniform sampler2D sampler;
+    uniform sampler2D palette;
+    uniform int palette_size;
+
+    void main() {
+        vec4 pixel = texture(sampler, v_src);
+        vec3 nearest = pixel.rgb;
+        float nearest_distance = 4.0;
+
+        for (int i = 0; i < palette_size; i++) {
+            vec3 candidate = texelFetch(palette, ivec2(i, 0), 0).rgb;
+            vec3 difference = candidate - pixel.rgb;
+            float distance = dot(difference, difference);
+            if (distance < nearest_distance) {
+                nearest = candidate;
+                nearest_distance = distance;
+            }
+        }
+
+        color = vec4(nearest, pixel.a);
+    }
+"#;
+
+#[derive(Copy, Clone)]
+struct EffectVertex {
+    position: [f32; 2],
+}
+
+implement_vertex!(EffectVertex, position);
+
+// Everything needed to apply effects, including a pair of textures
+// that each pass alternates between, which are reused between frames
+pub(crate) struct EffectChain {
+    vertex_buffer: glium::VertexBuffer<EffectVertex>,
+    indices: glium::IndexBuffer<u16>,
+    copy_program: glium::Program,
+    blur_program: glium::Program,
+    palette_program: glium::Program,
+    targets: [Option<Texture2d>; 2],
+    // Which target holds the result of the latest effects, if any
+    result: Option<usize>,
+    palette: Option<(Vec<RgbaPixel>, Texture2d)>,
+}
+
+impl EffectChain {
+    pub(crate) fn new(display: &glium::Display) -> Result<EffectChain, OpenUiError> {
+        let shape = [
+            EffectVertex { position: [-1.0, -1.0] },
+            EffectVertex { position: [ 1.0, -1.0] },
+            EffectVertex { position: [ 1.0,  1.0] },
+            EffectVertex { position: [-1.0,  1.0] },
+        ];
+
+        let program = |fragment_shader| crate::shader::program(display, VERTEX_SHADER_SRC, fragment_shader);
+
+        Ok(EffectChain {
+            vertex_buffer: glium::VertexBuffer::new(display, &shape)?,
+            indices: glium::IndexBuffer::new(display, glium::index::PrimitiveType::TrianglesList, &[0, 1, 2, 2, 3, 0])?,
+            copy_program: program(COPY_SHADER_SRC)?,
+            blur_program: program(BLUR_SHADER_SRC)?,
+            palette_program: program(PALETTE_SHADER_SRC)?,
+            targets: [None, None],
+            result: None,
+            palette: None,
+        })
+    }
+
+    // Applying each effect in turn, leaving the result to be retrieved
+    // with `result()`. If an effect fails, the frame is left unchanged.
+    pub(crate) fn apply(&mut self, display: &glium::Display, source: &Texture2d, effects: &[GpuEffect]) -> Result<(), OpenUiError> {
+        self.result = None;
+        self.result = self.apply_effects(display, source, effects)?;
+        Ok(())
+    }
+
+    fn apply_effects(&mut self, display: &glium::Display, source: &Texture2d, effects: &[GpuEffect]) -> Result<Option<usize>, OpenUiError> {
+        let mut current = None;
+
+        for effect in effects {
+            let input = self.input(current, source);
+            let (width, height) = (input.width(), input.height());
+
+            match effect {
+                GpuEffect::Scale { width: 0, .. } | GpuEffect::Scale { height: 0, .. } => {},
+                GpuEffect::Scale { width, height, smooth } => {
+                    current = Some(self.scale(display, current, source, (*width, *height), *smooth)?);
+                },
+                GpuEffect::GaussianBlur { radius: 0 } => {},
+                GpuEffect::GaussianBlur { radius } => {
+                    let radius = *radius as i32;
+                    let sigma = (radius as f32 / 2.0).max(0.5);
+                    for step in [[1.0 / width as f32, 0.0], [0.0, 1.0 / height as f32]] {
+                        let output = self.prepare_target(display, current, (width, height))?;
+                        let input = self.input(current, source);
+                        let uniforms = uniform! {
+                            sampler: nearest(input),
+                            step: step,
+                            radius: radius,
+                            sigma: sigma,
+                        };
+                        self.draw(display, output, &self.blur_program, &uniforms)?;
+                        current = Some(output);
+                    }
+                },
+                GpuEffect::PaletteMap(palette) if palette.is_empty() => {},
+                GpuEffect::PaletteMap(palette) => {
+                    let palette = &palette[..palette.len().min(MAX_PALETTE_SIZE)];
+                    self.prepare_palette(display, palette)?;
+
+                    let output = self.prepare_target(display, current, (width, height))?;
+                    let input = self.input(current, source);
+                    let uniforms = uniform! {
+                        sampler: nearest(input),
+                        palette: nearest(&self.palette.as_ref().unwrap().1),
+                        palette_size: palette.len() as i32,
+                    };
+                    self.draw(display, output, &self.palette_program, &uniforms)?;
+                    current = Some(output);
+                },
+            }
+        }
+
+        Ok(current)
+    }
+
+    // The texture holding the result of the latest effects, which
+    // is `source` itself if there was nothing to do
+    pub(crate) fn result<'a>(&'a self, source: &'a Texture2d) -> &'a Texture2d {
+        self.input(self.result, source)
+    }
+
+    fn scale(&mut self, display: &glium::Display, current: Option<usize>, source: &Texture2d, size: (u32, u32), smooth: bool) -> Result<usize, OpenUiError> {
+        let output = self.prepare_target(display, current, size)?;
+        let input = self.input(current, source);
+
+        let (magnify, minify) = if smooth {
+            (MagnifySamplerFilter::Linear, MinifySamplerFilter::Linear)
+        } else {
+            (MagnifySamplerFilter::Nearest, MinifySamplerFilter::Nearest)
+        };
+        let uniforms = uniform! {
+            sampler: input.sampled()
+                .wrap_function(SamplerWrapFunction::Clamp)
+                .magnify_filter(magnify)
+                .minify_filter(minify),
+        };
+
+        self.draw(display, output, &self.copy_program, &uniforms)?;
+        Ok(output)
+    }
+
+    fn input<'a>(&'a self, current: Option<usize>, source: &'a Texture2d) -> &'a Texture2d {
+        match current {
+            Some(index) => self.targets[index].as_ref().unwrap(),
+            None => source,
+        }
+    }
+
+    // Choosing the target that isn't holding the current result,
+    // and replacing it if its dimensions don't match
+    fn prepare_target(&mut self, display: &glium::Display, current: Option<usize>, size: (u32, u32)) -> Result<usize, OpenUiError> {
+        let index = match current {
+            Some(0) => 1,
+            _ => 0,
+        };
+
+        let reusable = self.targets[index].as_ref().is_some_and(|target| (target.width(), target.height()) == size);
+        if !reusable {
+            let target = Texture2d::empty_with_format(display, UncompressedFloatFormat::U8U8U8U8, MipmapsOption::NoMipmap, size.0, size.1);
+            self.targets[index] = Some(target?);
+        }
+
+        Ok(index)
+    }
+
+    // Uploading the palette as a one pixel tall texture, unless it's unchanged
+    fn prepare_palette(&mut self, display: &glium::Display, palette: &[RgbaPixel]) -> Result<(), OpenUiError> {
+        if matches!(&self.palette, Some((uploaded, _)) if uploaded == palette) {
+            return Ok(());
+        }
+
+        let bytes: Vec<u8> = palette.iter().flat_map(|&(r, g, b, a)| [r, g, b, a]).collect();
+        let image = RawImage2d::from_raw_rgba(bytes, (palette.len() as u32, 1));
+        self.palette = Some((palette.to_vec(), Texture2d::new(display, image)?));
+        Ok(())
+    }
+
+    fn draw<U: glium::uniforms::Uniforms>(&self, display: &glium::Display, target: usize, program: &glium::Program, uniforms: &U) -> Result<(), OpenUiError> {
+        let texture = self.targets[target].as_ref().unwrap();
+        let mut framebuffer = SimpleFrameBuffer::new(display, texture)?;
+        framebuffer.draw(&self.vertex_buffer, &self.indices, program, uniforms, &Default::default())?;
+        Ok(())
+    }
+}
+
+// Sampling exact pixels, and repeating the edges rather than wrapping around
+fn nearest(texture: &Texture2d) -> Sampler<'_, Texture2d> {
+    texture.sampled()
+        .wrap_function(SamplerWrapFunction::Clamp)
+        .magnify_filter(MagnifySamplerFilter::Nearest)
+        .minify_filter(MinifySamplerFilter::Nearest)
+}
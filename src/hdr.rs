@@ -0,0 +1,125 @@
+//! A floating-point canvas for accumulating light beyond the range that
+//! can be displayed, as in ray tracers, fractal renderers, or long
+//! exposures, and tonemapping it down to a displayable `RgbaImage`.
+//!
+//! Colors in an `RgbaImageF32` are linear, meaning that doubling a
+//! value doubles the amount of light, and 1.0 is the brightest value
+//! that can be displayed before tonemapping. Alpha ranges from 0.0 to 1.0.
+
+use crate::color::{linear_to_srgb, srgb_to_linear};
+use crate::pixel_formats::{Image, Pixel, Rgba16Pixel};
+use crate::RgbaImage;
+
+/// A pixel with linear floating-point red, green, blue and alpha channels.
+pub type RgbaPixelF32 = (f32, f32, f32, f32);
+
+/// An image with linear floating-point channels.
+pub type RgbaImageF32 = Image<RgbaPixelF32>;
+
+// Converting with `Image::convert()` clips values brighter than 1.0.
+// Use `tonemap()` to bring them into range instead.
+impl Pixel for RgbaPixelF32 {
+    const BLACK: RgbaPixelF32 = (0.0, 0.0, 0.0, 1.0);
+
+    fn to_rgba16(self) -> Rgba16Pixel {
+        let channel = |value: f32| (linear_to_srgb(value) * u16::MAX as f32).round() as u16;
+        let alpha = (self.3.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16;
+        (channel(self.0), channel(self.1), channel(self.2), alpha)
+    }
+
+    fn from_rgba16(pixel: Rgba16Pixel) -> RgbaPixelF32 {
+        let channel = |value: u16| srgb_to_linear(value as f32 / u16::MAX as f32);
+        (channel(pixel.0), channel(pixel.1), channel(pixel.2), pixel.3 as f32 / u16::MAX as f32)
+    }
+}
+
+/// How colors brighter than 1.0 are brought into the displayable range.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Tonemap {
+    /// Clip every channel at 1.0.
+    Clamp,
+    /// Compress bright values smoothly with `c / (1 + c)`, which
+    /// never quite reaches white.
+    Reinhard,
+    /// An approximation of the filmic curve used by ACES, which has
+    /// more contrast than Reinhard, and saturates to white.
+    Aces,
+}
+
+impl Tonemap {
+    fn apply(self, value: f32) -> f32 {
+        let value = value.max(0.0);
+        match self {
+            Tonemap::Clamp => value.min(1.0),
+            Tonemap::Reinhard => value / (1.0 + value),
+            Tonemap::Aces => {
+                let mapped = (value * (2.51 * value + 0.03)) / (value * (2.43 * value + 0.59) + 0.14);
+                mapped.clamp(0.0, 1.0)
+            },
+        }
+    }
+}
+
+impl RgbaImageF32 {
+    /// Add light to a pixel, leaving its alpha unchanged.
+    /// Returns false if the coordinates are outside of the image.
+    pub fn accumulate(&mut self, x: u32, y: u32, light: (f32, f32, f32)) -> bool {
+        match self.get_pixel(x, y) {
+            Some((r, g, b, a)) => self.set_pixel(x, y, (r + light.0, g + light.1, b + light.2, a)),
+            None => false,
+        }
+    }
+
+    /// Multiply the color of every pixel by `factor`, for example to
+    /// average the samples that have been accumulated so far.
+    pub fn scale(&mut self, factor: f32) {
+        for pixel in self.pixels_mut() {
+            *pixel = (pixel.0 * factor, pixel.1 * factor, pixel.2 * factor, pixel.3);
+        }
+    }
+
+    /// A displayable copy of this image. Colors are multiplied by
+    /// `exposure` before being tonemapped.
+    pub fn tonemap(&self, tonemap: Tonemap, exposure: f32) -> RgbaImage {
+        let mut image = RgbaImage::new(0, 0);
+        self.tonemap_into(tonemap, exposure, &mut image);
+        image
+    }
+
+    /// Like `tonemap()`, but replacing the contents of `image`
+    /// and reusing its allocation.
+    pub fn tonemap_into(&self, tonemap: Tonemap, exposure: f32, image: &mut RgbaImage) {
+        image.width = self.width();
+        image.height = self.height();
+        image.clip_rect = None;
+        image.bytes.clear();
+
+        let channel = |value: f32| (linear_to_srgb(tonemap.apply(value * exposure)) * 255.0).round() as u8;
+        for &(r, g, b, a) in self.pixels() {
+            let alpha = (a.clamp(0.0, 1.0) * 255.0).round() as u8;
+            image.bytes.extend_from_slice(&[channel(r), channel(g), channel(b), alpha]);
+        }
+    }
+}
+
+#[test]
+fn _tonemap() {
+    let mut image = RgbaImageF32::new(3, 1);
+    image.accumulate(0, 0, (0.5, 0.5, 0.5));
+    image.accumulate(1, 0, (4.0, 1.0, 0.0));
+    image.accumulate(2, 0, (1000.0, 1000.0, 1000.0));
+    image.accumulate(2, 0, (1000.0, 1000.0, 1000.0));
+    image.scale(0.5);
+
+    let clamped = image.tonemap(Tonemap::Clamp, 1.0);
+    assert_eq!(clamped.get_pixel(0, 0), Some((137, 137, 137, 255)));
+    assert_eq!(clamped.get_pixel(1, 0), Some((255, 188, 0, 255)));
+
+    // Bright values stay distinguishable with Reinhard, and reach white with ACES
+    let reinhard = image.tonemap(Tonemap::Reinhard, 1.0);
+    assert!(reinhard.get_pixel(1, 0).unwrap().0 > reinhard.get_pixel(1, 0).unwrap().1);
+    assert_eq!(image.tonemap(Tonemap::Aces, 1.0).get_pixel(2, 0), Some((255, 255, 255, 255)));
+
+    // Converting to another format clips, rather than tonemapping
+    assert_eq!(image.to_rgba().get_pixel(1, 0), Some((255, 188, 0, 255)));
+}
@@ -1,42 +1,220 @@
+#[cfg(feature = "window")]
 #[macro_use]
 extern crate glium;
 
-#[allow(unused_imports)]
-use glium::{glutin, Surface};
-use glium::glutin::dpi::LogicalSize;
+#[cfg(feature = "window")]
+use glium::glutin;
+#[cfg(feature = "window")]
 use glium::glutin::event::VirtualKeyCode;
-use glium::draw_parameters::Blend;
-use glium::glutin::event::Event::RedrawEventsCleared;
-use glium::glutin::event_loop::ControlFlow;
 
 use std::time::Duration;
-use std::time::Instant;
-use std::hash::Hasher;
-use std::hash::Hash;
-use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+
+// Logging problems that the application carries on from, through `tracing`
+// when the feature is enabled. Nothing is ever printed to the terminal.
+macro_rules! log_at {
+    ($level:ident, $($arg:tt)*) => {{
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)*);
+        #[cfg(not(feature = "tracing"))]
+        let _ = format_args!($($arg)*);
+    }};
+}
+
+macro_rules! log_error {
+    ($($arg:tt)*) => { log_at!(error, $($arg)*) };
+}
+
+#[allow(unused_macros)]
+macro_rules! log_warning {
+    ($($arg:tt)*) => { log_at!(warn, $($arg)*) };
+}
+
+pub mod accessibility;
+pub mod animation;
+pub mod assets;
+pub mod camera;
+pub mod canvas;
+pub mod capture;
+pub mod clipboard;
+pub mod collision;
+pub mod color;
+pub mod console;
+pub mod dialog;
+pub mod export;
+pub mod font;
+pub mod gamepad;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod hdr;
+pub mod menu;
+pub mod notification;
+pub mod filters;
+pub mod palette;
+pub mod path;
+pub mod pixel_formats;
+pub mod plugin;
+pub mod remote;
+pub mod replay;
+pub mod scheduler;
+pub mod shader;
+#[cfg(feature = "window")]
+pub mod shadertoy;
+pub mod terminal;
+pub mod tray;
+pub mod tween;
+#[cfg(feature = "video")]
+pub mod video;
+pub mod widgets;
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod simd;
+
+// Entering a `tracing` span for the rest of the enclosing
+// scope, when the `tracing` feature is enabled
+#[cfg(feature = "window")]
+macro_rules! trace_span {
+    ($name:literal) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!($name).entered();
+    };
+}
+
+#[cfg(feature = "window")]
+mod geometry;
+#[cfg(feature = "window")]
+mod overrides;
+#[cfg(feature = "window")]
+mod window;
+#[cfg(feature = "window")]
+pub use window::UIHandle;
 
 /// The initial settings that a windowed application
 /// will need to initialize and display itself.
+#[derive(Clone)]
 pub struct UIBlueprint {
     pub title: String,
+    pub accessible_description: Option<String>,
     pub dimensions: (u32, u32),
     pub resizeable: bool,
+    pub decorations: bool,
     pub maximized: bool,
+    pub remember_geometry: Option<String>,
     pub preserve_aspect_ratio: bool,
+    pub lock_aspect_ratio: bool,
+    pub mipmaps: bool,
+    pub multisampling: u16,
+    pub supersampling: u32,
     pub frames_per_second: u32,
+    pub sync_to_monitor: bool,
+    pub vsync: bool,
+    pub software_rendering: bool,
+    pub opengl_es: bool,
+    pub linux_backend: Option<LinuxBackend>,
+    pub catch_up_policy: CatchUpPolicy,
+    pub render_mode: RenderMode,
+    pub fullscreen: FullscreenMode,
+    pub monitor: MonitorChoice,
+    pub cursor_visible: bool,
+    pub transparent: bool,
+    pub theme: Option<WindowTheme>,
+    pub menus: Vec<menu::Menu>,
+    pub tray_icon: Option<tray::TrayIcon>,
+    pub close_policy: ClosePolicy,
+    pub video_export: Option<std::path::PathBuf>,
+    pub screenshot_key: Option<KeyboardKey>,
+    pub screenshot_directory: std::path::PathBuf,
+    pub debug_console: bool,
+    pub console_commands: Vec<console::ConsoleCommand>,
+    pub input_recording: Option<std::path::PathBuf>,
+    pub input_replay: Option<std::path::PathBuf>,
+    pub frame_hash_log: Option<std::path::PathBuf>,
+    pub remote_address: Option<String>,
+    pub multi_viewport: bool,
+    pub fragment_shader: Option<String>,
+    pub gamepad_mappings: Vec<String>,
+    pub raw_joysticks: bool,
 }
 
-impl UIBlueprint {
-    pub fn default() -> UIBlueprint {
+impl Default for UIBlueprint {
+    fn default() -> UIBlueprint {
         UIBlueprint {
             title: "".to_string(),
+            accessible_description: None,
             dimensions: (800, 800),
             resizeable: true,
+            decorations: true,
             maximized: false,
+            remember_geometry: None,
             preserve_aspect_ratio: true,
+            lock_aspect_ratio: false,
+            mipmaps: false,
+            multisampling: 0,
+            supersampling: 1,
             frames_per_second: 60,
+            sync_to_monitor: false,
+            vsync: false,
+            software_rendering: false,
+            opengl_es: false,
+            linux_backend: None,
+            catch_up_policy: CatchUpPolicy::Clamp,
+            render_mode: RenderMode::Continuous,
+            fullscreen: FullscreenMode::Windowed,
+            monitor: MonitorChoice::Primary,
+            cursor_visible: true,
+            transparent: false,
+            theme: None,
+            menus: vec![],
+            tray_icon: None,
+            close_policy: ClosePolicy::Exit,
+            video_export: None,
+            screenshot_key: None,
+            screenshot_directory: ".".into(),
+            debug_console: false,
+            console_commands: vec![],
+            input_recording: None,
+            input_replay: None,
+            frame_hash_log: None,
+            remote_address: None,
+            multi_viewport: false,
+            fragment_shader: None,
+            gamepad_mappings: vec![],
+            raw_joysticks: false,
         }
     }
+}
+
+// The largest factor that frames can be supersampled by
+const MAX_SUPERSAMPLING: u32 = 4;
+
+// The largest width or height that a window can be given, which is
+// also the largest texture size that most graphics drivers support
+const MAX_DIMENSION: u32 = 16384;
+
+impl UIBlueprint {
+    /// Check that the blueprint describes a window that can be opened.
+    /// This is done automatically when the application starts.
+    pub fn validate(&self) -> Result<(), BlueprintError> {
+        let (width, height) = self.dimensions;
+
+        if width == 0 || height == 0 {
+            return Err(BlueprintError::ZeroDimensions(self.dimensions));
+        }
+        if width > MAX_DIMENSION || height > MAX_DIMENSION {
+            return Err(BlueprintError::DimensionsTooLarge(self.dimensions));
+        }
+        if self.frames_per_second == 0 {
+            return Err(BlueprintError::ZeroFramesPerSecond);
+        }
+        if self.multisampling != 0 && !self.multisampling.is_power_of_two() {
+            return Err(BlueprintError::InvalidMultisampling(self.multisampling));
+        }
+        if !(1..=MAX_SUPERSAMPLING).contains(&self.supersampling) {
+            return Err(BlueprintError::InvalidSupersampling(self.supersampling));
+        }
+
+        Ok(())
+    }
 
     pub fn title(self, title: &str) -> UIBlueprint {
         UIBlueprint { title: title.to_string(), ..self }
@@ -50,75 +228,1059 @@ impl UIBlueprint {
         UIBlueprint { resizeable, ..self }
     }
 
+    /// Whether the window has the platform's title bar and borders.
+    /// Windows without them can still be moved and resized, by
+    /// declaring regions with `UIContext::set_hit_regions()`.
+    pub fn decorations(self, decorations: bool) -> UIBlueprint {
+        UIBlueprint { decorations, ..self }
+    }
+
     pub fn maximized(self, maximized: bool) -> UIBlueprint {
         UIBlueprint { maximized, ..self }
     }
 
+    /// Save the window's size, position and maximized state when it
+    /// closes, under the platform's configuration directory for
+    /// `app_name`, and reopen it the same way next time, in place of
+    /// `dimensions` and `maximized`. Fullscreen windows aren't restored.
+    pub fn remember_geometry(self, app_name: &str) -> UIBlueprint {
+        UIBlueprint { remember_geometry: Some(app_name.to_string()), ..self }
+    }
+
     pub fn preserve_aspect_ratio(self, preserve_aspect_ratio: bool) -> UIBlueprint {
         UIBlueprint { preserve_aspect_ratio, ..self }
     }
 
+    /// When used alongside `preserve_aspect_ratio`, resize the window
+    /// itself to match the aspect ratio of each frame, instead of
+    /// letterboxing frames that don't fit.
+    pub fn lock_aspect_ratio(self, lock_aspect_ratio: bool) -> UIBlueprint {
+        UIBlueprint { lock_aspect_ratio, ..self }
+    }
+
+    /// Generate mipmaps for every frame, so that frames displayed smaller
+    /// than their native size are smoothed rather than aliased, which
+    /// keeps fine details from sparkling. Frames displayed at their
+    /// native size or larger are unaffected.
+    pub fn mipmaps(self, mipmaps: bool) -> UIBlueprint {
+        UIBlueprint { mipmaps, ..self }
+    }
+
+    /// Smooth the edges of frames with multisample antialiasing, using
+    /// `samples` samples per pixel, which must be a power of two such
+    /// as 4, or 0 to disable it. If the graphics driver doesn't support
+    /// multisampling, the application runs without it.
+    pub fn multisampling(self, samples: u16) -> UIBlueprint {
+        UIBlueprint { multisampling: samples, ..self }
+    }
+
+    /// Draw frames onto an image `factor` times the size of the window,
+    /// then shrink it onto the window by averaging each `factor` by
+    /// `factor` block of pixels, which smooths frames that are scaled by
+    /// uneven amounts, or drawn with rotated or diagonal content. The
+    /// factor must be between 1 and 4, where 1 disables supersampling.
+    pub fn supersampling(self, factor: u32) -> UIBlueprint {
+        UIBlueprint { supersampling: factor, ..self }
+    }
+
     pub fn frames_per_second(self, frames_per_second: u32) -> UIBlueprint {
         UIBlueprint { frames_per_second, ..self }
     }
+
+    /// Produce frames at the refresh rate of the monitor that the window
+    /// is on, following the window between monitors. `frames_per_second`
+    /// is used if the refresh rate can't be determined, which is the case
+    /// for monitors that support several rates at their current resolution.
+    pub fn sync_to_monitor(self, sync_to_monitor: bool) -> UIBlueprint {
+        UIBlueprint { sync_to_monitor, ..self }
+    }
+
+    /// Wait for the monitor's vertical blank before displaying each
+    /// frame, which prevents tearing, at the cost of some latency.
+    pub fn vsync(self, vsync: bool) -> UIBlueprint {
+        UIBlueprint { vsync, ..self }
+    }
+
+    /// Ask for a graphics context that renders on the CPU, which can
+    /// work around broken graphics drivers. Not every platform has one.
+    pub fn software_rendering(self, software_rendering: bool) -> UIBlueprint {
+        UIBlueprint { software_rendering, ..self }
+    }
+
+    /// Ask for an OpenGL ES 3.0 context instead of desktop OpenGL, for
+    /// boards such as the Raspberry Pi whose drivers are fastest, or only
+    /// complete, with OpenGL ES. Shaders are translated to match.
+    pub fn opengl_es(self, opengl_es: bool) -> UIBlueprint {
+        UIBlueprint { opengl_es, ..self }
+    }
+
+    /// Force the window to open under X11 or Wayland on Linux, which can
+    /// work around problems with one of them. Without this, Wayland is
+    /// used when it's available. Ignored on other platforms.
+    pub fn linux_backend(self, backend: LinuxBackend) -> UIBlueprint {
+        UIBlueprint { linux_backend: Some(backend), ..self }
+    }
+
+    /// What `UI::launch()` should do when frames take longer than
+    /// `1 / frames_per_second` to produce.
+    pub fn catch_up_policy(self, catch_up_policy: CatchUpPolicy) -> UIBlueprint {
+        UIBlueprint { catch_up_policy, ..self }
+    }
+
+    /// Whether `UI::launch()` should produce frames continuously,
+    /// or only when something has changed.
+    pub fn render_mode(self, render_mode: RenderMode) -> UIBlueprint {
+        UIBlueprint { render_mode, ..self }
+    }
+
+    pub fn fullscreen(self, fullscreen: FullscreenMode) -> UIBlueprint {
+        UIBlueprint { fullscreen, ..self }
+    }
+
+    /// Which monitor the window covers in fullscreen, and whose video modes
+    /// are listed by `UIContext::video_modes()`. Falls back to the primary
+    /// monitor if the chosen one isn't connected.
+    pub fn monitor(self, monitor: MonitorChoice) -> UIBlueprint {
+        UIBlueprint { monitor, ..self }
+    }
+
+    /// Whether the mouse cursor should be drawn over the window.
+    pub fn cursor_visible(self, cursor_visible: bool) -> UIBlueprint {
+        UIBlueprint { cursor_visible, ..self }
+    }
+
+    /// Let the desktop show through the transparent parts of each
+    /// frame, where the platform supports it.
+    pub fn transparent(self, transparent: bool) -> UIBlueprint {
+        UIBlueprint { transparent, ..self }
+    }
+
+    /// Request a light or dark titlebar, instead of following the
+    /// system theme. Only Windows supports this. On macOS and Linux, the
+    /// window follows the system theme, since the windowing library has
+    /// no way to override it there, and the request is ignored.
+    pub fn theme(self, theme: WindowTheme) -> UIBlueprint {
+        UIBlueprint { theme: Some(theme), ..self }
+    }
+
+    /// Add a menu to the window's native menu bar. Only supported on
+    /// Windows and macOS, with the `menus` feature enabled.
+    pub fn menu(mut self, menu: menu::Menu) -> UIBlueprint {
+        self.menus.push(menu);
+        self
+    }
+
+    /// Display an icon in the system tray while the window is open. Only
+    /// supported on Windows and macOS, with the `tray` feature enabled.
+    pub fn tray_icon(self, tray_icon: tray::TrayIcon) -> UIBlueprint {
+        UIBlueprint { tray_icon: Some(tray_icon), ..self }
+    }
+
+    /// Choose what happens when the user tries to close the window.
+    pub fn close_policy(self, close_policy: ClosePolicy) -> UIBlueprint {
+        UIBlueprint { close_policy, ..self }
+    }
+
+    /// Record every frame that's displayed into a video file, at
+    /// `frames_per_second` or the monitor's refresh rate when using
+    /// `sync_to_monitor()`. See `export::VideoExporter` for details.
+    pub fn video_export(self, path: &str) -> UIBlueprint {
+        UIBlueprint { video_export: Some(path.into()), ..self }
+    }
+
+    /// Save the next frame as a timestamped PNG file whenever F12 is
+    /// pressed, whether or not the controller handles the key itself.
+    pub fn screenshots(self, enabled: bool) -> UIBlueprint {
+        let screenshot_key = if enabled { Some(KeyboardKey::F12) } else { None };
+        UIBlueprint { screenshot_key, ..self }
+    }
+
+    /// Take screenshots when `key` is pressed, instead of F12.
+    pub fn screenshot_key(self, key: KeyboardKey) -> UIBlueprint {
+        UIBlueprint { screenshot_key: Some(key), ..self }
+    }
+
+    /// Where screenshots are saved, which is the working directory by default.
+    pub fn screenshot_directory(self, directory: &str) -> UIBlueprint {
+        UIBlueprint { screenshot_directory: directory.into(), ..self }
+    }
+
+    /// Let the debug console be opened with the backtick key.
+    pub fn debug_console(self, debug_console: bool) -> UIBlueprint {
+        UIBlueprint { debug_console, ..self }
+    }
+
+    /// Register a command that can be entered into the debug console,
+    /// which is handled by `UIController::run_command()`.
+    pub fn console_command(mut self, name: &str, description: &str) -> UIBlueprint {
+        self.console_commands.push(console::ConsoleCommand {
+            name: name.to_string(),
+            description: description.to_string(),
+        });
+        self
+    }
+
+    /// Record the events that the controller receives into a file,
+    /// which can be replayed with `replay_input()`.
+    pub fn record_input(self, path: &str) -> UIBlueprint {
+        UIBlueprint { input_recording: Some(path.into()), ..self }
+    }
+
+    /// Feed the controller the events from a recording instead of live
+    /// input, until the recording ends. See the `replay` module for details.
+    pub fn replay_input(self, path: &str) -> UIBlueprint {
+        UIBlueprint { input_replay: Some(path.into()), ..self }
+    }
+
+    /// Write the `content_hash()` of every frame that's displayed to a
+    /// file, one line per frame, so that two runs replaying the same
+    /// input can be compared without saving their frames.
+    pub fn log_frame_hashes(self, path: &str) -> UIBlueprint {
+        UIBlueprint { frame_hash_log: Some(path.into()), ..self }
+    }
+
+    /// Stream every frame that's displayed to a `remote::RemoteViewer`
+    /// that connects to `address`, such as `"0.0.0.0:7878"`, and
+    /// receive its input. See the `remote` module for details.
+    pub fn remote_stream(self, address: &str) -> UIBlueprint {
+        UIBlueprint { remote_address: Some(address.to_string()), ..self }
+    }
+
+    /// Display the frames returned by `UIController::next_viewports()`,
+    /// each in its own rectangle of the window, instead of the frame
+    /// returned by `UIController::next_frame()`. Only `UI::launch()`
+    /// supports viewports, and they aren't recorded, saved as
+    /// screenshots, or streamed.
+    pub fn multi_viewport(self, multi_viewport: bool) -> UIBlueprint {
+        UIBlueprint { multi_viewport, ..self }
+    }
+
+    /// Draw frames onto the window with a custom GLSL fragment shader.
+    /// See the `shader` module for the inputs that shaders receive. If the
+    /// shader fails to compile, the error is logged, and frames are
+    /// drawn with the default shader instead.
+    pub fn fragment_shader(self, source: &str) -> UIBlueprint {
+        UIBlueprint { fragment_shader: Some(source.to_string()), ..self }
+    }
+
+    /// Add an SDL_GameControllerDB mapping, for a gamepad that is missing
+    /// from the included database, or is mapped incorrectly by it.
+    /// See the `gamepad` module for details.
+    pub fn gamepad_mapping(mut self, mapping: &str) -> UIBlueprint {
+        self.gamepad_mappings.push(mapping.to_string());
+        self
+    }
+
+    /// Report every button and axis of every joystick as a
+    /// `UIEvent::Joystick`, alongside the normalized gamepad events.
+    pub fn raw_joysticks(self, enabled: bool) -> UIBlueprint {
+        UIBlueprint { raw_joysticks: enabled, ..self }
+    }
+
+    /// Describe the application, which is announced along with the
+    /// title when the window opens.
+    /// See the `accessibility` module for details.
+    pub fn accessible_description(self, description: &str) -> UIBlueprint {
+        UIBlueprint { accessible_description: Some(description.to_string()), ..self }
+    }
+}
+
+/// What happens when the user tries to close the window,
+/// for example by clicking its close button.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ClosePolicy {
+    /// Exit right away.
+    Exit,
+    /// Deliver a `UIEvent::CloseRequested` to the controller, which can
+    /// save its work before letting the application exit, or ignore the
+    /// request. If a `timeout` is given, the application exits once it
+    /// has passed, whether or not the controller has responded.
+    Ask { timeout: Option<Duration> },
+}
+
+/// The display server that a window is opened with on Linux.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LinuxBackend {
+    X11,
+    Wayland,
+}
+
+/// Whether, and how, the window should cover the whole screen.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FullscreenMode {
+    /// An ordinary window, with the `dimensions` given by the blueprint.
+    Windowed,
+    /// A borderless window covering the blueprint's `monitor`, at the
+    /// monitor's current resolution.
+    Borderless,
+    /// Exclusive control of the blueprint's `monitor`, at its highest
+    /// resolution and refresh rate. Falls back to `Borderless` if
+    /// the monitor's video modes can't be determined.
+    Exclusive,
+    /// Exclusive control of the blueprint's `monitor`, in whichever of its
+    /// video modes is closest to the one given, such as a low resolution
+    /// for an emulator. The available modes are listed by
+    /// `UIContext::video_modes()`.
+    ExclusiveMode(VideoMode),
+}
+
+/// Which monitor to cover in fullscreen.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MonitorChoice {
+    /// The monitor that the OS considers to be the main one.
+    Primary,
+    /// A monitor by its `Monitor::id`. Monitors are numbered in the order
+    /// that the OS lists them in when the window opens, starting from zero.
+    Id(u32),
+    /// A monitor by its name, as in `Monitor::name`, which stays the
+    /// same when other monitors are connected or disconnected.
+    Name(String),
+}
+
+/// A monitor connected to the computer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Monitor {
+    /// Identifies the monitor for as long as it stays connected.
+    pub id: u32,
+    /// The name the OS gives the monitor, usually its model or the
+    /// connector it's plugged into, such as "HDMI-1".
+    pub name: Option<String>,
+    /// The position of the monitor's top left corner on the desktop,
+    /// in physical pixels.
+    pub position: (i32, i32),
+    /// The monitor's current resolution.
+    pub size: (u32, u32),
+    pub scale_factor: f64,
+    pub primary: bool,
+}
+
+/// A resolution and refresh rate that a monitor can be switched to
+/// in exclusive fullscreen.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct VideoMode {
+    pub width: u32,
+    pub height: u32,
+    pub refresh_rate: u32,
+    pub bit_depth: u16,
+}
+
+/// When the controller should be asked for new frames.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RenderMode {
+    /// Every `1 / frames_per_second`, whether or not anything happened.
+    Continuous,
+    /// Only after input events, or after a call to
+    /// `RedrawHandle::request_redraw()`. The application sleeps
+    /// in between, which saves power in editors and viewers
+    /// whose contents rarely change.
+    OnDemand,
+}
+
+/// Lets a controller ask for a new frame when using
+/// `RenderMode::OnDemand`. Can be cloned and sent to other threads.
+#[derive(Clone)]
+pub struct RedrawHandle {
+    #[cfg(feature = "window")]
+    proxy: glutin::event_loop::EventLoopProxy<()>,
+}
+
+impl RedrawHandle {
+    /// Have the controller process events and produce a frame as soon as
+    /// possible, even if no input has arrived. Does nothing once the
+    /// application has terminated.
+    pub fn request_redraw(&self) {
+        #[cfg(feature = "window")]
+        let _ = self.proxy.send_event(());
+    }
+}
+
+/// The reasons that a `UIBlueprint` can be rejected.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BlueprintError {
+    /// The window would have no area.
+    ZeroDimensions((u32, u32)),
+    /// The window would be wider or taller than any display can handle.
+    DimensionsTooLarge((u32, u32)),
+    /// Frames would never be produced.
+    ZeroFramesPerSecond,
+    /// Multisampling needs a number of samples that's a power of two.
+    InvalidMultisampling(u16),
+    /// The supersampling factor is outside of the supported range.
+    InvalidSupersampling(u32),
+}
+
+impl std::fmt::Display for BlueprintError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BlueprintError::ZeroDimensions((w, h)) => {
+                write!(f, "window dimensions must not be zero (got {}x{})", w, h)
+            },
+            BlueprintError::DimensionsTooLarge((w, h)) => {
+                write!(f, "window dimensions must not exceed {}x{} (got {}x{})", MAX_DIMENSION, MAX_DIMENSION, w, h)
+            },
+            BlueprintError::ZeroFramesPerSecond => {
+                write!(f, "frames per second must be at least 1")
+            },
+            BlueprintError::InvalidMultisampling(samples) => {
+                write!(f, "multisampling must use a power of two samples (got {})", samples)
+            },
+            BlueprintError::InvalidSupersampling(factor) => {
+                write!(f, "supersampling factor must be between 1 and {} (got {})", MAX_SUPERSAMPLING, factor)
+            },
+        }
+    }
+}
+
+impl std::error::Error for BlueprintError {}
+
+/// Everything that can stop an application from starting, or from
+/// drawing a frame, along with the reason a controller gave for exiting.
+/// Failures while drawing a frame don't stop the application. Instead,
+/// the frame is skipped, and the failure is passed to
+/// `UIController::render_error()`, or returned by `UIHandle::present()`.
+#[derive(Debug)]
+pub enum OpenUiError {
+    /// The blueprint was rejected before a window was opened.
+    Blueprint(BlueprintError),
+    /// The window, or its graphics context, couldn't be created.
+    Window(Box<dyn std::error::Error + Send + Sync>),
+    /// The graphics driver couldn't compile a shader, create a
+    /// texture or buffer, or draw.
+    Gpu(Box<dyn std::error::Error + Send + Sync>),
+    /// An image couldn't be decoded or encoded.
+    Image(assets::AssetError),
+    /// A file couldn't be read or written.
+    Io(std::io::Error),
+    /// The controller exited with `UIControlFlow::ExitWithError`.
+    Controller(String),
+}
+
+impl std::fmt::Display for OpenUiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            OpenUiError::Blueprint(error) => write!(f, "invalid blueprint: {}", error),
+            OpenUiError::Window(error) => write!(f, "couldn't open window: {}", error),
+            OpenUiError::Gpu(error) => write!(f, "graphics error: {}", error),
+            OpenUiError::Image(error) => write!(f, "{}", error),
+            OpenUiError::Io(error) => write!(f, "{}", error),
+            OpenUiError::Controller(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for OpenUiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OpenUiError::Blueprint(error) => Some(error),
+            OpenUiError::Image(error) => Some(error),
+            OpenUiError::Io(error) => Some(error),
+            OpenUiError::Window(error) | OpenUiError::Gpu(error) => Some(error.as_ref()),
+            OpenUiError::Controller(_) => None,
+        }
+    }
+}
+
+impl From<BlueprintError> for OpenUiError {
+    fn from(error: BlueprintError) -> OpenUiError {
+        OpenUiError::Blueprint(error)
+    }
+}
+
+impl From<assets::AssetError> for OpenUiError {
+    fn from(error: assets::AssetError) -> OpenUiError {
+        OpenUiError::Image(error)
+    }
+}
+
+impl From<std::io::Error> for OpenUiError {
+    fn from(error: std::io::Error) -> OpenUiError {
+        OpenUiError::Io(error)
+    }
+}
+
+#[cfg(feature = "window")]
+impl From<glium::backend::glutin::DisplayCreationError> for OpenUiError {
+    fn from(error: glium::backend::glutin::DisplayCreationError) -> OpenUiError {
+        OpenUiError::Window(Box::new(error))
+    }
+}
+
+// Treating every failure reported by glium as a GPU error
+macro_rules! gpu_errors {
+    ($($error:ty,)*) => {
+        $(
+            #[cfg(feature = "window")]
+            impl From<$error> for OpenUiError {
+                fn from(error: $error) -> OpenUiError {
+                    OpenUiError::Gpu(Box::new(error))
+                }
+            }
+        )*
+    };
+}
+
+gpu_errors! {
+    glium::ProgramCreationError,
+    glium::vertex::BufferCreationError,
+    glium::index::BufferCreationError,
+    glium::texture::TextureCreationError,
+    glium::framebuffer::ValidationError,
+    glium::DrawError,
+    glium::SwapBuffersError,
+}
+
+#[test]
+fn _open_ui_error() {
+    use std::error::Error;
+
+    let error = OpenUiError::from(BlueprintError::ZeroFramesPerSecond);
+    assert!(error.to_string().starts_with("invalid blueprint: "));
+    assert!(error.source().is_some());
+
+    let error = OpenUiError::from(std::io::Error::new(std::io::ErrorKind::NotFound, "missing"));
+    assert_eq!(error.to_string(), "missing");
+    assert!(matches!(error, OpenUiError::Io(_)));
+
+    assert_eq!(OpenUiError::Gpu("out of memory".into()).to_string(), "graphics error: out of memory");
+
+    // Keeping the original error, rather than only its message
+    #[cfg(feature = "window")]
+    {
+        let error = OpenUiError::from(glium::DrawError::NoDepthBuffer);
+        assert!(error.source().unwrap().is::<glium::DrawError>());
+    }
+}
+
+#[test]
+fn _validate_blueprint() {
+    assert_eq!(UIBlueprint::default().validate(), Ok(()));
+    assert_eq!(
+        UIBlueprint::default().dimensions((0, 600)).validate(),
+        Err(BlueprintError::ZeroDimensions((0, 600))),
+    );
+    assert_eq!(
+        UIBlueprint::default().dimensions((100_000, 600)).validate(),
+        Err(BlueprintError::DimensionsTooLarge((100_000, 600))),
+    );
+    assert_eq!(
+        UIBlueprint::default().frames_per_second(0).validate(),
+        Err(BlueprintError::ZeroFramesPerSecond),
+    );
+    assert_eq!(
+        UIBlueprint::default().multisampling(3).validate(),
+        Err(BlueprintError::InvalidMultisampling(3)),
+    );
+    assert_eq!(
+        UIBlueprint::default().supersampling(0).validate(),
+        Err(BlueprintError::InvalidSupersampling(0)),
+    );
+}
+
+/// What to do when the controller falls behind schedule, because
+/// producing a frame took longer than the time allotted to it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CatchUpPolicy {
+    /// Wait a full frame after every frame, no matter how late it was.
+    /// The application runs slower, but never skips anything.
+    SlowDown,
+    /// Start the next frame as soon as possible, then continue on the
+    /// usual schedule. Lost time is forgotten rather than made up.
+    Clamp,
+    /// Make up for lost time by processing events for the frames that
+    /// were missed, without rendering them. This keeps the application
+    /// in step with the clock, at the cost of visible stutter.
+    SkipRenders,
+}
+
+/// Whether the application should keep running,
+/// as decided by `UIController::process_events()`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UIControlFlow {
+    Continue,
+    Exit,
+    /// Exit, with the launch function returning the
+    /// message as `OpenUiError::Controller`.
+    ExitWithError(String),
+}
+
+/// How long each stage of a frame took, as passed to
+/// `UIController::frame_report()`.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct FrameReport {
+    /// The number of frames drawn before this one.
+    pub frame: u64,
+    /// Time spent waiting since the end of the previous frame.
+    pub wait: Duration,
+    /// Time spent in `UIController::process_events()`.
+    pub events: Duration,
+    /// Time spent in `UIController::next_frame()`.
+    pub build: Duration,
+    /// Time spent copying the frame to the GPU.
+    pub upload: Duration,
+    /// Time spent drawing the frame to the window.
+    pub present: Duration,
+}
+
+/// A request for OpenUI to change something about the running
+/// application, queued with `UIContext::push_command()`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UICommand {
+    /// Change the text in the window's title bar.
+    SetTitle(String),
+    /// Show or hide the cursor while it's over the window.
+    SetCursorVisible(bool),
+    /// Replace the regions that move and resize the window.
+    SetHitRegions(Vec<HitRegion>),
+    /// Enter or leave fullscreen, or switch to another video mode.
+    SetFullscreen(FullscreenMode),
+    /// Move the window onto another monitor.
+    SetMonitor(MonitorChoice),
+    /// Vibrate a gamepad. See the `gamepad` module for details.
+    SetRumble(gamepad::Rumble),
+    /// End the application once the current frame is over,
+    /// like returning `UIControlFlow::Exit`.
+    Quit,
+    /// Hand the window to another controller once the current frame is over.
+    ReplaceController(ControllerReplacement),
+}
+
+type ReplaceFn = Box<dyn FnOnce(Box<dyn UIController>) -> Box<dyn UIController> + Send>;
+
+/// A controller waiting to take over from the current one,
+/// queued by `UIContext::replace_controller()`.
+#[derive(Clone)]
+pub struct ControllerReplacement(std::sync::Arc<std::sync::Mutex<Option<ReplaceFn>>>);
+
+impl ControllerReplacement {
+    // Swapping in the new controller. Replacements only take
+    // effect once, however many times they're cloned.
+    #[cfg_attr(not(feature = "window"), allow(dead_code))]
+    fn replace(&self, controller: &mut Box<dyn UIController>) {
+        let replace = self.0.lock().unwrap_or_else(|error| error.into_inner()).take();
+        if let Some(replace) = replace {
+            let current = std::mem::replace(controller, Box::new(NoController));
+            *controller = replace(current);
+        }
+    }
+}
+
+impl std::fmt::Debug for ControllerReplacement {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("ControllerReplacement")
+    }
+}
+
+impl PartialEq for ControllerReplacement {
+    fn eq(&self, other: &ControllerReplacement) -> bool {
+        std::sync::Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+// Standing in for a controller while it's being replaced
+struct NoController;
+
+impl UIController for NoController {
+    fn next_frame(&mut self, _context: &mut UIContext) -> Option<RgbaImageRegion<'_>> {
+        None
+    }
+}
+
+// Carrying out any queued replacements of the controller, in order
+#[cfg_attr(not(feature = "window"), allow(dead_code))]
+fn replace_controller(controller: &mut Box<dyn UIController>, commands: &[UICommand]) {
+    for command in commands {
+        if let UICommand::ReplaceController(replacement) = command {
+            replacement.replace(controller);
+        }
+    }
+}
+
+/// Information about the running application, passed to
+/// `UIController::process_events()` and `UIController::next_frame()`,
+/// along with a queue of commands for OpenUI to carry out
+/// before the next frame begins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UIContext {
+    window_size: (u32, u32),
+    scale_factor: f64,
+    elapsed: Duration,
+    frame: u64,
+    commands: Vec<UICommand>,
+    uniforms: Vec<(String, shader::ShaderValue)>,
+    video_modes: Vec<VideoMode>,
+    monitors: Vec<Monitor>,
+    linux_backend: Option<LinuxBackend>,
+    #[cfg(feature = "gpu")]
+    gpu_effects: Vec<gpu::GpuEffect>,
+}
+
+impl UIContext {
+    // Contexts are created by OpenUI, and kept up to date every frame
+    fn new(window_size: (u32, u32), scale_factor: f64) -> UIContext {
+        UIContext {
+            window_size,
+            scale_factor,
+            elapsed: Duration::ZERO,
+            frame: 0,
+            commands: vec![],
+            uniforms: vec![],
+            video_modes: vec![],
+            monitors: vec![],
+            linux_backend: None,
+            #[cfg(feature = "gpu")]
+            gpu_effects: vec![],
+        }
+    }
+
+    /// The size of the window, as reported by `ResizeEvent`.
+    pub fn window_size(&self) -> (u32, u32) {
+        self.window_size
+    }
+
+    /// The number of physical pixels per logical pixel.
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// How long the application has been running, as of the start of this frame.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The number of frames drawn before this one.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Every video mode of the blueprint's `monitor`, from the largest
+    /// resolution and refresh rate to the smallest.
+    pub fn video_modes(&self) -> &[VideoMode] {
+        &self.video_modes
+    }
+
+    /// Every connected monitor, kept up to date as monitors are
+    /// connected and disconnected.
+    pub fn monitors(&self) -> &[Monitor] {
+        &self.monitors
+    }
+
+    /// Whether the window was opened with X11 or Wayland,
+    /// or `None` when not running on Linux.
+    pub fn linux_backend(&self) -> Option<LinuxBackend> {
+        self.linux_backend
+    }
+
+    /// Queue a command, to be carried out before the next frame begins.
+    pub fn push_command(&mut self, command: UICommand) {
+        self.commands.push(command);
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.push_command(UICommand::SetTitle(title.to_string()));
+    }
+
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.push_command(UICommand::SetCursorVisible(visible));
+    }
+
+    pub fn quit(&mut self) {
+        self.push_command(UICommand::Quit);
+    }
+
+    /// Switch the window between windowed, borderless and exclusive
+    /// fullscreen once the controller returns. See `FullscreenMode` for details.
+    pub fn set_fullscreen(&mut self, fullscreen: FullscreenMode) {
+        self.push_command(UICommand::SetFullscreen(fullscreen));
+    }
+
+    /// Move the window onto another monitor, such as a projector that
+    /// was just connected. Fullscreen windows cover the new monitor.
+    pub fn set_monitor(&mut self, monitor: MonitorChoice) {
+        self.push_command(UICommand::SetMonitor(monitor));
+    }
+
+    /// Vibrate a gamepad's strong and weak motors, each between 0.0 and 1.0,
+    /// for `duration`. See the `gamepad` module for details.
+    pub fn set_rumble(&mut self, gamepad_id: u64, strong: f32, weak: f32, duration: Duration) {
+        self.push_command(UICommand::SetRumble(gamepad::Rumble { gamepad_id, strong, weak, duration }));
+    }
+
+    /// Hand the window to another controller once the current frame is
+    /// over, such as a game started from a launcher. `replace` receives the
+    /// current controller, which the new one can keep, and hand back with
+    /// another replacement later. The new controller's `blueprint()` and
+    /// `plugins()` aren't used, and neither is `redraw_handle()` called, so
+    /// the handle given to the first controller should be passed along.
+    /// Controllers can be replaced in `UI::launch()` and `UI::launch_threaded()`.
+    pub fn replace_controller<F>(&mut self, replace: F)
+    where F: FnOnce(Box<dyn UIController>) -> Box<dyn UIController> + Send + 'static {
+        let replace: ReplaceFn = Box::new(replace);
+        let replacement = ControllerReplacement(std::sync::Arc::new(std::sync::Mutex::new(Some(replace))));
+        self.push_command(UICommand::ReplaceController(replacement));
+    }
+
+    /// Declare the parts of the window that move or resize it when
+    /// dragged with the left mouse button, for windows that draw their
+    /// own title bar and borders. The regions replace any declared
+    /// before, and where they overlap, the first one is used.
+    pub fn set_hit_regions(&mut self, regions: Vec<HitRegion>) {
+        self.push_command(UICommand::SetHitRegions(regions));
+    }
+
+    /// Set a uniform declared by the blueprint's custom fragment shader,
+    /// for this frame and every frame after it, until it's set again.
+    /// Uniforms aren't passed on by `UI::launch_threaded()`.
+    pub fn set_uniform(&mut self, name: &str, value: impl Into<shader::ShaderValue>) {
+        shader::set_uniform(&mut self.uniforms, name, value.into());
+    }
+
+    /// Apply effects on the GPU to this frame, and every frame after it,
+    /// until the effects are changed. See the `gpu` module for details.
+    /// Effects aren't applied by `UI::launch_threaded()`, or to viewports.
+    #[cfg(feature = "gpu")]
+    pub fn set_gpu_effects(&mut self, effects: Vec<gpu::GpuEffect>) {
+        self.gpu_effects = effects;
+    }
+
+    #[cfg(feature = "gpu")]
+    pub fn gpu_effects(&self) -> &[gpu::GpuEffect] {
+        &self.gpu_effects
+    }
+
+    // Removing the queued commands, so that they can be carried out
+    fn take_commands(&mut self) -> Vec<UICommand> {
+        std::mem::take(&mut self.commands)
+    }
+}
+
+// Whether any of the queued commands ends the application
+fn quit_requested(commands: &[UICommand]) -> bool {
+    commands.contains(&UICommand::Quit)
+}
+
+#[test]
+fn _ui_context() {
+    let mut context = UIContext::new((800, 600), 2.0);
+    context.set_title("Paused");
+    assert!(!quit_requested(&context.commands));
+
+    context.quit();
+    let commands = context.take_commands();
+    assert_eq!(commands, vec![UICommand::SetTitle("Paused".into()), UICommand::Quit]);
+    assert!(quit_requested(&commands));
+    assert!(context.take_commands().is_empty());
+}
+
+#[test]
+fn _replace_controller() {
+    struct Launcher;
+    impl UIController for Launcher {
+        fn next_frame(&mut self, _context: &mut UIContext) -> Option<RgbaImageRegion<'_>> { None }
+        fn run_command(&mut self, _name: &str, _args: &[&str]) -> String { "launcher".into() }
+    }
+
+    struct Game { launcher: Box<dyn UIController> }
+    impl UIController for Game {
+        fn next_frame(&mut self, _context: &mut UIContext) -> Option<RgbaImageRegion<'_>> { None }
+        fn run_command(&mut self, name: &str, args: &[&str]) -> String {
+            format!("game from {}", self.launcher.run_command(name, args))
+        }
+    }
+
+    let mut controller: Box<dyn UIController> = Box::new(Launcher);
+    let mut context = UIContext::new((800, 600), 1.0);
+    context.replace_controller(|launcher| Box::new(Game { launcher }));
+    let commands = context.take_commands();
+
+    replace_controller(&mut controller, &commands);
+    assert_eq!(controller.run_command("", &[]), "game from launcher");
+
+    // Replacements that have already been carried out are ignored
+    replace_controller(&mut controller, &commands);
+    assert_eq!(controller.run_command("", &[]), "game from launcher");
+}
+
+// Whether the event loop should end, based on a controller's decision,
+// along with what the launch function should return once it has
+fn exit_requested(flow: UIControlFlow) -> Option<Result<(), OpenUiError>> {
+    match flow {
+        UIControlFlow::Continue => None,
+        UIControlFlow::Exit => Some(Ok(())),
+        UIControlFlow::ExitWithError(message) => Some(Err(OpenUiError::Controller(message))),
+    }
 }
 
 pub trait UIController {
     /// This function wil be called once before the application opens,
     /// and determines the initial settings of the rendering window.
-    fn blueprint(&self) -> UIBlueprint;
+    fn blueprint(&self) -> UIBlueprint {
+        UIBlueprint::default()
+    }
 
     /// This function will be called called every frame,
     /// and returns the contents of the next render-able frame,
-    /// or `None` if the application should terminate.
-    fn next_frame(&mut self) -> Option<RgbaImageRegion>;
+    /// or `None` to keep displaying the previous one.
+    fn next_frame(&mut self, context: &mut UIContext) -> Option<RgbaImageRegion<'_>>;
+
+    /// This function will be called every frame in place of `next_frame()`
+    /// when the blueprint enables `multi_viewport`, and returns several
+    /// frames, each displayed in its own rectangle of the window, such as
+    /// the halves of a split screen. Returning no viewports keeps
+    /// displaying the previous ones.
+    fn next_viewports(&mut self, _context: &mut UIContext) -> Vec<Viewport<'_>> {
+        vec![]
+    }
 
     /// This function will be called every frame, receiving
     /// input events, and usually responding by modifying state.
-    fn process_events(&mut self, events: &Vec<UIEvent>);
+    /// Its return value determines whether the application keeps running.
+    fn process_events(&mut self, _events: &Vec<UIEvent>, _context: &mut UIContext) -> UIControlFlow {
+        UIControlFlow::Continue
+    }
 
-    fn should_terminate(&self) -> bool;
-}
+    /// This function will be called once after the application opens,
+    /// with a handle that can be used to request frames while using
+    /// `RenderMode::OnDemand`.
+    fn redraw_handle(&mut self, _handle: RedrawHandle) {}
+
+    /// This function will be called when a command registered with
+    /// `UIBlueprint::console_command()` is entered into the debug console,
+    /// and returns text to display in the console.
+    fn run_command(&mut self, _name: &str, _args: &[&str]) -> String {
+        String::new()
+    }
+
+    /// This function will be called after every frame with a breakdown
+    /// of how long the frame took, for diagnosing slow frames.
+    fn frame_report(&mut self, _report: &FrameReport) {}
 
-const VERTEX_SHADER_SRC: &str = r#"
-    #version 150
+    /// This function will be called when something goes wrong while displaying
+    /// a frame, such as the GPU running out of memory, or a screenshot failing
+    /// to save. The application carries on with the next frame. By default, the error is logged
+    /// through `tracing`, when that feature is enabled.
+    fn render_error(&mut self, error: OpenUiError) {
+        log_error!("unable to display frame: {}", error);
+    }
 
-    in vec2 dest;
-    in vec2 src;
-    out vec2 v_src;
+    /// This function will be called once before the application opens,
+    /// and returns the plugins that see events before this controller
+    /// does, and draw over its frames. See the `plugin` module.
+    fn plugins(&mut self) -> Vec<Box<dyn plugin::UIPlugin>> {
+        vec![]
+    }
+}
 
+/// An axis-aligned rectangle, described by its top-left corner and size.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
 
-    void main() {
-        v_src = src;
-        gl_Position = vec4(dest, 0.0, 1.0);
+impl Rect {
+    pub fn new(x: u32, y: u32, w: u32, h: u32) -> Rect {
+        Rect { x, y, w, h }
     }
-"#;
 
-const FRAGMENT_SHADER_SRC: &str = r#"
-    #version 150
+    /// Whether the given point falls inside of the rectangle.
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.x && y >= self.y && x - self.x < self.w && y - self.y < self.h
+    }
 
-    in vec2 v_src;
-    out vec4 color;
+    /// The area shared by both rectangles, if any.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let left = self.x.max(other.x);
+        let top = self.y.max(other.y);
+        let right = (self.x as u64 + self.w as u64).min(other.x as u64 + other.w as u64);
+        let bottom = (self.y as u64 + self.h as u64).min(other.y as u64 + other.h as u64);
 
-    uniform sampler2D sampler;
+        if right <= left as u64 || bottom <= top as u64 {
+            return None;
+        }
 
-    void main() {
-        color = texture(sampler, v_src);
+        Some(Rect::new(left, top, right as u32 - left, bottom as u32 - top))
     }
-"#;
+}
+
+/// What dragging a region of the window does.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HitTest {
+    /// Move the window, like dragging its title bar.
+    TitleBar,
+    /// Resize the window from one of its edges or corners.
+    Resize(ResizeEdge),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ResizeEdge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
 
+/// A rectangle of the window, measured like `CursorMovementEvent`,
+/// and what dragging it does. Clicks inside of hit regions are still
+/// passed on to the controller.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HitRegion {
+    pub rect: Rect,
+    pub hit_test: HitTest,
+}
+
+/// A frame, and the rectangle of the window that it's displayed in,
+/// as returned by `UIController::next_viewports()`. Rectangles are
+/// measured like `UIContext::window_size()`, from the top-left corner.
+pub struct Viewport<'a> {
+    pub frame: RgbaImageRegion<'a>,
+    pub destination: Rect,
+}
 
 /// A rectangular image made up of RGBA pixels
+#[derive(Clone)]
 pub struct RgbaImage {
     width: u32,
     height: u32,
     bytes: Vec<u8>,
+    clip_rect: Option<Rect>,
+}
+
+// Summarizing the image, since printing every byte is rarely useful
+impl std::fmt::Debug for RgbaImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("RgbaImage")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("clip_rect", &self.clip_rect)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Images are equal when they have the same dimensions and pixels.
+/// The clipping rectangle is not considered.
+impl PartialEq for RgbaImage {
+    fn eq(&self, other: &RgbaImage) -> bool {
+        self.width == other.width && self.height == other.height && self.bytes == other.bytes
+    }
 }
 
+impl Eq for RgbaImage {}
+
 /// A read-only region of an `RgbaImage`
+#[derive(Copy, Clone)]
 pub struct RgbaImageRegion<'a> {
     width: u32,
     height: u32,
+    // The number of bytes between the start of one row and the next,
+    // which is larger than `width * 4` when the region is narrower
+    // than the image it belongs to.
+    stride: usize,
     bytes: &'a[u8],
 }
 
@@ -131,50 +1293,449 @@ impl<'a> RgbaImageRegion<'a> {
         self.height
     }
 
+    /// A hash of the region's dimensions and pixels, for checking that two
+    /// runs drew identical frames without keeping the frames. The hash is
+    /// 64-bit FNV-1a over the width and height, as little-endian `u32`s,
+    /// followed by the RGBA bytes of each row from top to bottom. It won't
+    /// change between versions, but isn't suitable for security.
+    pub fn content_hash(&self) -> u64 {
+        let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+        let mut write = |bytes: &[u8]| {
+            for &byte in bytes {
+                hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+
+        write(&self.width.to_le_bytes());
+        write(&self.height.to_le_bytes());
+        for y in 0..self.height {
+            write(self.row(y).unwrap());
+        }
+
+        hash
+    }
+
     /// Retrieve a single pixel at a given point.
     pub fn get_pixel(&self, x: u32, y: u32) -> Option<RgbaPixel> {
-        let index = (((self.width * y) + x) * 4) as usize;
-    
-        if index >= self.bytes.len() {
+        if x >= self.width || y >= self.height {
             return None;
         }
+
+        let index = y as usize * self.stride + x as usize * 4;
     
         Some((
-            self.bytes[index + 0],
+            self.bytes[index],
             self.bytes[index + 1],
             self.bytes[index + 2],
             self.bytes[index + 3],
         ))
     }
 
+    /// Borrow a rectangular area of this region, or `None`
+    /// if the rectangle doesn't fit within the region.
+    pub fn region(&self, rect: Rect) -> Option<RgbaImageRegion<'a>> {
+        let fits = rect.w > 0 && rect.h > 0 &&
+            rect.x as u64 + rect.w as u64 <= self.width as u64 &&
+            rect.y as u64 + rect.h as u64 <= self.height as u64;
+        if !fits { return None; }
+
+        let start = rect.y as usize * self.stride + rect.x as usize * 4;
+        let end = (rect.y + rect.h - 1) as usize * self.stride + (rect.x + rect.w) as usize * 4;
+
+        Some(RgbaImageRegion {
+            width: rect.w,
+            height: rect.h,
+            stride: self.stride,
+            bytes: &self.bytes[start..end],
+        })
+    }
+
+    // The bytes of a single row of pixels
+    fn row(&self, y: u32) -> Option<&[u8]> {
+        if y >= self.height { return None; }
+        let start = y as usize * self.stride;
+        self.bytes.get(start..start + self.width as usize * 4)
+    }
+
+    // The bytes of every row, back to back
+    fn contiguous_bytes(&self) -> std::borrow::Cow<'_, [u8]> {
+        let row_length = self.width as usize * 4;
+
+        if self.stride == row_length {
+            return std::borrow::Cow::Borrowed(&self.bytes[..row_length * self.height as usize]);
+        }
+
+        let mut bytes = Vec::with_capacity(row_length * self.height as usize);
+        for y in 0..self.height {
+            bytes.extend_from_slice(self.row(y).unwrap());
+        }
+        std::borrow::Cow::Owned(bytes)
+    }
+
+    // Like `contiguous_bytes()`, but copying strided regions into `buffer`,
+    // so that its allocation can be reused from one frame to the next
+    #[cfg_attr(not(feature = "window"), allow(dead_code))]
+    fn contiguous_bytes_into<'b>(&'b self, buffer: &'b mut Vec<u8>) -> &'b [u8] {
+        let row_length = self.width as usize * 4;
+
+        if self.stride == row_length {
+            return &self.bytes[..row_length * self.height as usize];
+        }
+
+        buffer.clear();
+        for y in 0..self.height {
+            buffer.extend_from_slice(self.row(y).unwrap());
+        }
+        buffer
+    }
+}
+
+/// A writable region of an `RgbaImage`. Drawing operations use coordinates
+/// relative to the top-left corner of the region, and can't affect any
+/// pixels outside of it.
+pub struct RgbaImageRegionMut<'a> {
+    image: &'a mut RgbaImage,
+    rect: Rect,
+}
+
+impl<'a> RgbaImageRegionMut<'a> {
+    pub fn width(&self) -> u32 {
+        self.rect.w
+    }
+
+    pub fn height(&self) -> u32 {
+        self.rect.h
+    }
+
+    // Run `f` on the underlying image, with drawing restricted to the region
+    fn within<R>(&mut self, f: impl FnOnce(&mut RgbaImage, i32, i32) -> R) -> R {
+        let previous_clip_rect = self.image.clip_rect;
+
+        let clip_rect = match previous_clip_rect {
+            Some(clip_rect) => clip_rect.intersection(&self.rect),
+            None => Some(self.rect),
+        };
+
+        self.image.clip_rect = Some(clip_rect.unwrap_or(Rect::new(0, 0, 0, 0)));
+        let result = f(self.image, self.rect.x as i32, self.rect.y as i32);
+        self.image.clip_rect = previous_clip_rect;
+
+        result
+    }
+
+    /// Draw a single pixel at a given point.
+    pub fn set_pixel(&mut self, x: u32, y: u32, pixel: RgbaPixel) -> bool {
+        if x >= self.rect.w || y >= self.rect.h { return false; }
+        self.within(|img, left, top| img.set_pixel(left as u32 + x, top as u32 + y, pixel))
+    }
+
+    /// Retrieve a single pixel at a given point.
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<RgbaPixel> {
+        if x >= self.rect.w || y >= self.rect.h { return None; }
+        self.image.get_pixel(self.rect.x + x, self.rect.y + y)
+    }
+
+    /// Equivalent to `RgbaImage::draw()`.
+    pub fn draw<'b>(&mut self, img: impl Into<RgbaImageRegion<'b>>, x: i32, y: i32) {
+        self.within(|canvas, left, top| canvas.draw(img, left + x, top + y));
+    }
+
+    /// Equivalent to `RgbaImage::draw_with_alpha_mode()`.
+    pub fn draw_with_alpha_mode<'b>(&mut self, img: impl Into<RgbaImageRegion<'b>>, x: i32, y: i32, alpha_mode: AlphaMode) {
+        self.within(|canvas, left, top| canvas.draw_with_alpha_mode(img, left + x, top + y, alpha_mode));
+    }
+
+    /// Equivalent to `RgbaImage::draw_blended()`.
+    pub fn draw_blended<'b>(
+        &mut self,
+        img: impl Into<RgbaImageRegion<'b>>,
+        x: i32,
+        y: i32,
+        alpha_mode: AlphaMode,
+        blend_space: BlendSpace,
+    ) {
+        self.within(|canvas, left, top| canvas.draw_blended(img, left + x, top + y, alpha_mode, blend_space));
+    }
+
+    /// Equivalent to `RgbaImage::draw_masked()`.
+    pub fn draw_masked<'b, 'c>(
+        &mut self,
+        img: impl Into<RgbaImageRegion<'b>>,
+        mask: impl Into<RgbaImageRegion<'c>>,
+        x: i32,
+        y: i32,
+    ) {
+        self.within(|canvas, left, top| canvas.draw_masked(img, mask, left + x, top + y));
+    }
+
+    /// Equivalent to `RgbaImage::copy_from()`.
+    pub fn copy_from(&mut self, src: &RgbaImage, src_rect: Rect, dest_point: (i32, i32)) {
+        self.within(|canvas, left, top| {
+            canvas.copy_from(src, src_rect, (left + dest_point.0, top + dest_point.1))
+        });
+    }
+
+    /// Fill the entire region with a single color.
+    pub fn fill(&mut self, color: RgbaPixel) {
+        self.within(|canvas, _, _| canvas.fill(color));
+    }
+}
+
+impl<'a> From<&'a RgbaImage> for RgbaImageRegion<'a> {
+    fn from(img: &'a RgbaImage) -> RgbaImageRegion<'a> {
+        RgbaImageRegion {
+            width: img.width,
+            height: img.height,
+            stride: img.width as usize * 4,
+            bytes: &img.bytes,
+        }
+    }
 }
 
 pub type RgbaPixel = (u8,u8,u8,u8);
 
-const WHITE: RgbaPixel = (255, 255, 255, 255);
+/// Composite `src` on top of `dest` using the "source-over" operator,
+/// treating both pixels as straight (non-premultiplied) RGBA.
+/// The alpha channel of `dest` is taken into account and preserved,
+/// so drawing onto a transparent canvas keeps it transparent.
+fn source_over(src: &RgbaPixel, dest: &RgbaPixel) -> RgbaPixel {
+    let src_a = src.3 as f32 / 255.0;
+    let dest_a = dest.3 as f32 / 255.0;
+
+    // The fraction of the destination that remains visible
+    let dest_weight = dest_a * (1.0 - src_a);
+    let out_a = src_a + dest_weight;
+
+    if out_a <= 0.0 {
+        return (0, 0, 0, 0);
+    }
+
+    let blend = |s: u8, d: u8| {
+        ((s as f32 * src_a + d as f32 * dest_weight) / out_a).round() as u8
+    };
+
+    (
+        blend(src.0, dest.0),
+        blend(src.1, dest.1),
+        blend(src.2, dest.2),
+        (out_a * 255.0).round() as u8,
+    )
+}
 
-// Assumes that the color beneath is pure white
-fn de_alpha(pixel: &RgbaPixel, background: &RgbaPixel) -> RgbaPixel {
-    let a = pixel.3 as f32 / 255.0;
-    let r = pixel.0 as f32;
-    let g = pixel.1 as f32;
-    let b = pixel.2 as f32;
+/// Composite `src` on top of `dest` using the "source-over" operator,
+/// treating both pixels as premultiplied RGBA, where each color channel
+/// has already been multiplied by alpha.
+fn source_over_premultiplied(src: &RgbaPixel, dest: &RgbaPixel) -> RgbaPixel {
+    let inverse_src_a = 1.0 - src.3 as f32 / 255.0;
 
-    let bg_r = background.0 as f32;
-    let bg_g = background.1 as f32;
-    let bg_b = background.2 as f32;
+    let blend = |s: u8, d: u8| {
+        (s as f32 + d as f32 * inverse_src_a).round().min(255.0) as u8
+    };
 
-    let r = ((1.0 - a) * bg_r + a * r).round() as u8;
-    let g = ((1.0 - a) * bg_g + a * g).round() as u8;
-    let b = ((1.0 - a) * bg_b + a * b).round() as u8;
-    (r,g,b,255)
+    (
+        blend(src.0, dest.0),
+        blend(src.1, dest.1),
+        blend(src.2, dest.2),
+        blend(src.3, dest.3),
+    )
 }
 
 #[test]
-fn _de_alpha() {
+fn _source_over() {
+    // Semi-transparent color on an opaque background
     let rgba = (14, 18, 201, 128);
-    let after_de_alpha = de_alpha(&rgba, &WHITE);
-    assert_eq!(after_de_alpha, (134, 136, 228, 255));
+    let after_source_over = source_over(&rgba, &(255, 255, 255, 255));
+    assert_eq!(after_source_over, (134, 136, 228, 255));
+
+    // Semi-transparent color on a fully transparent background
+    let after_source_over = source_over(&rgba, &(255, 255, 255, 0));
+    assert_eq!(after_source_over, rgba);
+
+    // Semi-transparent color on a semi-transparent background
+    let after_source_over = source_over(&(255, 0, 0, 128), &(0, 0, 255, 128));
+    assert_eq!(after_source_over, (170, 0, 85, 192));
+}
+
+/// Composite `src` on top of `dest` like `source_over()`, or like
+/// `source_over_premultiplied()`, but mixing the colors as linear light
+/// rather than as sRGB-encoded values.
+fn source_over_linear(src: &RgbaPixel, dest: &RgbaPixel, alpha_mode: AlphaMode) -> RgbaPixel {
+    // Working with straight alpha, since premultiplied
+    // colors can't be decoded directly
+    let unpremultiply = |pixel: &RgbaPixel| match alpha_mode {
+        AlphaMode::Premultiplied if pixel.3 > 0 => {
+            let channel = |c: u8| (c as u32 * 255 / pixel.3 as u32).min(255) as u8;
+            (channel(pixel.0), channel(pixel.1), channel(pixel.2), pixel.3)
+        },
+        _ => *pixel,
+    };
+
+    let src = color::pixel_to_linear(&unpremultiply(src));
+    let dest = color::pixel_to_linear(&unpremultiply(dest));
+
+    let dest_weight = dest.3 * (1.0 - src.3);
+    let out_a = src.3 + dest_weight;
+    if out_a <= 0.0 {
+        return (0, 0, 0, 0);
+    }
+
+    let blend = |s: f32, d: f32| (s * src.3 + d * dest_weight) / out_a;
+    let (r, g, b, a) = color::pixel_to_srgb(&(blend(src.0, dest.0), blend(src.1, dest.1), blend(src.2, dest.2), out_a));
+
+    match alpha_mode {
+        AlphaMode::Straight => (r, g, b, a),
+        AlphaMode::Premultiplied => {
+            let channel = |c: u8| (c as f32 * out_a).round() as u8;
+            (channel(r), channel(g), channel(b), a)
+        },
+    }
+}
+
+#[test]
+fn _source_over_linear() {
+    // Mixing red and green evenly gives a brighter yellow in linear light
+    let mixed = source_over_linear(&(255, 0, 0, 128), &(0, 255, 0, 255), AlphaMode::Straight);
+    assert_eq!(mixed, (188, 187, 0, 255));
+    assert_eq!(source_over(&(255, 0, 0, 128), &(0, 255, 0, 255)), (128, 127, 0, 255));
+
+    let premultiplied = source_over_linear(&(128, 0, 0, 128), &(0, 255, 0, 255), AlphaMode::Premultiplied);
+    assert_eq!(premultiplied, mixed);
+}
+
+#[test]
+fn _source_over_premultiplied() {
+    let after_source_over = source_over_premultiplied(&(128, 0, 0, 128), &(0, 0, 128, 128));
+    assert_eq!(after_source_over, (128, 0, 64, 192));
+}
+
+// Composite a row of source pixels onto a row of destination pixels
+// of the same length. Fully opaque and fully transparent source pixels
+// are handled without any arithmetic, and straight alpha onto an opaque
+// destination (by far the most common case) uses integer math.
+fn blend_row(dest: &mut [u8], src: &[u8], alpha_mode: AlphaMode, blend_space: BlendSpace) {
+    if blend_space == BlendSpace::Linear {
+        return blend_pixels_linear(dest, src, alpha_mode);
+    }
+
+    let composite = match alpha_mode {
+        AlphaMode::Straight => source_over,
+        AlphaMode::Premultiplied => source_over_premultiplied,
+    };
+
+    let mut dest_chunks = dest.chunks_exact_mut(16);
+    let mut src_chunks = src.chunks_exact(16);
+
+    // Blending four pixels at a time using SIMD instructions
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    if alpha_mode == AlphaMode::Straight {
+        for (d, s) in (&mut dest_chunks).zip(&mut src_chunks) {
+            if d[3] & d[7] & d[11] & d[15] == 255 {
+                simd::blend_onto_opaque_x4(d, s);
+            } else {
+                blend_pixels(d, s, alpha_mode, composite);
+            }
+        }
+    }
+
+    for (d, s) in (&mut dest_chunks).zip(&mut src_chunks) {
+        blend_pixels(d, s, alpha_mode, composite);
+    }
+
+    blend_pixels(dest_chunks.into_remainder(), src_chunks.remainder(), alpha_mode, composite);
+}
+
+fn blend_pixels(
+    dest: &mut [u8],
+    src: &[u8],
+    alpha_mode: AlphaMode,
+    composite: fn(&RgbaPixel, &RgbaPixel) -> RgbaPixel,
+) {
+    for (d, s) in dest.chunks_exact_mut(4).zip(src.chunks_exact(4)) {
+        if alpha_mode == AlphaMode::Straight {
+            match (s[3], d[3]) {
+                (255, _) => { d.copy_from_slice(s); continue }
+                (0, _) => { continue }
+                (a, 255) => {
+                    // Equivalent to `source_over()` for an opaque destination
+                    let (a, inverse_a) = (a as u32, 255 - a as u32);
+                    for channel in 0..3 {
+                        let sum = s[channel] as u32 * a + d[channel] as u32 * inverse_a;
+                        d[channel] = ((sum + 127) / 255) as u8;
+                    }
+                    continue;
+                },
+                _ => {},
+            }
+        }
+
+        let (r, g, b, a) = composite(&(s[0], s[1], s[2], s[3]), &(d[0], d[1], d[2], d[3]));
+        d.copy_from_slice(&[r, g, b, a]);
+    }
+}
+
+fn blend_pixels_linear(dest: &mut [u8], src: &[u8], alpha_mode: AlphaMode) {
+    for (d, s) in dest.chunks_exact_mut(4).zip(src.chunks_exact(4)) {
+        match s[3] {
+            255 => { d.copy_from_slice(s); continue }
+            0 if alpha_mode == AlphaMode::Straight => { continue }
+            _ => {},
+        }
+
+        let (r, g, b, a) = source_over_linear(&(s[0], s[1], s[2], s[3]), &(d[0], d[1], d[2], d[3]), alpha_mode);
+        d.copy_from_slice(&[r, g, b, a]);
+    }
+}
+
+#[test]
+fn _blend_row() {
+    let src: Vec<u8> = (0..9u8).flat_map(|i| [i * 20, 255 - i * 20, 90, i * 30]).collect();
+    let mut dest: Vec<u8> = (0..9u8).flat_map(|i| [200, i * 10, 50, 255 - (i / 4 % 2) * (i % 2) * 100]).collect();
+    let expected: Vec<u8> = src.chunks(4).zip(dest.chunks(4)).flat_map(|(s, d)| {
+        let (r, g, b, a) = source_over(&(s[0], s[1], s[2], s[3]), &(d[0], d[1], d[2], d[3]));
+        [r, g, b, a]
+    }).collect();
+
+    blend_row(&mut dest, &src, AlphaMode::Straight, BlendSpace::Srgb);
+    assert_eq!(dest, expected);
+}
+
+/// The way that color channels relate to the alpha channel of an image.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AlphaMode {
+    /// Color channels are independent of alpha.
+    Straight,
+    /// Color channels have already been multiplied by alpha.
+    Premultiplied,
+}
+
+/// How colors are mixed when one image is drawn over another.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BlendSpace {
+    /// Mixing the sRGB-encoded values directly, which is fast, but
+    /// makes blends between bright colors look darker than they should.
+    Srgb,
+    /// Mixing colors as linear light, which avoids dark fringes around
+    /// anti-aliased and translucent sprites, at some cost in speed.
+    Linear,
+}
+
+/// Call `f` with the index and bytes of every row of an image that is
+/// `width` pixels wide. When the `rayon` feature is enabled, rows are
+/// processed in parallel across threads.
+pub(crate) fn for_each_row<F>(bytes: &mut [u8], width: u32, f: F)
+where
+    F: Fn(u32, &mut [u8]) + Send + Sync,
+{
+    let row_length = (width as usize * 4).max(1);
+
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        bytes.par_chunks_mut(row_length).enumerate().for_each(|(y, row)| f(y as u32, row));
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    bytes.chunks_mut(row_length).enumerate().for_each(|(y, row)| f(y as u32, row));
 }
 
 impl RgbaImage {
@@ -184,6 +1745,7 @@ impl RgbaImage {
             width: w,
             height: h,
             bytes: vec![0; (w as usize * h as usize) * 4],
+            clip_rect: None,
         }
     }
 
@@ -195,14 +1757,29 @@ impl RgbaImage {
         self.height
     }
 
+    /// Restrict all subsequent drawing to the given area of the image,
+    /// or remove the restriction by passing `None`.
+    pub fn set_clip_rect(&mut self, clip_rect: Option<Rect>) {
+        self.clip_rect = clip_rect;
+    }
+
+    pub fn clip_rect(&self) -> Option<Rect> {
+        self.clip_rect
+    }
+
     /// Draw a single pixel at a given point.
+    /// Points outside of the image or the clipping rectangle are ignored.
     pub fn set_pixel(&mut self, x: u32, y: u32, pixel: RgbaPixel) -> bool {
         if x >= self.width { return false; }
         if y >= self.height { return false; }
 
+        if let Some(clip_rect) = self.clip_rect {
+            if !clip_rect.contains(x, y) { return false; }
+        }
+
         let index = (((self.width * y) + x) * 4) as usize;
 
-        self.bytes[index + 0] = pixel.0;
+        self.bytes[index] = pixel.0;
         self.bytes[index + 1] = pixel.1;
         self.bytes[index + 2] = pixel.2;
         self.bytes[index + 3] = pixel.3;
@@ -219,45 +1796,278 @@ impl RgbaImage {
         }
 
         Some((
-            self.bytes[index + 0],
+            self.bytes[index],
             self.bytes[index + 1],
             self.bytes[index + 2],
             self.bytes[index + 3],
         ))
     }
 
-    /// Superimpose another `RgbaImage` on top of this one,
-    /// with its top-left corner at the given point.
-    pub fn draw(&mut self, img: &RgbaImage, x: i32, y: i32) {
-        for img_y in 0..img.height {
-            for img_x in 0..img.width {
-                let pixel = img.get_pixel(img_x, img_y).unwrap();
+    /// Superimpose another `RgbaImage` (or a region of one) on top of
+    /// this one, with its top-left corner at the given point.
+    pub fn draw<'a>(&mut self, img: impl Into<RgbaImageRegion<'a>>, x: i32, y: i32) {
+        self.draw_with_alpha_mode(img, x, y, AlphaMode::Straight);
+    }
+
+    /// Superimpose another `RgbaImage` (or a region of one) on top of
+    /// this one, with its top-left corner at the given point, interpreting
+    /// both images according to `alpha_mode`.
+    pub fn draw_with_alpha_mode<'a>(&mut self, img: impl Into<RgbaImageRegion<'a>>, x: i32, y: i32, alpha_mode: AlphaMode) {
+        self.draw_blended(img, x, y, alpha_mode, BlendSpace::Srgb);
+    }
+
+    /// Superimpose another `RgbaImage` (or a region of one) on top of
+    /// this one, like `draw_with_alpha_mode()`, mixing colors in `blend_space`.
+    pub fn draw_blended<'a>(
+        &mut self,
+        img: impl Into<RgbaImageRegion<'a>>,
+        x: i32,
+        y: i32,
+        alpha_mode: AlphaMode,
+        blend_space: BlendSpace,
+    ) {
+        let img = img.into();
+
+        let area = match self.writable_area() {
+            Some(area) => area,
+            None => return,
+        };
+
+        // Determining which columns of this image `img` overlaps
+        let (x, y) = (x as i64, y as i64);
+        let start_x = x.max(area.x as i64);
+        let end_x = (x + img.width as i64).min((area.x + area.w) as i64);
+        if end_x <= start_x { return }
+
+        for_each_row(&mut self.bytes, self.width, |canvas_y, row| {
+            if !area.contains(area.x, canvas_y) { return }
+
+            let img_y = canvas_y as i64 - y;
+            if img_y < 0 || img_y >= img.height as i64 { return }
+
+            let src_row = match img.row(img_y as u32) {
+                Some(src_row) => src_row,
+                None => return,
+            };
+
+            let src = &src_row[(start_x - x) as usize * 4..(end_x - x) as usize * 4];
+            let dest = &mut row[start_x as usize * 4..end_x as usize * 4];
+            blend_row(dest, src, alpha_mode, blend_space);
+        });
+    }
+
+    /// Superimpose another `RgbaImage` on top of this one, like `draw()`,
+    /// but only where `mask` allows it. The mask is aligned with `img`,
+    /// and each of its pixels gates the corresponding pixel of `img`:
+    /// opaque white lets it through entirely, while black or transparent
+    /// pixels block it. Shades in between produce partial coverage, so both
+    /// grayscale masks and alpha masks work as expected.
+    pub fn draw_masked<'a, 'b>(
+        &mut self,
+        img: impl Into<RgbaImageRegion<'a>>,
+        mask: impl Into<RgbaImageRegion<'b>>,
+        x: i32,
+        y: i32,
+    ) {
+        let img = img.into();
+        let mask = mask.into();
+
+        for img_y in 0..img.height {
+            for img_x in 0..img.width {
+                let coverage = match mask.get_pixel(img_x, img_y) {
+                    Some((r, g, b, a)) => {
+                        let luminance = (r as f32 + g as f32 + b as f32) / (3.0 * 255.0);
+                        luminance * (a as f32 / 255.0)
+                    },
+                    None => { continue }
+                };
+
+                let canvas_x = x + img_x as i32;
+                let canvas_y = y + img_y as i32;
+
+                if canvas_x >= 0 && canvas_y >= 0 {
+                    let target_pixel = match self.get_pixel(canvas_x as u32, canvas_y as u32) {
+                        Some(pixel) => pixel,
+                        None => { continue }
+                    };
+
+                    // Scaling the opacity of the incoming pixel by the mask
+                    let mut pixel = img.get_pixel(img_x, img_y).unwrap();
+                    pixel.3 = (pixel.3 as f32 * coverage).round() as u8;
+
+                    let pixel = source_over(&pixel, &target_pixel);
+                    self.set_pixel(canvas_x as u32, canvas_y as u32, pixel);
+                }
+            }
+        }
+    }
+
+    /// Copy a rectangular area of `src` into this image, with its top-left
+    /// corner at `dest_point`. Pixels are copied exactly, one row at a time,
+    /// without any alpha blending. This is much faster than `draw()`
+    /// for opaque images like tiles.
+    pub fn copy_from(&mut self, src: &RgbaImage, src_rect: Rect, dest_point: (i32, i32)) {
+        let src_bounds = Rect::new(0, 0, src.width, src.height);
+        let src_rect = match src_rect.intersection(&src_bounds) {
+            Some(rect) => rect,
+            None => return,
+        };
+
+        let dest_bounds = match self.writable_area() {
+            Some(area) => area,
+            None => return,
+        };
+
+        // Positioning the source rectangle in this image
+        let (dest_x, dest_y) = (dest_point.0 as i64, dest_point.1 as i64);
+        let left = dest_x.max(dest_bounds.x as i64);
+        let top = dest_y.max(dest_bounds.y as i64);
+        let right = (dest_x + src_rect.w as i64).min((dest_bounds.x + dest_bounds.w) as i64);
+        let bottom = (dest_y + src_rect.h as i64).min((dest_bounds.y + dest_bounds.h) as i64);
+
+        if right <= left || bottom <= top {
+            return;
+        }
+
+        let row_length = ((right - left) * 4) as usize;
+
+        for y in top..bottom {
+            let src_x = src_rect.x as i64 + (left - dest_x);
+            let src_y = src_rect.y as i64 + (y - dest_y);
+
+            let src_start = ((src_y * src.width as i64 + src_x) * 4) as usize;
+            let dest_start = ((y * self.width as i64 + left) * 4) as usize;
+
+            self.bytes[dest_start..dest_start + row_length]
+                .copy_from_slice(&src.bytes[src_start..src_start + row_length]);
+        }
+    }
+
+    // The area of the image that drawing operations are allowed to change
+    fn writable_area(&self) -> Option<Rect> {
+        let bounds = Rect::new(0, 0, self.width, self.height);
+        if bounds.w == 0 || bounds.h == 0 { return None; }
+
+        match self.clip_rect {
+            Some(clip_rect) => clip_rect.intersection(&bounds),
+            None => Some(bounds),
+        }
+    }
+
+    /// Fill the entire image with a single color.
+    pub fn fill(&mut self, color: RgbaPixel) {
+        let area = match self.writable_area() {
+            Some(area) => area,
+            None => return,
+        };
+
+        // Building a single row of color, which can be copied in bulk
+        let span = [color.0, color.1, color.2, color.3].repeat(area.w as usize);
+        let start = area.x as usize * 4;
+
+        for_each_row(&mut self.bytes, self.width, |y, row| {
+            if !area.contains(area.x, y) { return }
+            row[start..start + span.len()].copy_from_slice(&span);
+        });
+    }
+
+    /// Fill a rectangular area of the image with a single color.
+    pub fn fill_rect(&mut self, rect: Rect, color: RgbaPixel) {
+        let previous = self.clip_rect;
+
+        // Narrowing the clipping rectangle to the area being filled
+        let area = match previous {
+            Some(clip_rect) => clip_rect.intersection(&rect),
+            None => Some(rect),
+        };
+
+        if let Some(area) = area {
+            self.clip_rect = Some(area);
+            self.fill(color);
+        }
+
+        self.clip_rect = previous;
+    }
+
+    // Replace every pixel with the result of `f`
+    fn map_pixels(&mut self, f: impl Fn(RgbaPixel) -> RgbaPixel + Send + Sync) {
+        let area = match self.writable_area() {
+            Some(area) => area,
+            None => return,
+        };
+
+        let start = area.x as usize * 4;
+        let end = (area.x + area.w) as usize * 4;
+
+        for_each_row(&mut self.bytes, self.width, |y, row| {
+            if !area.contains(area.x, y) { return }
+
+            for pixel in row[start..end].chunks_exact_mut(4) {
+                let (r, g, b, a) = f((pixel[0], pixel[1], pixel[2], pixel[3]));
+                pixel.copy_from_slice(&[r, g, b, a]);
+            }
+        });
+    }
+
+    /// Rotate the hue of every pixel by the given number of degrees.
+    pub fn adjust_hue(&mut self, degrees: f32) {
+        self.map_pixels(|pixel| {
+            let (h, s, v) = color::rgb_to_hsv(&pixel);
+            color::hsv_to_rgb((h + degrees, s, v), pixel.3)
+        });
+    }
+
+    /// Multiply the saturation of every pixel by `factor`.
+    /// A factor of `0.0` produces a grayscale image.
+    pub fn adjust_saturation(&mut self, factor: f32) {
+        self.map_pixels(|pixel| {
+            let (h, s, v) = color::rgb_to_hsv(&pixel);
+            color::hsv_to_rgb((h, s * factor, v), pixel.3)
+        });
+    }
+
+    /// Multiply the brightness (HSV value) of every pixel by `factor`.
+    pub fn adjust_brightness(&mut self, factor: f32) {
+        self.map_pixels(|pixel| {
+            let (h, s, v) = color::rgb_to_hsv(&pixel);
+            color::hsv_to_rgb((h, s, v * factor), pixel.3)
+        });
+    }
 
-                let canvas_x = x + img_x as i32;
-                let canvas_y = y + img_y as i32;
+    /// Replace the color of every pixel with its luminance.
+    pub fn to_grayscale(&mut self) {
+        self.map_pixels(|pixel| {
+            let luminance = color::luminance(&pixel);
+            (luminance, luminance, luminance, pixel.3)
+        });
+    }
 
-                if canvas_x >= 0 && canvas_y >= 0 {
-                    let target_pixel = match self.get_pixel(canvas_x as u32, canvas_y as u32) {
-                        Some(pixel) => pixel,
-                        None => { continue }
-                    };
+    /// Replace the color of every pixel with its opposite.
+    pub fn invert(&mut self) {
+        self.map_pixels(|(r, g, b, a)| (255 - r, 255 - g, 255 - b, a));
+    }
 
-                    // Converting both pixels to RGB before overwriting
-                    let target_pixel = de_alpha(&target_pixel, &WHITE);
-                    let pixel = de_alpha(&pixel, &target_pixel);
-                    self.set_pixel(canvas_x as u32, canvas_y as u32, pixel);
-                }
-            }
-        }
+    /// Give every pixel the warm brown tint of an old photograph.
+    pub fn sepia(&mut self) {
+        self.map_pixels(|(r, g, b, a)| {
+            let (r, g, b) = (r as f32, g as f32, b as f32);
+            let channel = |c: f32| c.round().min(255.0) as u8;
+            (
+                channel(0.393 * r + 0.769 * g + 0.189 * b),
+                channel(0.349 * r + 0.686 * g + 0.168 * b),
+                channel(0.272 * r + 0.534 * g + 0.131 * b),
+                a,
+            )
+        });
     }
 
-    /// Fill the entire image with a single color.
-    pub fn fill(&mut self, color: RgbaPixel) {
-        for y in 0..self.height {
-            for x in 0..self.width {
-                self.set_pixel(x, y, color);
-            }
-        }
+    /// Make every pixel white if its luminance is at least `level`,
+    /// or black otherwise.
+    pub fn threshold(&mut self, level: u8) {
+        self.map_pixels(|pixel| {
+            let value = if color::luminance(&pixel) >= level { 255 } else { 0 };
+            (value, value, value, pixel.3)
+        });
     }
 
     /// Expand or shrink the entire image by a given scaling factor.
@@ -271,8 +2081,8 @@ impl RgbaImage {
         let ratio_x = 1.0 / new_img.width as f32;
         let ratio_y = 1.0 / new_img.height as f32;
 
-        for y in 0..new_img.height {
-            for x in 0..new_img.width {
+        for_each_row(&mut new_img.bytes, new_img.width, |y, row| {
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
 
                 // Determining which x and y values to sample from
                 let progress_x = ratio_x * x as f32;
@@ -282,84 +2092,379 @@ impl RgbaImage {
                 let src_y = progress_y * img.height as f32;
 
                 // Applying the sampled pixel to the output image
-                let pixel = img.get_pixel(src_x as u32, src_y as u32).unwrap();
-                new_img.set_pixel(x, y, pixel);
+                let (r, g, b, a) = img.get_pixel(src_x as u32, src_y as u32).unwrap();
+                pixel.copy_from_slice(&[r, g, b, a]);
             }
-        }
+        });
 
         new_img
     }
 
 
-    pub fn as_region(&self) -> RgbaImageRegion {
-        self.get_region(
-            (0, 0),
-            (self.width() - 1, self.height() - 1),
-        ).unwrap()
+    /// Approximate the image as text that is `width` characters wide, for
+    /// printing to a terminal. Brighter pixels are drawn with denser
+    /// characters, and transparent pixels are treated as black. Each
+    /// character covers an area twice as tall as it is wide, to account
+    /// for the shape of terminal fonts.
+    pub fn to_ascii(&self, width: u32) -> String {
+        const RAMP: &[u8] = b" .:-=+*#%@";
+
+        if width == 0 || self.width == 0 || self.height == 0 {
+            return String::new();
+        }
+
+        let cell_w = self.width as f32 / width as f32;
+        let cell_h = cell_w * 2.0;
+        let height = ((self.height as f32 / cell_h).ceil() as u32).max(1);
+
+        let mut ascii = String::with_capacity(((width + 1) * height) as usize);
+
+        for row in 0..height {
+            for column in 0..width {
+                // Determining which pixels fall within this character
+                let start_x = (column as f32 * cell_w) as u32;
+                let start_y = (row as f32 * cell_h) as u32;
+                let end_x = (((column + 1) as f32 * cell_w) as u32).clamp(start_x + 1, self.width);
+                let end_y = (((row + 1) as f32 * cell_h) as u32).clamp(start_y + 1, self.height);
+
+                // Averaging their brightness
+                let mut total = 0.0;
+                for y in start_y..end_y {
+                    for x in start_x..end_x {
+                        let pixel = self.get_pixel(x, y).unwrap();
+                        total += color::luminance(&pixel) as f32 * pixel.3 as f32 / 255.0;
+                    }
+                }
+                let average = total / ((end_x - start_x) * (end_y - start_y)) as f32;
+
+                let index = (average / 256.0 * RAMP.len() as f32) as usize;
+                ascii.push(RAMP[index.min(RAMP.len() - 1)] as char);
+            }
+            ascii.push('\n');
+        }
+
+        ascii
+    }
+
+    /// Borrow a rectangular area of the image for drawing,
+    /// or `None` if the rectangle doesn't fit within the image.
+    pub fn region_mut(&mut self, rect: Rect) -> Option<RgbaImageRegionMut<'_>> {
+        if !self.fits(&rect) { return None; }
+        Some(RgbaImageRegionMut { image: self, rect })
+    }
+
+    // Whether `rect` is non-empty, and entirely within the image
+    fn fits(&self, rect: &Rect) -> bool {
+        rect.w > 0 && rect.h > 0 &&
+        rect.x as u64 + rect.w as u64 <= self.width as u64 &&
+        rect.y as u64 + rect.h as u64 <= self.height as u64
     }
 
-    pub fn get_region(&self, top_left: (u32, u32), bottom_right: (u32, u32)) -> Option<RgbaImageRegion> {
-        let (start_x, start_y) = top_left;
-        let start_index = (((self.width * start_y) + start_x) * 4) as usize;
+    // Replace the contents and dimensions of this image with a copy of
+    // `region`, reusing the existing allocation where possible
+    fn copy_region(&mut self, region: &RgbaImageRegion) {
+        self.width = region.width;
+        self.height = region.height;
+        self.bytes.clear();
+        self.bytes.extend_from_slice(&region.contiguous_bytes());
+    }
+
+    pub fn as_region(&self) -> RgbaImageRegion<'_> {
+        self.into()
+    }
+
+    /// Borrow a rectangular area of the image for reading,
+    /// or `None` if the rectangle doesn't fit within the image.
+    pub fn region(&self, rect: Rect) -> Option<RgbaImageRegion<'_>> {
+        if !self.fits(&rect) { return None; }
+
+        let stride = self.width as usize * 4;
+        let start = rect.y as usize * stride + rect.x as usize * 4;
+        let end = (rect.y + rect.h - 1) as usize * stride + (rect.x + rect.w) as usize * 4;
 
+        Some(RgbaImageRegion {
+            width: rect.w,
+            height: rect.h,
+            stride,
+            bytes: &self.bytes[start..end],
+        })
+    }
+
+    /// Borrow the rectangular area of the image between two corners,
+    /// both of which are included in the region.
+    pub fn get_region(&self, top_left: (u32, u32), bottom_right: (u32, u32)) -> Option<RgbaImageRegion<'_>> {
+        let (start_x, start_y) = top_left;
         let (end_x, end_y) = bottom_right;
-        let end_index = (((self.width * end_y) + end_x) * 4) as usize + 3;
 
         if end_x < start_x { return None; }
         if end_y < start_y { return None; }
 
-        if start_index >= self.bytes.len() { return None; }
-        if end_index > self.bytes.len() { return None; }
+        self.region(Rect::new(start_x, start_y, 1 + end_x - start_x, 1 + end_y - start_y))
+    }
+}
+
+#[test]
+fn _clip_rect() {
+    let mut img = RgbaImage::new(4, 4);
+    img.set_clip_rect(Some(Rect::new(1, 1, 2, 2)));
+    img.fill((255, 0, 0, 255));
+
+    assert_eq!(img.get_pixel(0, 0), Some((0, 0, 0, 0)));
+    assert_eq!(img.get_pixel(1, 1), Some((255, 0, 0, 255)));
+    assert_eq!(img.get_pixel(2, 2), Some((255, 0, 0, 255)));
+    assert_eq!(img.get_pixel(3, 3), Some((0, 0, 0, 0)));
+}
 
-        let width = 1 + end_x - start_x;
-        let height = 1 + end_y - start_y;
-        let bytes =  &self.bytes[start_index..end_index+1];
+#[test]
+fn _draw_region() {
+    let mut atlas = RgbaImage::new(2, 2);
+    atlas.set_pixel(0, 1, (255, 0, 0, 255));
+    atlas.set_pixel(1, 1, (0, 255, 0, 255));
 
-        Some(RgbaImageRegion {
-            width,
-            height,
-            bytes,
-        })
-    }
+    let mut canvas = RgbaImage::new(2, 1);
+    canvas.draw(atlas.get_region((0, 1), (1, 1)).unwrap(), 0, 0);
+
+    assert_eq!(canvas.get_pixel(0, 0), Some((255, 0, 0, 255)));
+    assert_eq!(canvas.get_pixel(1, 0), Some((0, 255, 0, 255)));
+}
+
+#[test]
+fn _copy_from() {
+    let mut src = RgbaImage::new(3, 3);
+    src.set_pixel(1, 1, (1, 2, 3, 4));
+    src.set_pixel(2, 2, (5, 6, 7, 8));
+
+    let mut dest = RgbaImage::new(3, 3);
+    dest.copy_from(&src, Rect::new(1, 1, 2, 2), (-1, 0));
+
+    assert_eq!(dest.get_pixel(0, 0), Some((0, 0, 0, 0)));
+    assert_eq!(dest.get_pixel(0, 1), Some((5, 6, 7, 8)));
+    assert_eq!(dest.get_pixel(1, 1), Some((0, 0, 0, 0)));
+}
+
+#[test]
+fn _draw_masked() {
+    let mut img = RgbaImage::new(4, 1);
+    img.fill((255, 255, 255, 255));
+
+    // Opaque white, half transparent white, opaque mid-gray, and black
+    let mut mask = RgbaImage::new(4, 1);
+    mask.set_pixel(0, 0, (255, 255, 255, 255));
+    mask.set_pixel(1, 0, (255, 255, 255, 128));
+    mask.set_pixel(2, 0, (128, 128, 128, 255));
+    mask.set_pixel(3, 0, (0, 0, 0, 255));
+
+    let mut canvas = RgbaImage::new(4, 1);
+    canvas.fill((0, 0, 0, 255));
+    canvas.draw_masked(&img, &mask, 0, 0);
+
+    let row: Vec<_> = (0..4).map(|x| canvas.get_pixel(x, 0).unwrap().0).collect();
+    assert_eq!(row, vec![255, 128, 128, 0]);
+
+    // Pixels outside the canvas, or outside its clip rect, are left alone
+    canvas.fill((0, 0, 0, 255));
+    canvas.set_clip_rect(Some(Rect::new(0, 0, 2, 1)));
+    canvas.draw_masked(&img, &mask, 1, 0);
+
+    let row: Vec<_> = (0..4).map(|x| canvas.get_pixel(x, 0).unwrap().0).collect();
+    assert_eq!(row, vec![0, 255, 0, 0]);
+
+    canvas.set_clip_rect(None);
+    canvas.draw_masked(&img, &mask, -1, 0);
+
+    let row: Vec<_> = (0..4).map(|x| canvas.get_pixel(x, 0).unwrap().0).collect();
+    assert_eq!(row, vec![128, 255, 0, 0]);
+}
+
+#[test]
+fn _to_grayscale() {
+    let mut img = RgbaImage::new(2, 1);
+    img.set_pixel(0, 0, (255, 0, 0, 255));
+    img.set_pixel(1, 0, (0, 0, 255, 100));
+    img.to_grayscale();
+
+    assert_eq!(img.get_pixel(0, 0), Some((76, 76, 76, 255)));
+    assert_eq!(img.get_pixel(1, 0), Some((29, 29, 29, 100)));
+}
+
+#[test]
+fn _invert() {
+    let mut img = RgbaImage::new(2, 1);
+    img.fill((10, 20, 30, 40));
+
+    // Only pixels within the clip rect are changed
+    img.set_clip_rect(Some(Rect::new(1, 0, 1, 1)));
+    img.invert();
+
+    assert_eq!(img.get_pixel(0, 0), Some((10, 20, 30, 40)));
+    assert_eq!(img.get_pixel(1, 0), Some((245, 235, 225, 40)));
+}
+
+#[test]
+fn _sepia() {
+    let mut img = RgbaImage::new(2, 1);
+    img.set_pixel(0, 0, (100, 100, 100, 255));
+    img.set_pixel(1, 0, (255, 255, 255, 7));
+    img.sepia();
+
+    assert_eq!(img.get_pixel(0, 0), Some((135, 120, 94, 255)));
+    assert_eq!(img.get_pixel(1, 0), Some((255, 255, 239, 7)));
+}
+
+#[test]
+fn _threshold() {
+    let mut img = RgbaImage::new(3, 1);
+    img.set_pixel(0, 0, (127, 127, 127, 255));
+    img.set_pixel(1, 0, (128, 128, 128, 50));
+    img.set_pixel(2, 0, (0, 255, 0, 255));
+    img.threshold(128);
+
+    assert_eq!(img.get_pixel(0, 0), Some((0, 0, 0, 255)));
+    assert_eq!(img.get_pixel(1, 0), Some((255, 255, 255, 50)));
+    assert_eq!(img.get_pixel(2, 0), Some((255, 255, 255, 255)));
+}
+
+#[test]
+fn _image_traits() {
+    let mut img = RgbaImage::new(2, 2);
+    img.set_pixel(1, 1, (1, 2, 3, 4));
+
+    // Clones don't share pixels, and equality ignores the clip rect
+    let mut copy = img.clone();
+    assert_eq!(copy, img);
+    copy.set_clip_rect(Some(Rect::new(0, 0, 1, 1)));
+    assert_eq!(copy, img);
+    copy.set_clip_rect(None);
+    copy.set_pixel(0, 0, (9, 9, 9, 9));
+    assert_ne!(copy, img);
+    assert_eq!(img.get_pixel(0, 0), Some((0, 0, 0, 0)));
+    assert_ne!(RgbaImage::new(1, 4), RgbaImage::new(4, 1));
+
+    // Debug output summarizes the image instead of listing its bytes
+    let debug = format!("{:?}", img);
+    assert_eq!(debug, "RgbaImage { width: 2, height: 2, clip_rect: None, .. }");
+
+    let region = img.as_region();
+    let copied = region;
+    assert_eq!(copied.get_pixel(1, 1), region.get_pixel(1, 1));
+}
+
+#[test]
+fn _content_hash() {
+    let mut img = RgbaImage::new(2, 1);
+    img.set_pixel(0, 0, (255, 0, 0, 255));
+    img.set_pixel(1, 0, (0, 0, 255, 255));
+    assert_eq!(img.as_region().content_hash(), 0x226f_de2b_8d0b_756a);
+
+    // Regions hash the same as copies of them, and dimensions count
+    let mut larger = RgbaImage::new(3, 2);
+    larger.copy_from(&img, Rect::new(0, 0, 2, 1), (1, 1));
+    let region = larger.region(Rect::new(1, 1, 2, 1)).unwrap();
+    assert_eq!(region.content_hash(), img.as_region().content_hash());
+    assert_ne!(RgbaImage::new(1, 2).as_region().content_hash(), RgbaImage::new(2, 1).as_region().content_hash());
+}
+
+#[test]
+fn _region_mut() {
+    let mut img = RgbaImage::new(4, 4);
+    assert!(img.region_mut(Rect::new(2, 2, 4, 4)).is_none());
+
+    let mut region = img.region_mut(Rect::new(2, 2, 2, 2)).unwrap();
+
+    region.fill((255, 0, 0, 255));
+    region.draw(&RgbaImage::new(1, 1), -1, -1);
+
+    assert_eq!(img.get_pixel(1, 1), Some((0, 0, 0, 0)));
+    assert_eq!(img.get_pixel(2, 2), Some((255, 0, 0, 255)));
+    assert_eq!(img.get_pixel(3, 3), Some((255, 0, 0, 255)));
+    assert_eq!(img.clip_rect(), None);
 }
 
-#[derive(Copy, Clone, Debug)]
-struct Vertex {
-    // The vector denoting the area of incoming textures that will be
-    // used for drawing. This could be used to crop incoming textures.
-    src: [f32; 2],
-    // The vector denoting the area of the window that incoming textures
-    // will be drawn onto. This could be used to only draw textures on
-    // a partial area of the output.
-    dest: [f32; 2],
+#[test]
+fn _region() {
+    let mut img = RgbaImage::new(4, 4);
+    img.set_pixel(1, 2, (1, 2, 3, 4));
+    img.set_pixel(2, 2, (5, 6, 7, 8));
+
+    let region = img.region(Rect::new(1, 1, 2, 2)).unwrap();
+    assert_eq!(region.get_pixel(0, 1), Some((1, 2, 3, 4)));
+    assert_eq!(region.get_pixel(1, 1), Some((5, 6, 7, 8)));
+    assert_eq!(region.get_pixel(2, 1), None);
+    assert_eq!(region.contiguous_bytes().len(), 16);
+
+    assert!(img.region(Rect::new(3, 3, 2, 1)).is_none());
+
+    // Regions of regions are relative to the outer region
+    let inner = region.region(Rect::new(1, 1, 1, 1)).unwrap();
+    assert_eq!(inner.get_pixel(0, 0), Some((5, 6, 7, 8)));
+    assert!(region.region(Rect::new(1, 1, 2, 1)).is_none());
+}
+
+#[test]
+fn _to_ascii() {
+    let mut img = RgbaImage::new(4, 4);
+    img.region_mut(Rect::new(2, 0, 2, 4)).unwrap().fill((255, 255, 255, 255));
+    assert_eq!(img.to_ascii(2), " @\n");
+    assert_eq!(img.to_ascii(4), "  @@\n  @@\n");
 }
 
-fn calculate_vertices(size: &LogicalSize<f32>, pixels: &RgbaImageRegion) -> Vec<Vertex> {
-    let ui_h = size.height;
-    let ui_w = size.width;
+// The size that a frame will be displayed at within the window, in
+// physical pixels, when its aspect ratio is preserved
+fn letterboxed_size(size: (f32, f32), frame_w: u32, frame_h: u32) -> (f32, f32) {
+    let (ui_w, ui_h) = size;
 
     // Defining the number that the image will be scaled by
     // to fit nicely on the UI
-    let scalar = {
-        if ui_w > ui_h { ui_h / pixels.height as f32 }
-        else { ui_w / pixels.width as f32 }
+    let scalar = (ui_w / frame_w.max(1) as f32).min(ui_h / frame_h.max(1) as f32);
+
+    // Leaving a whole number of pixels on either side, so that the frame's
+    // edges line up with the screen's pixels at fractional scale factors
+    let pixel_aligned = |ui: f32, img: f32| ui - 2.0 * ((ui - img) / 2.0).round().max(0.0);
+
+    (pixel_aligned(ui_w, frame_w as f32 * scalar), pixel_aligned(ui_h, frame_h as f32 * scalar))
+}
+
+#[test]
+fn _letterboxed_size() {
+    assert_eq!(letterboxed_size((200.0, 100.0), 10, 10), (100.0, 100.0));
+    // Tall frames in wide windows are limited by the window's height
+    assert_eq!(letterboxed_size((400.0, 300.0), 100, 10), (400.0, 40.0));
+    // Odd leftovers are split evenly, rather than offsetting the frame by half a pixel
+    assert_eq!(letterboxed_size((960.0, 541.0), 320, 180), (960.0, 539.0));
+    assert_eq!(letterboxed_size((963.0, 540.0), 320, 180), (959.0, 540.0));
+}
+
+/// Convert a position within the window, as reported by
+/// `CursorMovementEvent`, into a position on frames with the given
+/// dimensions, accounting for the way that frames are stretched or
+/// letterboxed to fit the window. The result can lie outside of
+/// the frame, if the position is within the letterboxing.
+pub fn window_to_frame(
+    position: (u32, u32),
+    window_size: (u32, u32),
+    frame_size: (u32, u32),
+    preserve_aspect_ratio: bool,
+) -> (f32, f32) {
+    let (window_w, window_h) = (window_size.0 as f32, window_size.1 as f32);
+    let (img_w, img_h) = if preserve_aspect_ratio {
+        letterboxed_size((window_w, window_h), frame_size.0, frame_size.1)
+    } else {
+        (window_w, window_h)
     };
 
-    // Defining "actual image width / height"
-    let img_w = pixels.width as f32 * scalar;
-    let img_h = pixels.height as f32 * scalar;
+    // Measuring from the top-left corner of the displayed frame
+    let x = position.0 as f32 - (window_w - img_w) / 2.0;
+    let y = position.1 as f32 - (window_h - img_h) / 2.0;
 
-    // Defining vector magnitudes that will correctly
-    // position the 4 vertices.
-    let mag_x = img_w / ui_w;
-    let mag_y = img_h / ui_h;
+    (x * frame_size.0 as f32 / img_w, y * frame_size.1 as f32 / img_h)
+}
 
-    vec![
-        Vertex { dest: [-mag_x, -mag_y ], src: [0.0, 0.0] },
-        Vertex { dest: [ mag_x, -mag_y ], src: [1.0, 0.0] },
-        Vertex { dest: [ mag_x,  mag_y ], src: [1.0, 1.0] },
-        Vertex { dest: [-mag_x,  mag_y ], src: [0.0, 1.0] },
-    ]
+#[test]
+fn _window_to_frame() {
+    // A square frame in a wide window is letterboxed on both sides
+    assert_eq!(window_to_frame((100, 50), (200, 100), (10, 10), true), (5.0, 5.0));
+    assert_eq!(window_to_frame((40, 0), (200, 100), (10, 10), true), (-1.0, 0.0));
+    assert_eq!(window_to_frame((150, 50), (200, 100), (10, 10), false), (7.5, 5.0));
 }
 
 /// A data-less struct that manages the application.
@@ -368,572 +2473,472 @@ fn calculate_vertices(size: &LogicalSize<f32>, pixels: &RgbaImageRegion) -> Vec<
 pub struct UI;
 
 impl UI {
-    /// Start the application using the given `UIController` 
-    pub fn launch<T: 'static + UIController>(mut controller: T) {
-        implement_vertex!(Vertex, dest, src);
-
+    /// Run a controller for `frames` frames without opening a window, as
+    /// fast as possible, saving each frame to a numbered PNG file such as
+    /// `frame-00000.png` in `directory`. When the controller returns `None`,
+    /// the previous frame is saved again. Stops early if the controller
+    /// asks to exit, returning its error if it gave one. No events are
+    /// delivered, unless the controller's blueprint replays a recording
+    /// with `replay_input()`.
+    pub fn render_offline<T: UIController>(mut controller: T, frames: u32, directory: &str) -> Result<(), OpenUiError> {
         let blueprint = controller.blueprint();
-        let event_loop = glutin::event_loop::EventLoop::new();
-
-        let (width, height) = blueprint.dimensions;
-        let mut size = LogicalSize::new(width as f32, height as f32);
-        let preserve_aspect_ratio = blueprint.preserve_aspect_ratio;
-
-        let wb = glutin::window::WindowBuilder::new()
-            .with_title(blueprint.title)
-            .with_inner_size(size)
-            .with_maximized(blueprint.maximized)
-            .with_resizable(blueprint.resizeable);
-
-        let cb = glutin::ContextBuilder::new();
-        let display = glium::Display::new(wb, cb, &event_loop).unwrap();
-
-        let indices: [u16; 6] = [0,1,2,2,3,0];
-        let indices = glium::IndexBuffer::new(
-            &display,
-            glium::index::PrimitiveType::TrianglesList,
-            &indices
-        ).unwrap();
-    
-        let program = glium::Program::from_source(
-            &display,
-            VERTEX_SHADER_SRC,
-            FRAGMENT_SHADER_SRC,
-            None
-        ).unwrap();
-
-        let shape = vec![
-            Vertex { dest: [-1.0, -1.0 ], src: [0.0, 0.0] },
-            Vertex { dest: [ 1.0, -1.0 ], src: [1.0, 0.0] },
-            Vertex { dest: [ 1.0,  1.0 ], src: [1.0, 1.0] },
-            Vertex { dest: [-1.0,  1.0 ], src: [0.0, 1.0] },
-        ];
-
-        let mut vertex_buffer = glium::VertexBuffer::new(&display, &shape).unwrap();
-
-        // The extra parameters that will be used when drawing frames.
-        // Blend is important because it allows the alpha channel of
-        // RGBA to work.
-        let draw_params = glium::DrawParameters {
-            blend: Blend::alpha_blending(),
-            .. Default::default()
-        };
-
-        // Setting up timekeeping
-        let fps = blueprint.frames_per_second;
-        let refresh_interval = Duration::from_nanos(1_000_000_000 / fps as u64);
+        let directory = std::path::Path::new(directory);
+        std::fs::create_dir_all(directory)?;
 
+        let mut tape = replay::InputTape::new(blueprint.input_recording.as_deref(), blueprint.input_replay.as_deref());
+        let mut frame_hashes = replay::FrameHashLog::new(blueprint.frame_hash_log.as_deref());
         let mut ui_events = vec![];
+        let mut previous_png = None;
 
-        event_loop.run(move |event, _, control_flow| {
+        // Time passes at the blueprint's frame rate, however long frames take
+        let mut context = UIContext::new(blueprint.dimensions, 1.0);
 
-            if controller.should_terminate() {
-                return *control_flow = ControlFlow::Exit;
-            }
+        for frame in 0..frames {
+            context.frame = frame as u64;
+            context.elapsed = Duration::from_secs(frame as u64) / blueprint.frames_per_second.max(1);
 
-            if event == RedrawEventsCleared {
-
-                // Handling events that have been collected
-                // during the previous frame
-                controller.process_events(&ui_events);
-                ui_events.clear();
-
-                // Drawing the next frame, if applicable
-                if let Some(pixels) = controller.next_frame() {
-                    let image = glium::texture::RawImage2d::from_raw_rgba_reversed(
-                        pixels.bytes,
-                        (pixels.width, pixels.height),
-                    );
-                    
-                    // If the aspect ratio of the UI doesn't match that of `image`
-                    // imposing letterboxing to leave the aspect ratio of `image` unchanged.
-                    if preserve_aspect_ratio {
-                        let shape = calculate_vertices(&size, &pixels);
-                        vertex_buffer = glium::VertexBuffer::new(&display, &shape).unwrap();
-                    }
-                    
-                    let texture = glium::texture::Texture2d::new(&display, image).unwrap();
-                    
-                    let uniforms = uniform! {
-                        // Applying filters to prevent unwanted image smoothing
-                        sampler: texture.sampled()
-                            .magnify_filter(glium::uniforms::MagnifySamplerFilter::Nearest)
-                            .minify_filter(glium::uniforms::MinifySamplerFilter::Nearest)
-                    };
-                    
-                    let mut frame = display.draw();
-                    
-                    // Erasing the previous frame
-                    frame.clear_color(0.0,0.0,0.0,255.0);
-                    
-                    // Drawing on the next frame
-                    frame.draw(&vertex_buffer, &indices, &program, &uniforms,
-                        &draw_params).unwrap();
-                        
-                    // Committing the drawn frame
-                    frame.finish().unwrap();
-                }
+            tape.apply(&mut ui_events);
+            if let Some(outcome) = exit_requested(controller.process_events(&ui_events, &mut context)) {
+                return outcome;
+            }
+            ui_events.clear();
 
-                // Waiting until the next frame
-                let next_frame_time = Instant::now() + refresh_interval;
-                *control_flow = ControlFlow::WaitUntil(next_frame_time);
+            let pixels = controller.next_frame(&mut context);
+            if let Some(pixels) = pixels {
+                frame_hashes.log(&pixels);
+                let png = assets::encode_png(&pixels)?;
+                previous_png = Some(png);
             }
 
-            // Responding to UI events
-            match event {
-                glutin::event::Event::WindowEvent { event, .. } => match event {
-                    glutin::event::WindowEvent::CloseRequested => {
-                        *control_flow = glutin::event_loop::ControlFlow::Exit;
-                        return;
-                    },
-                    glutin::event::WindowEvent::KeyboardInput { device_id, input, .. } => {
-                        apply_keyboard_event(&device_id, &input, &mut ui_events);
-                    },
-                    glutin::event::WindowEvent::MouseInput { device_id, state, button, .. } => {
-                        apply_mouse_button_event(&device_id, &state, &button, &mut ui_events);
-                    },
-                    glutin::event::WindowEvent::Resized(phys_size) => {
-                        size = phys_size.to_logical(1.0);
-                        apply_resize_event(&size, &mut ui_events);
-                    },
-                    glutin::event::WindowEvent::CursorMoved { device_id, position, .. } => {
-                        apply_cursor_movement_event(&device_id, &position, &mut ui_events);
-                    },
-                    _ => return,
-                },
-                _ => {}
+            if let Some(png) = &previous_png {
+                std::fs::write(directory.join(format!("frame-{:05}.png", frame)), png)?;
             }
 
+            // Only quitting applies, since there's no window to change
+            if quit_requested(&context.take_commands()) {
+                break;
+            }
+        }
 
+        Ok(())
+    }
+}
 
-        });
+#[test]
+fn _render_offline() {
+    struct Counter { frame: RgbaImage, count: u8 }
+    impl UIController for Counter {
+        fn process_events(&mut self, _events: &Vec<UIEvent>, _context: &mut UIContext) -> UIControlFlow {
+            self.count += 1;
+            if self.count > 3 { UIControlFlow::Exit } else { UIControlFlow::Continue }
+        }
+        fn next_frame(&mut self, _context: &mut UIContext) -> Option<RgbaImageRegion<'_>> {
+            // Leaving the second frame unchanged
+            if self.count == 2 { return None }
+            self.frame.fill((self.count, 0, 0, 255));
+            Some(self.frame.as_region())
+        }
+    }
 
+    let directory = std::env::temp_dir().join(format!("open_ui_render_offline_{}", std::process::id()));
+    let counter = Counter { frame: RgbaImage::new(2, 2), count: 0 };
+    UI::render_offline(counter, 10, directory.to_str().unwrap()).unwrap();
+
+    let first = std::fs::read(directory.join("frame-00000.png")).unwrap();
+    let second = std::fs::read(directory.join("frame-00001.png")).unwrap();
+    assert_eq!(first, second);
+    assert!(directory.join("frame-00002.png").exists());
+    assert!(!directory.join("frame-00003.png").exists());
+
+    // Exiting with an error hands the error back
+    struct Failing;
+    impl UIController for Failing {
+        fn process_events(&mut self, _events: &Vec<UIEvent>, _context: &mut UIContext) -> UIControlFlow {
+            UIControlFlow::ExitWithError("no save file".into())
+        }
+        fn next_frame(&mut self, _context: &mut UIContext) -> Option<RgbaImageRegion<'_>> { None }
     }
+    let error = UI::render_offline(Failing, 10, directory.to_str().unwrap()).unwrap_err();
+    assert!(matches!(error, OpenUiError::Controller(message) if message == "no save file"));
+    std::fs::remove_dir_all(directory).unwrap();
 }
 
-fn hash<T: Hash>(value: T) -> u64 {
-    let mut hasher = DefaultHasher::new();
-    value.hash(&mut hasher);
-    hasher.finish()
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// Whether a keyboard key was pressed or released.
+pub enum KeyboardAction {
+    Press,
+    Release,
 }
 
-fn apply_resize_event(
-    size: &glutin::dpi::LogicalSize<f32>,
-    ui_events: &mut Vec<UIEvent>,
-) {
-    ui_events.push(UIEvent::Resize(ResizeEvent {
-        width: size.width as u32,
-        height: size.height as u32,
-    }));
-}
+// Defining every supported key alongside the glutin key code it comes
+// from and its name, so that they can never fall out of sync.
+macro_rules! keyboard_keys {
+    ($($key:ident => $virtual_keycode:ident, $name:literal,)*) => {
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+        /// A physical key on a keyboard device.
+        pub enum KeyboardKey {
+            $($key,)*
+        }
+
+        impl KeyboardKey {
+            /// Every supported key.
+            pub const ALL: &'static [KeyboardKey] = &[$(KeyboardKey::$key,)*];
+
+            /// The name of the key as printed on most keyboards, for showing
+            /// to users, such as "Page Up" or "W". Names are in English, and
+            /// may change between versions.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    // Using Apple's names on Apple keyboards
+                    KeyboardKey::LAlt if cfg!(target_os = "macos") => "Left Option",
+                    KeyboardKey::RAlt if cfg!(target_os = "macos") => "Right Option",
+                    KeyboardKey::LWin if cfg!(target_os = "macos") => "Left Command",
+                    KeyboardKey::RWin if cfg!(target_os = "macos") => "Right Command",
+                    $(KeyboardKey::$key => $name,)*
+                }
+            }
 
+            /// The identifier of the key, such as "PageUp" or "W", which
+            /// never changes between versions, so it can be saved in
+            /// configuration files and parsed back with `str::parse()`.
+            pub fn identifier(&self) -> &'static str {
+                match self {
+                    $(KeyboardKey::$key => stringify!($key),)*
+                }
+            }
+        }
 
-fn apply_cursor_movement_event(
-    device_id: &glutin::event::DeviceId,
-    position:  &glutin::dpi::PhysicalPosition<f64>,
-    ui_events: &mut Vec<UIEvent>,
-) {
-    let position = position.to_logical::<f32>(1.0);
+        // Converting glutin key codes to native keys
+        #[cfg(feature = "window")]
+        fn translate_virtual_keycode(virtual_keycode: VirtualKeyCode) -> Option<KeyboardKey> {
+            match virtual_keycode {
+                $(VirtualKeyCode::$virtual_keycode => Some(KeyboardKey::$key),)*
 
-    ui_events.push(UIEvent::CursorMovement(CursorMovementEvent {
-        device_id: hash(device_id),
-        x: position.x as u32,
-        y: position.y as u32,
-    }));
+                // Guarding against key codes added in future glutin versions
+                #[allow(unreachable_patterns)]
+                _ => None,
+            }
+        }
+    };
 }
 
+keyboard_keys! {
+    Num0 => Key0, "0",
+    Num1 => Key1, "1",
+    Num2 => Key2, "2",
+    Num3 => Key3, "3",
+    Num4 => Key4, "4",
+    Num5 => Key5, "5",
+    Num6 => Key6, "6",
+    Num7 => Key7, "7",
+    Num8 => Key8, "8",
+    Num9 => Key9, "9",
+    A => A, "A",
+    B => B, "B",
+    C => C, "C",
+    D => D, "D",
+    E => E, "E",
+    F => F, "F",
+    G => G, "G",
+    H => H, "H",
+    I => I, "I",
+    J => J, "J",
+    K => K, "K",
+    L => L, "L",
+    M => M, "M",
+    N => N, "N",
+    O => O, "O",
+    P => P, "P",
+    Q => Q, "Q",
+    R => R, "R",
+    S => S, "S",
+    T => T, "T",
+    U => U, "U",
+    V => V, "V",
+    W => W, "W",
+    X => X, "X",
+    Y => Y, "Y",
+    Z => Z, "Z",
+    Escape => Escape, "Escape",
+    F1 => F1, "F1",
+    F2 => F2, "F2",
+    F3 => F3, "F3",
+    F4 => F4, "F4",
+    F5 => F5, "F5",
+    F6 => F6, "F6",
+    F7 => F7, "F7",
+    F8 => F8, "F8",
+    F9 => F9, "F9",
+    F10 => F10, "F10",
+    F11 => F11, "F11",
+    F12 => F12, "F12",
+    F13 => F13, "F13",
+    F14 => F14, "F14",
+    F15 => F15, "F15",
+    F16 => F16, "F16",
+    F17 => F17, "F17",
+    F18 => F18, "F18",
+    F19 => F19, "F19",
+    F20 => F20, "F20",
+    F21 => F21, "F21",
+    F22 => F22, "F22",
+    F23 => F23, "F23",
+    F24 => F24, "F24",
+    Snapshot => Snapshot, "Print Screen",
+    Scroll => Scroll, "Scroll Lock",
+    Pause => Pause, "Pause",
+    Insert => Insert, "Insert",
+    Home => Home, "Home",
+    Delete => Delete, "Delete",
+    End => End, "End",
+    PageDown => PageDown, "Page Down",
+    PageUp => PageUp, "Page Up",
+    Left => Left, "Left Arrow",
+    Up => Up, "Up Arrow",
+    Right => Right, "Right Arrow",
+    Down => Down, "Down Arrow",
+    Back => Back, "Backspace",
+    Return => Return, "Enter",
+    Space => Space, "Space",
+    Compose => Compose, "Compose",
+    Caret => Caret, "^",
+    Numlock => Numlock, "Num Lock",
+    Numpad0 => Numpad0, "Numpad 0",
+    Numpad1 => Numpad1, "Numpad 1",
+    Numpad2 => Numpad2, "Numpad 2",
+    Numpad3 => Numpad3, "Numpad 3",
+    Numpad4 => Numpad4, "Numpad 4",
+    Numpad5 => Numpad5, "Numpad 5",
+    Numpad6 => Numpad6, "Numpad 6",
+    Numpad7 => Numpad7, "Numpad 7",
+    Numpad8 => Numpad8, "Numpad 8",
+    Numpad9 => Numpad9, "Numpad 9",
+    NumpadAdd => NumpadAdd, "Numpad +",
+    NumpadDivide => NumpadDivide, "Numpad /",
+    NumpadDecimal => NumpadDecimal, "Numpad .",
+    NumpadComma => NumpadComma, "Numpad ,",
+    NumpadEnter => NumpadEnter, "Numpad Enter",
+    NumpadEquals => NumpadEquals, "Numpad =",
+    NumpadMultiply => NumpadMultiply, "Numpad *",
+    NumpadSubtract => NumpadSubtract, "Numpad -",
+    AbntC1 => AbntC1, "ABNT C1",
+    AbntC2 => AbntC2, "ABNT C2",
+    Apostrophe => Apostrophe, "'",
+    Apps => Apps, "Menu",
+    Asterisk => Asterisk, "*",
+    At => At, "@",
+    Ax => Ax, "AX",
+    Backslash => Backslash, "\\",
+    Calculator => Calculator, "Calculator",
+    Capital => Capital, "Caps Lock",
+    Colon => Colon, ":",
+    Comma => Comma, ",",
+    Convert => Convert, "Convert",
+    Equals => Equals, "=",
+    Grave => Grave, "`",
+    Kana => Kana, "Kana",
+    Kanji => Kanji, "Kanji",
+    LAlt => LAlt, "Left Alt",
+    LBracket => LBracket, "[",
+    LControl => LControl, "Left Ctrl",
+    LShift => LShift, "Left Shift",
+    LWin => LWin, "Left Windows",
+    Mail => Mail, "Mail",
+    MediaSelect => MediaSelect, "Media Select",
+    MediaStop => MediaStop, "Media Stop",
+    Minus => Minus, "-",
+    Mute => Mute, "Mute",
+    MyComputer => MyComputer, "My Computer",
+    NavigateForward => NavigateForward, "Navigate Forward",
+    NavigateBackward => NavigateBackward, "Navigate Backward",
+    NextTrack => NextTrack, "Next Track",
+    NoConvert => NoConvert, "No Convert",
+    OEM102 => OEM102, "OEM 102",
+    Period => Period, ".",
+    PlayPause => PlayPause, "Play/Pause",
+    Plus => Plus, "+",
+    Power => Power, "Power",
+    PrevTrack => PrevTrack, "Previous Track",
+    RAlt => RAlt, "Right Alt",
+    RBracket => RBracket, "]",
+    RControl => RControl, "Right Ctrl",
+    RShift => RShift, "Right Shift",
+    RWin => RWin, "Right Windows",
+    Semicolon => Semicolon, ";",
+    Slash => Slash, "/",
+    Sleep => Sleep, "Sleep",
+    Stop => Stop, "Stop",
+    Sysrq => Sysrq, "SysRq",
+    Tab => Tab, "Tab",
+    Underline => Underline, "_",
+    Unlabeled => Unlabeled, "Unlabeled",
+    VolumeDown => VolumeDown, "Volume Down",
+    VolumeUp => VolumeUp, "Volume Up",
+    Wake => Wake, "Wake",
+    WebBack => WebBack, "Web Back",
+    WebFavorites => WebFavorites, "Web Favorites",
+    WebForward => WebForward, "Web Forward",
+    WebHome => WebHome, "Web Home",
+    WebRefresh => WebRefresh, "Web Refresh",
+    WebSearch => WebSearch, "Web Search",
+    WebStop => WebStop, "Web Stop",
+    Yen => Yen, "¥",
+    Copy => Copy, "Copy",
+    Paste => Paste, "Paste",
+    Cut => Cut, "Cut",
+}
 
-fn apply_keyboard_event(
-    device_id: &glutin::event::DeviceId,
-    input: &glutin::event::KeyboardInput,
-    ui_events: &mut Vec<UIEvent>
-) {
-    let device_id = hash(device_id);
+impl KeyboardKey {
+    /// The ASCII character that the key types on a US keyboard, with or
+    /// without shift held, or `None` for keys that don't type anything.
+    /// This is a fallback for simple cases such as entering a name for a
+    /// high score. It ignores the user's keyboard layout, caps lock and
+    /// dead keys, so text input should use `KeyboardEvent::character`
+    /// and `UIEvent::Text`, and `KeyboardLayout` should be used to label
+    /// keys for the user.
+    pub fn to_char(&self, shift: bool) -> Option<char> {
+        use KeyboardKey::*;
+
+        let letter = |lowercase: char| Some(if shift { lowercase.to_ascii_uppercase() } else { lowercase });
+        let symbol = |unshifted: char, shifted: char| Some(if shift { shifted } else { unshifted });
+
+        match self {
+            A => letter('a'), B => letter('b'), C => letter('c'), D => letter('d'), E => letter('e'),
+            F => letter('f'), G => letter('g'), H => letter('h'), I => letter('i'), J => letter('j'),
+            K => letter('k'), L => letter('l'), M => letter('m'), N => letter('n'), O => letter('o'),
+            P => letter('p'), Q => letter('q'), R => letter('r'), S => letter('s'), T => letter('t'),
+            U => letter('u'), V => letter('v'), W => letter('w'), X => letter('x'), Y => letter('y'),
+            Z => letter('z'),
+            Num1 => symbol('1', '!'), Num2 => symbol('2', '@'), Num3 => symbol('3', '#'),
+            Num4 => symbol('4', '$'), Num5 => symbol('5', '%'), Num6 => symbol('6', '^'),
+            Num7 => symbol('7', '&'), Num8 => symbol('8', '*'), Num9 => symbol('9', '('),
+            Num0 => symbol('0', ')'),
+            Grave => symbol('`', '~'),
+            Minus => symbol('-', '_'),
+            Equals => symbol('=', '+'),
+            LBracket => symbol('[', '{'),
+            RBracket => symbol(']', '}'),
+            Backslash => symbol('\\', '|'),
+            Semicolon => symbol(';', ':'),
+            Apostrophe => symbol('\'', '"'),
+            Comma => symbol(',', '<'),
+            Period => symbol('.', '>'),
+            Slash => symbol('/', '?'),
+            Space => Some(' '),
+
+            // Keys on the numeric keypad type the same with or without shift
+            Numpad0 => Some('0'), Numpad1 => Some('1'), Numpad2 => Some('2'), Numpad3 => Some('3'),
+            Numpad4 => Some('4'), Numpad5 => Some('5'), Numpad6 => Some('6'), Numpad7 => Some('7'),
+            Numpad8 => Some('8'), Numpad9 => Some('9'),
+            NumpadAdd => Some('+'),
+            NumpadSubtract => Some('-'),
+            NumpadMultiply => Some('*'),
+            NumpadDivide => Some('/'),
+            NumpadDecimal => Some('.'),
+            NumpadComma => Some(','),
+            NumpadEquals => Some('='),
+            _ => None,
+        }
+    }
+}
 
-    let action = match input.state {
-        glutin::event::ElementState::Pressed => KeyboardAction::Press,
-        glutin::event::ElementState::Released => KeyboardAction::Release,
-    };
+#[test]
+fn _to_char() {
+    assert_eq!(KeyboardKey::Q.to_char(false), Some('q'));
+    assert_eq!(KeyboardKey::Q.to_char(true), Some('Q'));
+    assert_eq!(KeyboardKey::Num2.to_char(true), Some('@'));
+    assert_eq!(KeyboardKey::Apostrophe.to_char(true), Some('"'));
+    assert_eq!(KeyboardKey::Numpad5.to_char(true), Some('5'));
+    assert_eq!(KeyboardKey::Escape.to_char(false), None);
+}
 
-    let key = match input.virtual_keycode {
-        Some(VirtualKeyCode::Key0) => KeyboardKey::Num0,
-        Some(VirtualKeyCode::Key1) => KeyboardKey::Num1,
-        Some(VirtualKeyCode::Key2) => KeyboardKey::Num2,
-        Some(VirtualKeyCode::Key3) => KeyboardKey::Num3,
-        Some(VirtualKeyCode::Key4) => KeyboardKey::Num4,
-        Some(VirtualKeyCode::Key5) => KeyboardKey::Num5,
-        Some(VirtualKeyCode::Key6) => KeyboardKey::Num6,
-        Some(VirtualKeyCode::Key7) => KeyboardKey::Num7,
-        Some(VirtualKeyCode::Key8) => KeyboardKey::Num8,
-        Some(VirtualKeyCode::Key9) => KeyboardKey::Num9,
-        Some(VirtualKeyCode::A) => KeyboardKey::A,
-        Some(VirtualKeyCode::B) => KeyboardKey::B,
-        Some(VirtualKeyCode::C) => KeyboardKey::C,
-        Some(VirtualKeyCode::D) => KeyboardKey::D,
-        Some(VirtualKeyCode::E) => KeyboardKey::E,
-        Some(VirtualKeyCode::F) => KeyboardKey::F,
-        Some(VirtualKeyCode::G) => KeyboardKey::G,
-        Some(VirtualKeyCode::H) => KeyboardKey::H,
-        Some(VirtualKeyCode::I) => KeyboardKey::I,
-        Some(VirtualKeyCode::J) => KeyboardKey::J,
-        Some(VirtualKeyCode::K) => KeyboardKey::K,
-        Some(VirtualKeyCode::L) => KeyboardKey::L,
-        Some(VirtualKeyCode::M) => KeyboardKey::N,
-        Some(VirtualKeyCode::N) => KeyboardKey::M,
-        Some(VirtualKeyCode::O) => KeyboardKey::O,
-        Some(VirtualKeyCode::P) => KeyboardKey::P,
-        Some(VirtualKeyCode::Q) => KeyboardKey::Q,
-        Some(VirtualKeyCode::R) => KeyboardKey::R,
-        Some(VirtualKeyCode::S) => KeyboardKey::S,
-        Some(VirtualKeyCode::T) => KeyboardKey::T,
-        Some(VirtualKeyCode::U) => KeyboardKey::U,
-        Some(VirtualKeyCode::V) => KeyboardKey::V,
-        Some(VirtualKeyCode::W) => KeyboardKey::W,
-        Some(VirtualKeyCode::X) => KeyboardKey::X,
-        Some(VirtualKeyCode::Y) => KeyboardKey::Y,
-        Some(VirtualKeyCode::Z) => KeyboardKey::Z,
-        Some(VirtualKeyCode::Escape) => KeyboardKey::Escape,
-        Some(VirtualKeyCode::F1) => KeyboardKey::F1,
-        Some(VirtualKeyCode::F2) => KeyboardKey::F2,
-        Some(VirtualKeyCode::F3) => KeyboardKey::F3,
-        Some(VirtualKeyCode::F4) => KeyboardKey::F4,
-        Some(VirtualKeyCode::F5) => KeyboardKey::F5,
-        Some(VirtualKeyCode::F6) => KeyboardKey::F6,
-        Some(VirtualKeyCode::F7) => KeyboardKey::F7,
-        Some(VirtualKeyCode::F8) => KeyboardKey::F8,
-        Some(VirtualKeyCode::F9) => KeyboardKey::F9,
-        Some(VirtualKeyCode::F10) => KeyboardKey::F10,
-        Some(VirtualKeyCode::F11) => KeyboardKey::F11,
-        Some(VirtualKeyCode::F12) => KeyboardKey::F12,
-        Some(VirtualKeyCode::F13) => KeyboardKey::F13,
-        Some(VirtualKeyCode::F14) => KeyboardKey::F14,
-        Some(VirtualKeyCode::F15) => KeyboardKey::F15,
-        Some(VirtualKeyCode::F16) => KeyboardKey::F16,
-        Some(VirtualKeyCode::F17) => KeyboardKey::F17,
-        Some(VirtualKeyCode::F18) => KeyboardKey::F18,
-        Some(VirtualKeyCode::F19) => KeyboardKey::F19,
-        Some(VirtualKeyCode::F20) => KeyboardKey::F20,
-        Some(VirtualKeyCode::F21) => KeyboardKey::F21,
-        Some(VirtualKeyCode::F22) => KeyboardKey::F22,
-        Some(VirtualKeyCode::F23) => KeyboardKey::F23,
-        Some(VirtualKeyCode::F24) => KeyboardKey::F24,
-        Some(VirtualKeyCode::Snapshot) => KeyboardKey::Snapshot,
-        Some(VirtualKeyCode::Scroll) => KeyboardKey::Scroll,
-        Some(VirtualKeyCode::Pause) => KeyboardKey::Pause,
-        Some(VirtualKeyCode::Insert) => KeyboardKey::Insert,
-        Some(VirtualKeyCode::Home) => KeyboardKey::Home,
-        Some(VirtualKeyCode::Delete) => KeyboardKey::Delete,
-        Some(VirtualKeyCode::End) => KeyboardKey::Delete,
-        Some(VirtualKeyCode::PageDown) => KeyboardKey::Delete,
-        Some(VirtualKeyCode::PageUp) => KeyboardKey::Delete,
-        Some(VirtualKeyCode::Left) => KeyboardKey::Left,
-        Some(VirtualKeyCode::Up) => KeyboardKey::Up,
-        Some(VirtualKeyCode::Right) => KeyboardKey::Right,
-        Some(VirtualKeyCode::Down) => KeyboardKey::Down,
-        Some(VirtualKeyCode::Back) => KeyboardKey::Back,
-        Some(VirtualKeyCode::Return) => KeyboardKey::Return,
-        Some(VirtualKeyCode::Space) => KeyboardKey::Space,
-        Some(VirtualKeyCode::Compose) => KeyboardKey::Compose,
-        Some(VirtualKeyCode::Caret) => KeyboardKey::Caret,
-        Some(VirtualKeyCode::Numlock) => KeyboardKey::Numlock,
-        Some(VirtualKeyCode::Numpad1) => KeyboardKey::Numpad1,
-        Some(VirtualKeyCode::Numpad2) => KeyboardKey::Numpad2,
-        Some(VirtualKeyCode::Numpad3) => KeyboardKey::Numpad3,
-        Some(VirtualKeyCode::Numpad4) => KeyboardKey::Numpad4,
-        Some(VirtualKeyCode::Numpad5) => KeyboardKey::Numpad5,
-        Some(VirtualKeyCode::Numpad6) => KeyboardKey::Numpad6,
-        Some(VirtualKeyCode::Numpad7) => KeyboardKey::Numpad7,
-        Some(VirtualKeyCode::Numpad8) => KeyboardKey::Numpad8,
-        Some(VirtualKeyCode::Numpad9) => KeyboardKey::Numpad9,
-        Some(VirtualKeyCode::NumpadAdd) => KeyboardKey::NumpadAdd,
-        Some(VirtualKeyCode::NumpadDivide) => KeyboardKey::NumpadDivide,
-        Some(VirtualKeyCode::NumpadDecimal) => KeyboardKey::NumpadDecimal,
-        Some(VirtualKeyCode::NumpadComma) => KeyboardKey::NumpadComma,
-        Some(VirtualKeyCode::NumpadEnter) => KeyboardKey::NumpadEnter,
-        Some(VirtualKeyCode::NumpadEquals) => KeyboardKey::NumpadEquals,
-        Some(VirtualKeyCode::NumpadMultiply) => KeyboardKey::NumpadMultiply,
-        Some(VirtualKeyCode::NumpadSubtract) => KeyboardKey::NumpadSubtract,
-        Some(VirtualKeyCode::AbntC1) => KeyboardKey::AbntC1,
-        Some(VirtualKeyCode::AbntC2) => KeyboardKey::AbntC2,
-        Some(VirtualKeyCode::Apostrophe) => KeyboardKey::Apostrophe,
-        Some(VirtualKeyCode::Apps) => KeyboardKey::Apps,
-        Some(VirtualKeyCode::Asterisk) => KeyboardKey::Asterisk,
-        Some(VirtualKeyCode::At) => KeyboardKey::At,
-        Some(VirtualKeyCode::Ax) => KeyboardKey::Ax,
-        Some(VirtualKeyCode::Backslash) => KeyboardKey::Backslash,
-        Some(VirtualKeyCode::Calculator) => KeyboardKey::Calculator,
-        Some(VirtualKeyCode::Capital) => KeyboardKey::Capital,
-        Some(VirtualKeyCode::Colon) => KeyboardKey::Colon,
-        Some(VirtualKeyCode::Comma) => KeyboardKey::Comma,
-        Some(VirtualKeyCode::Convert) => KeyboardKey::Convert,
-        Some(VirtualKeyCode::Equals) => KeyboardKey::Equals,
-        Some(VirtualKeyCode::Grave) => KeyboardKey::Grave,
-        Some(VirtualKeyCode::Kana) => KeyboardKey::Kana,
-        Some(VirtualKeyCode::Kanji) => KeyboardKey::Kanji,
-        Some(VirtualKeyCode::LAlt) => KeyboardKey::LAlt,
-        Some(VirtualKeyCode::LBracket) => KeyboardKey::LBracket,
-        Some(VirtualKeyCode::LControl) => KeyboardKey::LControl,
-        Some(VirtualKeyCode::LShift) => KeyboardKey::LShift,
-        Some(VirtualKeyCode::LWin) => KeyboardKey::LWin,
-        Some(VirtualKeyCode::Mail) => KeyboardKey::Mail,
-        Some(VirtualKeyCode::MediaSelect) => KeyboardKey::MediaSelect,
-        Some(VirtualKeyCode::MediaStop) => KeyboardKey::MediaStop,
-        Some(VirtualKeyCode::Minus) => KeyboardKey::Minus,
-        Some(VirtualKeyCode::Mute) => KeyboardKey::Mute,
-        Some(VirtualKeyCode::MyComputer) => KeyboardKey::MyComputer,
-        Some(VirtualKeyCode::NavigateForward) => KeyboardKey::NavigateForward,
-        Some(VirtualKeyCode::NavigateBackward) => KeyboardKey::NavigateBackward,
-        Some(VirtualKeyCode::NextTrack) => KeyboardKey::NextTrack,
-        Some(VirtualKeyCode::NoConvert) => KeyboardKey::NoConvert,
-        Some(VirtualKeyCode::OEM102) => KeyboardKey::OEM102,
-        Some(VirtualKeyCode::Period) => KeyboardKey::Period,
-        Some(VirtualKeyCode::PlayPause) => KeyboardKey::PlayPause,
-        Some(VirtualKeyCode::Plus) => KeyboardKey::Plus,
-        Some(VirtualKeyCode::Power) => KeyboardKey::Power,
-        Some(VirtualKeyCode::PrevTrack) => KeyboardKey::PrevTrack,
-        Some(VirtualKeyCode::RAlt) => KeyboardKey::RAlt,
-        Some(VirtualKeyCode::RBracket) => KeyboardKey::RBracket,
-        Some(VirtualKeyCode::RControl) => KeyboardKey::RControl,
-        Some(VirtualKeyCode::RShift) => KeyboardKey::RShift,
-        Some(VirtualKeyCode::RWin) => KeyboardKey::RWin,
-        Some(VirtualKeyCode::Semicolon) => KeyboardKey::Semicolon,
-        Some(VirtualKeyCode::Slash) => KeyboardKey::Slash,
-        Some(VirtualKeyCode::Sleep) => KeyboardKey::Sleep,
-        Some(VirtualKeyCode::Stop) => KeyboardKey::Stop,
-        Some(VirtualKeyCode::Sysrq) => KeyboardKey::Sysrq,
-        Some(VirtualKeyCode::Tab) => KeyboardKey::Tab,
-        Some(VirtualKeyCode::Underline) => KeyboardKey::Underline,
-        Some(VirtualKeyCode::Unlabeled) => KeyboardKey::Unlabeled,
-        Some(VirtualKeyCode::VolumeDown) => KeyboardKey::VolumeDown,
-        Some(VirtualKeyCode::VolumeUp) => KeyboardKey::VolumeUp,
-        Some(VirtualKeyCode::Wake) => KeyboardKey::Wake,
-        Some(VirtualKeyCode::WebBack) => KeyboardKey::WebBack,
-        Some(VirtualKeyCode::WebFavorites) => KeyboardKey::WebFavorites,
-        Some(VirtualKeyCode::WebForward) => KeyboardKey::WebForward,
-        Some(VirtualKeyCode::WebHome) => KeyboardKey::WebHome,
-        Some(VirtualKeyCode::WebRefresh) => KeyboardKey::WebRefresh,
-        Some(VirtualKeyCode::WebSearch) => KeyboardKey::WebSearch,
-        Some(VirtualKeyCode::WebStop) => KeyboardKey::WebStop,
-        Some(VirtualKeyCode::Yen) => KeyboardKey::Yen,
-        Some(VirtualKeyCode::Copy) => KeyboardKey::Copy,
-        Some(VirtualKeyCode::Paste) => KeyboardKey::Paste,
-        Some(VirtualKeyCode::Cut) => KeyboardKey::Cut,
-        _ => return,
-    };
+impl std::fmt::Display for KeyboardKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
 
-    let keyboard_event = KeyboardEvent {
-        device_id,
-        action,
-        key,
-    };
+/// The reason that text couldn't be parsed as a `KeyboardKey`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownKeyError(pub String);
 
-    ui_events.push(UIEvent::Keyboard(keyboard_event));
+impl std::fmt::Display for UnknownKeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "unknown key: {:?}", self.0)
+    }
 }
 
-// Converting glutin mouse events to native mouse button events
-fn apply_mouse_button_event(
-    device_id: &glutin::event::DeviceId,
-    state: &glutin::event::ElementState,
-    button: &glutin::event::MouseButton,
-    ui_events: &mut Vec<UIEvent>,
-) {
-    let device_id = hash(device_id);
-
-    // Determining button pressed/released
-    let button = match button {
-        glutin::event::MouseButton::Left => MouseButton::Left,
-        glutin::event::MouseButton::Right => MouseButton::Right,
-        glutin::event::MouseButton::Middle => MouseButton::Middle,
-        glutin::event::MouseButton::Other(num) => MouseButton::Other(*num),
-    };
+impl std::error::Error for UnknownKeyError {}
 
-    let action = match state {
-        glutin::event::ElementState::Pressed => MouseButtonAction::Press,
-        glutin::event::ElementState::Released => MouseButtonAction::Release,
-    };
+// Accepting either identifiers or names, ignoring case,
+// so that people can write either in configuration files
+impl std::str::FromStr for KeyboardKey {
+    type Err = UnknownKeyError;
 
-    let event = MouseButtonEvent {
-        device_id,
-        button,
-        action,
-    };
+    fn from_str(text: &str) -> Result<KeyboardKey, UnknownKeyError> {
+        let text = text.trim();
+        KeyboardKey::ALL.iter()
+            .find(|key| key.identifier().eq_ignore_ascii_case(text))
+            .or_else(|| KeyboardKey::ALL.iter().find(|key| key.name().eq_ignore_ascii_case(text)))
+            .copied()
+            .ok_or_else(|| UnknownKeyError(text.to_string()))
+    }
+}
 
-    ui_events.push(UIEvent::MouseButton(event));
+// Keys are serialized as their identifiers
+#[cfg(feature = "serde")]
+impl serde::Serialize for KeyboardKey {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.identifier())
+    }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-/// Whether a keyboard key was pressed or released.
-pub enum KeyboardAction {
-    Press,
-    Release,
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for KeyboardKey {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<KeyboardKey, D::Error> {
+        let text = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-/// A physical key on a keyboard device.
-pub enum KeyboardKey {
-    Num0,
-    Num1,
-    Num2,
-    Num3,
-    Num4,
-    Num5,
-    Num6,
-    Num7,
-    Num8,
-    Num9,
-    A,
-    B,
-    C,
-    D,
-    E,
-    F,
-    G,
-    H,
-    I,
-    J,
-    K,
-    L,
-    M,
-    N,
-    O,
-    P,
-    Q,
-    R,
-    S,
-    T,
-    U,
-    V,
-    W,
-    X,
-    Y,
-    Z,
-    Escape,
-    F1,
-    F2,
-    F3,
-    F4,
-    F5,
-    F6,
-    F7,
-    F8,
-    F9,
-    F10,
-    F11,
-    F12,
-    F13,
-    F14,
-    F15,
-    F16,
-    F17,
-    F18,
-    F19,
-    F20,
-    F21,
-    F22,
-    F23,
-    F24,
-    Snapshot,
-    Scroll,
-    Pause,
-    Insert,
-    Home,
-    Delete,
-    End,
-    PageDown,
-    PageUp,
-    Left,
-    Up,
-    Right,
-    Down,
-    Back,
-    Return,
-    Space,
-    Compose,
-    Caret,
-    Numlock,
-    Numpad0,
-    Numpad1,
-    Numpad2,
-    Numpad3,
-    Numpad4,
-    Numpad5,
-    Numpad6,
-    Numpad7,
-    Numpad8,
-    Numpad9,
-    NumpadAdd,
-    NumpadDivide,
-    NumpadDecimal,
-    NumpadComma,
-    NumpadEnter,
-    NumpadEquals,
-    NumpadMultiply,
-    NumpadSubtract,
-    AbntC1,
-    AbntC2,
-    Apostrophe,
-    Apps,
-    Asterisk,
-    At,
-    Ax,
-    Backslash,
-    Calculator,
-    Capital,
-    Colon,
-    Comma,
-    Convert,
-    Equals,
-    Grave,
-    Kana,
-    Kanji,
-    LAlt,
-    LBracket,
-    LControl,
-    LShift,
-    LWin,
-    Mail,
-    MediaSelect,
-    MediaStop,
-    Minus,
-    Mute,
-    MyComputer,
-    NavigateForward,
-    NavigateBackward,
-    NextTrack,
-    NoConvert,
-    OEM102,
-    Period,
-    PlayPause,
-    Plus,
-    Power,
-    PrevTrack,
-    RAlt,
-    RBracket,
-    RControl,
-    RShift,
-    RWin,
-    Semicolon,
-    Slash,
-    Sleep,
-    Stop,
-    Sysrq,
-    Tab,
-    Underline,
-    Unlabeled,
-    VolumeDown,
-    VolumeUp,
-    Wake,
-    WebBack,
-    WebFavorites,
-    WebForward,
-    WebHome,
-    WebRefresh,
-    WebSearch,
-    WebStop,
-    Yen,
-    Copy,
-    Paste,
-    Cut,
+#[test]
+fn _key_names() {
+    assert_eq!(KeyboardKey::W.name(), "W");
+    assert_eq!(KeyboardKey::Num7.name(), "7");
+    assert_eq!(KeyboardKey::PageUp.to_string(), "Page Up");
+    assert_eq!(KeyboardKey::NumpadAdd.identifier(), "NumpadAdd");
+
+    assert_eq!("PageUp".parse(), Ok(KeyboardKey::PageUp));
+    assert_eq!(" page up ".parse(), Ok(KeyboardKey::PageUp));
+    assert_eq!("Numpad 0".parse(), Ok(KeyboardKey::Numpad0));
+    assert_eq!("Hyper".parse::<KeyboardKey>(), Err(UnknownKeyError("Hyper".to_string())));
+
+    // Every key can be found from its identifier and its name
+    for key in KeyboardKey::ALL {
+        assert_eq!(key.identifier().parse(), Ok(*key));
+        assert_eq!(key.name().parse(), Ok(*key));
+    }
+}
+
+#[cfg(feature = "window")]
+#[test]
+fn _translate_virtual_keycode() {
+    assert_eq!(translate_virtual_keycode(VirtualKeyCode::M), Some(KeyboardKey::M));
+    assert_eq!(translate_virtual_keycode(VirtualKeyCode::N), Some(KeyboardKey::N));
+    assert_eq!(translate_virtual_keycode(VirtualKeyCode::End), Some(KeyboardKey::End));
+    assert_eq!(translate_virtual_keycode(VirtualKeyCode::PageUp), Some(KeyboardKey::PageUp));
+    assert_eq!(translate_virtual_keycode(VirtualKeyCode::PageDown), Some(KeyboardKey::PageDown));
+    assert_eq!(translate_virtual_keycode(VirtualKeyCode::Numpad0), Some(KeyboardKey::Numpad0));
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+/// The modifier keys that were held down during a keyboard event.
+pub struct KeyboardModifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    /// The Windows key on PC keyboards, or the Command key on Mac keyboards.
+    pub logo: bool,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -942,6 +2947,81 @@ pub struct KeyboardEvent {
     pub device_id: u64,
     pub key: KeyboardKey,
     pub action: KeyboardAction,
+    pub modifiers: KeyboardModifiers,
+    /// The printable character that the user's keyboard layout produced
+    /// for this key press, if any. Always `None` for releases. Characters
+    /// composed with dead keys or the compose key, such as `é`, belong
+    /// to the last key press of the sequence, and the presses before it
+    /// have no character.
+    pub character: Option<char>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// A character that was typed without a key press of its own, such as
+/// the accent when a dead key is followed by a key it can't be combined
+/// with, which types both, or text from an input method.
+pub struct TextEvent {
+    pub character: char,
+}
+
+/// The characters that the user's keyboard layout produces, learned by
+/// observing keyboard events. This makes it possible to translate keys
+/// into characters for non-QWERTY layouts, for example to display
+/// key bindings, without hard-coding any particular layout.
+///
+/// There's no way to ask the platform what a key would type without
+/// pressing it, so keys are assumed to follow the US layout until they've
+/// been seen. On an AZERTY keyboard, for example, `KeyboardKey::Q` is
+/// labeled "q" until the user presses it, after which it's labeled "a".
+#[derive(Debug, Clone, Default)]
+pub struct KeyboardLayout {
+    characters: HashMap<(KeyboardKey, KeyboardModifiers), char>,
+}
+
+impl KeyboardLayout {
+    pub fn new() -> KeyboardLayout {
+        KeyboardLayout::default()
+    }
+
+    /// Remember the character produced by a keyboard event, if any.
+    pub fn observe(&mut self, event: &KeyboardEvent) {
+        if let Some(character) = event.character {
+            self.characters.insert((event.key, event.modifiers), character);
+        }
+    }
+
+    /// The character that the user's layout produces for `key` while
+    /// `modifiers` are held. Combinations that haven't been observed yet
+    /// fall back to `KeyboardKey::to_char()`, when only shift is held.
+    pub fn translate(&self, key: KeyboardKey, modifiers: KeyboardModifiers) -> Option<char> {
+        match self.characters.get(&(key, modifiers)) {
+            Some(&character) => Some(character),
+            None if !modifiers.ctrl && !modifiers.alt && !modifiers.logo => key.to_char(modifiers.shift),
+            None => None,
+        }
+    }
+}
+
+#[test]
+fn _keyboard_layout() {
+    let shift = KeyboardModifiers { shift: true, ..Default::default() };
+    let ctrl = KeyboardModifiers { ctrl: true, ..Default::default() };
+
+    // Keys follow the US layout until they've been seen
+    let mut layout = KeyboardLayout::new();
+    assert_eq!(layout.translate(KeyboardKey::Q, KeyboardModifiers::default()), Some('q'));
+    assert_eq!(layout.translate(KeyboardKey::Num2, shift), Some('@'));
+    assert_eq!(layout.translate(KeyboardKey::Q, ctrl), None);
+
+    // Pressing Q on an AZERTY keyboard types an "a"
+    layout.observe(&KeyboardEvent {
+        device_id: 0,
+        key: KeyboardKey::Q,
+        action: KeyboardAction::Press,
+        modifiers: KeyboardModifiers::default(),
+        character: Some('a'),
+    });
+    assert_eq!(layout.translate(KeyboardKey::Q, KeyboardModifiers::default()), Some('a'));
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -976,6 +3056,122 @@ pub struct CursorMovementEvent {
     pub y: u32,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// A report that a frame started later than scheduled, because
+/// previous frames took too long to produce.
+pub struct FrameBudgetEvent {
+    /// How far behind schedule the frame started.
+    pub late_by: Duration,
+    /// How many frames were processed without being rendered, in order
+    /// to catch up. Always zero unless the blueprint's catch-up policy
+    /// is `CatchUpPolicy::SkipRenders`.
+    pub skipped_renders: u32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// The new frame rate, after the window moved onto a monitor with a
+/// different refresh rate while `sync_to_monitor` is enabled.
+pub struct RefreshRateEvent {
+    pub frames_per_second: u32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// What happened to a monitor.
+pub enum MonitorAction {
+    /// The monitor was plugged in, and is now in `UIContext::monitors()`.
+    Connected,
+    Disconnected,
+    /// The window moved onto the monitor, which is where most of it is now.
+    WindowEntered,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// The `Monitor::id` of a monitor, and what happened to it. Monitors are
+/// checked about once a second, so a new one is noticed shortly after
+/// it's plugged in.
+pub struct MonitorEvent {
+    pub monitor_id: u32,
+    pub action: MonitorAction,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// Measurements of how precisely frames are starting on time,
+/// delivered once per frame.
+pub struct FrameTimingEvent {
+    /// How far after its scheduled time the current frame started.
+    pub jitter: Duration,
+    /// How late the OS timer typically wakes the application. Frame
+    /// pacing compensates for this by waking up early and spinning.
+    pub timer_slop: Duration,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// How far a scroll wheel or trackpad was scrolled. Positive `y` values
+/// scroll up, as when a mouse wheel is rolled away from the user, and
+/// positive `x` values scroll right.
+pub enum ScrollDelta {
+    /// Scrolling by whole lines or rows, as with a notched mouse wheel.
+    Lines { x: f32, y: f32 },
+    /// Scrolling by an exact number of pixels, as with a trackpad.
+    /// These values are not rounded, so that smooth scrolling stays smooth.
+    Pixels { x: f32, y: f32 },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// An interaction that was created using a scroll wheel or trackpad.
+pub struct ScrollEvent {
+    pub device_id: u64,
+    pub delta: ScrollDelta,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// The stage of a pen's contact with the screen or tablet.
+pub enum PenPhase {
+    Down,
+    Move,
+    Up,
+    Cancel,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// An interaction that was created using a pen, stylus, or touchscreen.
+/// Positions are not rounded to whole pixels, so that strokes stay smooth.
+pub struct PenEvent {
+    pub device_id: u64,
+    /// Distinguishes simultaneous contacts, such as multiple fingers.
+    pub contact_id: u64,
+    pub phase: PenPhase,
+    pub x: f32,
+    pub y: f32,
+    /// How hard the pen is pressed, from 0.0 to 1.0,
+    /// if the device and platform report it.
+    pub pressure: Option<f32>,
+    /// The angle between the pen and the surface in radians, from 0.0
+    /// (parallel) to π/2 (perpendicular), if the device and platform report it.
+    pub tilt: Option<f32>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The color scheme of the window's titlebar and borders.
+pub enum WindowTheme {
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// The new theme of the window, after the system theme changed.
+/// Theme changes are only reported on Windows.
+pub struct ThemeEvent {
+    pub theme: WindowTheme,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// Whether the window has become hidden, or visible again. Windows are
+/// considered hidden while minimized, and frames aren't drawn meanwhile.
+pub struct OcclusionEvent {
+    pub occluded: bool,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 /// The new size of the window after being resized.
 pub struct ResizeEvent {
@@ -983,12 +3179,38 @@ pub struct ResizeEvent {
     pub height: u32,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 /// An action that an end-user takes
 /// to interact with the application.
 pub enum UIEvent {
     Keyboard(KeyboardEvent),
+    Text(TextEvent),
     MouseButton(MouseButtonEvent),
     CursorMovement(CursorMovementEvent),
-    Resize(ResizeEvent)
+    Resize(ResizeEvent),
+    Pen(PenEvent),
+    Scroll(ScrollEvent),
+    FrameBudgetExceeded(FrameBudgetEvent),
+    FrameTiming(FrameTimingEvent),
+    Theme(ThemeEvent),
+    Menu(menu::MenuEvent),
+    TrayIcon(tray::TrayIconEvent),
+    Gamepad(gamepad::GamepadEvent),
+    Joystick(gamepad::JoystickEvent),
+    /// The user tried to close the window. Controllers only receive this
+    /// when their blueprint's `close_policy` is `ClosePolicy::Ask`.
+    CloseRequested,
+    /// The application has been sent to the background by the OS, and
+    /// frames won't be drawn until it's resumed. Only reported on
+    /// platforms that suspend applications, such as mobile platforms.
+    Suspended,
+    Resumed,
+    Occlusion(OcclusionEvent),
+    RefreshRate(RefreshRateEvent),
+    Monitor(MonitorEvent),
+    /// The graphics context was lost, for example after the computer
+    /// resumed from sleep, the GPU was reset, or its driver was updated,
+    /// and the window has been recreated. Anything the controller drew
+    /// on the GPU itself must be drawn again.
+    DisplayReset,
 }
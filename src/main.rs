@@ -2,6 +2,8 @@
 use open_ui::{
     UI,
     UIController,
+    UIControlFlow,
+    UIContext,
     UIBlueprint,
     UIEvent,
     RgbaImage,
@@ -9,11 +11,13 @@ use open_ui::{
     KeyboardKey::*,
     KeyboardAction::*,
 };
+use open_ui::scheduler::{Scheduler, TimerId};
+use std::time::Duration;
 
 
 fn main() {
     let application = SnakeGame::new();
-    UI::launch(application);
+    UI::launch(application).unwrap();
 }
 
 
@@ -75,6 +79,12 @@ struct Food {
 pub struct PseudoRandomness {
     seed: std::time::Instant,
 }
+impl Default for PseudoRandomness {
+    fn default() -> PseudoRandomness {
+        PseudoRandomness::new()
+    }
+}
+
 impl PseudoRandomness {
     // Create a new instance, "seeded" with the current time
     pub fn new() -> PseudoRandomness {
@@ -93,12 +103,19 @@ pub struct SnakeGame {
     canvas: RgbaImage,
     snake: Snake,
     food: Food,
-    frame_count: u64,
+    scheduler: Scheduler,
+    step_timer: TimerId,
     rng: PseudoRandomness,
     paused: bool,
     finished: bool,
 }
 
+impl Default for SnakeGame {
+    fn default() -> SnakeGame {
+        SnakeGame::new()
+    }
+}
+
 impl SnakeGame {
     // A method we can use to initialize the application's data
     pub fn new() -> SnakeGame {
@@ -119,13 +136,19 @@ impl SnakeGame {
 
         let rng = PseudoRandomness::new();
 
+        // Moving the snake on a timer, rather than every frame, so the game
+        // doesn't move too quickly for the player to respond
+        let mut scheduler = Scheduler::new();
+        let step_timer = scheduler.every(Duration::from_millis(83));
+
         let food = Food {
             x: rng.integer_between(0, (canvas.width() - 1) as i32),
             y: rng.integer_between(0, (canvas.height() - 1) as i32),
         };
     
         SnakeGame {
-            frame_count: 0,
+            scheduler,
+            step_timer,
             paused: false,
             finished: false,
             canvas,
@@ -183,15 +206,15 @@ impl SnakeGame {
             self.paused = true;
         }
 
+        // Keeping track of how much time has passed since the last frame
+        self.scheduler.tick();
+
         // Not doing anything if the game is paused
         if self.paused { return }
-        self.frame_count += 1;
 
-        // Only applying changes once every 10 frames, so the game doesn't move
-        // to quickly for the player to respond. A similar effect could be
-        // achieved by using floating point numbers for `x` and
-        // `y`, or just lowering the framerate.
-        if self.frame_count % 5 == 0 {
+        // Only applying changes when the step timer goes off, so the game
+        // moves at the same pace no matter what the framerate is
+        if self.scheduler.fired(self.step_timer) > 0 {
             self.snake.last_direction = self.snake.direction;
 
             let head = self.snake.segments.first().unwrap();
@@ -243,43 +266,42 @@ impl UIController for SnakeGame {
 
     // A function that will use a player's inputs to affect application data.
     // This will be executed at the beginning of each frame.
-    fn process_events(&mut self, events: &Vec<UIEvent>) {
+    fn process_events(&mut self, events: &Vec<UIEvent>, _context: &mut UIContext) -> UIControlFlow {
         for &event in events {
-            match event {
-                UIEvent::Keyboard(event) => {
-                    if event.key == Escape && event.action == Press {
-                        self.finished = true;
-                    }
-                    if event.key == Space && event.action == Press {
-                        self.toggle_pause();
-                    }
-                    if event.key == Up && event.action == Press {
-                        self.snake.change_direction(Direction::Up);
-                    }
-                    if event.key == Down && event.action == Press {
-                        self.snake.change_direction(Direction::Down);
-                    }
-                    if event.key == Right && event.action == Press {
-                        self.snake.change_direction(Direction::Right);
-                    }
-                    if event.key == Left && event.action == Press {
-                        self.snake.change_direction(Direction::Left);
-                    }
-                },
-                _ => {},
+            if let UIEvent::Keyboard(event) = event {
+                if event.key == Escape && event.action == Press {
+                    self.finished = true;
+                }
+                if event.key == Space && event.action == Press {
+                    self.toggle_pause();
+                }
+                if event.key == Up && event.action == Press {
+                    self.snake.change_direction(Direction::Up);
+                }
+                if event.key == Down && event.action == Press {
+                    self.snake.change_direction(Direction::Down);
+                }
+                if event.key == Right && event.action == Press {
+                    self.snake.change_direction(Direction::Right);
+                }
+                if event.key == Left && event.action == Press {
+                    self.snake.change_direction(Direction::Left);
+                }
             }
         }
 
         // Applying game logic
         self.calculate_changes();
+
+        if self.finished { UIControlFlow::Exit } else { UIControlFlow::Continue }
     }
 
     // A function that will use application data to decide which image to
-    // render on the next frame. If no image is returned, the application
-    // will terminate.
-    fn next_frame(&mut self) -> Option<RgbaImageRegion> {
+    // render on the next frame. If no image is returned, the previous
+    // frame stays on screen.
+    fn next_frame(&mut self, _context: &mut UIContext) -> Option<RgbaImageRegion<'_>> {
 
-        // Not rendering the next frame if the player has canceled the game
+        // Not rendering the next frame while the game is paused
         if self.paused {
             return None
         }
@@ -311,9 +333,5 @@ impl UIController for SnakeGame {
 
         Some(self.canvas.as_region())
     }
-
-    fn should_terminate(&self) -> bool {
-        self.finished
-    }
 }
 
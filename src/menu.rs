@@ -0,0 +1,168 @@
+//! Native menu bars, described in the `UIBlueprint`.
+//!
+//! Menus are displayed on Windows and macOS when the `menus` feature
+//! is enabled, and ignored everywhere else. Choosing a menu item, either
+//! from the menu bar or from the tray icon's menu, produces a
+//! `UIEvent::Menu` containing the item's id.
+//!
+//! On macOS, the menu bar always starts with the standard application menu,
+//! and ends with a Window menu, so that the usual shortcuts work. Choosing
+//! Quit (Cmd+Q) or Close Window (Cmd+W) is handled like clicking the
+//! window's close button, following the blueprint's `ClosePolicy`.
+
+use crate::UIEvent;
+
+/// One of the menus in a menu bar, such as "File" or "Edit".
+#[derive(Debug, Clone, PartialEq)]
+pub struct Menu {
+    pub title: String,
+    pub items: Vec<MenuItem>,
+}
+
+/// An entry within a menu.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MenuItem {
+    /// An item that produces a `MenuEvent` with the given id when chosen.
+    Action { id: u32, label: String },
+    /// A line separating groups of items.
+    Separator,
+}
+
+impl Menu {
+    pub fn new(title: &str) -> Menu {
+        Menu { title: title.to_string(), items: vec![] }
+    }
+
+    pub fn item(mut self, id: u32, label: &str) -> Menu {
+        self.items.push(MenuItem::Action { id, label: label.to_string() });
+        self
+    }
+
+    pub fn separator(mut self) -> Menu {
+        self.items.push(MenuItem::Separator);
+        self
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// A menu item that was chosen by the user.
+pub struct MenuEvent {
+    pub id: u32,
+}
+
+/// A menu bar that has been attached to a window, which must be
+/// kept alive for as long as the window is open.
+#[cfg(feature = "window")]
+pub(crate) struct NativeMenuBar {
+    #[cfg(all(feature = "menus", any(target_os = "windows", target_os = "macos")))]
+    _menu: muda::Menu,
+}
+
+/// Attach a menu bar to the window, where the platform supports it.
+#[cfg(feature = "window")]
+#[allow(unused_variables)]
+pub(crate) fn install(menus: &[Menu], title: &str, window: &glium::glutin::window::Window) -> Option<NativeMenuBar> {
+    #[cfg(all(feature = "menus", any(target_os = "windows", target_os = "macos")))]
+    {
+        if menus.is_empty() && cfg!(not(target_os = "macos")) { return None }
+        let menu_bar = muda::Menu::new();
+
+        #[cfg(target_os = "macos")]
+        let (application_menu, window_menu) = standard_menus(title)?;
+        #[cfg(target_os = "macos")]
+        menu_bar.append(&application_menu).ok()?;
+
+        for menu in menus {
+            let items = native_items(&menu.items);
+            let items: Vec<&dyn muda::IsMenuItem> = items.iter().map(|item| item.as_ref()).collect();
+            let submenu = muda::Submenu::with_items(&menu.title, true, &items).ok()?;
+            menu_bar.append(&submenu).ok()?;
+        }
+
+        #[cfg(target_os = "macos")]
+        menu_bar.append(&window_menu).ok()?;
+
+        #[cfg(target_os = "windows")]
+        {
+            use glium::glutin::platform::windows::WindowExtWindows;
+            // The window handle is valid, since the window outlives the menu bar
+            unsafe { menu_bar.init_for_hwnd(window.hwnd() as isize).ok()? };
+        }
+
+        #[cfg(target_os = "macos")]
+        menu_bar.init_for_nsapp();
+
+        Some(NativeMenuBar { _menu: menu_bar })
+    }
+
+    #[cfg(not(all(feature = "menus", any(target_os = "windows", target_os = "macos"))))]
+    None
+}
+
+// The ids of standard menu items, which can't be mistaken for a `MenuEvent`'s
+#[cfg(all(any(feature = "menus", feature = "tray"), any(target_os = "windows", target_os = "macos")))]
+const QUIT_ID: &str = "open_ui:quit";
+#[cfg(all(any(feature = "menus", feature = "tray"), any(target_os = "windows", target_os = "macos")))]
+const CLOSE_WINDOW_ID: &str = "open_ui:close_window";
+
+// The application menu and the Window menu that every Mac application has
+#[cfg(all(feature = "window", feature = "menus", target_os = "macos"))]
+fn standard_menus(title: &str) -> Option<(muda::Submenu, muda::Submenu)> {
+    use muda::accelerator::{Accelerator, Code, Modifiers};
+    use muda::PredefinedMenuItem;
+
+    let command = |code| Some(Accelerator::new(Some(Modifiers::SUPER), code));
+    let quit = match title {
+        "" => "Quit".to_string(),
+        title => format!("Quit {}", title),
+    };
+
+    let application_menu = muda::Submenu::with_items(title, true, &[
+        &PredefinedMenuItem::about(None, None),
+        &PredefinedMenuItem::separator(),
+        &PredefinedMenuItem::hide(None),
+        &PredefinedMenuItem::hide_others(None),
+        &PredefinedMenuItem::show_all(None),
+        &PredefinedMenuItem::separator(),
+        &muda::MenuItem::with_id(QUIT_ID, quit, true, command(Code::KeyQ)),
+    ]).ok()?;
+
+    let window_menu = muda::Submenu::with_items("Window", true, &[
+        &PredefinedMenuItem::minimize(None),
+        &muda::MenuItem::with_id(CLOSE_WINDOW_ID, "Close Window", true, command(Code::KeyW)),
+    ]).ok()?;
+
+    Some((application_menu, window_menu))
+}
+
+/// Create native equivalents of `items`, with ids that
+/// `apply_menu_events()` can map back to `MenuEvent`s.
+#[cfg(all(any(feature = "menus", feature = "tray"), any(target_os = "windows", target_os = "macos")))]
+pub(crate) fn native_items(items: &[MenuItem]) -> Vec<Box<dyn muda::IsMenuItem>> {
+    items.iter().map(|item| -> Box<dyn muda::IsMenuItem> {
+        match item {
+            MenuItem::Action { id, label } => Box::new(muda::MenuItem::with_id(id.to_string(), label, true, None)),
+            MenuItem::Separator => Box::new(muda::PredefinedMenuItem::separator()),
+        }
+    }).collect()
+}
+
+/// Collect the menu items that have been chosen since the last call,
+/// returning whether the user chose to quit or close the window.
+#[allow(unused_variables, unused_mut, clippy::ptr_arg)]
+#[cfg_attr(not(feature = "window"), allow(dead_code))]
+pub(crate) fn apply_menu_events(ui_events: &mut Vec<UIEvent>) -> bool {
+    let mut close_requested = false;
+
+    #[cfg(all(any(feature = "menus", feature = "tray"), any(target_os = "windows", target_os = "macos")))]
+    while let Ok(event) = muda::MenuEvent::receiver().try_recv() {
+        match event.id().0.as_str() {
+            QUIT_ID | CLOSE_WINDOW_ID => close_requested = true,
+            id => if let Ok(id) = id.parse() {
+                ui_events.push(UIEvent::Menu(MenuEvent { id }));
+            },
+        }
+    }
+
+    close_requested
+}
@@ -0,0 +1,21 @@
+//! Desktop notifications, for alerting the user while the
+//! application is in the background.
+//!
+//! Notifications are only shown when the `notifications` feature is enabled.
+
+/// Show a desktop notification with a short summary and a longer body,
+/// returning whether the notification could be shown.
+#[allow(unused_variables)]
+pub fn notify(summary: &str, body: &str) -> bool {
+    #[cfg(feature = "notifications")]
+    {
+        notify_rust::Notification::new()
+            .summary(summary)
+            .body(body)
+            .show()
+            .is_ok()
+    }
+
+    #[cfg(not(feature = "notifications"))]
+    false
+}
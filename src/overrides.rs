@@ -0,0 +1,127 @@
+// Overriding parts of the blueprint from environment variables, so that
+// users can work around problems on a particular machine without the
+// application being recompiled. These are applied whenever a window opens:
+//
+// - `OPEN_UI_FPS=30` replaces `frames_per_second`.
+// - `OPEN_UI_SIZE=1280x720` replaces `dimensions`.
+// - `OPEN_UI_VSYNC=0` or `1` disables or enables vsync.
+// - `OPEN_UI_SOFTWARE=1` requests a software renderer.
+// - `OPEN_UI_GLES=1` requests an OpenGL ES context.
+// - `OPEN_UI_BACKEND=x11` or `wayland` replaces `linux_backend`.
+// - `OPEN_UI_MONITOR=1` or `HDMI-1` replaces `monitor`, by id or name.
+// - `OPEN_UI_CONFIG=path` reads any of the above from a file, with one
+//   `NAME=value` per line. Environment variables take priority.
+
+use crate::{LinuxBackend, MonitorChoice, UIBlueprint};
+
+impl UIBlueprint {
+    // The blueprint with overrides from the environment applied
+    pub(crate) fn with_overrides(self) -> UIBlueprint {
+        let config = std::env::var("OPEN_UI_CONFIG").ok().and_then(|path| {
+            std::fs::read_to_string(&path)
+                .map_err(|error| log_error!("couldn't read {}: {}", path, error))
+                .ok()
+        });
+
+        self.apply_overrides(|name| {
+            std::env::var(name).ok().or_else(|| config.as_deref().and_then(|config| config_value(config, name)))
+        })
+    }
+
+    fn apply_overrides(mut self, variable: impl Fn(&str) -> Option<String>) -> UIBlueprint {
+        let dimensions = |value: &str| {
+            let (width, height) = value.split_once('x')?;
+            Some((width.parse().ok()?, height.parse().ok()?))
+        };
+
+        if let Some(frames_per_second) = read(&variable, "OPEN_UI_FPS", |value| value.parse().ok()) {
+            self.frames_per_second = frames_per_second;
+        }
+        if let Some(dimensions) = read(&variable, "OPEN_UI_SIZE", dimensions) {
+            self.dimensions = dimensions;
+        }
+        if let Some(vsync) = read(&variable, "OPEN_UI_VSYNC", flag) {
+            self.vsync = vsync;
+        }
+        if let Some(software_rendering) = read(&variable, "OPEN_UI_SOFTWARE", flag) {
+            self.software_rendering = software_rendering;
+        }
+        if let Some(opengl_es) = read(&variable, "OPEN_UI_GLES", flag) {
+            self.opengl_es = opengl_es;
+        }
+        if let Some(linux_backend) = read(&variable, "OPEN_UI_BACKEND", linux_backend) {
+            self.linux_backend = Some(linux_backend);
+        }
+        if let Some(monitor) = read(&variable, "OPEN_UI_MONITOR", monitor) {
+            self.monitor = monitor;
+        }
+        self
+    }
+}
+
+// Ignoring malformed values, after saying so
+fn read<T>(variable: &dyn Fn(&str) -> Option<String>, name: &str, parse: impl Fn(&str) -> Option<T>) -> Option<T> {
+    let value = variable(name)?;
+    let parsed = parse(value.trim());
+    if parsed.is_none() {
+        log_warning!("ignoring {}={}", name, value);
+    }
+    parsed
+}
+
+fn flag(value: &str) -> Option<bool> {
+    match value {
+        "1" | "true" => Some(true),
+        "0" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+fn linux_backend(value: &str) -> Option<LinuxBackend> {
+    match value.to_ascii_lowercase().as_str() {
+        "x11" => Some(LinuxBackend::X11),
+        "wayland" => Some(LinuxBackend::Wayland),
+        _ => None,
+    }
+}
+
+fn monitor(value: &str) -> Option<MonitorChoice> {
+    match value.parse() {
+        Ok(id) => Some(MonitorChoice::Id(id)),
+        Err(_) if value.is_empty() => None,
+        Err(_) => Some(MonitorChoice::Name(value.to_string())),
+    }
+}
+
+// Finding a `NAME=value` line in a config file, skipping comments
+fn config_value(config: &str, name: &str) -> Option<String> {
+    config.lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .find_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            (key.trim() == name).then(|| value.trim().to_string())
+        })
+}
+
+#[test]
+fn _apply_overrides() {
+    let config = "# Debugging the kiosk\nOPEN_UI_FPS = 24\nOPEN_UI_SIZE=640x480\nOPEN_UI_VSYNC=maybe\n";
+    let blueprint = UIBlueprint::default().apply_overrides(|name| match name {
+        "OPEN_UI_SOFTWARE" => Some("1".to_string()),
+        "OPEN_UI_BACKEND" => Some("X11".to_string()),
+        "OPEN_UI_MONITOR" => Some("HDMI-1".to_string()),
+        name => config_value(config, name),
+    });
+
+    assert_eq!(blueprint.frames_per_second, 24);
+    assert_eq!(blueprint.dimensions, (640, 480));
+    assert!(blueprint.software_rendering);
+    assert_eq!(blueprint.linux_backend, Some(LinuxBackend::X11));
+    assert_eq!(blueprint.monitor, MonitorChoice::Name("HDMI-1".to_string()));
+
+    // Malformed values leave the blueprint as it was
+    assert!(!blueprint.vsync);
+    let blueprint = UIBlueprint::default().apply_overrides(|_| Some("big".to_string()));
+    assert_eq!(blueprint.dimensions, UIBlueprint::default().dimensions);
+}
@@ -0,0 +1,148 @@
+//! Reducing images to a limited palette of colors.
+
+use crate::{RgbaImage, RgbaPixel};
+
+/// How to disguise the banding that appears when an image is
+/// reduced to a small number of colors.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Dithering {
+    /// Replace every pixel with its nearest palette color.
+    None,
+    /// Offset every pixel using a repeating 4x4 pattern before
+    /// choosing its nearest palette color.
+    Ordered,
+    /// Spread the difference between every pixel and its nearest
+    /// palette color onto the neighboring pixels.
+    FloydSteinberg,
+}
+
+// A 4x4 Bayer threshold matrix
+const BAYER_4X4: [[f32; 4]; 4] = [
+    [ 0.0,  8.0,  2.0, 10.0],
+    [12.0,  4.0, 14.0,  6.0],
+    [ 3.0, 11.0,  1.0,  9.0],
+    [15.0,  7.0, 13.0,  5.0],
+];
+
+/// The palette color closest to `pixel`, ignoring alpha.
+/// Returns `pixel` itself if the palette is empty.
+pub fn nearest_color(pixel: &RgbaPixel, palette: &[RgbaPixel]) -> RgbaPixel {
+    nearest_color_f32((pixel.0 as f32, pixel.1 as f32, pixel.2 as f32), palette)
+        .unwrap_or(*pixel)
+}
+
+fn nearest_color_f32(rgb: (f32, f32, f32), palette: &[RgbaPixel]) -> Option<RgbaPixel> {
+    let distance = |color: &RgbaPixel| {
+        let dr = color.0 as f32 - rgb.0;
+        let dg = color.1 as f32 - rgb.1;
+        let db = color.2 as f32 - rgb.2;
+        dr * dr + dg * dg + db * db
+    };
+
+    palette.iter()
+        .min_by(|a, b| distance(a).partial_cmp(&distance(b)).unwrap())
+        .copied()
+}
+
+/// Produce a copy of `img` that only uses colors from `palette`.
+/// The alpha channel of every pixel is preserved. Every pixel is
+/// quantized, even outside a clip rect, and the copy isn't clipped.
+pub fn quantize(img: &RgbaImage, palette: &[RgbaPixel], dithering: Dithering) -> RgbaImage {
+    let mut new_img = img.clone();
+    new_img.set_clip_rect(None);
+    if palette.is_empty() { return new_img; }
+
+    let (width, height) = (img.width(), img.height());
+
+    // The accumulated error for every channel of every pixel,
+    // used for Floyd-Steinberg dithering
+    let mut errors = vec![(0.0f32, 0.0f32, 0.0f32); (width * height) as usize];
+
+    // The strength of ordered dithering, which is roughly the
+    // distance between neighboring palette colors on each channel
+    let spread = 255.0 / (palette.len() as f32).cbrt();
+
+    for y in 0..height {
+        for x in 0..width {
+            let (r, g, b, a) = img.get_pixel(x, y).unwrap();
+            let mut rgb = (r as f32, g as f32, b as f32);
+
+            match dithering {
+                Dithering::None => {},
+                Dithering::Ordered => {
+                    let offset = (BAYER_4X4[(y % 4) as usize][(x % 4) as usize] + 0.5) / 16.0 - 0.5;
+                    rgb.0 += offset * spread;
+                    rgb.1 += offset * spread;
+                    rgb.2 += offset * spread;
+                },
+                Dithering::FloydSteinberg => {
+                    let error = errors[(y * width + x) as usize];
+                    rgb.0 += error.0;
+                    rgb.1 += error.1;
+                    rgb.2 += error.2;
+                },
+            }
+
+            let color = nearest_color_f32(rgb, palette).unwrap();
+            new_img.set_pixel(x, y, (color.0, color.1, color.2, a));
+
+            if dithering == Dithering::FloydSteinberg {
+                let error = (
+                    rgb.0 - color.0 as f32,
+                    rgb.1 - color.1 as f32,
+                    rgb.2 - color.2 as f32,
+                );
+
+                // Distributing the error to pixels that haven't been visited yet
+                let mut spread_error = |dx: i64, dy: i64, weight: f32| {
+                    let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                    if nx < 0 || nx >= width as i64 || ny >= height as i64 { return }
+
+                    let neighbor = &mut errors[(ny * width as i64 + nx) as usize];
+                    neighbor.0 += error.0 * weight;
+                    neighbor.1 += error.1 * weight;
+                    neighbor.2 += error.2 * weight;
+                };
+
+                spread_error(1, 0, 7.0 / 16.0);
+                spread_error(-1, 1, 3.0 / 16.0);
+                spread_error(0, 1, 5.0 / 16.0);
+                spread_error(1, 1, 1.0 / 16.0);
+            }
+        }
+    }
+
+    new_img
+}
+
+#[test]
+fn _quantize() {
+    let black = (0, 0, 0, 255);
+    let white = (255, 255, 255, 255);
+
+    let mut img = RgbaImage::new(4, 4);
+    img.fill((128, 128, 128, 255));
+
+    // Without dithering, mid-gray becomes entirely white
+    let quantized = quantize(&img, &[black, white], Dithering::None);
+    assert_eq!(quantized.get_pixel(0, 0), Some(white));
+    assert_eq!(quantized.get_pixel(3, 3), Some(white));
+
+    // With dithering, mid-gray becomes a mix of black and white
+    for dithering in [Dithering::Ordered, Dithering::FloydSteinberg] {
+        let quantized = quantize(&img, &[black, white], dithering);
+        let mut whites = 0;
+        for y in 0..4 {
+            for x in 0..4 {
+                if quantized.get_pixel(x, y) == Some(white) { whites += 1; }
+            }
+        }
+        assert_eq!(whites, 8);
+    }
+
+    // Clipping doesn't stop pixels from being quantized
+    img.set_clip_rect(Some(crate::Rect::new(0, 0, 1, 1)));
+    let quantized = quantize(&img, &[black, white], Dithering::None);
+    assert_eq!(quantized.get_pixel(3, 3), Some(white));
+    assert_eq!(quantized.clip_rect(), None);
+}
@@ -0,0 +1,404 @@
+//! Drawing smooth shapes, such as chart lines and rounded buttons, onto an
+//! `RgbaImage`, with anti-aliased edges.
+//!
+//! A `Path` is built from lines and curves, then filled or stroked. Points
+//! are in pixels, where pixel `(0, 0)` covers everything from `(0.0, 0.0)`
+//! to `(1.0, 1.0)`, so a line through the middle of a row of pixels runs
+//! along `y + 0.5`. Edge pixels are blended by how much of them the shape
+//! covers, and drawing respects the image's clipping rectangle.
+
+use crate::{source_over, RgbaImage, RgbaPixel};
+
+/// How overlapping parts of a path decide what's inside it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FillRule {
+    /// Inside wherever the path winds around a point more times in one
+    /// direction than the other, so overlapping shapes are filled.
+    NonZero,
+    /// Inside wherever the path crosses an odd number of times, so
+    /// a shape drawn inside another cuts a hole in it.
+    EvenOdd,
+}
+
+/// An outline made of lines and curves, as one or more subpaths.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    // Curves are flattened into lines as they're added, so each point
+    // records whether it was given explicitly, or is part of a curve
+    subpaths: Vec<Vec<(f32, f32)>>,
+    vertices: Vec<Vec<bool>>,
+    closed: Vec<bool>,
+    fill_rule: FillRule,
+}
+
+impl Path {
+    pub fn new() -> Path {
+        Path { subpaths: vec![], vertices: vec![], closed: vec![], fill_rule: FillRule::NonZero }
+    }
+
+    /// Choose how `fill_path()` treats overlapping parts of the path.
+    /// The default is `FillRule::NonZero`.
+    pub fn fill_rule(self, fill_rule: FillRule) -> Path {
+        Path { fill_rule, ..self }
+    }
+
+    /// Start a new subpath at the given point.
+    pub fn move_to(mut self, x: f32, y: f32) -> Path {
+        self.subpaths.push(vec![(x, y)]);
+        self.vertices.push(vec![true]);
+        self.closed.push(false);
+        self
+    }
+
+    /// Add a straight line from the current point.
+    pub fn line_to(mut self, x: f32, y: f32) -> Path {
+        self.current().push((x, y));
+        self.vertices.last_mut().unwrap().push(true);
+        self
+    }
+
+    /// Add a quadratic Bézier curve from the current point, bending
+    /// towards `control`.
+    pub fn quad_to(self, control: (f32, f32), x: f32, y: f32) -> Path {
+        self.flatten(x, y, |start, t| {
+            let u = 1.0 - t;
+            [start, control, (x, y)].iter().zip([u * u, 2.0 * u * t, t * t])
+                .fold((0.0, 0.0), |sum, (point, weight)| (sum.0 + point.0 * weight, sum.1 + point.1 * weight))
+        })
+    }
+
+    /// Add a cubic Bézier curve from the current point, leaving it
+    /// towards `control1` and arriving from `control2`.
+    pub fn cubic_to(self, control1: (f32, f32), control2: (f32, f32), x: f32, y: f32) -> Path {
+        self.flatten(x, y, |start, t| {
+            let u = 1.0 - t;
+            [start, control1, control2, (x, y)].iter().zip([u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t])
+                .fold((0.0, 0.0), |sum, (point, weight)| (sum.0 + point.0 * weight, sum.1 + point.1 * weight))
+        })
+    }
+
+    /// Join the current subpath back to its first point.
+    pub fn close(mut self) -> Path {
+        if let Some(closed) = self.closed.last_mut() {
+            *closed = true;
+        }
+        self
+    }
+
+    /// A rectangle, as a closed subpath.
+    pub fn rect(self, x: f32, y: f32, width: f32, height: f32) -> Path {
+        self.move_to(x, y)
+            .line_to(x + width, y)
+            .line_to(x + width, y + height)
+            .line_to(x, y + height)
+            .close()
+    }
+
+    /// A circle, as a closed subpath.
+    pub fn circle(mut self, x: f32, y: f32, radius: f32) -> Path {
+        let points = circle_points((x, y), radius);
+        self.vertices.push(vec![false; points.len()]);
+        self.subpaths.push(points);
+        self.closed.push(true);
+        self
+    }
+
+    // The subpath being added to, starting one at the origin if there's none
+    fn current(&mut self) -> &mut Vec<(f32, f32)> {
+        if self.subpaths.is_empty() {
+            self.subpaths.push(vec![(0.0, 0.0)]);
+            self.vertices.push(vec![true]);
+            self.closed.push(false);
+        }
+        self.subpaths.last_mut().unwrap()
+    }
+
+    // Adding a curve as enough short lines that the joins between them
+    // can't be seen, judged by the length of its control polygon
+    fn flatten(mut self, x: f32, y: f32, point_at: impl Fn((f32, f32), f32) -> (f32, f32)) -> Path {
+        let start = *self.current().last().unwrap();
+        let length = distance(start, (x, y)) + distance(start, point_at(start, 0.5)) * 2.0;
+        let steps = ((length / 3.0).ceil() as usize).clamp(1, 256);
+
+        let points = (1..=steps).map(|step| point_at(start, step as f32 / steps as f32));
+        self.current().extend(points);
+        self.vertices.last_mut().unwrap().extend((1..=steps).map(|step| step == steps));
+        self
+    }
+
+    // Every line in the path, with each subpath closed
+    fn edges(&self) -> impl Iterator<Item = Edge> + '_ {
+        self.subpaths.iter().flat_map(|points| {
+            let closing = (*points.last().unwrap(), points[0]);
+            points.windows(2).map(|pair| (pair[0], pair[1])).chain(std::iter::once(closing))
+        })
+    }
+}
+
+impl Default for Path {
+    fn default() -> Path {
+        Path::new()
+    }
+}
+
+/// Fill the inside of a path with a color.
+pub fn fill_path(img: &mut RgbaImage, path: &Path, color: RgbaPixel) {
+    let edges: Vec<_> = path.edges().collect();
+    rasterize(img, &edges, path.fill_rule, color);
+}
+
+/// Draw the outline of a path, `width` pixels wide, with rounded
+/// corners and ends. Curves bend smoothly rather than being rounded
+/// at every short line they're made of.
+pub fn stroke_path(img: &mut RgbaImage, path: &Path, width: f32, color: RgbaPixel) {
+    let radius = width / 2.0;
+    if radius <= 0.0 {
+        return;
+    }
+
+    // Outlining every line and corner with shapes that wind the same way,
+    // so that filling where they overlap covers them only once
+    let mut edges = vec![];
+    for ((points, vertices), &closed) in path.subpaths.iter().zip(&path.vertices).zip(&path.closed) {
+        let lines = (1..points.len()).map(|i| (i - 1, i));
+        let closing = (closed && points.len() > 2).then(|| (points.len() - 1, 0));
+        let lines: Vec<_> = lines.chain(closing).filter(|&(from, to)| distance(points[from], points[to]) > 0.0).collect();
+
+        let normals: Vec<_> = lines.iter().map(|&(from, to)| {
+            let (from, to) = (points[from], points[to]);
+            let length = distance(from, to);
+            ((to.1 - from.1) / length * radius, (from.0 - to.0) / length * radius)
+        }).collect();
+
+        for (&(from, to), normal) in lines.iter().zip(&normals) {
+            let (from, to) = (points[from], points[to]);
+            let corners = [
+                (from.0 + normal.0, from.1 + normal.1),
+                (to.0 + normal.0, to.1 + normal.1),
+                (to.0 - normal.0, to.1 - normal.1),
+                (from.0 - normal.0, from.1 - normal.1),
+            ];
+            edges.extend((0..4).map(|i| (corners[i], corners[(i + 1) % 4])));
+        }
+
+        // Filling the small gaps where lines meet partway along a curve
+        let closing_join = (closing.is_some() && lines.len() > 1).then(|| (lines.len() - 1, 0));
+        for (before, after) in (1..lines.len()).map(|i| (i - 1, i)).chain(closing_join) {
+            let joint = lines[before].1;
+            if vertices[joint] {
+                continue;
+            }
+            let (point, a, b) = (points[joint], normals[before], normals[after]);
+            for side in [1.0, -1.0] {
+                let mut triangle = [point, (point.0 + a.0 * side, point.1 + a.1 * side), (point.0 + b.0 * side, point.1 + b.1 * side)];
+                if signed_area(&triangle) < 0.0 {
+                    triangle.swap(1, 2);
+                }
+                edges.extend((0..3).map(|i| (triangle[i], triangle[(i + 1) % 3])));
+            }
+        }
+
+        // Rounding the corners and ends
+        for (&point, &vertex) in points.iter().zip(vertices) {
+            if !vertex {
+                continue;
+            }
+            let circle = circle_points(point, radius);
+            edges.extend((0..circle.len()).map(|i| (circle[i], circle[(i + 1) % circle.len()])));
+        }
+    }
+
+    rasterize(img, &edges, FillRule::NonZero, color);
+}
+
+// A line from one point to another
+type Edge = ((f32, f32), (f32, f32));
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+// Twice the area of a polygon, positive when it goes the same way round
+// as a rectangle that's drawn clockwise on screen
+fn signed_area(points: &[(f32, f32)]) -> f32 {
+    (0..points.len()).map(|i| {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        a.0 * b.1 - b.0 * a.1
+    }).sum()
+}
+
+// Points around a circle, going the same way round as a rectangle
+// that's drawn clockwise on screen
+fn circle_points(center: (f32, f32), radius: f32) -> Vec<(f32, f32)> {
+    let count = ((radius * 2.0).ceil() as usize).clamp(8, 256);
+
+    (0..count).map(|i| {
+        let angle = i as f32 / count as f32 * std::f32::consts::TAU;
+        (center.0 + radius * angle.cos(), center.1 + radius * angle.sin())
+    }).collect()
+}
+
+// How many rows each row of pixels is sampled at. Coverage across each
+// row is measured exactly, so this only limits the smoothness of edges
+// that are close to horizontal.
+const SUBROWS: usize = 16;
+
+// Filling the area inside a set of edges, blending each pixel by
+// how much of it is covered
+fn rasterize(img: &mut RgbaImage, edges: &[Edge], fill_rule: FillRule, color: RgbaPixel) {
+    let area = match img.writable_area() {
+        Some(area) => area,
+        None => return,
+    };
+
+    let top = edges.iter().map(|(a, b)| a.1.min(b.1)).fold(f32::INFINITY, f32::min);
+    let bottom = edges.iter().map(|(a, b)| a.1.max(b.1)).fold(f32::NEG_INFINITY, f32::max);
+    if edges.is_empty() || top >= bottom {
+        return;
+    }
+
+    let first_row = (top.floor().max(area.y as f32)) as u32;
+    let last_row = (bottom.ceil().min((area.y + area.h) as f32)) as u32;
+    let (left, right) = (area.x as f32, (area.x + area.w) as f32);
+
+    // Sorting edges from the top down, pointing them downwards and
+    // remembering which way they went, so that each row only needs to
+    // visit the edges that reach it
+    let mut sorted: Vec<_> = edges.iter()
+        .filter(|(a, b)| a.1 != b.1)
+        .map(|&(a, b)| if a.1 < b.1 { (a, b, 1) } else { (b, a, -1) })
+        .collect();
+    sorted.sort_by(|a, b| a.0.1.total_cmp(&b.0.1));
+    let mut next_edge = 0;
+    let mut active = vec![];
+
+    let mut coverage = vec![0.0f32; area.w as usize];
+    let mut crossings = vec![];
+
+    for y in first_row..last_row {
+        coverage.iter_mut().for_each(|value| *value = 0.0);
+
+        let (row_top, row_bottom) = (y as f32, y as f32 + 1.0);
+        while next_edge < sorted.len() && sorted[next_edge].0.1 < row_bottom {
+            active.push(sorted[next_edge]);
+            next_edge += 1;
+        }
+        active.retain(|(_, lower, _)| lower.1 > row_top);
+
+        for subrow in 0..SUBROWS {
+            let sample_y = y as f32 + (subrow as f32 + 0.5) / SUBROWS as f32;
+
+            // Where each edge crosses the sample row, and which way
+            crossings.clear();
+            for &(upper, lower, winding) in &active {
+                if sample_y < upper.1 || sample_y >= lower.1 {
+                    continue;
+                }
+                let x = upper.0 + (sample_y - upper.1) / (lower.1 - upper.1) * (lower.0 - upper.0);
+                crossings.push((x, winding));
+            }
+            crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let mut winding = 0;
+            for pair in crossings.windows(2) {
+                winding += pair[0].1;
+                let inside = match fill_rule {
+                    FillRule::NonZero => winding != 0,
+                    FillRule::EvenOdd => winding % 2 != 0,
+                };
+                if inside {
+                    let (from, to) = (pair[0].0.clamp(left, right) - left, pair[1].0.clamp(left, right) - left);
+                    add_span(&mut coverage, from, to, 1.0 / SUBROWS as f32);
+                }
+            }
+        }
+
+        let start = ((y * img.width + area.x) * 4) as usize;
+        let row = &mut img.bytes[start..start + area.w as usize * 4];
+
+        for (pixel, &covered) in row.chunks_exact_mut(4).zip(&coverage) {
+            let alpha = (color.3 as f32 * covered.min(1.0)).round() as u8;
+            if alpha == 0 {
+                continue;
+            }
+            let (r, g, b, a) = source_over(&(color.0, color.1, color.2, alpha), &(pixel[0], pixel[1], pixel[2], pixel[3]));
+            pixel.copy_from_slice(&[r, g, b, a]);
+        }
+    }
+}
+
+// Adding `weight` to the pixels between `from` and `to`, in proportion
+// to how much of each pixel the span covers
+fn add_span(coverage: &mut [f32], from: f32, to: f32, weight: f32) {
+    if to <= from {
+        return;
+    }
+
+    let (first, last) = (from as usize, to as usize);
+    if first == last {
+        coverage[first] += (to - from) * weight;
+        return;
+    }
+
+    coverage[first] += (first as f32 + 1.0 - from) * weight;
+    for value in &mut coverage[first + 1..last] {
+        *value += weight;
+    }
+    if let Some(value) = coverage.get_mut(last) {
+        *value += (to - last as f32) * weight;
+    }
+}
+
+#[test]
+fn _fill_path() {
+    let opaque = (255, 0, 0, 255);
+
+    // Edges on pixel boundaries are crisp, and edges through the middle
+    // of pixels half cover them
+    let mut img = RgbaImage::new(8, 4);
+    fill_path(&mut img, &Path::new().rect(1.0, 1.0, 3.5, 2.0), opaque);
+    assert_eq!(img.get_pixel(0, 1), Some((0, 0, 0, 0)));
+    assert_eq!(img.get_pixel(1, 1), Some(opaque));
+    assert_eq!(img.get_pixel(4, 2), Some((255, 0, 0, 128)));
+    assert_eq!(img.get_pixel(2, 3), Some((0, 0, 0, 0)));
+
+    // A square inside another either adds to it, or cuts a hole in it
+    let rings = Path::new().rect(0.0, 0.0, 6.0, 6.0).rect(2.0, 2.0, 2.0, 2.0);
+    let mut img = RgbaImage::new(6, 6);
+    fill_path(&mut img, &rings, opaque);
+    assert_eq!(img.get_pixel(3, 3), Some(opaque));
+    let mut img = RgbaImage::new(6, 6);
+    fill_path(&mut img, &rings.fill_rule(FillRule::EvenOdd), opaque);
+    assert_eq!(img.get_pixel(3, 3), Some((0, 0, 0, 0)));
+    assert_eq!(img.get_pixel(1, 3), Some(opaque));
+
+    // A stroke that doubles back over itself is blended only once,
+    // and nothing is drawn outside the clipping rectangle
+    let mut img = RgbaImage::new(10, 10);
+    img.set_clip_rect(Some(crate::Rect::new(0, 0, 10, 5)));
+    let path = Path::new()
+        .move_to(1.0, 2.5).line_to(9.0, 2.5).line_to(5.0, 2.5)
+        .move_to(1.0, 9.0).quad_to((5.0, 5.0), 9.0, 1.0);
+    stroke_path(&mut img, &path, 2.0, (0, 0, 255, 128));
+    assert_eq!(img.get_pixel(3, 2), Some((0, 0, 255, 128)));
+    assert_eq!(img.get_pixel(1, 2), Some((0, 0, 255, 128)));
+    assert_eq!(img.get_pixel(6, 2), Some((0, 0, 255, 128)));
+    assert_eq!(img.get_pixel(3, 1), Some((0, 0, 255, 64)));
+    assert_eq!(img.get_pixel(5, 5), Some((0, 0, 0, 0)));
+
+    // Corners are rounded, but the points that curves are made of aren't
+    let mut img = RgbaImage::new(14, 14);
+    stroke_path(&mut img, &Path::new().move_to(2.0, 4.0).line_to(8.0, 4.0).line_to(8.0, 12.0), 8.0, opaque);
+    assert_eq!(img.get_pixel(11, 0), Some((0, 0, 0, 0)));
+    assert_eq!(img.get_pixel(9, 1), Some(opaque));
+    let circle = Path::new().circle(6.0, 6.0, 4.0);
+    assert!(circle.vertices.iter().flatten().all(|&vertex| !vertex));
+    let curve = Path::new().move_to(0.0, 0.0).quad_to((6.0, 0.0), 6.0, 6.0);
+    assert!(curve.vertices[0].first() == Some(&true) && curve.vertices[0].last() == Some(&true));
+    assert!(curve.vertices[0][1..curve.vertices[0].len() - 1].iter().all(|&vertex| !vertex));
+    let mut img = RgbaImage::new(12, 12);
+    stroke_path(&mut img, &circle, 2.0, opaque);
+    assert_eq!(img.get_pixel(6, 6), Some((0, 0, 0, 0)));
+    assert_eq!(img.get_pixel(6, 2), Some(opaque));
+    assert_eq!(img.get_pixel(2, 6), Some(opaque));
+}
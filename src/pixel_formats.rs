@@ -0,0 +1,212 @@
+//! Images in pixel formats other than 8-bit RGBA: 8-bit grayscale,
+//! 8-bit RGB without alpha, and 16-bit RGBA, for data that would lose
+//! precision if it were converted to `RgbaImage` right away.
+//!
+//! Images can be converted between formats with `Image::convert()`.
+//! Frames are always displayed as 8-bit RGBA, so images in other
+//! formats are converted before display. `UIHandle::present_image()`
+//! does this itself. Controllers can keep an `RgbaImage` around, convert
+//! into it each frame with `Image::write_rgba()`, and return it from
+//! `UIController::next_frame()` with `as_region()`.
+
+use crate::{RgbaImage, RgbaPixel};
+
+/// A pixel with 8-bit red, green and blue channels, and no alpha.
+pub type RgbPixel = (u8, u8, u8);
+
+/// A pixel with 16-bit red, green, blue and alpha channels.
+pub type Rgba16Pixel = (u16, u16, u16, u16);
+
+/// A pixel with a single 8-bit brightness channel.
+pub type GrayPixel = u8;
+
+/// A format that the pixels of an `Image` can be stored in. Every
+/// format converts losslessly to and from 16-bit RGBA, except where
+/// the other format has fewer channels or less precision.
+pub trait Pixel: Copy + PartialEq + std::fmt::Debug {
+    const BLACK: Self;
+    fn to_rgba16(self) -> Rgba16Pixel;
+    fn from_rgba16(pixel: Rgba16Pixel) -> Self;
+}
+
+// Widening 8-bit channels so that 255 becomes 65535
+fn widen(channel: u8) -> u16 {
+    channel as u16 * 257
+}
+
+fn narrow(channel: u16) -> u8 {
+    ((channel as u32 + 128) / 257) as u8
+}
+
+impl Pixel for RgbaPixel {
+    const BLACK: RgbaPixel = (0, 0, 0, 255);
+
+    fn to_rgba16(self) -> Rgba16Pixel {
+        (widen(self.0), widen(self.1), widen(self.2), widen(self.3))
+    }
+
+    fn from_rgba16(pixel: Rgba16Pixel) -> RgbaPixel {
+        (narrow(pixel.0), narrow(pixel.1), narrow(pixel.2), narrow(pixel.3))
+    }
+}
+
+impl Pixel for Rgba16Pixel {
+    const BLACK: Rgba16Pixel = (0, 0, 0, u16::MAX);
+
+    fn to_rgba16(self) -> Rgba16Pixel {
+        self
+    }
+
+    fn from_rgba16(pixel: Rgba16Pixel) -> Rgba16Pixel {
+        pixel
+    }
+}
+
+impl Pixel for RgbPixel {
+    const BLACK: RgbPixel = (0, 0, 0);
+
+    fn to_rgba16(self) -> Rgba16Pixel {
+        (widen(self.0), widen(self.1), widen(self.2), u16::MAX)
+    }
+
+    // Discarding the alpha channel
+    fn from_rgba16(pixel: Rgba16Pixel) -> RgbPixel {
+        (narrow(pixel.0), narrow(pixel.1), narrow(pixel.2))
+    }
+}
+
+impl Pixel for GrayPixel {
+    const BLACK: GrayPixel = 0;
+
+    fn to_rgba16(self) -> Rgba16Pixel {
+        let value = widen(self);
+        (value, value, value, u16::MAX)
+    }
+
+    // Weighting each channel by its perceived brightness, like
+    // `color::luminance()`, and discarding the alpha channel
+    fn from_rgba16(pixel: Rgba16Pixel) -> GrayPixel {
+        let luminance = 0.299 * pixel.0 as f32 + 0.587 * pixel.1 as f32 + 0.114 * pixel.2 as f32;
+        narrow(luminance.round().min(u16::MAX as f32) as u16)
+    }
+}
+
+/// A grid of pixels in any `Pixel` format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image<P: Pixel> {
+    width: u32,
+    height: u32,
+    pixels: Vec<P>,
+}
+
+pub type GrayImage = Image<GrayPixel>;
+pub type RgbImage = Image<RgbPixel>;
+pub type Rgba16Image = Image<Rgba16Pixel>;
+
+impl<P: Pixel> Image<P> {
+    /// Create an image that is entirely black, and opaque
+    /// if the format has an alpha channel.
+    pub fn new(width: u32, height: u32) -> Image<P> {
+        Image { width, height, pixels: vec![P::BLACK; width as usize * height as usize] }
+    }
+
+    /// Create an image from pixels listed row by row, returning `None`
+    /// if the number of pixels doesn't match the dimensions.
+    pub fn from_pixels(width: u32, height: u32, pixels: Vec<P>) -> Option<Image<P>> {
+        if pixels.len() != width as usize * height as usize {
+            return None;
+        }
+        Some(Image { width, height, pixels })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Every pixel, row by row.
+    pub fn pixels(&self) -> &[P] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [P] {
+        &mut self.pixels
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<P> {
+        if x >= self.width || y >= self.height { return None }
+        Some(self.pixels[(y * self.width + x) as usize])
+    }
+
+    /// Returns false if the coordinates are outside of the image.
+    pub fn set_pixel(&mut self, x: u32, y: u32, pixel: P) -> bool {
+        if x >= self.width || y >= self.height { return false }
+        self.pixels[(y * self.width + x) as usize] = pixel;
+        true
+    }
+
+    pub fn fill(&mut self, pixel: P) {
+        self.pixels.fill(pixel);
+    }
+
+    /// A copy of this image in another pixel format.
+    pub fn convert<Q: Pixel>(&self) -> Image<Q> {
+        Image {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.iter().map(|pixel| Q::from_rgba16(pixel.to_rgba16())).collect(),
+        }
+    }
+
+    /// A copy of this image as an `RgbaImage`, ready to be displayed.
+    pub fn to_rgba(&self) -> RgbaImage {
+        let mut image = RgbaImage::new(0, 0);
+        self.write_rgba(&mut image);
+        image
+    }
+
+    /// Replace the contents of `image` with a copy of this image,
+    /// reusing its allocation, which avoids an allocation per frame.
+    pub fn write_rgba(&self, image: &mut RgbaImage) {
+        image.width = self.width;
+        image.height = self.height;
+        image.clip_rect = None;
+        image.bytes.clear();
+
+        for pixel in &self.pixels {
+            let (r, g, b, a) = RgbaPixel::from_rgba16(pixel.to_rgba16());
+            image.bytes.extend_from_slice(&[r, g, b, a]);
+        }
+    }
+
+    /// A copy of an `RgbaImage` in this pixel format.
+    pub fn from_rgba(image: &RgbaImage) -> Image<P> {
+        let pixels = image.bytes.chunks_exact(4)
+            .map(|pixel| P::from_rgba16((pixel[0], pixel[1], pixel[2], pixel[3]).to_rgba16()))
+            .collect();
+
+        Image { width: image.width, height: image.height, pixels }
+    }
+}
+
+#[test]
+fn _pixel_formats() {
+    // 16-bit values survive a round trip through 16-bit images only
+    let mut deep = Rgba16Image::new(2, 1);
+    deep.set_pixel(0, 0, (1000, 40000, 65535, 32768));
+    assert_eq!(deep.convert::<Rgba16Pixel>(), deep);
+    assert_eq!(deep.to_rgba().get_pixel(0, 0), Some((4, 156, 255, 128)));
+
+    // 8-bit values survive a round trip through 16 bits
+    let mut rgb = RgbImage::new(1, 1);
+    rgb.fill((12, 200, 255));
+    assert_eq!(rgb.convert::<Rgba16Pixel>().convert::<RgbPixel>(), rgb);
+    assert_eq!(rgb.to_rgba().get_pixel(0, 0), Some((12, 200, 255, 255)));
+
+    let gray = GrayImage::from_rgba(&rgb.to_rgba());
+    assert_eq!(gray.get_pixel(0, 0), Some(crate::color::luminance(&(12, 200, 255, 255))));
+    assert!(GrayImage::from_pixels(2, 2, vec![0; 3]).is_none());
+}
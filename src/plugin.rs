@@ -0,0 +1,118 @@
+//! Plugins, which sit between the window and the controller, for features
+//! that work with any application, such as an overlay that displays the
+//! buttons being pressed, or a recorder of analytics.
+//!
+//! Plugins are returned by `UIController::plugins()` when the application
+//! launches, or added to a window opened by `UI::open()` with
+//! `UIHandle::add_plugin()`. Every frame, each plugin in turn can observe
+//! and change the events that are about to reach the controller, and draw
+//! over the finished frame. Overlays are included in screenshots,
+//! recordings and remote streams, but not in multi-viewport frames.
+
+use crate::{RgbaImage, RgbaImageRegion, UIEvent};
+
+/// An extension that observes the events a controller receives,
+/// and the frames it draws.
+pub trait UIPlugin {
+    /// This function will be called before the controller receives events,
+    /// and can add, remove or change them. Plugins registered later see
+    /// the events as changed by earlier ones.
+    fn process_events(&mut self, _events: &mut Vec<UIEvent>) {}
+
+    /// Whether `draw_overlay()` should be called. Frames are only
+    /// copied for overlays if at least one plugin draws one.
+    fn draws_overlay(&self) -> bool {
+        false
+    }
+
+    /// This function will be called with a copy of every frame before it
+    /// is displayed, after any earlier plugins have drawn over it.
+    fn draw_overlay(&mut self, _frame: &mut RgbaImage) {}
+}
+
+// Every plugin of a window, in the order they were registered
+#[cfg_attr(not(feature = "window"), allow(dead_code))]
+pub(crate) struct Plugins {
+    plugins: Vec<Box<dyn UIPlugin>>,
+    // A copy of the latest frame, with overlays drawn on top
+    overlay: RgbaImage,
+}
+
+#[cfg_attr(not(feature = "window"), allow(dead_code))]
+impl Plugins {
+    pub(crate) fn new(plugins: Vec<Box<dyn UIPlugin>>) -> Plugins {
+        Plugins { plugins, overlay: RgbaImage::new(0, 0) }
+    }
+
+    pub(crate) fn push(&mut self, plugin: Box<dyn UIPlugin>) {
+        self.plugins.push(plugin);
+    }
+
+    pub(crate) fn process_events(&mut self, ui_events: &mut Vec<UIEvent>) {
+        for plugin in &mut self.plugins {
+            plugin.process_events(ui_events);
+        }
+    }
+
+    // Drawing every plugin's overlay onto a copy of the frame,
+    // or returning `None` if no plugin draws one
+    pub(crate) fn overlay(&mut self, frame: &RgbaImageRegion) -> Option<RgbaImageRegion<'_>> {
+        if !self.plugins.iter().any(|plugin| plugin.draws_overlay()) {
+            return None;
+        }
+
+        self.overlay.copy_region(frame);
+        for plugin in self.plugins.iter_mut().filter(|plugin| plugin.draws_overlay()) {
+            plugin.draw_overlay(&mut self.overlay);
+        }
+
+        Some(self.overlay.as_region())
+    }
+}
+
+impl Default for Plugins {
+    fn default() -> Plugins {
+        Plugins::new(vec![])
+    }
+}
+
+#[test]
+fn _plugins() {
+    use crate::{CursorMovementEvent, Rect};
+
+    // Hiding the cursor from the controller, and marking where it was
+    struct CursorThief { last: Option<(u32, u32)> }
+    impl UIPlugin for CursorThief {
+        fn process_events(&mut self, events: &mut Vec<UIEvent>) {
+            events.retain(|event| match event {
+                UIEvent::CursorMovement(movement) => {
+                    self.last = Some((movement.x, movement.y));
+                    false
+                },
+                _ => true,
+            });
+        }
+        fn draws_overlay(&self) -> bool { self.last.is_some() }
+        fn draw_overlay(&mut self, frame: &mut RgbaImage) {
+            let (x, y) = self.last.unwrap();
+            frame.fill_rect(Rect::new(x, y, 1, 1), (255, 0, 0, 255));
+        }
+    }
+
+    let mut frame = RgbaImage::new(4, 4);
+    frame.fill((0, 0, 0, 255));
+
+    let mut plugins = Plugins::new(vec![Box::new(CursorThief { last: None })]);
+    assert!(plugins.overlay(&frame.as_region()).is_none());
+
+    let mut events = vec![
+        UIEvent::CursorMovement(CursorMovementEvent { device_id: 0, x: 2, y: 1 }),
+        UIEvent::CloseRequested,
+    ];
+    plugins.process_events(&mut events);
+    assert_eq!(events, vec![UIEvent::CloseRequested]);
+
+    let overlay = plugins.overlay(&frame.as_region()).unwrap();
+    assert_eq!(overlay.get_pixel(2, 1), Some((255, 0, 0, 255)));
+    assert_eq!(frame.get_pixel(2, 1), Some((0, 0, 0, 255)));
+}
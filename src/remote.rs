@@ -0,0 +1,269 @@
+//! Streaming frames over the network to a viewer on another machine,
+//! and receiving its input in return, for applications running on
+//! machines without a screen of their own.
+//!
+//! `UIBlueprint::remote_stream()` makes an application stream every frame
+//! that it displays. Applications without a window can drive a
+//! `RemoteServer` directly instead. On the other machine, launching a
+//! `RemoteViewer` displays the stream, and forwards keyboard, mouse,
+//! pen and scroll events back to the application.
+//!
+//! Frames are sent uncompressed, so streaming is best suited to small
+//! frames and fast local networks. Connections aren't authenticated
+//! or encrypted, so only listen on trusted networks.
+
+use crate::replay::{decode_event, encode_event};
+use crate::{window_to_frame, CursorMovementEvent, PenEvent, RedrawHandle, RgbaImage, RgbaImageRegion, UIContext, UIController, UIEvent};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// A frame's dimensions, followed by its pixels
+type FrameMessage = (u32, u32, Vec<u8>);
+
+// The largest frames that a viewer accepts, so that a corrupt or malicious
+// header can't make it allocate more memory than it has
+const MAX_FRAME_DIMENSION: u32 = 16384;
+const MAX_FRAME_BYTES: usize = 256 * 1024 * 1024;
+
+/// Accepts a viewer connection, streams frames to it, and collects the
+/// events that it sends back. A new viewer replaces the previous one.
+pub struct RemoteServer {
+    address: SocketAddr,
+    viewer: Arc<Mutex<Option<SyncSender<FrameMessage>>>>,
+    events: Receiver<UIEvent>,
+}
+
+impl RemoteServer {
+    /// Start listening for a viewer, at an address such as `"0.0.0.0:7878"`.
+    pub fn bind<A: ToSocketAddrs>(address: A) -> io::Result<RemoteServer> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let viewer = Arc::new(Mutex::new(None));
+        let (event_sender, events) = mpsc::channel();
+
+        let shared_viewer = viewer.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Ok(frames) = serve_viewer(stream, event_sender.clone()) {
+                    *shared_viewer.lock().unwrap_or_else(|error| error.into_inner()) = Some(frames);
+                }
+            }
+        });
+
+        Ok(RemoteServer { address, viewer, events })
+    }
+
+    /// The address that the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Send a frame to the viewer, if one is connected. Frames are
+    /// dropped rather than queued while the viewer is falling behind.
+    pub fn send_frame(&self, frame: &RgbaImageRegion) {
+        let mut viewer = self.viewer.lock().unwrap_or_else(|error| error.into_inner());
+
+        let frames = match &*viewer {
+            Some(frames) => frames,
+            None => return,
+        };
+
+        let message = (frame.width(), frame.height(), frame.contiguous_bytes().into_owned());
+        if let Err(TrySendError::Disconnected(_)) = frames.try_send(message) {
+            *viewer = None;
+        }
+    }
+
+    /// Collect the events that viewers have sent since the last call.
+    pub fn poll_events(&self, ui_events: &mut Vec<UIEvent>) {
+        ui_events.extend(self.events.try_iter());
+    }
+}
+
+// Writing frames to a viewer, and reading its events, on threads of their own
+fn serve_viewer(stream: TcpStream, events: Sender<UIEvent>) -> io::Result<SyncSender<FrameMessage>> {
+    stream.set_nodelay(true)?;
+    let mut writer = stream.try_clone()?;
+    let (frames, frame_receiver) = mpsc::sync_channel::<FrameMessage>(1);
+
+    thread::spawn(move || {
+        for (width, height, bytes) in frame_receiver {
+            let header = [width.to_be_bytes(), height.to_be_bytes()].concat();
+            if writer.write_all(&header).and_then(|_| writer.write_all(&bytes)).is_err() {
+                break;
+            }
+        }
+    });
+
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let event = match line {
+                Ok(line) => decode_event(&line),
+                Err(_) => break,
+            };
+            if let Some(event) = event {
+                if events.send(event).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(frames)
+}
+
+/// A controller that displays the frames streamed by a `RemoteServer`,
+/// and sends it the user's input. Cursor and pen positions are sent
+/// in frame pixels, rather than relative to the viewer's window.
+pub struct RemoteViewer {
+    stream: TcpStream,
+    frames: Receiver<RgbaImage>,
+    frame: RgbaImage,
+    redraw_handle: Arc<Mutex<Option<RedrawHandle>>>,
+    window_size: (u32, u32),
+}
+
+impl RemoteViewer {
+    /// Connect to a server, such as `"raspberrypi.local:7878"`. The window
+    /// size should match the dimensions of the viewer's blueprint.
+    pub fn connect<A: ToSocketAddrs>(address: A, window_size: (u32, u32)) -> io::Result<RemoteViewer> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        let mut reader = stream.try_clone()?;
+        let (frame_sender, frames) = mpsc::channel();
+        let redraw_handle: Arc<Mutex<Option<RedrawHandle>>> = Arc::new(Mutex::new(None));
+
+        let shared_handle = redraw_handle.clone();
+        thread::spawn(move || {
+            while let Ok(frame) = read_frame(&mut reader) {
+                if frame_sender.send(frame).is_err() {
+                    break;
+                }
+                if let Some(handle) = &*shared_handle.lock().unwrap_or_else(|error| error.into_inner()) {
+                    handle.request_redraw();
+                }
+            }
+        });
+
+        Ok(RemoteViewer { stream, frames, frame: RgbaImage::new(0, 0), redraw_handle, window_size })
+    }
+}
+
+fn read_frame(reader: &mut impl Read) -> io::Result<RgbaImage> {
+    let mut header = [0; 8];
+    reader.read_exact(&mut header)?;
+    let width = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+    let height = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+
+    let too_large = width > MAX_FRAME_DIMENSION || height > MAX_FRAME_DIMENSION
+        || width as usize * height as usize * 4 > MAX_FRAME_BYTES;
+    if too_large {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("frame too large ({}x{})", width, height)));
+    }
+
+    let mut bytes = vec![0; width as usize * height as usize * 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(RgbaImage { width, height, bytes, clip_rect: None })
+}
+
+impl UIController for RemoteViewer {
+    fn process_events(&mut self, events: &Vec<UIEvent>, _context: &mut UIContext) -> crate::UIControlFlow {
+        let frame_size = (self.frame.width(), self.frame.height());
+
+        for event in events {
+            let event = match *event {
+                UIEvent::Resize(resize) => {
+                    self.window_size = (resize.width, resize.height);
+                    continue;
+                },
+                // Positions can't be translated until the first frame arrives
+                UIEvent::CursorMovement(_) | UIEvent::Pen(_) if frame_size.0 == 0 || frame_size.1 == 0 => continue,
+                UIEvent::CursorMovement(movement) => {
+                    let (x, y) = window_to_frame((movement.x, movement.y), self.window_size, frame_size, true);
+                    UIEvent::CursorMovement(CursorMovementEvent { x: x.max(0.0) as u32, y: y.max(0.0) as u32, ..movement })
+                },
+                UIEvent::Pen(pen) => {
+                    let position = (pen.x.max(0.0) as u32, pen.y.max(0.0) as u32);
+                    let (x, y) = window_to_frame(position, self.window_size, frame_size, true);
+                    UIEvent::Pen(PenEvent { x, y, ..pen })
+                },
+                UIEvent::Keyboard(_) | UIEvent::Text(_) | UIEvent::MouseButton(_) | UIEvent::Scroll(_) => *event,
+                _ => continue,
+            };
+
+            // Ignoring failures, since the server may come back
+            let _ = writeln!(self.stream, "{}", encode_event(&event));
+        }
+
+        crate::UIControlFlow::Continue
+    }
+
+    fn next_frame(&mut self, _context: &mut UIContext) -> Option<RgbaImageRegion<'_>> {
+        let latest = self.frames.try_iter().last()?;
+        self.frame = latest;
+        Some(self.frame.as_region())
+    }
+
+    fn redraw_handle(&mut self, handle: RedrawHandle) {
+        *self.redraw_handle.lock().unwrap_or_else(|error| error.into_inner()) = Some(handle);
+    }
+}
+
+#[test]
+fn _remote() {
+    use crate::{KeyboardAction, KeyboardEvent, KeyboardKey, KeyboardModifiers};
+
+    let server = RemoteServer::bind("127.0.0.1:0").unwrap();
+    let mut viewer = RemoteViewer::connect(server.local_addr(), (4, 4)).unwrap();
+    let mut context = UIContext::new((4, 4), 1.0);
+
+    // Waiting for the server to accept the viewer
+    while server.viewer.lock().unwrap().is_none() {
+        thread::yield_now();
+    }
+
+    let mut frame = RgbaImage::new(2, 2);
+    frame.fill((1, 2, 3, 4));
+    server.send_frame(&frame.as_region());
+
+    let received = loop {
+        if let Some(received) = viewer.next_frame(&mut context) {
+            break received.contiguous_bytes().into_owned();
+        }
+        thread::yield_now();
+    };
+    assert_eq!(received, frame.as_region().contiguous_bytes().into_owned());
+
+    let key = UIEvent::Keyboard(KeyboardEvent {
+        device_id: 0,
+        key: KeyboardKey::Up,
+        action: KeyboardAction::Press,
+        modifiers: KeyboardModifiers::default(),
+        character: None,
+    });
+    viewer.process_events(&vec![key], &mut context);
+
+    let mut events = vec![];
+    while events.is_empty() {
+        server.poll_events(&mut events);
+        thread::yield_now();
+    }
+    assert_eq!(events, vec![key]);
+}
+
+#[test]
+fn _read_frame() {
+    let mut message = vec![0, 0, 0, 1, 0, 0, 0, 1, 10, 20, 30, 40];
+    let frame = read_frame(&mut message.as_slice()).unwrap();
+    assert_eq!(frame.get_pixel(0, 0), Some((10, 20, 30, 40)));
+
+    // Rejecting huge frames before allocating anything for them
+    for (width, height) in [(u32::MAX, u32::MAX), (100_000, 1), (16384, 16384)] {
+        message.splice(..8, width.to_be_bytes().into_iter().chain(height.to_be_bytes()));
+        let error = read_frame(&mut message.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
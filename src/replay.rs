@@ -0,0 +1,461 @@
+//! Recording the events that a controller receives, and replaying them
+//! later in place of live input. Together with a controller that doesn't
+//! depend on the clock or on randomness, a replay reproduces a session
+//! exactly, which is useful for bug reports, demos, and automated tests.
+//!
+//! Events are grouped into steps, where each step is one call to
+//! `UIController::process_events()`. Recordings are plain text files,
+//! with one event per line, preceded by the index of its step.
+//!
+//! To check that a replay drew the same frames as the original session,
+//! log the hash of every frame with `UIBlueprint::log_frame_hashes()`
+//! during both, and compare the logs.
+
+use crate::gamepad::{GamepadAction, GamepadAxis, GamepadButton, GamepadEvent, JoystickAction, JoystickEvent};
+use crate::menu::MenuEvent;
+use crate::tray::TrayIconEvent;
+use crate::{
+    CursorMovementEvent, FrameBudgetEvent, FrameTimingEvent, KeyboardAction, KeyboardEvent, KeyboardKey,
+    KeyboardModifiers, MonitorAction, MonitorEvent, MouseButton, MouseButtonAction, MouseButtonEvent, OcclusionEvent,
+    PenEvent, PenPhase,
+    RefreshRateEvent, ResizeEvent, RgbaImageRegion, ScrollDelta, ScrollEvent, TextEvent, ThemeEvent, UIEvent,
+    WindowTheme,
+};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Writes the events of each step to a recording file.
+pub struct InputRecorder {
+    writer: BufWriter<File>,
+}
+
+impl InputRecorder {
+    /// Start a new recording, replacing the file at `path` if it already exists.
+    pub fn create(path: &Path) -> io::Result<InputRecorder> {
+        Ok(InputRecorder { writer: BufWriter::new(File::create(path)?) })
+    }
+
+    pub fn record(&mut self, step: u64, events: &[UIEvent]) -> io::Result<()> {
+        for event in events {
+            writeln!(self.writer, "{} {}", step, encode_event(event))?;
+        }
+        Ok(())
+    }
+
+    /// Write any buffered events to the file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// The events of a recording, which can be fed back to a controller
+/// one step at a time.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct InputReplay {
+    steps: BTreeMap<u64, Vec<UIEvent>>,
+}
+
+impl InputReplay {
+    pub fn open(path: &Path) -> io::Result<InputReplay> {
+        InputReplay::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Read a recording from the contents of a recording file.
+    pub fn parse(text: &str) -> io::Result<InputReplay> {
+        let mut steps: BTreeMap<u64, Vec<UIEvent>> = BTreeMap::new();
+
+        for (index, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("invalid recording on line {}", index + 1));
+            let (step, event) = line.split_once(' ').ok_or_else(invalid)?;
+            let step = step.parse().map_err(|_| invalid())?;
+            let event = decode_event(event).ok_or_else(invalid)?;
+            steps.entry(step).or_default().push(event);
+        }
+
+        Ok(InputReplay { steps })
+    }
+
+    /// The events that were recorded during `step`.
+    pub fn events(&self, step: u64) -> Vec<UIEvent> {
+        self.steps.get(&step).cloned().unwrap_or_default()
+    }
+
+    /// The number of steps before the last recorded event has been replayed.
+    pub fn len(&self) -> u64 {
+        self.steps.keys().next_back().map_or(0, |step| step + 1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+// Recording or replaying the events received by `UI::launch()`. Once
+// a replay is over, live input is passed through again.
+pub(crate) struct InputTape {
+    recorder: Option<InputRecorder>,
+    replay: Option<InputReplay>,
+    step: u64,
+}
+
+impl InputTape {
+    pub(crate) fn new(recording: Option<&Path>, replay: Option<&Path>) -> InputTape {
+        let recorder = recording.and_then(|path| InputRecorder::create(path)
+            .map_err(|error| log_error!("unable to record input to {}: {}", path.display(), error))
+            .ok());
+
+        let replay = replay.and_then(|path| InputReplay::open(path)
+            .map_err(|error| log_error!("unable to replay input from {}: {}", path.display(), error))
+            .ok());
+
+        InputTape { recorder, replay, step: 0 }
+    }
+
+    pub(crate) fn is_replaying(&self) -> bool {
+        self.replay.as_ref().is_some_and(|replay| self.step < replay.len())
+    }
+
+    // Swapping live events for recorded ones while replaying,
+    // and recording whichever events the controller will see
+    pub(crate) fn apply(&mut self, ui_events: &mut Vec<UIEvent>) {
+        if self.is_replaying() {
+            if let Some(replay) = &self.replay {
+                *ui_events = replay.events(self.step);
+            }
+        }
+
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(error) = recorder.record(self.step, ui_events) {
+                log_error!("unable to record input: {}", error);
+                self.recorder = None;
+            }
+        }
+
+        self.step += 1;
+    }
+}
+
+impl Drop for InputTape {
+    fn drop(&mut self) {
+        if let Some(recorder) = &mut self.recorder {
+            let _ = recorder.flush();
+        }
+    }
+}
+
+// Writing the `content_hash()` of every displayed frame to a file,
+// one line per frame, so that runs can be compared cheaply
+#[derive(Default)]
+pub(crate) struct FrameHashLog {
+    writer: Option<BufWriter<File>>,
+    frame: u64,
+}
+
+impl FrameHashLog {
+    pub(crate) fn new(path: Option<&Path>) -> FrameHashLog {
+        let writer = path.and_then(|path| File::create(path)
+            .map(BufWriter::new)
+            .map_err(|error| log_error!("unable to log frame hashes to {}: {}", path.display(), error))
+            .ok());
+
+        FrameHashLog { writer, frame: 0 }
+    }
+
+    pub(crate) fn log(&mut self, pixels: &RgbaImageRegion) {
+        if let Some(writer) = &mut self.writer {
+            if let Err(error) = writeln!(writer, "{} {:016x}", self.frame, pixels.content_hash()) {
+                log_error!("unable to log frame hashes: {}", error);
+                self.writer = None;
+            }
+        }
+        self.frame += 1;
+    }
+}
+
+pub(crate) fn encode_event(event: &UIEvent) -> String {
+    let flag = |value: bool| if value { "1" } else { "0" };
+    let optional = |value: Option<f32>| value.map_or("-".to_string(), |value| value.to_string());
+
+    match event {
+        UIEvent::Keyboard(event) => {
+            let modifiers = event.modifiers;
+            format!("key {} {:?} {} {}{}{}{} {}",
+                event.device_id,
+                event.key,
+                if event.action == KeyboardAction::Press { "press" } else { "release" },
+                flag(modifiers.shift), flag(modifiers.ctrl), flag(modifiers.alt), flag(modifiers.logo),
+                event.character.map_or("-".to_string(), |character| (character as u32).to_string()),
+            )
+        },
+        UIEvent::Text(event) => format!("text {}", event.character as u32),
+        UIEvent::MouseButton(event) => format!("mouse {} {} {}",
+            event.device_id,
+            encode_mouse_button(event.button),
+            if event.action == MouseButtonAction::Press { "press" } else { "release" },
+        ),
+        UIEvent::CursorMovement(event) => format!("cursor {} {} {}", event.device_id, event.x, event.y),
+        UIEvent::Resize(event) => format!("resize {} {}", event.width, event.height),
+        UIEvent::Pen(event) => format!("pen {} {} {} {} {} {} {}",
+            event.device_id,
+            event.contact_id,
+            match event.phase {
+                PenPhase::Down => "down",
+                PenPhase::Move => "move",
+                PenPhase::Up => "up",
+                PenPhase::Cancel => "cancel",
+            },
+            event.x,
+            event.y,
+            optional(event.pressure),
+            optional(event.tilt),
+        ),
+        UIEvent::Scroll(event) => match event.delta {
+            ScrollDelta::Lines { x, y } => format!("scroll {} lines {} {}", event.device_id, x, y),
+            ScrollDelta::Pixels { x, y } => format!("scroll {} pixels {} {}", event.device_id, x, y),
+        },
+        UIEvent::FrameBudgetExceeded(event) => format!("late {} {}", event.late_by.as_nanos(), event.skipped_renders),
+        UIEvent::FrameTiming(event) => format!("timing {} {}", event.jitter.as_nanos(), event.timer_slop.as_nanos()),
+        UIEvent::Theme(event) => match event.theme {
+            WindowTheme::Light => "theme light".to_string(),
+            WindowTheme::Dark => "theme dark".to_string(),
+        },
+        UIEvent::Menu(event) => format!("menu {}", event.id),
+        UIEvent::TrayIcon(event) => format!("tray {} {}", encode_mouse_button(event.button), flag(event.double_click)),
+        UIEvent::Gamepad(event) => match event.action {
+            GamepadAction::Connected => format!("gamepad {} connected", event.gamepad_id),
+            GamepadAction::Disconnected => format!("gamepad {} disconnected", event.gamepad_id),
+            GamepadAction::Press(button) => format!("gamepad {} press {:?}", event.gamepad_id, button),
+            GamepadAction::Release(button) => format!("gamepad {} release {:?}", event.gamepad_id, button),
+            GamepadAction::Axis(axis, value) => format!("gamepad {} axis {:?} {}", event.gamepad_id, axis, value),
+        },
+        UIEvent::Joystick(event) => match event.action {
+            JoystickAction::Button { code, pressed } => format!("joystick {} button {} {}", event.joystick_id, code, flag(pressed)),
+            JoystickAction::Axis { code, value } => format!("joystick {} axis {} {}", event.joystick_id, code, value),
+        },
+        UIEvent::CloseRequested => "close".to_string(),
+        UIEvent::Suspended => "suspended".to_string(),
+        UIEvent::Resumed => "resumed".to_string(),
+        UIEvent::Occlusion(event) => format!("occlusion {}", flag(event.occluded)),
+        UIEvent::RefreshRate(event) => format!("refresh {}", event.frames_per_second),
+        UIEvent::Monitor(event) => format!("monitor {} {}", event.monitor_id, match event.action {
+            MonitorAction::Connected => "connected",
+            MonitorAction::Disconnected => "disconnected",
+            MonitorAction::WindowEntered => "entered",
+        }),
+        UIEvent::DisplayReset => "display_reset".to_string(),
+    }
+}
+
+pub(crate) fn decode_event(text: &str) -> Option<UIEvent> {
+    let mut fields = text.split(' ');
+    let kind = fields.next()?;
+    let fields: Vec<&str> = fields.collect();
+
+    let flag = |text: &str| match text {
+        "1" => Some(true),
+        "0" => Some(false),
+        _ => None,
+    };
+    let optional = |text: &str| match text {
+        "-" => Some(None),
+        _ => text.parse().ok().map(Some),
+    };
+    let nanos = |text: &str| text.parse().ok().map(Duration::from_nanos);
+
+    let event = match (kind, fields.as_slice()) {
+        ("key", [device_id, key, action, modifiers, character]) => {
+            let modifiers: Vec<bool> = modifiers.chars().map(|c| flag(&c.to_string())).collect::<Option<_>>()?;
+            if modifiers.len() != 4 {
+                return None;
+            }
+
+            UIEvent::Keyboard(KeyboardEvent {
+                device_id: device_id.parse().ok()?,
+                key: *KeyboardKey::ALL.iter().find(|k| format!("{:?}", k) == *key)?,
+                action: match *action {
+                    "press" => KeyboardAction::Press,
+                    "release" => KeyboardAction::Release,
+                    _ => return None,
+                },
+                modifiers: KeyboardModifiers {
+                    shift: modifiers[0],
+                    ctrl: modifiers[1],
+                    alt: modifiers[2],
+                    logo: modifiers[3],
+                },
+                character: match *character {
+                    "-" => None,
+                    code => Some(char::from_u32(code.parse().ok()?)?),
+                },
+            })
+        },
+        ("mouse", [device_id, button, action]) => UIEvent::MouseButton(MouseButtonEvent {
+            device_id: device_id.parse().ok()?,
+            button: decode_mouse_button(button)?,
+            action: match *action {
+                "press" => MouseButtonAction::Press,
+                "release" => MouseButtonAction::Release,
+                _ => return None,
+            },
+        }),
+        ("cursor", [device_id, x, y]) => UIEvent::CursorMovement(CursorMovementEvent {
+            device_id: device_id.parse().ok()?,
+            x: x.parse().ok()?,
+            y: y.parse().ok()?,
+        }),
+        ("resize", [width, height]) => UIEvent::Resize(ResizeEvent {
+            width: width.parse().ok()?,
+            height: height.parse().ok()?,
+        }),
+        ("pen", [device_id, contact_id, phase, x, y, pressure, tilt]) => UIEvent::Pen(PenEvent {
+            device_id: device_id.parse().ok()?,
+            contact_id: contact_id.parse().ok()?,
+            phase: match *phase {
+                "down" => PenPhase::Down,
+                "move" => PenPhase::Move,
+                "up" => PenPhase::Up,
+                "cancel" => PenPhase::Cancel,
+                _ => return None,
+            },
+            x: x.parse().ok()?,
+            y: y.parse().ok()?,
+            pressure: optional(pressure)?,
+            tilt: optional(tilt)?,
+        }),
+        ("scroll", [device_id, unit, x, y]) => {
+            let (x, y) = (x.parse().ok()?, y.parse().ok()?);
+            UIEvent::Scroll(ScrollEvent {
+                device_id: device_id.parse().ok()?,
+                delta: match *unit {
+                    "lines" => ScrollDelta::Lines { x, y },
+                    "pixels" => ScrollDelta::Pixels { x, y },
+                    _ => return None,
+                },
+            })
+        },
+        ("late", [late_by, skipped_renders]) => UIEvent::FrameBudgetExceeded(FrameBudgetEvent {
+            late_by: nanos(late_by)?,
+            skipped_renders: skipped_renders.parse().ok()?,
+        }),
+        ("timing", [jitter, timer_slop]) => UIEvent::FrameTiming(FrameTimingEvent {
+            jitter: nanos(jitter)?,
+            timer_slop: nanos(timer_slop)?,
+        }),
+        ("theme", ["light"]) => UIEvent::Theme(ThemeEvent { theme: WindowTheme::Light }),
+        ("theme", ["dark"]) => UIEvent::Theme(ThemeEvent { theme: WindowTheme::Dark }),
+        ("menu", [id]) => UIEvent::Menu(MenuEvent { id: id.parse().ok()? }),
+        ("tray", [button, double_click]) => UIEvent::TrayIcon(TrayIconEvent {
+            button: decode_mouse_button(button)?,
+            double_click: flag(double_click)?,
+        }),
+        ("gamepad", [gamepad_id, action @ ..]) => {
+            let button = |name: &str| GamepadButton::ALL.iter().find(|b| format!("{:?}", b) == name).copied();
+            let axis = |name: &str| GamepadAxis::ALL.iter().find(|a| format!("{:?}", a) == name).copied();
+
+            UIEvent::Gamepad(GamepadEvent {
+                gamepad_id: gamepad_id.parse().ok()?,
+                action: match action {
+                    ["connected"] => GamepadAction::Connected,
+                    ["disconnected"] => GamepadAction::Disconnected,
+                    ["press", name] => GamepadAction::Press(button(name)?),
+                    ["release", name] => GamepadAction::Release(button(name)?),
+                    ["axis", name, value] => GamepadAction::Axis(axis(name)?, value.parse().ok()?),
+                    _ => return None,
+                },
+            })
+        },
+        ("joystick", [joystick_id, kind, code, value]) => UIEvent::Joystick(JoystickEvent {
+            joystick_id: joystick_id.parse().ok()?,
+            action: match *kind {
+                "button" => JoystickAction::Button { code: code.parse().ok()?, pressed: flag(value)? },
+                "axis" => JoystickAction::Axis { code: code.parse().ok()?, value: value.parse().ok()? },
+                _ => return None,
+            },
+        }),
+        ("text", [character]) => UIEvent::Text(TextEvent { character: char::from_u32(character.parse().ok()?)? }),
+        ("close", []) => UIEvent::CloseRequested,
+        ("suspended", []) => UIEvent::Suspended,
+        ("resumed", []) => UIEvent::Resumed,
+        ("display_reset", []) => UIEvent::DisplayReset,
+        ("occlusion", [occluded]) => UIEvent::Occlusion(OcclusionEvent { occluded: flag(occluded)? }),
+        ("refresh", [frames_per_second]) => UIEvent::RefreshRate(RefreshRateEvent {
+            frames_per_second: frames_per_second.parse().ok()?,
+        }),
+        ("monitor", [monitor_id, action]) => UIEvent::Monitor(MonitorEvent {
+            monitor_id: monitor_id.parse().ok()?,
+            action: match *action {
+                "connected" => MonitorAction::Connected,
+                "disconnected" => MonitorAction::Disconnected,
+                "entered" => MonitorAction::WindowEntered,
+                _ => return None,
+            },
+        }),
+        _ => return None,
+    };
+
+    Some(event)
+}
+
+fn encode_mouse_button(button: MouseButton) -> String {
+    match button {
+        MouseButton::Left => "left".to_string(),
+        MouseButton::Right => "right".to_string(),
+        MouseButton::Middle => "middle".to_string(),
+        MouseButton::Other(code) => code.to_string(),
+    }
+}
+
+fn decode_mouse_button(text: &str) -> Option<MouseButton> {
+    match text {
+        "left" => Some(MouseButton::Left),
+        "right" => Some(MouseButton::Right),
+        "middle" => Some(MouseButton::Middle),
+        code => code.parse().ok().map(MouseButton::Other),
+    }
+}
+
+#[test]
+fn _replay() {
+    let events = vec![
+        UIEvent::Keyboard(KeyboardEvent {
+            device_id: 3,
+            key: KeyboardKey::Space,
+            action: KeyboardAction::Press,
+            modifiers: KeyboardModifiers { shift: true, ..KeyboardModifiers::default() },
+            character: Some(' '),
+        }),
+        UIEvent::Pen(PenEvent {
+            device_id: 1,
+            contact_id: 2,
+            phase: PenPhase::Move,
+            x: 10.25,
+            y: 0.1,
+            pressure: Some(0.5),
+            tilt: None,
+        }),
+        UIEvent::Scroll(ScrollEvent { device_id: 0, delta: ScrollDelta::Pixels { x: -1.5, y: 3.0 } }),
+        UIEvent::MouseButton(MouseButtonEvent { device_id: 0, button: MouseButton::Other(8), action: MouseButtonAction::Release }),
+        UIEvent::Gamepad(GamepadEvent { gamepad_id: 1, action: GamepadAction::Press(GamepadButton::LeftBumper) }),
+        UIEvent::Gamepad(GamepadEvent { gamepad_id: 1, action: GamepadAction::Axis(GamepadAxis::RightTrigger, 0.25) }),
+        UIEvent::Joystick(JoystickEvent { joystick_id: 2, action: JoystickAction::Axis { code: 65542, value: -1.0 } }),
+        UIEvent::Text(TextEvent { character: 'ß' }),
+        UIEvent::CloseRequested,
+        UIEvent::DisplayReset,
+        UIEvent::Monitor(MonitorEvent { monitor_id: 2, action: MonitorAction::WindowEntered }),
+    ];
+
+    let text: String = events.iter().map(|event| format!("7 {}\n", encode_event(event))).collect();
+    let replay = InputReplay::parse(&text).unwrap();
+    assert_eq!(replay.events(7), events);
+    assert!(replay.events(6).is_empty());
+    assert_eq!(replay.len(), 8);
+
+    assert!(InputReplay::parse("0 key 0 NotAKey press 0000 -").is_err());
+}
@@ -0,0 +1,167 @@
+//! Timers measured in real time, rather than in frames, so that game
+//! logic keeps the same pace regardless of the frame rate.
+
+use std::time::{Duration, Instant};
+
+/// Identifies a timer created by a `Scheduler`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TimerId(u64);
+
+struct Timer {
+    id: TimerId,
+    // When the timer is next due, measured from the scheduler's creation
+    due: Duration,
+    // How often the timer repeats, if it does
+    period: Option<Duration>,
+    // How many times the timer went off during the latest update
+    fired: u32,
+}
+
+/// A collection of one-shot and repeating timers, which are usually
+/// advanced once per frame in `UIController::process_events()`, and then
+/// queried to decide what should happen during that frame.
+pub struct Scheduler {
+    elapsed: Duration,
+    delta: Duration,
+    last_tick: Option<Instant>,
+    timers: Vec<Timer>,
+    next_id: u64,
+}
+
+impl Default for Scheduler {
+    fn default() -> Scheduler {
+        Scheduler::new()
+    }
+}
+
+impl Scheduler {
+    pub fn new() -> Scheduler {
+        Scheduler {
+            elapsed: Duration::ZERO,
+            delta: Duration::ZERO,
+            last_tick: None,
+            timers: vec![],
+            next_id: 0,
+        }
+    }
+
+    fn add_timer(&mut self, delay: Duration, period: Option<Duration>) -> TimerId {
+        let id = TimerId(self.next_id);
+        self.next_id += 1;
+
+        self.timers.push(Timer { id, due: self.elapsed + delay, period, fired: 0 });
+        id
+    }
+
+    /// Create a timer that goes off once, after `delay`.
+    pub fn after(&mut self, delay: Duration) -> TimerId {
+        self.add_timer(delay, None)
+    }
+
+    /// Create a timer that goes off every `period`, starting
+    /// one period from now.
+    pub fn every(&mut self, period: Duration) -> TimerId {
+        let period = period.max(Duration::from_nanos(1));
+        self.add_timer(period, Some(period))
+    }
+
+    /// Stop a timer from going off again.
+    pub fn cancel(&mut self, id: TimerId) {
+        self.timers.retain(|timer| timer.id != id);
+    }
+
+    /// The total time that the scheduler has been advanced by.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The time by which the latest update advanced the scheduler,
+    /// which is usually the length of the previous frame.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    /// Advance the scheduler by the real time that has passed since the
+    /// previous call. The first call establishes the starting point.
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        let elapsed = self.last_tick.map(|last| now - last).unwrap_or_default();
+        self.last_tick = Some(now);
+        self.advance(elapsed);
+    }
+
+    /// Advance the scheduler by a specific amount of time, which is
+    /// useful for fixed time steps, and for tests.
+    pub fn advance(&mut self, elapsed: Duration) {
+        // Forgetting one-shot timers that went off during the previous update
+        self.timers.retain(|timer| timer.period.is_some() || timer.fired == 0);
+        self.elapsed += elapsed;
+        self.delta = elapsed;
+
+        for timer in &mut self.timers {
+            timer.fired = 0;
+            if timer.due > self.elapsed { continue }
+
+            match timer.period {
+                None => timer.fired = 1,
+                Some(period) => {
+                    // Counting every period that passed, in case a
+                    // long frame has made the timer go off repeatedly
+                    let overdue = (self.elapsed - timer.due).as_nanos() / period.as_nanos();
+                    let fired = overdue as u32 + 1;
+                    timer.fired = fired;
+                    timer.due += period * fired;
+                },
+            }
+        }
+    }
+
+    /// How many times a timer went off during the latest update.
+    /// Repeating timers can go off more than once if a frame was slow.
+    pub fn fired(&self, id: TimerId) -> u32 {
+        self.timers.iter()
+            .find(|timer| timer.id == id)
+            .map(|timer| timer.fired)
+            .unwrap_or(0)
+    }
+
+    /// The timers that went off during the latest update.
+    pub fn expired(&self) -> impl Iterator<Item = TimerId> + '_ {
+        self.timers.iter().filter(|timer| timer.fired > 0).map(|timer| timer.id)
+    }
+
+    /// How long until a timer goes off next, or `None` if it
+    /// has been canceled or has already gone off.
+    pub fn remaining(&self, id: TimerId) -> Option<Duration> {
+        self.timers.iter()
+            .find(|timer| timer.id == id && (timer.period.is_some() || timer.fired == 0))
+            .map(|timer| timer.due - self.elapsed)
+    }
+}
+
+#[test]
+fn _scheduler() {
+    let ms = Duration::from_millis;
+    let mut scheduler = Scheduler::new();
+    let once = scheduler.after(ms(300));
+    let repeating = scheduler.every(ms(100));
+
+    scheduler.advance(ms(50));
+    assert_eq!(scheduler.expired().count(), 0);
+    assert_eq!(scheduler.remaining(once), Some(ms(250)));
+
+    // A slow frame makes the repeating timer go off several times
+    scheduler.advance(ms(260));
+    assert_eq!(scheduler.fired(repeating), 3);
+    assert_eq!(scheduler.fired(once), 1);
+    assert_eq!(scheduler.remaining(once), None);
+
+    // One-shot timers only go off once
+    scheduler.advance(ms(100));
+    assert_eq!(scheduler.fired(repeating), 1);
+    assert_eq!(scheduler.fired(once), 0);
+
+    scheduler.cancel(repeating);
+    scheduler.advance(ms(1000));
+    assert_eq!(scheduler.expired().count(), 0);
+}
@@ -0,0 +1,111 @@
+//! Custom fragment shaders, which replace the shader that draws frames
+//! onto the window, for effects such as scanlines or color grading.
+//!
+//! Shaders are written in GLSL 1.50, and are set with
+//! `UIBlueprint::fragment_shader()`. On OpenGL ES, shaders that start
+//! with `#version 150` are translated to GLSL ES 3.00, with high
+//! precision floats, so the same shader works on both. They receive
+//! the texture coordinates of the frame as `in vec2 v_src`, the frame
+//! itself as `uniform sampler2D sampler`, and write to `out vec4 color`.
+//!
+//! These uniforms are filled in by OpenUI every frame, and can
+//! be declared by any shader that needs them:
+//!
+//! - `float u_time`, the seconds since the window opened.
+//! - `int u_frame`, the number of frames displayed before this one.
+//! - `vec2 u_canvas_size`, the size of the texture being sampled, which
+//!   is the frame, unless the frame is too large and is split into tiles.
+//! - `vec2 u_window_size`, the size of the window, as reported by `ResizeEvent`.
+//! - `vec2 u_mouse`, the position of the cursor within the window,
+//!   as reported by `CursorMovementEvent`.
+//!
+//! Controllers can set uniforms of their own with `UIContext::set_uniform()`.
+
+/// A value for a uniform declared by a custom shader.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ShaderValue {
+    Int(i32),
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+}
+
+impl From<i32> for ShaderValue {
+    fn from(value: i32) -> ShaderValue {
+        ShaderValue::Int(value)
+    }
+}
+
+impl From<f32> for ShaderValue {
+    fn from(value: f32) -> ShaderValue {
+        ShaderValue::Float(value)
+    }
+}
+
+impl From<[f32; 2]> for ShaderValue {
+    fn from(value: [f32; 2]) -> ShaderValue {
+        ShaderValue::Vec2(value)
+    }
+}
+
+impl From<[f32; 3]> for ShaderValue {
+    fn from(value: [f32; 3]) -> ShaderValue {
+        ShaderValue::Vec3(value)
+    }
+}
+
+impl From<[f32; 4]> for ShaderValue {
+    fn from(value: [f32; 4]) -> ShaderValue {
+        ShaderValue::Vec4(value)
+    }
+}
+
+// Replacing the value of a uniform, or adding it if it's new
+pub(crate) fn set_uniform(uniforms: &mut Vec<(String, ShaderValue)>, name: &str, value: ShaderValue) {
+    match uniforms.iter_mut().find(|(existing, _)| existing == name) {
+        Some((_, existing)) => *existing = value,
+        None => uniforms.push((name.to_string(), value)),
+    }
+}
+
+// Compiling shaders written in GLSL 1.50, which are
+// translated first if the context is OpenGL ES
+#[cfg(feature = "window")]
+pub(crate) fn program(display: &glium::Display, vertex_shader: &str, fragment_shader: &str) -> Result<glium::Program, glium::ProgramCreationError> {
+    if display.get_opengl_version().0 == glium::Api::GlEs {
+        glium::Program::from_source(display, &to_glsl_es(vertex_shader), &to_glsl_es(fragment_shader), None)
+    } else {
+        glium::Program::from_source(display, vertex_shader, fragment_shader, None)
+    }
+}
+
+// Swapping the version directive, which can only be preceded by
+// whitespace, and giving floats a precision, which GLSL ES requires
+#[cfg_attr(not(feature = "window"), allow(dead_code))]
+fn to_glsl_es(source: &str) -> String {
+    match source.trim_start().strip_prefix("#version 150") {
+        Some(rest) => format!("#version 300 es\nprecision highp float;{}", rest),
+        None => source.to_string(),
+    }
+}
+
+#[test]
+fn _to_glsl_es() {
+    let source = "\n    #version 150\n\n    out vec4 color;\n";
+    assert_eq!(to_glsl_es(source), "#version 300 es\nprecision highp float;\n\n    out vec4 color;\n");
+    assert_eq!(to_glsl_es("#version 330\n"), "#version 330\n");
+}
+
+#[test]
+fn _set_uniform() {
+    let mut uniforms = vec![];
+    set_uniform(&mut uniforms, "glow", 0.5.into());
+    set_uniform(&mut uniforms, "tint", [1.0, 0.0, 0.0].into());
+    set_uniform(&mut uniforms, "glow", 0.75.into());
+
+    assert_eq!(uniforms, vec![
+        ("glow".to_string(), ShaderValue::Float(0.75)),
+        ("tint".to_string(), ShaderValue::Vec3([1.0, 0.0, 0.0])),
+    ]);
+}
@@ -0,0 +1,231 @@
+//! Running Shadertoy-style fragment shaders, where every frame is drawn
+//! entirely on the GPU, without a controller or a canvas. Start one with
+//! `UI::launch_shadertoy()`. Only available with the `window` feature.
+//!
+//! Shaders define `void mainImage(out vec4 fragColor, in vec2 fragCoord)`,
+//! exactly as on Shadertoy, and are called once per pixel of the window.
+//! `fragCoord` is measured in pixels from the bottom left corner, and the
+//! alpha channel of `fragColor` is ignored. These uniforms are declared
+//! for every shader, and filled in every frame:
+//!
+//! - `vec3 iResolution`, the size of the window in pixels, and a pixel aspect ratio of 1.
+//! - `float iTime`, the seconds since the window opened.
+//! - `float iTimeDelta`, the seconds since the previous frame.
+//! - `float iFrameRate`, the frames per second of the blueprint.
+//! - `int iFrame`, the number of frames drawn before this one.
+//! - `vec4 iMouse`, the position of the cursor while the left button is held
+//!   in `xy`, and where it was pressed in `zw`. `z` is negative once the
+//!   button is released, and `w` is only positive on the frame it's pressed.
+//! - `vec4 iDate`, the year, month counted from zero, day, and seconds
+//!   since midnight, in UTC.
+//!
+//! Texture inputs such as `iChannel0` aren't supported, and neither
+//! are screenshots, recordings or remote streams of these windows.
+
+use crate::{MouseButton, MouseButtonAction, OpenUiError, UIEvent};
+use glium::Surface;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+const VERTEX_SHADER_SRC: &str = r#"
+    #version 150
+
+    in vec2 position;
+
+    void main() {
+        gl_Position = vec4(position, 0.0, 1.0);
+    }
+"#;
+
+const FRAGMENT_HEADER: &str = r#"#version 150
+
+uniform vec3 iResolution;
+uniform float iTime;
+uniform float iTimeDelta;
+uniform float iFrameRate;
+uniform int iFrame;
+uniform vec4 iMouse;
+uniform vec4 iDate;
+
+out vec4 open_ui_color;
+
+"#;
+
+// Ignoring alpha, like Shadertoy, so shaders that leave it at zero
+// don't turn transparent windows invisible
+const FRAGMENT_FOOTER: &str = r#"
+
+void main() {
+    vec4 color = vec4(0.0, 0.0, 0.0, 1.0);
+    mainImage(color, gl_FragCoord.xy);
+    open_ui_color = vec4(color.rgb, 1.0);
+}
+"#;
+
+// Surrounding a shader with the declarations it expects, and resetting
+// the line count so that compilation errors refer to its own lines
+fn fragment_source(source: &str) -> String {
+    format!("{}#line 1\n{}{}", FRAGMENT_HEADER, source, FRAGMENT_FOOTER)
+}
+
+#[test]
+fn _fragment_source() {
+    let source = fragment_source("void mainImage(out vec4 c, in vec2 p) { c = vec4(1.0); }");
+    assert!(source.starts_with("#version 150"));
+    assert!(source.contains("uniform vec4 iMouse;"));
+    assert!(source.contains("#line 1\nvoid mainImage"));
+    assert!(source.contains("mainImage(color, gl_FragCoord.xy);"));
+}
+
+// The state behind `iMouse`, which follows Shadertoy's conventions
+#[derive(Debug, Default)]
+struct Mouse {
+    cursor: [f32; 2],
+    position: [f32; 2],
+    click: [f32; 2],
+    held: bool,
+    clicked: bool,
+}
+
+impl Mouse {
+    // Window coordinates count down from the top, but fragment
+    // coordinates count up from the bottom
+    fn observe(&mut self, events: &[UIEvent], window_height: u32) {
+        self.clicked = false;
+
+        for event in events {
+            match event {
+                UIEvent::CursorMovement(movement) => {
+                    self.cursor = [movement.x as f32, window_height as f32 - movement.y as f32];
+                    if self.held {
+                        self.position = self.cursor;
+                    }
+                },
+                UIEvent::MouseButton(event) if event.button == MouseButton::Left => match event.action {
+                    MouseButtonAction::Press => {
+                        self.held = true;
+                        self.clicked = true;
+                        self.position = self.cursor;
+                        self.click = self.cursor;
+                    },
+                    MouseButtonAction::Release => self.held = false,
+                },
+                _ => {},
+            }
+        }
+    }
+
+    fn uniform(&self) -> [f32; 4] {
+        let z = if self.held { self.click[0] } else { -self.click[0] };
+        let w = if self.clicked { self.click[1] } else { -self.click[1] };
+        [self.position[0], self.position[1], z, w]
+    }
+}
+
+#[test]
+fn _mouse() {
+    use crate::{CursorMovementEvent, MouseButtonEvent};
+
+    let movement = |x, y| UIEvent::CursorMovement(CursorMovementEvent { device_id: 0, x, y });
+    let button = |action| UIEvent::MouseButton(MouseButtonEvent { device_id: 0, button: MouseButton::Left, action });
+
+    let mut mouse = Mouse::default();
+    mouse.observe(&[movement(10, 90)], 100);
+    assert_eq!(mouse.uniform(), [0.0, 0.0, -0.0, -0.0]);
+
+    // Pressing, then dragging
+    mouse.observe(&[button(MouseButtonAction::Press)], 100);
+    assert_eq!(mouse.uniform(), [10.0, 10.0, 10.0, 10.0]);
+    mouse.observe(&[movement(30, 60)], 100);
+    assert_eq!(mouse.uniform(), [30.0, 40.0, 10.0, -10.0]);
+
+    // The position stops following the cursor once the button is released
+    mouse.observe(&[button(MouseButtonAction::Release), movement(50, 50)], 100);
+    assert_eq!(mouse.uniform(), [30.0, 40.0, -10.0, -10.0]);
+}
+
+// The value of `iDate` at the given time
+fn date(time: SystemTime) -> [f32; 4] {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let (year, month, day) = crate::window::civil_date(since_epoch.as_secs() / 86400);
+    let seconds = (since_epoch.as_secs() % 86400) as f32 + since_epoch.subsec_millis() as f32 / 1000.0;
+    [year as f32, month as f32 - 1.0, day as f32, seconds]
+}
+
+#[test]
+fn _date() {
+    let time = UNIX_EPOCH + std::time::Duration::from_millis(1_700_000_000_500);
+    assert_eq!(date(time), [2023.0, 10.0, 14.0, 80000.5]);
+}
+
+#[derive(Copy, Clone)]
+struct ShadertoyVertex {
+    position: [f32; 2],
+}
+
+implement_vertex!(ShadertoyVertex, position);
+
+// Everything needed to draw a shader over the whole window
+pub(crate) struct ShadertoyRunner {
+    program: glium::Program,
+    vertex_buffer: glium::VertexBuffer<ShadertoyVertex>,
+    indices: glium::IndexBuffer<u16>,
+    frames_per_second: u32,
+    launched: Instant,
+    last_frame: Instant,
+    frame: i32,
+    mouse: Mouse,
+}
+
+impl ShadertoyRunner {
+    pub(crate) fn new(display: &glium::Display, source: &str, frames_per_second: u32) -> Result<ShadertoyRunner, OpenUiError> {
+        let shape = [
+            ShadertoyVertex { position: [-1.0, -1.0] },
+            ShadertoyVertex { position: [ 1.0, -1.0] },
+            ShadertoyVertex { position: [ 1.0,  1.0] },
+            ShadertoyVertex { position: [-1.0,  1.0] },
+        ];
+
+        let program = crate::shader::program(display, VERTEX_SHADER_SRC, &fragment_source(source))?;
+        let now = Instant::now();
+
+        Ok(ShadertoyRunner {
+            program,
+            vertex_buffer: glium::VertexBuffer::new(display, &shape)?,
+            indices: glium::IndexBuffer::new(display, glium::index::PrimitiveType::TrianglesList, &[0, 1, 2, 2, 3, 0])?,
+            frames_per_second,
+            launched: now,
+            last_frame: now,
+            frame: 0,
+            mouse: Mouse::default(),
+        })
+    }
+
+    // Drawing the next frame, after catching up on the cursor
+    pub(crate) fn draw(&mut self, display: &glium::Display, events: &[UIEvent]) {
+        let (width, height) = display.get_framebuffer_dimensions();
+        self.mouse.observe(events, height);
+
+        let now = Instant::now();
+        let uniforms = uniform! {
+            iResolution: [width as f32, height as f32, 1.0],
+            iTime: now.duration_since(self.launched).as_secs_f32(),
+            iTimeDelta: now.duration_since(self.last_frame).as_secs_f32(),
+            iFrameRate: self.frames_per_second as f32,
+            iFrame: self.frame,
+            iMouse: self.mouse.uniform(),
+            iDate: date(SystemTime::now()),
+        };
+
+        let mut target = display.draw();
+        target.clear_color(0.0, 0.0, 0.0, 1.0);
+        if let Err(error) = target.draw(&self.vertex_buffer, &self.indices, &self.program, &uniforms, &Default::default()) {
+            log_error!("unable to display frame: {}", OpenUiError::from(error));
+        }
+        if let Err(error) = target.finish() {
+            log_error!("unable to display frame: {}", OpenUiError::from(error));
+        }
+
+        self.last_frame = now;
+        self.frame += 1;
+    }
+}
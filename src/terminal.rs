@@ -0,0 +1,338 @@
+//! Running controllers in a terminal instead of a window, for example over
+//! SSH, or to watch automated demos in CI logs.
+//!
+//! `UI::launch_terminal()` draws each frame with the upper half block
+//! character, `▀`, so every character is two pixels tall, in the 24-bit
+//! color that most terminals support. Frames are scaled down to fit the
+//! terminal, and letterboxed to preserve their aspect ratio. The window size
+//! reported to the controller is the terminal's size in these pixels.
+//!
+//! Typed keys are delivered as `KeyboardEvent`s, each followed right away by
+//! its release, since terminals don't report releases. On Unix, the terminal
+//! is switched into raw mode while the controller runs, so that keys arrive
+//! as they're typed, and Ctrl+C is a request to close, following the
+//! blueprint's `ClosePolicy`. Commands other than quitting are ignored.
+
+use crate::{
+    exit_requested, letterboxed_size, quit_requested, replay, ClosePolicy, KeyboardAction, KeyboardEvent,
+    KeyboardKey, KeyboardModifiers, OpenUiError, ResizeEvent, RgbaImage, RgbaImageRegion, TextEvent, UIContext, UIController,
+    UIEvent, UI,
+};
+use std::io::{Read, Write};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+impl UI {
+    /// Run a controller in the terminal, until it asks to exit, returning
+    /// its error if it exited with `UIControlFlow::ExitWithError`.
+    /// See the `terminal` module for how frames and keys are handled.
+    pub fn launch_terminal<T: UIController>(mut controller: T) -> Result<(), OpenUiError> {
+        let blueprint = controller.blueprint();
+        let interval = Duration::from_secs(1) / blueprint.frames_per_second.max(1);
+        let _terminal = RawMode::enable();
+
+        // Reading keys on another thread, since reading blocks until they're typed
+        let (input_sender, input_receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut stdin = std::io::stdin();
+            let mut buffer = [0; 64];
+            loop {
+                match stdin.read(&mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(read) => if input_sender.send(buffer[..read].to_vec()).is_err() { break },
+                }
+            }
+        });
+
+        let mut stdout = std::io::stdout();
+        let mut tape = replay::InputTape::new(blueprint.input_recording.as_deref(), blueprint.input_replay.as_deref());
+        let mut frame_hashes = replay::FrameHashLog::new(blueprint.frame_hash_log.as_deref());
+        let mut context = UIContext::new((0, 0), 1.0);
+        let mut ui_events = vec![];
+        let mut frame = RgbaImage::new(0, 0);
+        let mut output = String::new();
+        let mut size = (0, 0);
+        let mut size_checked = None;
+        let mut close_deadline = None;
+        let launched = Instant::now();
+
+        loop {
+            let started = Instant::now();
+            let mut redraw = false;
+
+            // Checking the terminal's size every second,
+            // since doing so means running another program
+            if !matches!(size_checked, Some(checked) if started - checked < Duration::from_secs(1)) {
+                let latest = terminal_size();
+                if latest != size {
+                    size = latest;
+                    redraw = true;
+                    ui_events.push(UIEvent::Resize(ResizeEvent { width: size.0, height: size.1 * 2 }));
+                }
+                size_checked = Some(started);
+            }
+
+            while let Ok(bytes) = input_receiver.try_recv() {
+                if !apply_terminal_input(&bytes, &mut ui_events) {
+                    continue;
+                }
+                match blueprint.close_policy {
+                    ClosePolicy::Exit => return Ok(()),
+                    ClosePolicy::Ask { timeout } => {
+                        ui_events.push(UIEvent::CloseRequested);
+                        if close_deadline.is_none() {
+                            close_deadline = timeout.map(|timeout| started + timeout);
+                        }
+                    },
+                }
+            }
+            if matches!(close_deadline, Some(deadline) if started >= deadline) {
+                return Ok(());
+            }
+
+            context.window_size = (size.0, size.1 * 2);
+            context.elapsed = launched.elapsed();
+
+            tape.apply(&mut ui_events);
+            if let Some(outcome) = exit_requested(controller.process_events(&ui_events, &mut context)) {
+                return outcome;
+            }
+            ui_events.clear();
+
+            // Keeping a copy of the frame, to draw it again if the terminal is resized
+            if let Some(pixels) = controller.next_frame(&mut context) {
+                frame.copy_region(&pixels);
+                frame_hashes.log(&pixels);
+                redraw = true;
+            }
+            if redraw {
+                output.clear();
+                encode_half_blocks(&frame.as_region(), context.window_size, &mut output);
+                stdout.write_all(output.as_bytes())?;
+                stdout.flush()?;
+            }
+
+            if quit_requested(&context.take_commands()) {
+                return Ok(());
+            }
+            context.frame += 1;
+
+            if let Some(remaining) = interval.checked_sub(started.elapsed()) {
+                std::thread::sleep(remaining);
+            }
+        }
+    }
+}
+
+// Drawing a frame as rows of half blocks, where `size` is the terminal's size
+// in pixels, leaving the letterboxing black and transparent pixels dark
+fn encode_half_blocks(frame: &RgbaImageRegion, size: (u32, u32), output: &mut String) {
+    use std::fmt::Write;
+
+    let (width, height) = size;
+    let (img_w, img_h) = letterboxed_size((width as f32, height as f32), frame.width, frame.height);
+    let (left, top) = ((width as f32 - img_w) / 2.0, (height as f32 - img_h) / 2.0);
+
+    let sample = |x: u32, y: u32| -> (u8, u8, u8) {
+        let frame_x = (x as f32 + 0.5 - left) * frame.width as f32 / img_w;
+        let frame_y = (y as f32 + 0.5 - top) * frame.height as f32 / img_h;
+        if !(frame_x >= 0.0 && frame_y >= 0.0) {
+            return (0, 0, 0);
+        }
+
+        let blend = |channel: u8, alpha: u8| (channel as u16 * alpha as u16 / 255) as u8;
+        match frame.get_pixel(frame_x as u32, frame_y as u32) {
+            Some((r, g, b, a)) => (blend(r, a), blend(g, a), blend(b, a)),
+            None => (0, 0, 0),
+        }
+    };
+
+    // Only changing colors when they differ from the previous character's
+    let mut colors = None;
+    for row in 0..height / 2 {
+        let _ = write!(output, "\x1b[{};1H", row + 1);
+        for x in 0..width {
+            let (upper, lower) = (sample(x, row * 2), sample(x, row * 2 + 1));
+            if colors != Some((upper, lower)) {
+                let _ = write!(
+                    output,
+                    "\x1b[38;2;{};{};{};48;2;{};{};{}m",
+                    upper.0, upper.1, upper.2, lower.0, lower.1, lower.2,
+                );
+                colors = Some((upper, lower));
+            }
+            output.push('▀');
+        }
+    }
+    output.push_str("\x1b[0m");
+}
+
+#[test]
+fn _encode_half_blocks() {
+    let mut frame = RgbaImage::new(1, 2);
+    frame.set_pixel(0, 0, (255, 0, 0, 255));
+    frame.set_pixel(0, 1, (0, 0, 255, 128));
+
+    // The frame is letterboxed into the middle of three columns
+    let mut output = String::new();
+    encode_half_blocks(&frame.as_region(), (3, 2), &mut output);
+    assert_eq!(output, concat!(
+        "\x1b[1;1H",
+        "\x1b[38;2;0;0;0;48;2;0;0;0m▀",
+        "\x1b[38;2;255;0;0;48;2;0;0;128m▀",
+        "\x1b[38;2;0;0;0;48;2;0;0;0m▀",
+        "\x1b[0m",
+    ));
+}
+
+// The keys that terminals send as escape sequences
+const ESCAPE_SEQUENCES: &[(&str, KeyboardKey)] = &[
+    ("\x1b[A", KeyboardKey::Up), ("\x1bOA", KeyboardKey::Up),
+    ("\x1b[B", KeyboardKey::Down), ("\x1bOB", KeyboardKey::Down),
+    ("\x1b[C", KeyboardKey::Right), ("\x1bOC", KeyboardKey::Right),
+    ("\x1b[D", KeyboardKey::Left), ("\x1bOD", KeyboardKey::Left),
+    ("\x1b[H", KeyboardKey::Home), ("\x1bOH", KeyboardKey::Home),
+    ("\x1b[F", KeyboardKey::End), ("\x1bOF", KeyboardKey::End),
+    ("\x1b[2~", KeyboardKey::Insert),
+    ("\x1b[3~", KeyboardKey::Delete),
+    ("\x1b[5~", KeyboardKey::PageUp),
+    ("\x1b[6~", KeyboardKey::PageDown),
+];
+
+// Translating what the terminal sends for typed keys into
+// presses and releases, returning true if Ctrl+C was typed
+fn apply_terminal_input(bytes: &[u8], ui_events: &mut Vec<UIEvent>) -> bool {
+    let text = String::from_utf8_lossy(bytes);
+    let mut rest = text.as_ref();
+    let mut interrupted = false;
+
+    while let Some(character) = rest.chars().next() {
+        if let Some((sequence, key)) = ESCAPE_SEQUENCES.iter().find(|(sequence, _)| rest.starts_with(sequence)) {
+            type_key(ui_events, *key, KeyboardModifiers::default(), None);
+            rest = &rest[sequence.len()..];
+            continue;
+        }
+        rest = &rest[character.len_utf8()..];
+
+        let ctrl = KeyboardModifiers { ctrl: true, ..Default::default() };
+        match character {
+            '\x03' => interrupted = true,
+            '\x1b' => type_key(ui_events, KeyboardKey::Escape, KeyboardModifiers::default(), None),
+            '\r' | '\n' => type_key(ui_events, KeyboardKey::Return, KeyboardModifiers::default(), None),
+            '\t' => type_key(ui_events, KeyboardKey::Tab, KeyboardModifiers::default(), None),
+            '\x08' | '\x7f' => type_key(ui_events, KeyboardKey::Back, KeyboardModifiers::default(), None),
+            // Ctrl+A to Ctrl+Z are sent as the bytes 1 to 26
+            '\x01'..='\x1a' => {
+                let letter = (b'a' + character as u8 - 1) as char;
+                if let Some((key, _)) = key_for_char(letter) {
+                    type_key(ui_events, key, ctrl, None);
+                }
+            },
+            character => match key_for_char(character) {
+                Some((key, shift)) => type_key(ui_events, key, KeyboardModifiers { shift, ..Default::default() }, Some(character)),
+                None if !character.is_control() => ui_events.push(UIEvent::Text(TextEvent { character })),
+                None => {},
+            },
+        }
+    }
+
+    interrupted
+}
+
+// Pressing and releasing a key at once
+fn type_key(ui_events: &mut Vec<UIEvent>, key: KeyboardKey, modifiers: KeyboardModifiers, character: Option<char>) {
+    for (action, character) in [(KeyboardAction::Press, character), (KeyboardAction::Release, None)] {
+        ui_events.push(UIEvent::Keyboard(KeyboardEvent { device_id: 0, key, action, modifiers, character }));
+    }
+}
+
+// The key that types a character on a US keyboard,
+// and whether shift has to be held to type it
+fn key_for_char(character: char) -> Option<(KeyboardKey, bool)> {
+    [false, true].into_iter().find_map(|shift| {
+        let key = KeyboardKey::ALL.iter().find(|key| key.to_char(shift) == Some(character))?;
+        Some((*key, shift))
+    })
+}
+
+#[test]
+fn _apply_terminal_input() {
+    let mut events = vec![];
+    assert!(!apply_terminal_input("A\x1b[D\x01é".as_bytes(), &mut events));
+
+    let shift = KeyboardModifiers { shift: true, ..Default::default() };
+    let ctrl = KeyboardModifiers { ctrl: true, ..Default::default() };
+    let press = |key, modifiers, character| UIEvent::Keyboard(KeyboardEvent {
+        device_id: 0, key, action: KeyboardAction::Press, modifiers, character,
+    });
+    let release = |key, modifiers| UIEvent::Keyboard(KeyboardEvent {
+        device_id: 0, key, action: KeyboardAction::Release, modifiers, character: None,
+    });
+
+    assert_eq!(events, vec![
+        press(KeyboardKey::A, shift, Some('A')),
+        release(KeyboardKey::A, shift),
+        press(KeyboardKey::Left, KeyboardModifiers::default(), None),
+        release(KeyboardKey::Left, KeyboardModifiers::default()),
+        press(KeyboardKey::A, ctrl, None),
+        release(KeyboardKey::A, ctrl),
+        UIEvent::Text(TextEvent { character: 'é' }),
+    ]);
+
+    assert!(apply_terminal_input(b"\x03", &mut events));
+}
+
+// The terminal's size in pixels, which are half a character tall,
+// falling back to the size that terminals usually start at
+fn terminal_size() -> (u32, u32) {
+    let stty_size = stty(&["size"]).and_then(|size| {
+        let (rows, columns) = size.trim().split_once(' ')?;
+        Some((columns.parse().ok()?, rows.parse().ok()?))
+    });
+    let environment_size = || {
+        let variable = |name| std::env::var(name).ok()?.parse().ok();
+        Some((variable("COLUMNS")?, variable("LINES")?))
+    };
+
+    stty_size.or_else(environment_size).unwrap_or((80, 24))
+}
+
+// Switching the terminal into raw mode with `stty`, so that keys arrive as
+// soon as they're typed, without being echoed, and hiding the cursor, until
+// this is dropped. Terminals without `stty` are left as they are.
+struct RawMode {
+    saved: Option<String>,
+}
+
+impl RawMode {
+    fn enable() -> RawMode {
+        let saved = stty(&["-g"]);
+        if saved.is_some() {
+            stty(&["raw", "-echo"]);
+        }
+        print!("\x1b[?25l\x1b[2J");
+        RawMode { saved }
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        if let Some(saved) = &self.saved {
+            stty(&[saved.trim()]);
+        }
+        print!("\x1b[0m\x1b[?25h\r\n");
+        let _ = std::io::stdout().flush();
+    }
+}
+
+// Running `stty` on the terminal that the application was started from
+fn stty(args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new("stty")
+        .args(args)
+        .stdin(std::process::Stdio::inherit())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,104 @@
+//! An icon in the system tray, with a small menu, for applications
+//! that keep running in the background.
+//!
+//! Tray icons are displayed on Windows and macOS when the `tray` feature
+//! is enabled, and ignored everywhere else. Choosing an item from the
+//! icon's menu produces a `UIEvent::Menu`, and clicking the icon itself
+//! produces a `UIEvent::TrayIcon`.
+
+use crate::menu::MenuItem;
+use crate::{MouseButton, RgbaImage, UIEvent};
+
+/// The appearance and menu of a tray icon.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrayIcon {
+    pub icon: RgbaImage,
+    pub tooltip: String,
+    pub items: Vec<MenuItem>,
+}
+
+impl TrayIcon {
+    pub fn new(icon: RgbaImage) -> TrayIcon {
+        TrayIcon { icon, tooltip: "".to_string(), items: vec![] }
+    }
+
+    /// Text that is displayed while the cursor hovers over the icon.
+    pub fn tooltip(self, tooltip: &str) -> TrayIcon {
+        TrayIcon { tooltip: tooltip.to_string(), ..self }
+    }
+
+    pub fn item(mut self, id: u32, label: &str) -> TrayIcon {
+        self.items.push(MenuItem::Action { id, label: label.to_string() });
+        self
+    }
+
+    pub fn separator(mut self) -> TrayIcon {
+        self.items.push(MenuItem::Separator);
+        self
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// A click on the tray icon.
+pub struct TrayIconEvent {
+    pub button: MouseButton,
+    /// Whether this was the second click of a double-click.
+    /// Double-clicks are only reported on Windows.
+    pub double_click: bool,
+}
+
+/// A tray icon that has been displayed, which is removed when dropped.
+#[cfg_attr(not(feature = "window"), allow(dead_code))]
+pub(crate) struct NativeTrayIcon {
+    #[cfg(all(feature = "tray", any(target_os = "windows", target_os = "macos")))]
+    _tray_icon: tray_icon::TrayIcon,
+}
+
+/// Display a tray icon, where the platform supports it.
+#[cfg_attr(not(feature = "window"), allow(dead_code))]
+#[allow(unused_variables)]
+pub(crate) fn install(tray_icon: &TrayIcon) -> Option<NativeTrayIcon> {
+    #[cfg(all(feature = "tray", any(target_os = "windows", target_os = "macos")))]
+    {
+        let icon = &tray_icon.icon;
+        let icon = tray_icon::Icon::from_rgba(icon.bytes.clone(), icon.width(), icon.height()).ok()?;
+
+        let items = crate::menu::native_items(&tray_icon.items);
+        let items: Vec<&dyn muda::IsMenuItem> = items.iter().map(|item| item.as_ref()).collect();
+        let menu = muda::Menu::with_items(&items).ok()?;
+
+        let native = tray_icon::TrayIconBuilder::new()
+            .with_icon(icon)
+            .with_tooltip(&tray_icon.tooltip)
+            .with_menu(Box::new(menu))
+            .build()
+            .ok()?;
+
+        Some(NativeTrayIcon { _tray_icon: native })
+    }
+
+    #[cfg(not(all(feature = "tray", any(target_os = "windows", target_os = "macos"))))]
+    None
+}
+
+/// Collect the clicks on the tray icon since the last call.
+#[cfg_attr(not(feature = "window"), allow(dead_code))]
+#[allow(unused_variables, clippy::ptr_arg)]
+pub(crate) fn apply_tray_events(ui_events: &mut Vec<UIEvent>) {
+    #[cfg(all(feature = "tray", any(target_os = "windows", target_os = "macos")))]
+    while let Ok(event) = tray_icon::TrayIconEvent::receiver().try_recv() {
+        let (button, double_click) = match event {
+            tray_icon::TrayIconEvent::Click { button, button_state: tray_icon::MouseButtonState::Up, .. } => (button, false),
+            tray_icon::TrayIconEvent::DoubleClick { button, .. } => (button, true),
+            _ => continue,
+        };
+
+        let button = match button {
+            tray_icon::MouseButton::Left => MouseButton::Left,
+            tray_icon::MouseButton::Right => MouseButton::Right,
+            tray_icon::MouseButton::Middle => MouseButton::Middle,
+        };
+
+        ui_events.push(UIEvent::TrayIcon(TrayIconEvent { button, double_click }));
+    }
+}
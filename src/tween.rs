@@ -0,0 +1,243 @@
+//! Interpolating between values over time, for animating movement,
+//! fades, and other transitions.
+
+use crate::RgbaPixel;
+use std::time::Duration;
+
+/// Values that can be blended smoothly from one to another.
+pub trait Lerp: Copy {
+    /// The value `t` of the way from `self` to `other`,
+    /// where `t` is usually between 0 and 1.
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, other: &f32, t: f32) -> f32 {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for (f32, f32) {
+    fn lerp(&self, other: &(f32, f32), t: f32) -> (f32, f32) {
+        (self.0.lerp(&other.0, t), self.1.lerp(&other.1, t))
+    }
+}
+
+impl Lerp for RgbaPixel {
+    fn lerp(&self, other: &RgbaPixel, t: f32) -> RgbaPixel {
+        let channel = |a: u8, b: u8| (a as f32).lerp(&(b as f32), t).round().clamp(0.0, 255.0) as u8;
+        (
+            channel(self.0, other.0),
+            channel(self.1, other.1),
+            channel(self.2, other.2),
+            channel(self.3, other.3),
+        )
+    }
+}
+
+/// The rate at which a tween progresses from its start to its end.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Easing {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineInOut,
+    /// Overshoot the end slightly, then settle back onto it.
+    BackOut,
+    /// Bounce against the end a few times before coming to rest.
+    BounceOut,
+}
+
+impl Easing {
+    /// Map progress through time (0-1) to progress
+    /// through the tween (usually, but not always, 0-1).
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::QuadInOut => {
+                if t < 0.5 { 2.0 * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0 }
+            },
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => {
+                if t < 0.5 { 4.0 * t * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0 }
+            },
+            Easing::SineInOut => -((std::f32::consts::PI * t).cos() - 1.0) / 2.0,
+            Easing::BackOut => {
+                let c1 = 1.70158;
+                let c3 = c1 + 1.0;
+                1.0 + c3 * (t - 1.0).powi(3) + c1 * (t - 1.0).powi(2)
+            },
+            Easing::BounceOut => {
+                let (n1, d1) = (7.5625, 2.75);
+                if t < 1.0 / d1 {
+                    n1 * t * t
+                } else if t < 2.0 / d1 {
+                    let t = t - 1.5 / d1;
+                    n1 * t * t + 0.75
+                } else if t < 2.5 / d1 {
+                    let t = t - 2.25 / d1;
+                    n1 * t * t + 0.9375
+                } else {
+                    let t = t - 2.625 / d1;
+                    n1 * t * t + 0.984375
+                }
+            },
+        }
+    }
+}
+
+/// A transition from one value to another over a fixed duration.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Tween<T: Lerp> {
+    pub from: T,
+    pub to: T,
+    pub duration: Duration,
+    pub easing: Easing,
+    elapsed: Duration,
+}
+
+impl<T: Lerp> Tween<T> {
+    pub fn new(from: T, to: T, duration: Duration, easing: Easing) -> Tween<T> {
+        Tween { from, to, duration, easing, elapsed: Duration::ZERO }
+    }
+
+    /// Move the tween forward in time, usually by the length of a frame,
+    /// as measured by `Scheduler::delta()`.
+    pub fn advance(&mut self, elapsed: Duration) {
+        self.elapsed = (self.elapsed + elapsed).min(self.duration);
+    }
+
+    /// Start the tween over from the beginning.
+    pub fn restart(&mut self) {
+        self.elapsed = Duration::ZERO;
+    }
+
+    /// How far through its duration the tween is (0-1).
+    pub fn progress(&self) -> f32 {
+        if self.duration.is_zero() { return 1.0 }
+        self.elapsed.as_secs_f32() / self.duration.as_secs_f32()
+    }
+
+    /// The value at the tween's current point in time.
+    pub fn value(&self) -> T {
+        self.from.lerp(&self.to, self.easing.apply(self.progress()))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+/// A chain of tweens that play one after another,
+/// each starting where the previous one ended.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sequence<T: Lerp> {
+    start: T,
+    steps: Vec<Tween<T>>,
+    // The index of the tween that is currently playing
+    current: usize,
+    looping: bool,
+}
+
+impl<T: Lerp> Sequence<T> {
+    /// Create an empty sequence, resting at `start`.
+    pub fn new(start: T) -> Sequence<T> {
+        Sequence { start, steps: vec![], current: 0, looping: false }
+    }
+
+    /// Add a transition from the end of the sequence to `to`.
+    pub fn then(mut self, to: T, duration: Duration, easing: Easing) -> Sequence<T> {
+        let from = self.steps.last().map(|step| step.to).unwrap_or(self.start);
+        self.steps.push(Tween::new(from, to, duration, easing));
+        self
+    }
+
+    /// Hold the current value for `duration`.
+    pub fn wait(self, duration: Duration) -> Sequence<T> {
+        let value = self.steps.last().map(|step| step.to).unwrap_or(self.start);
+        self.then(value, duration, Easing::Linear)
+    }
+
+    /// Start over from the beginning whenever the sequence finishes.
+    pub fn looping(self, looping: bool) -> Sequence<T> {
+        Sequence { looping, ..self }
+    }
+
+    /// Move the sequence forward in time, carrying any time left over
+    /// at the end of one tween into the next.
+    pub fn advance(&mut self, elapsed: Duration) {
+        let mut remaining = elapsed;
+        let total: Duration = self.steps.iter().map(|step| step.duration).sum();
+
+        // Skipping whole loops at once, so that zero-length
+        // sequences and long frames can't loop forever
+        if self.looping && !total.is_zero() {
+            let loops = remaining.as_nanos() / total.as_nanos();
+            remaining -= total * loops as u32;
+        }
+
+        while let Some(step) = self.steps.get_mut(self.current) {
+            let left_in_step = step.duration - step.elapsed;
+            step.advance(remaining);
+            remaining = remaining.saturating_sub(left_in_step);
+
+            if !step.is_finished() { return }
+
+            if self.current + 1 < self.steps.len() {
+                self.current += 1;
+            } else if self.looping && !remaining.is_zero() && !total.is_zero() {
+                self.restart();
+            } else {
+                return;
+            }
+        }
+    }
+
+    /// Start the sequence over from the beginning.
+    pub fn restart(&mut self) {
+        self.current = 0;
+        self.steps.iter_mut().for_each(|step| step.restart());
+    }
+
+    /// The value at the sequence's current point in time.
+    pub fn value(&self) -> T {
+        self.steps.get(self.current).map(|step| step.value()).unwrap_or(self.start)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.steps.iter().all(|step| step.is_finished())
+    }
+}
+
+#[test]
+fn _tween() {
+    let ms = Duration::from_millis;
+
+    let mut tween = Tween::new(0.0, 10.0, ms(100), Easing::Linear);
+    tween.advance(ms(25));
+    assert_eq!(tween.value(), 2.5);
+    tween.advance(ms(500));
+    assert_eq!(tween.value(), 10.0);
+    assert!(tween.is_finished());
+
+    // Leftover time carries into the next step of a sequence
+    let mut sequence = Sequence::new(0.0)
+        .then(10.0, ms(100), Easing::Linear)
+        .then(0.0, ms(100), Easing::Linear);
+    sequence.advance(ms(150));
+    assert_eq!(sequence.value(), 5.0);
+    sequence.advance(ms(100));
+    assert!(sequence.is_finished());
+
+    assert_eq!(Easing::QuadIn.apply(0.5), 0.25);
+    assert_eq!(Easing::BounceOut.apply(1.0), 1.0);
+}
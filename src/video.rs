@@ -0,0 +1,179 @@
+//! Playing video files onto the canvas, for cutscenes and media viewers,
+//! by reading decoded frames from an `ffmpeg` process. Like exporting,
+//! this needs `ffmpeg`, along with `ffprobe`, installed and available on
+//! the `PATH`.
+//!
+//! Frames are handed out according to the video's timestamps rather than
+//! as fast as they're decoded, so playback keeps to the video's own frame
+//! rate whatever the application's is. Frames are skipped when the
+//! application falls behind, and repeated when it draws faster than the
+//! video. Audio isn't played, and frames are shown as they're stored,
+//! without the rotation that phones record for videos filmed upright.
+
+use crate::RgbaImage;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// A video file being decoded by `ffmpeg`.
+pub struct VideoPlayer {
+    child: Child,
+    stdout: BufReader<ChildStdout>,
+    dimensions: (u32, u32),
+    frames_per_second: f64,
+    frame: RgbaImage,
+    frames_decoded: u64,
+    started: Option<Instant>,
+    finished: bool,
+}
+
+impl VideoPlayer {
+    /// Start decoding the video file at `path`.
+    pub fn open(path: &Path) -> io::Result<VideoPlayer> {
+        let output = Command::new("ffprobe")
+            .args(["-v", "error", "-select_streams", "v:0"])
+            .args(["-show_entries", "stream=width,height,avg_frame_rate", "-of", "csv=p=0"])
+            .arg(path)
+            .stderr(Stdio::null())
+            .output()?;
+
+        let (dimensions, frames_per_second) = parse_probe(&String::from_utf8_lossy(&output.stdout))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no video stream found"))?;
+
+        // Older versions of ffmpeg only understand `-vsync`, which
+        // newer versions have deprecated in favor of `-fps_mode`
+        let version = Command::new("ffmpeg").arg("-version").stderr(Stdio::null()).output()?;
+        let fps_mode = if supports_fps_mode(&String::from_utf8_lossy(&version.stdout)) { "-fps_mode" } else { "-vsync" };
+
+        // Keeping frames as they're stored, since the size reported by
+        // `ffprobe` doesn't account for rotation, such as that recorded
+        // by phones held upright
+        let mut child = Command::new("ffmpeg")
+            .args(["-loglevel", "error", "-noautorotate", "-i"])
+            .arg(path)
+            .args(["-an", "-f", "rawvideo", "-pix_fmt", "rgba", fps_mode, "cfr", "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+
+        Ok(VideoPlayer {
+            child,
+            stdout,
+            dimensions,
+            frames_per_second,
+            frame: RgbaImage::new(dimensions.0, dimensions.1),
+            frames_decoded: 0,
+            started: None,
+            finished: false,
+        })
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+
+    pub fn frames_per_second(&self) -> f64 {
+        self.frames_per_second
+    }
+
+    /// Whether every frame has been decoded.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// The frame to show now, timed from the first call, or `None`
+    /// once the video has ended.
+    pub fn current_frame(&mut self) -> Option<&RgbaImage> {
+        let started = *self.started.get_or_insert_with(Instant::now);
+        self.frame_at(started.elapsed())
+    }
+
+    /// The frame to show at `elapsed` into the video, or `None` once the
+    /// video has ended. Videos can only be played forward, so asking for an
+    /// earlier frame than the last one returns the last one again.
+    pub fn frame_at(&mut self, elapsed: Duration) -> Option<&RgbaImage> {
+        let wanted = frame_index(elapsed, self.frames_per_second);
+
+        while self.frames_decoded <= wanted && !self.finished {
+            match self.stdout.read_exact(&mut self.frame.bytes) {
+                Ok(()) => self.frames_decoded += 1,
+                Err(error) => {
+                    if error.kind() != io::ErrorKind::UnexpectedEof {
+                        log_error!("unable to decode video: {}", error);
+                    }
+                    self.finished = true;
+                },
+            }
+        }
+
+        if self.frames_decoded > wanted { Some(&self.frame) } else { None }
+    }
+}
+
+impl Drop for VideoPlayer {
+    fn drop(&mut self) {
+        // Stopping ffmpeg, which would otherwise decode the rest of the video
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// Which frame is due at a given time into the video
+fn frame_index(elapsed: Duration, frames_per_second: f64) -> u64 {
+    (elapsed.as_secs_f64() * frames_per_second) as u64
+}
+
+// Whether the `ffmpeg -version` output is from version 5.1 or later, which
+// introduced `-fps_mode`. Builds from git, such as `N-109421-g…`, are
+// assumed to be recent.
+fn supports_fps_mode(version_output: &str) -> bool {
+    let version = version_output.split_whitespace().nth(2).unwrap_or("");
+    let version = version.strip_prefix('n').unwrap_or(version);
+    if version.starts_with("N-") {
+        return true;
+    }
+
+    let mut numbers = version.split(|c: char| !c.is_ascii_digit()).map(|number| number.parse::<u32>().unwrap_or(0));
+    let major = numbers.next().unwrap_or(0);
+    let minor = numbers.next().unwrap_or(0);
+    (major, minor) >= (5, 1)
+}
+
+// Reading the `width,height,avg_frame_rate` that `ffprobe` reports, where
+// the frame rate is a fraction, such as `30000/1001` for NTSC video
+fn parse_probe(output: &str) -> Option<((u32, u32), f64)> {
+    let mut fields = output.lines().next()?.trim().split(',');
+    let width = fields.next()?.parse().ok()?;
+    let height = fields.next()?.parse().ok()?;
+
+    let (numerator, denominator) = fields.next()?.split_once('/')?;
+    let frames_per_second = numerator.parse::<f64>().ok()? / denominator.parse::<f64>().ok()?;
+    if !frames_per_second.is_finite() || frames_per_second <= 0.0 {
+        return None;
+    }
+
+    Some(((width, height), frames_per_second))
+}
+
+#[test]
+fn _parse_probe() {
+    let ((width, height), frames_per_second) = parse_probe("1920,1080,30000/1001\n").unwrap();
+    assert_eq!((width, height), (1920, 1080));
+    assert!((frames_per_second - 29.97).abs() < 0.001);
+
+    assert_eq!(parse_probe("640,480,0/0\n"), None);
+    assert_eq!(parse_probe(""), None);
+
+    assert_eq!(frame_index(Duration::from_millis(999), 30.0), 29);
+    assert_eq!(frame_index(Duration::from_secs(1), 30.0), 30);
+
+    assert!(supports_fps_mode("ffmpeg version 6.0 Copyright (c) 2000-2023"));
+    assert!(supports_fps_mode("ffmpeg version n5.1.2 Copyright"));
+    assert!(supports_fps_mode("ffmpeg version N-109421-g2f0a8b3 Copyright"));
+    assert!(!supports_fps_mode("ffmpeg version 4.4.2-0ubuntu0.22.04.1 Copyright"));
+    assert!(!supports_fps_mode(""));
+}
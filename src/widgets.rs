@@ -0,0 +1,657 @@
+//! A minimal immediate-mode GUI, for settings menus, editor panels,
+//! and other simple interfaces drawn directly onto a frame.
+//!
+//! Every frame, pass the frame's events to `Gui::process_events()`, then
+//! call a method for each widget while drawing the frame. Each method
+//! draws the widget, and reports whether the user interacted with it.
+
+use crate::clipboard::Clipboard;
+use crate::font::{self, ADVANCE, GLYPH_HEIGHT};
+use crate::{
+    window_to_frame, KeyboardAction, KeyboardEvent, KeyboardKey, MouseButton, MouseButtonAction,
+    Rect, RgbaImage, RgbaPixel, UIBlueprint, UIEvent,
+};
+
+/// The colors used to draw widgets.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GuiStyle {
+    pub text: RgbaPixel,
+    pub background: RgbaPixel,
+    pub hovered: RgbaPixel,
+    pub pressed: RgbaPixel,
+    pub border: RgbaPixel,
+    pub accent: RgbaPixel,
+    pub selection: RgbaPixel,
+}
+
+impl Default for GuiStyle {
+    fn default() -> GuiStyle {
+        GuiStyle {
+            text: (240, 240, 240, 255),
+            background: (60, 60, 80, 255),
+            hovered: (80, 80, 110, 255),
+            pressed: (40, 40, 55, 255),
+            border: (20, 20, 30, 255),
+            accent: (250, 200, 60, 255),
+            selection: (70, 110, 200, 255),
+        }
+    }
+}
+
+/// The state of the mouse with respect to a single widget
+struct Interaction {
+    hovered: bool,
+    held: bool,
+    clicked: bool,
+}
+
+/// Keeps track of the mouse between frames, and draws widgets.
+pub struct Gui {
+    pub style: GuiStyle,
+    /// Where text inputs copy text to, and paste text from.
+    pub clipboard: Clipboard,
+    window_size: (u32, u32),
+    preserve_aspect_ratio: bool,
+    // The latest cursor position, in window coordinates
+    cursor: Option<(u32, u32)>,
+    mouse_down: bool,
+    mouse_pressed: bool,
+    mouse_released: bool,
+    // The widget that the mouse was pressed on, if it's still held
+    active: Option<u64>,
+    // Widgets are identified by the order they're drawn in each frame
+    next_id: u64,
+    // Key presses and typed text during the current frame, for text inputs
+    key_presses: Vec<UIEvent>,
+}
+
+impl Gui {
+    /// Create a GUI for a window with the given blueprint, which
+    /// determines how cursor positions are mapped onto frames.
+    pub fn new(blueprint: &UIBlueprint) -> Gui {
+        Gui {
+            style: GuiStyle::default(),
+            clipboard: Clipboard::new(),
+            window_size: blueprint.dimensions,
+            preserve_aspect_ratio: blueprint.preserve_aspect_ratio,
+            cursor: None,
+            mouse_down: false,
+            mouse_pressed: false,
+            mouse_released: false,
+            active: None,
+            next_id: 0,
+            key_presses: vec![],
+        }
+    }
+
+    /// Begin a new frame, taking note of mouse movement and clicks.
+    pub fn process_events(&mut self, events: &[UIEvent]) {
+        self.next_id = 0;
+        self.mouse_pressed = false;
+        self.mouse_released = false;
+        self.key_presses.clear();
+
+        if !self.mouse_down {
+            self.active = None;
+        }
+
+        for event in events {
+            match event {
+                UIEvent::CursorMovement(event) => {
+                    self.cursor = Some((event.x, event.y));
+                },
+                UIEvent::MouseButton(event) if event.button == MouseButton::Left => {
+                    match event.action {
+                        MouseButtonAction::Press => {
+                            self.mouse_down = true;
+                            self.mouse_pressed = true;
+                        },
+                        MouseButtonAction::Release => {
+                            self.mouse_down = false;
+                            self.mouse_released = true;
+                        },
+                    }
+                },
+                UIEvent::Resize(event) => {
+                    self.window_size = (event.width, event.height);
+                },
+                UIEvent::Keyboard(KeyboardEvent { action: KeyboardAction::Press, .. }) | UIEvent::Text(_) => {
+                    self.key_presses.push(*event);
+                },
+                _ => {},
+            }
+        }
+    }
+
+    // The cursor's position on the canvas
+    fn cursor_on(&self, canvas: &RgbaImage) -> Option<(f32, f32)> {
+        let canvas_size = (canvas.width(), canvas.height());
+        self.cursor.map(|cursor| {
+            window_to_frame(cursor, self.window_size, canvas_size, self.preserve_aspect_ratio)
+        })
+    }
+
+    fn interact(&mut self, canvas: &RgbaImage, rect: Rect) -> Interaction {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let hovered = match self.cursor_on(canvas) {
+            Some((x, y)) => x >= 0.0 && y >= 0.0 && rect.contains(x as u32, y as u32),
+            None => false,
+        };
+
+        if hovered && self.mouse_pressed {
+            self.active = Some(id);
+        }
+
+        let is_active = self.active == Some(id);
+
+        Interaction {
+            hovered,
+            held: is_active && self.mouse_down,
+            clicked: is_active && hovered && self.mouse_released,
+        }
+    }
+
+    // Drawing a filled rectangle with a one pixel border
+    fn draw_box(&self, canvas: &mut RgbaImage, rect: Rect, fill: RgbaPixel) {
+        canvas.fill_rect(rect, self.style.border);
+        if rect.w > 2 && rect.h > 2 {
+            canvas.fill_rect(Rect::new(rect.x + 1, rect.y + 1, rect.w - 2, rect.h - 2), fill);
+        }
+    }
+
+    fn box_color(&self, interaction: &Interaction) -> RgbaPixel {
+        if interaction.held { self.style.pressed }
+        else if interaction.hovered { self.style.hovered }
+        else { self.style.background }
+    }
+
+    /// Draw text with its top-left corner at the given point.
+    pub fn label(&mut self, canvas: &mut RgbaImage, text: &str, x: u32, y: u32) {
+        font::draw_text(canvas, text, x as i32, y as i32, self.style.text);
+    }
+
+    /// Draw a button, returning true if it was clicked this frame.
+    pub fn button(&mut self, canvas: &mut RgbaImage, text: &str, rect: Rect) -> bool {
+        let interaction = self.interact(canvas, rect);
+        self.draw_box(canvas, rect, self.box_color(&interaction));
+
+        // Centering the text within the button
+        let (text_w, text_h) = font::text_size(text);
+        let x = rect.x as i32 + (rect.w as i32 - text_w as i32) / 2;
+        let y = rect.y as i32 + (rect.h as i32 - text_h as i32) / 2;
+        font::draw_text(canvas, text, x, y, self.style.text);
+
+        interaction.clicked
+    }
+
+    /// Draw a checkbox followed by a label, toggling `value` when either
+    /// is clicked. Returns true if `value` changed this frame.
+    pub fn checkbox(&mut self, canvas: &mut RgbaImage, text: &str, x: u32, y: u32, value: &mut bool) -> bool {
+        let size = GLYPH_HEIGHT + 2;
+        let (text_w, _) = font::text_size(text);
+        let rect = Rect::new(x, y, size + 2 + text_w, size);
+
+        let interaction = self.interact(canvas, rect);
+        if interaction.clicked {
+            *value = !*value;
+        }
+
+        let check_box = Rect::new(x, y, size, size);
+        self.draw_box(canvas, check_box, self.box_color(&interaction));
+        if *value {
+            canvas.fill_rect(Rect::new(x + 2, y + 2, size - 4, size - 4), self.style.accent);
+        }
+
+        font::draw_text(canvas, text, (x + size + 2) as i32, (y + 1) as i32, self.style.text);
+        interaction.clicked
+    }
+
+    /// Draw a horizontal slider that can be dragged to choose `value`
+    /// between `min` and `max`. Returns true if `value` changed this frame.
+    pub fn slider(&mut self, canvas: &mut RgbaImage, rect: Rect, value: &mut f32, min: f32, max: f32) -> bool {
+        let interaction = self.interact(canvas, rect);
+        let previous = *value;
+
+        // Following the cursor while the slider is being dragged
+        if interaction.held {
+            if let Some((cursor_x, _)) = self.cursor_on(canvas) {
+                let t = (cursor_x - rect.x as f32) / rect.w.max(1) as f32;
+                *value = min + (max - min) * t.clamp(0.0, 1.0);
+            }
+        }
+
+        self.draw_box(canvas, rect, self.style.background);
+
+        // Drawing the handle at the current value
+        let t = if max > min { ((*value - min) / (max - min)).clamp(0.0, 1.0) } else { 0.0 };
+        let handle_w = 3.min(rect.w);
+        let handle_x = rect.x + ((rect.w - handle_w) as f32 * t).round() as u32;
+        let handle_color = if interaction.hovered || interaction.held { self.style.accent } else { self.style.text };
+        canvas.fill_rect(Rect::new(handle_x, rect.y, handle_w, rect.h), handle_color);
+
+        *value != previous
+    }
+
+    /// Draw a single-line text input. Clicking the input focuses it, after
+    /// which it responds to typing, cursor movement, selection, and the
+    /// usual clipboard shortcuts. Returns true if the text changed this frame.
+    pub fn text_input(&mut self, canvas: &mut RgbaImage, rect: Rect, input: &mut TextInput) -> bool {
+        let interaction = self.interact(canvas, rect);
+        let text_x = rect.x + 2;
+        let visible_columns = (rect.w.saturating_sub(4) / ADVANCE) as usize;
+
+        if self.mouse_pressed {
+            input.focused = interaction.hovered;
+        }
+
+        // Placing the cursor with the mouse, and selecting by dragging
+        if interaction.held {
+            if let Some((cursor_x, _)) = self.cursor_on(canvas) {
+                let column = ((cursor_x - text_x as f32) / ADVANCE as f32).round().max(0.0) as usize;
+                let position = (column + input.scroll).min(input.len());
+
+                if self.mouse_pressed {
+                    input.anchor = Some(position);
+                }
+                input.cursor = position;
+            }
+        }
+
+        let mut changed = false;
+        if input.focused {
+            for event in &self.key_presses {
+                changed |= match event {
+                    UIEvent::Keyboard(event) => input.handle_key(event, &mut self.clipboard),
+                    UIEvent::Text(event) => input.handle_text(event.character),
+                    _ => false,
+                };
+            }
+        }
+
+        // Scrolling so that the cursor stays visible
+        if input.cursor < input.scroll {
+            input.scroll = input.cursor;
+        } else if input.cursor > input.scroll + visible_columns {
+            input.scroll = input.cursor - visible_columns;
+        }
+
+        let fill = if input.focused { self.style.pressed } else { self.box_color(&interaction) };
+        self.draw_box(canvas, rect, fill);
+
+        // Keeping the text within the borders of the input
+        let previous_clip = canvas.clip_rect();
+        let inner = Rect::new(rect.x + 1, rect.y + 1, rect.w.saturating_sub(2), rect.h.saturating_sub(2));
+        canvas.set_clip_rect(previous_clip.map_or(Some(inner), |clip| clip.intersection(&inner)));
+
+        let text_y = rect.y as i32 + (rect.h as i32 - GLYPH_HEIGHT as i32) / 2;
+        let column_x = |position: usize| text_x as i32 + ((position as i32 - input.scroll as i32) * ADVANCE as i32);
+
+        if let Some((start, end)) = input.selection() {
+            // Starting the highlight at the left edge when the start of the
+            // selection has scrolled out of view
+            let start = start.max(input.scroll);
+            let x = column_x(start) as u32;
+            let w = (column_x(end.max(start)) - column_x(start)) as u32;
+            canvas.fill_rect(Rect::new(x.saturating_sub(1), (text_y - 1).max(0) as u32, w + 1, GLYPH_HEIGHT + 2), self.style.selection);
+        }
+
+        let visible: String = input.text.chars().skip(input.scroll).collect();
+        font::draw_text(canvas, &visible, text_x as i32, text_y, self.style.text);
+
+        if input.focused {
+            let x = column_x(input.cursor) - 1;
+            if x >= 0 {
+                canvas.fill_rect(Rect::new(x as u32, (text_y - 1).max(0) as u32, 1, GLYPH_HEIGHT + 2), self.style.accent);
+            }
+        }
+
+        canvas.set_clip_rect(previous_clip);
+        changed
+    }
+
+    /// Draw a dialog in the middle of the canvas, with a title, a message,
+    /// and a row of buttons. Returns the index of the button that was clicked
+    /// this frame, if any. This is a fallback for platforms without native
+    /// dialogs, so other widgets should be skipped while it's open.
+    pub fn message_box(&mut self, canvas: &mut RgbaImage, title: &str, message: &str, buttons: &[&str]) -> Option<usize> {
+        let padding = 6;
+        let (title_w, title_h) = font::text_size(title);
+        let (message_w, message_h) = font::text_size(message);
+
+        // Giving every button the same size, to fit the longest label
+        let button_w = buttons.iter().map(|text| font::text_size(text).0).max().unwrap_or(0) + 8;
+        let button_h = GLYPH_HEIGHT + 6;
+        let buttons_w = (button_w + padding) * buttons.len() as u32;
+
+        let w = title_w.max(message_w).max(buttons_w.saturating_sub(padding)) + padding * 2;
+        let h = title_h + message_h + button_h + padding * 5;
+        let x = canvas.width().saturating_sub(w) / 2;
+        let y = canvas.height().saturating_sub(h) / 2;
+
+        self.draw_box(canvas, Rect::new(x, y, w, h), self.style.background);
+        font::draw_text(canvas, title, (x + padding) as i32, (y + padding) as i32, self.style.accent);
+
+        let divider_y = y + padding * 2 + title_h;
+        canvas.fill_rect(Rect::new(x + 1, divider_y, w.saturating_sub(2), 1), self.style.border);
+        font::draw_text(canvas, message, (x + padding) as i32, (divider_y + padding) as i32, self.style.text);
+
+        // Lining the buttons up along the bottom-right corner
+        let mut clicked = None;
+        let button_y = y + h - padding - button_h;
+        let mut button_x = (x + w).saturating_sub(buttons_w);
+
+        for (index, text) in buttons.iter().enumerate() {
+            if self.button(canvas, text, Rect::new(button_x, button_y, button_w, button_h)) {
+                clicked = Some(index);
+            }
+            button_x += button_w + padding;
+        }
+
+        clicked
+    }
+}
+
+/// The contents and editing state of a single-line text field, drawn
+/// with `Gui::text_input()`. Positions are measured in characters.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TextInput {
+    text: String,
+    cursor: usize,
+    // The other end of the selection, if there is one
+    anchor: Option<usize>,
+    // The first visible character
+    scroll: usize,
+    pub focused: bool,
+}
+
+impl TextInput {
+    pub fn new(text: &str) -> TextInput {
+        TextInput { text: text.to_string(), cursor: text.chars().count(), ..TextInput::default() }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replace the text, moving the cursor to the end.
+    pub fn set_text(&mut self, text: &str) {
+        *self = TextInput { focused: self.focused, ..TextInput::new(text) };
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    /// The start and end of the selected text, if any is selected.
+    pub fn selection(&self) -> Option<(usize, usize)> {
+        match self.anchor {
+            Some(anchor) if anchor != self.cursor => {
+                Some((anchor.min(self.cursor), anchor.max(self.cursor)))
+            },
+            _ => None,
+        }
+    }
+
+    pub fn selected_text(&self) -> Option<&str> {
+        self.selection().map(|(start, end)| &self.text[self.byte_index(start)..self.byte_index(end)])
+    }
+
+    // Converting a character position into a byte position within `text`
+    fn byte_index(&self, position: usize) -> usize {
+        self.text.char_indices().nth(position).map_or(self.text.len(), |(index, _)| index)
+    }
+
+    // Moving the cursor, either extending the selection or clearing it
+    fn move_cursor(&mut self, position: usize, extend_selection: bool) {
+        if extend_selection {
+            self.anchor = Some(self.anchor.unwrap_or(self.cursor));
+        } else {
+            self.anchor = None;
+        }
+        self.cursor = position.min(self.len());
+    }
+
+    // Removing the selected text, returning true if there was any
+    fn delete_selection(&mut self) -> bool {
+        match self.selection() {
+            Some((start, end)) => {
+                self.text.replace_range(self.byte_index(start)..self.byte_index(end), "");
+                self.cursor = start;
+                self.anchor = None;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Insert text at the cursor, replacing any selected text.
+    pub fn insert(&mut self, text: &str) {
+        self.delete_selection();
+        let index = self.byte_index(self.cursor);
+        self.text.insert_str(index, text);
+        self.cursor += text.chars().count();
+    }
+
+    // The start of the word before the cursor, or the end of the word after it
+    fn word_boundary(&self, forward: bool) -> usize {
+        let chars: Vec<char> = self.text.chars().collect();
+        let mut position = self.cursor;
+
+        if forward {
+            while position < chars.len() && !chars[position].is_alphanumeric() { position += 1 }
+            while position < chars.len() && chars[position].is_alphanumeric() { position += 1 }
+        } else {
+            while position > 0 && !chars[position - 1].is_alphanumeric() { position -= 1 }
+            while position > 0 && chars[position - 1].is_alphanumeric() { position -= 1 }
+        }
+
+        position
+    }
+
+    /// Respond to a character typed without a key press of its own,
+    /// as reported by `UIEvent::Text`. Returns true if the text changed.
+    pub fn handle_text(&mut self, character: char) -> bool {
+        if character.is_control() {
+            return false;
+        }
+        self.insert(&character.to_string());
+        true
+    }
+
+    /// Respond to a key press, as if the input were focused.
+    /// Returns true if the text changed.
+    pub fn handle_key(&mut self, event: &KeyboardEvent, clipboard: &mut Clipboard) -> bool {
+        let shift = event.modifiers.shift;
+        let shortcut = event.modifiers.ctrl || event.modifiers.logo;
+
+        match event.key {
+            KeyboardKey::Left => {
+                let position = match self.selection() {
+                    Some((start, _)) if !shift => start,
+                    _ if shortcut => self.word_boundary(false),
+                    _ => self.cursor.saturating_sub(1),
+                };
+                self.move_cursor(position, shift);
+            },
+            KeyboardKey::Right => {
+                let position = match self.selection() {
+                    Some((_, end)) if !shift => end,
+                    _ if shortcut => self.word_boundary(true),
+                    _ => self.cursor + 1,
+                };
+                self.move_cursor(position, shift);
+            },
+            KeyboardKey::Home => self.move_cursor(0, shift),
+            KeyboardKey::End => self.move_cursor(self.len(), shift),
+            KeyboardKey::Back => {
+                if self.delete_selection() { return true }
+                if self.cursor == 0 { return false }
+
+                self.anchor = Some(self.cursor - 1);
+                return self.delete_selection();
+            },
+            KeyboardKey::Delete => {
+                if self.delete_selection() { return true }
+                if self.cursor == self.len() { return false }
+
+                self.anchor = Some(self.cursor + 1);
+                return self.delete_selection();
+            },
+            KeyboardKey::A if shortcut => {
+                self.anchor = Some(0);
+                self.cursor = self.len();
+            },
+            KeyboardKey::C if shortcut => {
+                if let Some(text) = self.selected_text() {
+                    clipboard.set_text(text);
+                }
+            },
+            KeyboardKey::X if shortcut => {
+                if let Some(text) = self.selected_text() {
+                    clipboard.set_text(text);
+                    return self.delete_selection();
+                }
+            },
+            KeyboardKey::V if shortcut => {
+                // Flattening pasted text onto a single line
+                if let Some(text) = clipboard.get_text() {
+                    let text: String = text.chars()
+                        .map(|c| if c == '\n' { ' ' } else { c })
+                        .filter(|c| !c.is_control())
+                        .collect();
+                    self.insert(&text);
+                    return true;
+                }
+            },
+            _ => {
+                if let Some(character) = event.character {
+                    if !shortcut && !character.is_control() {
+                        self.insert(&character.to_string());
+                        return true;
+                    }
+                }
+            },
+        }
+
+        false
+    }
+}
+
+#[test]
+fn _text_input() {
+    use crate::KeyboardModifiers;
+
+    let press = |key, character, shift, ctrl| KeyboardEvent {
+        device_id: 0,
+        key,
+        action: KeyboardAction::Press,
+        modifiers: KeyboardModifiers { shift, ctrl, ..KeyboardModifiers::default() },
+        character,
+    };
+
+    let mut clipboard = Clipboard::new();
+    let mut input = TextInput::new("hello world");
+
+    // Selecting the last word and cutting it
+    input.handle_key(&press(KeyboardKey::Left, None, true, true), &mut clipboard);
+    assert_eq!(input.selected_text(), Some("world"));
+    assert!(input.handle_key(&press(KeyboardKey::X, Some('x'), false, true), &mut clipboard));
+    assert_eq!(input.text(), "hello ");
+
+    // Typing replaces the selection, and pasting inserts at the cursor
+    input.handle_key(&press(KeyboardKey::Home, None, false, false), &mut clipboard);
+    input.handle_key(&press(KeyboardKey::V, Some('v'), false, true), &mut clipboard);
+    input.handle_key(&press(KeyboardKey::Space, Some(' '), false, false), &mut clipboard);
+    assert_eq!(input.text(), "world hello ");
+
+    input.handle_key(&press(KeyboardKey::Back, None, false, false), &mut clipboard);
+    input.handle_key(&press(KeyboardKey::Delete, None, false, false), &mut clipboard);
+    assert_eq!(input.text(), "worldello ");
+
+    // Characters typed without a key press of their own
+    assert!(input.handle_text('é'));
+    assert_eq!(input.text(), "worldéello ");
+}
+
+#[test]
+fn _text_input_overflowing_selection() {
+    use crate::KeyboardModifiers;
+
+    let blueprint = UIBlueprint::default().dimensions((40, 9));
+    let mut gui = Gui::new(&blueprint);
+    let mut canvas = RgbaImage::new(40, 9);
+    let mut input = TextInput::new("far too long to fit in the field");
+    input.focused = true;
+
+    // Selecting everything, which scrolls the start of the selection out of view
+    gui.process_events(&[UIEvent::Keyboard(KeyboardEvent {
+        device_id: 0,
+        key: KeyboardKey::A,
+        action: KeyboardAction::Press,
+        modifiers: KeyboardModifiers { ctrl: true, ..KeyboardModifiers::default() },
+        character: Some('a'),
+    })]);
+    gui.text_input(&mut canvas, Rect::new(0, 0, 40, 9), &mut input);
+
+    assert_eq!(input.selected_text(), Some(input.text()));
+    assert_eq!(canvas.get_pixel(1, 2), Some(gui.style.selection));
+}
+
+#[test]
+fn _button() {
+    use crate::{CursorMovementEvent, MouseButtonEvent};
+
+    let blueprint = UIBlueprint::default().dimensions((32, 32));
+    let mut gui = Gui::new(&blueprint);
+    let mut canvas = RgbaImage::new(32, 32);
+    let rect = Rect::new(4, 4, 20, 10);
+
+    let cursor = UIEvent::CursorMovement(CursorMovementEvent { device_id: 0, x: 10, y: 8 });
+    let click = |action| UIEvent::MouseButton(MouseButtonEvent {
+        device_id: 0,
+        button: MouseButton::Left,
+        action,
+    });
+
+    // Pressing the button doesn't click it until the mouse is released
+    gui.process_events(&[cursor, click(MouseButtonAction::Press)]);
+    assert!(!gui.button(&mut canvas, "OK", rect));
+    assert_eq!(canvas.get_pixel(5, 5), Some(gui.style.pressed));
+
+    gui.process_events(&[click(MouseButtonAction::Release)]);
+    assert!(gui.button(&mut canvas, "OK", rect));
+
+    // Releasing the mouse over a button it wasn't pressed on does nothing
+    gui.process_events(&[click(MouseButtonAction::Release)]);
+    assert!(!gui.button(&mut canvas, "OK", rect));
+}
+
+#[test]
+fn _message_box() {
+    use crate::{CursorMovementEvent, MouseButtonEvent};
+
+    let blueprint = UIBlueprint::default().dimensions((100, 60));
+    let mut gui = Gui::new(&blueprint);
+    let mut canvas = RgbaImage::new(100, 60);
+    let buttons = ["OK", "Cancel"];
+
+    let cursor = UIEvent::CursorMovement(CursorMovementEvent { device_id: 0, x: 60, y: 42 });
+    let click = |action| UIEvent::MouseButton(MouseButtonEvent {
+        device_id: 0,
+        button: MouseButton::Left,
+        action,
+    });
+
+    gui.process_events(&[cursor, click(MouseButtonAction::Press)]);
+    assert_eq!(gui.message_box(&mut canvas, "Quit?", "Discard changes?", &buttons), None);
+
+    // Clicking the second button
+    gui.process_events(&[click(MouseButtonAction::Release)]);
+    assert_eq!(gui.message_box(&mut canvas, "Quit?", "Discard changes?", &buttons), Some(1));
+}
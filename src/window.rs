@@ -0,0 +1,2922 @@
+//! Displaying frames in a window, and translating window events into
+//! `UIEvent`s. Only available with the `window` feature, which is
+//! enabled by default. Without it, the image and drawing APIs can be
+//! used on their own, for example to render images on a server.
+
+use crate::{accessibility, assets, console, export, gamepad, geometry, menu, plugin, remote, replay, tray};
+#[cfg(feature = "gpu")]
+use crate::gpu;
+use crate::pixel_formats::{Image, Pixel};
+use crate::shader::{self, ShaderValue};
+use crate::shadertoy::ShadertoyRunner;
+use crate::{exit_requested, letterboxed_size, quit_requested, replace_controller, translate_virtual_keycode};
+use crate::{
+    CatchUpPolicy, ClosePolicy, CursorMovementEvent, FrameBudgetEvent, FrameReport,
+    FrameTimingEvent, FullscreenMode, HitRegion, HitTest, KeyboardAction, KeyboardEvent, KeyboardKey, KeyboardModifiers,
+    LinuxBackend, Monitor, MonitorAction, MonitorChoice, MonitorEvent, MouseButton, MouseButtonAction, MouseButtonEvent, OcclusionEvent, OpenUiError, PenEvent, PenPhase, RedrawHandle,
+    Rect, RefreshRateEvent, RenderMode, ResizeEdge, ResizeEvent, RgbaImage, RgbaImageRegion, ScrollDelta, ScrollEvent,
+    TextEvent, ThemeEvent, UIBlueprint, UICommand, UIContext, UIControlFlow, UIController, UIEvent, VideoMode, Viewport, WindowTheme, UI,
+};
+use glium::{glutin, CapabilitiesSource, Surface};
+use glium::glutin::dpi::LogicalSize;
+use glium::draw_parameters::Blend;
+use glium::glutin::event::Event::{NewEvents, RedrawEventsCleared, RedrawRequested, UserEvent};
+use glium::glutin::event::StartCause;
+use glium::glutin::window::Fullscreen;
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::texture::{MipmapsOption, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, UniformValue};
+use glium::glutin::event_loop::ControlFlow;
+use glium::glutin::platform::run_return::EventLoopExtRunReturn;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+// The most renders that can be skipped in a row, so that a controller
+// that can never keep up still gets to render occasionally
+const MAX_SKIPPED_RENDERS: u32 = 5;
+
+// How long before a frame is due to stop sleeping and start spinning,
+// on top of the measured imprecision of the OS timer
+const SPIN_MARGIN: Duration = Duration::from_micros(500);
+
+// The most time that will ever be spent spinning before a frame
+const MAX_SPIN: Duration = Duration::from_millis(4);
+
+// Decides when each frame should start, according to a `CatchUpPolicy`
+struct FrameScheduler {
+    interval: Duration,
+    policy: CatchUpPolicy,
+    next_frame_time: Instant,
+    // A running estimate of how late the OS wakes the event loop
+    timer_slop: Duration,
+    // How far from its scheduled time the latest frame started
+    jitter: Duration,
+}
+
+impl FrameScheduler {
+    fn new(interval: Duration, policy: CatchUpPolicy, now: Instant) -> FrameScheduler {
+        FrameScheduler {
+            interval,
+            policy,
+            next_frame_time: now,
+            timer_slop: Duration::ZERO,
+            jitter: Duration::ZERO,
+        }
+    }
+
+    // How long before the next frame to stop sleeping and start spinning
+    fn spin_duration(&self) -> Duration {
+        (self.timer_slop + SPIN_MARGIN).min(MAX_SPIN)
+    }
+
+    // The time to ask the OS to wake the event loop
+    fn wake_time(&self) -> Instant {
+        self.next_frame_time.checked_sub(self.spin_duration()).unwrap_or(self.next_frame_time)
+    }
+
+    // Records how late the OS woke the event loop, compared to the
+    // time it was asked for, so that future sleeps can end earlier
+    fn observe_wakeup(&mut self, requested: Instant, actual: Instant) {
+        let slop = actual.saturating_duration_since(requested);
+        self.timer_slop = (self.timer_slop * 7 + slop) / 8;
+    }
+
+    // Returns true once the next frame is due. If it will be due
+    // shortly, this spins until then rather than risk oversleeping.
+    fn wait_for_frame(&self) -> bool {
+        let now = Instant::now();
+
+        if now >= self.next_frame_time {
+            return true;
+        }
+
+        if self.next_frame_time - now > self.spin_duration() {
+            return false;
+        }
+
+        while Instant::now() < self.next_frame_time {
+            std::hint::spin_loop();
+        }
+
+        true
+    }
+
+    // How precisely frames are starting on time
+    fn timing(&self) -> FrameTimingEvent {
+        FrameTimingEvent { jitter: self.jitter, timer_slop: self.timer_slop }
+    }
+
+    // Called at the start of every frame. Returns the number of renders
+    // to skip, and a description of the delay if the frame is late.
+    fn begin_frame(&mut self, now: Instant) -> (u32, Option<FrameBudgetEvent>) {
+        let late_by = now.saturating_duration_since(self.next_frame_time);
+        self.jitter = late_by;
+
+        let missed = (late_by.as_nanos() / self.interval.as_nanos().max(1)) as u32;
+
+        if missed == 0 {
+            return (0, None);
+        }
+
+        let skipped_renders = match self.policy {
+            CatchUpPolicy::SkipRenders => missed.min(MAX_SKIPPED_RENDERS),
+            _ => 0,
+        };
+
+        // Moving the schedule past the skipped frames, or giving up
+        // on catching up if the controller is hopelessly behind
+        if missed > MAX_SKIPPED_RENDERS {
+            self.next_frame_time = now;
+        } else {
+            self.next_frame_time += self.interval * skipped_renders;
+        }
+
+        (skipped_renders, Some(FrameBudgetEvent { late_by, skipped_renders }))
+    }
+
+    // Called when the frame rate changes
+    fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    // Called when frames resume after a pause, so that the
+    // pause isn't mistaken for a very late frame
+    fn restart(&mut self, now: Instant) {
+        self.next_frame_time = now;
+    }
+
+    // Called at the end of every frame. Returns the time that the
+    // next frame should start.
+    fn end_frame(&mut self, now: Instant) -> Instant {
+        self.next_frame_time = match self.policy {
+            CatchUpPolicy::SlowDown => now + self.interval,
+            CatchUpPolicy::Clamp => (self.next_frame_time + self.interval).max(now),
+            CatchUpPolicy::SkipRenders => self.next_frame_time + self.interval,
+        };
+
+        self.next_frame_time
+    }
+}
+
+#[test]
+fn _frame_scheduler() {
+    let interval = Duration::from_millis(10);
+    let start = Instant::now();
+
+    // Frames that finish on time keep to the schedule
+    let mut scheduler = FrameScheduler::new(interval, CatchUpPolicy::Clamp, start);
+    assert_eq!(scheduler.begin_frame(start), (0, None));
+    assert_eq!(scheduler.end_frame(start + Duration::from_millis(4)), start + interval);
+
+    // Late frames are reported, and can skip renders to catch up
+    let mut scheduler = FrameScheduler::new(interval, CatchUpPolicy::SkipRenders, start);
+    let (skipped, report) = scheduler.begin_frame(start + Duration::from_millis(25));
+    assert_eq!(skipped, 2);
+    assert_eq!(report.unwrap().late_by, Duration::from_millis(25));
+    assert_eq!(scheduler.end_frame(start + Duration::from_millis(26)), start + interval * 3);
+
+    // Sleeps end early enough to make up for a sluggish OS timer
+    let mut scheduler = FrameScheduler::new(interval, CatchUpPolicy::Clamp, start);
+    scheduler.end_frame(start);
+    for _ in 0..64 {
+        scheduler.observe_wakeup(start, start + Duration::from_millis(2));
+    }
+    assert!(scheduler.timer_slop > Duration::from_micros(1900));
+    assert!(scheduler.wake_time() < start + interval - Duration::from_millis(2));
+}
+
+const VERTEX_SHADER_SRC: &str = r#"
+    #version 150
+
+    in vec2 dest;
+    in vec2 src;
+    out vec2 v_src;
+
+
+    void main() {
+        v_src = src;
+        gl_Position = vec4(dest, 0.0, 1.0);
+    }
+"#;
+
+const FRAGMENT_SHADER_SRC: &str = r#"
+    #version 150
+
+    in vec2 v_src;
+    out vec4 color;
+
+    uniform sampler2D sampler;
+
+    void main() {
+        color = texture(sampler, v_src);
+    }
+"#;
+
+// Shrinking a supersampled image onto the window, by averaging
+// the block of pixels that lands on each pixel of the window
+const DOWNSAMPLE_SHADER_SRC: &str = r#"
+    #version 150
+
+    in vec2 v_src;
+    out vec4 color;
+
+    uniform sampler2D sampler;
+    uniform int factor;
+
+    void main() {
+        vec2 size = vec2(textureSize(sampler, 0));
+        vec2 start = v_src * size - float(factor) * 0.5;
+
+        vec4 sum = vec4(0.0);
+        for (int y = 0; y < factor; y++) {
+            for (int x = 0; x < factor; x++) {
+                sum += texture(sampler, (start + vec2(x, y) + 0.5) / size);
+            }
+        }
+        color = sum / float(factor * factor);
+    }
+"#;
+
+#[derive(Copy, Clone, Debug)]
+struct Vertex {
+    // The vector denoting the area of incoming textures that will be
+    // used for drawing. This could be used to crop incoming textures.
+    src: [f32; 2],
+    // The vector denoting the area of the window that incoming textures
+    // will be drawn onto. This could be used to only draw textures on
+    // a partial area of the output.
+    dest: [f32; 2],
+}
+
+implement_vertex!(Vertex, dest, src);
+
+fn calculate_vertices(size: &LogicalSize<f32>, pixels: &RgbaImageRegion) -> Vec<Vertex> {
+    let whole_frame = Rect::new(0, 0, pixels.width, pixels.height);
+    tile_vertices(frame_magnitude(size, pixels), (pixels.width, pixels.height), whole_frame)
+}
+
+// How far a letterboxed frame extends from the center of the window,
+// in coordinates between -1.0 and 1.0
+fn frame_magnitude(size: &LogicalSize<f32>, pixels: &RgbaImageRegion) -> (f32, f32) {
+    let ui_h = size.height;
+    let ui_w = size.width;
+
+    // Defining "actual image width / height"
+    let (img_w, img_h) = letterboxed_size((ui_w, ui_h), pixels.width, pixels.height);
+
+    (img_w / ui_w, img_h / ui_h)
+}
+
+// The vertices that draw one tile of a frame, where the whole
+// frame extends `magnitude` from the center of the window
+fn tile_vertices(magnitude: (f32, f32), frame: (u32, u32), tile: Rect) -> Vec<Vertex> {
+    let (mag_x, mag_y) = magnitude;
+    let (frame_w, frame_h) = (frame.0 as f32, frame.1 as f32);
+
+    let left = -mag_x + 2.0 * mag_x * tile.x as f32 / frame_w;
+    let right = -mag_x + 2.0 * mag_x * (tile.x + tile.w) as f32 / frame_w;
+    let top = mag_y - 2.0 * mag_y * tile.y as f32 / frame_h;
+    let bottom = mag_y - 2.0 * mag_y * (tile.y + tile.h) as f32 / frame_h;
+
+    vec![
+        Vertex { dest: [left,  bottom], src: [0.0, 1.0] },
+        Vertex { dest: [right, bottom], src: [1.0, 1.0] },
+        Vertex { dest: [right, top   ], src: [1.0, 0.0] },
+        Vertex { dest: [left,  top   ], src: [0.0, 0.0] },
+    ]
+}
+
+// Splitting a frame into tiles that each fit within a texture
+fn tile_rects(frame: (u32, u32), max_texture_size: u32) -> Vec<Rect> {
+    let mut tiles = vec![];
+
+    for y in (0..frame.1).step_by(max_texture_size as usize) {
+        for x in (0..frame.0).step_by(max_texture_size as usize) {
+            let w = max_texture_size.min(frame.0 - x);
+            let h = max_texture_size.min(frame.1 - y);
+            tiles.push(Rect::new(x, y, w, h));
+        }
+    }
+
+    tiles
+}
+
+#[test]
+fn _tile_rects() {
+    let tiles = tile_rects((10, 4), 4);
+    assert_eq!(tiles.len(), 3);
+    assert_eq!(tiles[2], Rect::new(8, 0, 2, 4));
+    assert_eq!(tile_rects((4, 4), 4), vec![Rect::new(0, 0, 4, 4)]);
+
+    // The right-hand tile covers the right fifth of the frame
+    let vertices = tile_vertices((1.0, 1.0), (10, 4), tiles[2]);
+    assert_eq!(vertices[0].dest, [0.6, -1.0]);
+    assert_eq!(vertices[2].dest, [1.0, 1.0]);
+}
+
+// The vertices that draw a viewport's frame into its rectangle of the
+// window, centered and letterboxed if aspect ratios are preserved
+fn viewport_vertices(size: &LogicalSize<f32>, viewport: &Viewport, preserve_aspect_ratio: bool) -> Vec<Vertex> {
+    let Rect { x, y, w, h } = viewport.destination;
+    let (frame_w, frame_h) = (viewport.frame.width.max(1) as f32, viewport.frame.height.max(1) as f32);
+
+    let (img_w, img_h) = if preserve_aspect_ratio {
+        let scalar = (w as f32 / frame_w).min(h as f32 / frame_h);
+        (frame_w * scalar, frame_h * scalar)
+    } else {
+        (w as f32, h as f32)
+    };
+
+    let left = x as f32 + (w as f32 - img_w) / 2.0;
+    let top = y as f32 + (h as f32 - img_h) / 2.0;
+
+    // Converting from window coordinates, where y points down,
+    // to coordinates between -1.0 and 1.0, where y points up
+    let dest_x = |x: f32| x / size.width * 2.0 - 1.0;
+    let dest_y = |y: f32| 1.0 - y / size.height * 2.0;
+
+    vec![
+        Vertex { dest: [dest_x(left),         dest_y(top + img_h)], src: [0.0, 1.0] },
+        Vertex { dest: [dest_x(left + img_w), dest_y(top + img_h)], src: [1.0, 1.0] },
+        Vertex { dest: [dest_x(left + img_w), dest_y(top)        ], src: [1.0, 0.0] },
+        Vertex { dest: [dest_x(left),         dest_y(top)        ], src: [0.0, 0.0] },
+    ]
+}
+
+#[test]
+fn _viewport_vertices() {
+    let frame = RgbaImage::new(10, 10);
+    let size = LogicalSize::new(200.0, 100.0);
+
+    // The right half of the window
+    let viewport = Viewport { frame: frame.as_region(), destination: Rect::new(100, 0, 100, 100) };
+    let vertices = viewport_vertices(&size, &viewport, true);
+    assert_eq!(vertices[0].dest, [0.0, -1.0]);
+    assert_eq!(vertices[2].dest, [1.0, 1.0]);
+
+    // Letterboxing within a rectangle that's too wide
+    let viewport = Viewport { frame: frame.as_region(), destination: Rect::new(0, 0, 200, 100) };
+    let vertices = viewport_vertices(&size, &viewport, true);
+    assert_eq!(vertices[0].dest, [-0.5, -1.0]);
+    assert_eq!(viewport_vertices(&size, &viewport, false)[0].dest, [-1.0, -1.0]);
+}
+
+// The size that a window should be resized to, so that its aspect ratio
+// matches that of the frames being displayed. The dimension that changed
+// the most since the previous size is kept, so that users can drag any
+// edge of the window.
+fn aspect_ratio_correction(
+    previous: glutin::dpi::PhysicalSize<u32>,
+    current: glutin::dpi::PhysicalSize<u32>,
+    frame_dimensions: (u32, u32),
+) -> Option<glutin::dpi::PhysicalSize<u32>> {
+    let (frame_w, frame_h) = (frame_dimensions.0 as f64, frame_dimensions.1 as f64);
+    if frame_w == 0.0 || frame_h == 0.0 { return None; }
+
+    let width_change = (current.width as i64 - previous.width as i64).abs();
+    let height_change = (current.height as i64 - previous.height as i64).abs();
+
+    let corrected = if height_change > width_change {
+        let width = (current.height as f64 * frame_w / frame_h).round() as u32;
+        glutin::dpi::PhysicalSize::new(width, current.height)
+    } else {
+        let height = (current.width as f64 * frame_h / frame_w).round() as u32;
+        glutin::dpi::PhysicalSize::new(current.width, height)
+    };
+
+    // Ignoring rounding errors, which would otherwise cause endless resizing
+    let off_by_w = (corrected.width as i64 - current.width as i64).abs();
+    let off_by_h = (corrected.height as i64 - current.height as i64).abs();
+    if off_by_w <= 1 && off_by_h <= 1 { return None; }
+
+    Some(corrected)
+}
+
+#[test]
+fn _aspect_ratio_correction() {
+    use glutin::dpi::PhysicalSize;
+
+    // Dragging the right edge adjusts the height
+    let corrected = aspect_ratio_correction(PhysicalSize::new(200, 100), PhysicalSize::new(300, 100), (2, 1));
+    assert_eq!(corrected, Some(PhysicalSize::new(300, 150)));
+
+    // Dragging the bottom edge adjusts the width
+    let corrected = aspect_ratio_correction(PhysicalSize::new(200, 100), PhysicalSize::new(200, 150), (2, 1));
+    assert_eq!(corrected, Some(PhysicalSize::new(300, 150)));
+
+    // Sizes that already match are left alone
+    let corrected = aspect_ratio_correction(PhysicalSize::new(200, 100), PhysicalSize::new(301, 150), (2, 1));
+    assert_eq!(corrected, None);
+}
+
+// Adapts a closure to the `UIController` trait, for `UI::launch_fn()`
+struct ClosureController<F> {
+    blueprint: UIBlueprint,
+    render: F,
+    events: Vec<UIEvent>,
+    frame: Option<RgbaImage>,
+}
+
+impl<F: FnMut(&[UIEvent]) -> Option<RgbaImage>> UIController for ClosureController<F> {
+    fn blueprint(&self) -> UIBlueprint {
+        self.blueprint.clone()
+    }
+
+    fn process_events(&mut self, events: &Vec<UIEvent>, _context: &mut UIContext) -> UIControlFlow {
+        self.events.extend_from_slice(events);
+        UIControlFlow::Continue
+    }
+
+    fn next_frame(&mut self, _context: &mut UIContext) -> Option<RgbaImageRegion<'_>> {
+        // Keeping the previous frame if the closure has nothing new
+        if let Some(frame) = (self.render)(&self.events) {
+            self.frame = Some(frame);
+        }
+
+        self.events.clear();
+        self.frame.as_ref().map(|frame| frame.as_region())
+    }
+}
+
+impl UI {
+    /// Start the application using the given `UIController`, returning
+    /// once the window closes. Returns an error without opening a window
+    /// if the controller's blueprint is invalid, and the controller's error
+    /// if it exited with `UIControlFlow::ExitWithError`.
+    pub fn launch<T: 'static + UIController>(mut controller: T) -> Result<(), OpenUiError> {
+        let blueprint = controller.blueprint().with_overrides();
+        blueprint.validate()?;
+        let mut event_loop = create_event_loop(&blueprint);
+        let mut renderer = Renderer::new(&blueprint, &event_loop)?;
+        renderer.plugins = plugin::Plugins::new(controller.plugins());
+        let mut controller: Box<dyn UIController> = Box::new(controller);
+
+        // Setting up timekeeping
+        let mut fps = blueprint.frames_per_second;
+        if blueprint.sync_to_monitor {
+            fps = renderer.monitor_refresh_rate().unwrap_or(fps);
+        }
+        renderer.video_fps = fps;
+        let refresh_interval = frame_interval(fps);
+        let mut scheduler = FrameScheduler::new(refresh_interval, blueprint.catch_up_policy, Instant::now());
+
+        let mut ui_events = vec![];
+        let mut modifiers = KeyboardModifiers::default();
+
+        // Frames are only produced on request when rendering on demand,
+        // starting with the very first one
+        let on_demand = blueprint.render_mode == RenderMode::OnDemand;
+        let mut redraw_requested = true;
+        controller.redraw_handle(RedrawHandle { proxy: event_loop.create_proxy() });
+
+        // The time after which a request to close the window is
+        // granted, whether or not the controller has responded
+        let close_policy = blueprint.close_policy;
+        let mut close_deadline = None;
+        let mut visibility = Visibility::default();
+
+        let mut console = blueprint.debug_console
+            .then(|| console::Console::new(blueprint.console_commands.clone()));
+
+        let mut tape = replay::InputTape::new(blueprint.input_recording.as_deref(), blueprint.input_replay.as_deref());
+
+        // Keeping track of frames, for frame reports and the controller's context
+        let launched = Instant::now();
+        let mut last_frame_end = launched;
+        let mut context = UIContext::new((0, 0), 1.0);
+
+        // Returning from the event loop when it ends, rather than exiting,
+        // so that everything it owns is dropped and cleaned up
+        let mut outcome = Ok(());
+        event_loop.run_return(|event, event_loop, control_flow| {
+
+            if matches!(close_deadline, Some(deadline) if Instant::now() >= deadline) {
+                return *control_flow = ControlFlow::Exit;
+            }
+
+            // Starting afresh when frames can be displayed again
+            let was_paused = visibility.paused();
+            apply_visibility_event(&event, &mut visibility, &mut ui_events);
+            if was_paused && !visibility.paused() {
+                scheduler.restart(Instant::now());
+                redraw_requested = true;
+            }
+
+            // Following the window onto monitors with different refresh rates
+            if let Some(new_fps) = monitor_change(&event, &renderer, fps, blueprint.sync_to_monitor) {
+                fps = new_fps;
+                scheduler.set_interval(frame_interval(fps));
+                renderer.video_fps = fps;
+                ui_events.push(UIEvent::RefreshRate(RefreshRateEvent { frames_per_second: fps }));
+            }
+
+            // Learning how precise the OS timer is
+            if let NewEvents(StartCause::ResumeTimeReached { start, requested_resume }) = event {
+                scheduler.observe_wakeup(requested_resume, start);
+            }
+
+            // Noticing requests for frames, including those from the OS
+            // when the window's contents need to be restored
+            if let UserEvent(()) | RedrawRequested(_) = event {
+                redraw_requested = true;
+            }
+
+            // Reopening the window if the graphics context was lost
+            if event == RedrawEventsCleared && renderer.context_lost() {
+                if let Err(error) = renderer.recover(event_loop) {
+                    outcome = Err(error);
+                    return *control_flow = ControlFlow::Exit;
+                }
+                ui_events.push(UIEvent::DisplayReset);
+                redraw_requested = true;
+            }
+
+            if event == RedrawEventsCleared {
+                if menu::apply_menu_events(&mut ui_events) && request_close(close_policy, &mut close_deadline, &mut ui_events) {
+                    return *control_flow = ControlFlow::Exit;
+                }
+                tray::apply_tray_events(&mut ui_events);
+                renderer.gamepads.apply_gamepad_events(&mut ui_events);
+                renderer.apply_monitor_events(&mut ui_events);
+                renderer.apply_remote_events(&mut ui_events);
+                renderer.observe_events(&ui_events);
+
+                if let Some(console) = &mut console {
+                    redraw_requested |= console.process_events(&mut ui_events, &mut *controller);
+                }
+            }
+
+            // Sleeping while frames can't be displayed, after
+            // letting the controller know why
+            if visibility.paused() && event == RedrawEventsCleared {
+                if !ui_events.is_empty() {
+                    renderer.plugins.process_events(&mut ui_events);
+                    renderer.refresh_context(&mut context, launched);
+                    if let Some(result) = exit_requested(controller.process_events(&ui_events, &mut context)) {
+                        outcome = result;
+                        return *control_flow = ControlFlow::Exit;
+                    }
+                    let commands = context.take_commands();
+                    replace_controller(&mut controller, &commands);
+                    if renderer.apply_commands(commands) {
+                        return *control_flow = ControlFlow::Exit;
+                    }
+                    ui_events.clear();
+                }
+                return *control_flow = close_deadline.map_or(ControlFlow::Wait, ControlFlow::WaitUntil);
+            }
+
+            if on_demand && event == RedrawEventsCleared {
+
+                // Sleeping until there's something new to draw,
+                // or until a pending close request is granted
+                // Replays keep drawing frames until they're over, since
+                // recorded events don't wake the event loop
+                let wait = if tape.is_replaying() {
+                    ControlFlow::Poll
+                } else {
+                    close_deadline.map_or(ControlFlow::Wait, ControlFlow::WaitUntil)
+                };
+                if !redraw_requested && ui_events.is_empty() && !tape.is_replaying() {
+                    return *control_flow = wait;
+                }
+
+                trace_span!("frame");
+                let mut report = FrameReport { frame: context.frame, wait: last_frame_end.elapsed(), ..FrameReport::default() };
+                renderer.refresh_context(&mut context, launched);
+                if let Some(result) = run_frame(&mut controller, &mut renderer, &mut context, &mut console, &mut tape, &mut ui_events, &mut report) {
+                    outcome = result;
+                    return *control_flow = ControlFlow::Exit;
+                }
+                controller.frame_report(&report);
+                context.frame += 1;
+                last_frame_end = Instant::now();
+
+                redraw_requested = false;
+                *control_flow = wait;
+            } else if event == RedrawEventsCleared {
+
+                // Sleeping until shortly before the next frame is due, then
+                // spinning for the remainder, since the OS timer is imprecise
+                let frame_is_due = {
+                    trace_span!("wait");
+                    scheduler.wait_for_frame()
+                };
+                if !frame_is_due {
+                    return *control_flow = ControlFlow::WaitUntil(scheduler.wake_time());
+                }
+
+                trace_span!("frame");
+                let mut report = FrameReport { frame: context.frame, wait: last_frame_end.elapsed(), ..FrameReport::default() };
+                renderer.refresh_context(&mut context, launched);
+
+                // Letting the controller know if this frame is late
+                let (skipped_renders, late) = scheduler.begin_frame(Instant::now());
+                if let Some(late) = late {
+                    ui_events.push(UIEvent::FrameBudgetExceeded(late));
+                }
+                ui_events.push(UIEvent::FrameTiming(scheduler.timing()));
+
+                // Catching up on frames that were missed, without rendering them
+                for _ in 0..skipped_renders {
+                    trace_span!("process_events");
+                    let started = Instant::now();
+                    tape.apply(&mut ui_events);
+                    renderer.plugins.process_events(&mut ui_events);
+                    if let Some(result) = exit_requested(controller.process_events(&ui_events, &mut context)) {
+                        outcome = result;
+                        return *control_flow = ControlFlow::Exit;
+                    }
+                    let commands = context.take_commands();
+                    replace_controller(&mut controller, &commands);
+                    if renderer.apply_commands(commands) {
+                        return *control_flow = ControlFlow::Exit;
+                    }
+                    ui_events.clear();
+                    report.events += started.elapsed();
+                }
+
+                // Handling events that have been collected during
+                // the previous frame, then drawing the next frame
+                if let Some(result) = run_frame(&mut controller, &mut renderer, &mut context, &mut console, &mut tape, &mut ui_events, &mut report) {
+                    outcome = result;
+                    return *control_flow = ControlFlow::Exit;
+                }
+                controller.frame_report(&report);
+                context.frame += 1;
+                last_frame_end = Instant::now();
+
+                // Waiting until the next frame
+                scheduler.end_frame(Instant::now());
+                *control_flow = ControlFlow::WaitUntil(scheduler.wake_time());
+            }
+
+            // Responding to UI events
+            if let glutin::event::Event::WindowEvent { event, .. } = event {
+                match event {
+                    glutin::event::WindowEvent::CloseRequested => {
+                        if request_close(close_policy, &mut close_deadline, &mut ui_events) {
+                            *control_flow = ControlFlow::Exit;
+                        }
+                    },
+                    glutin::event::WindowEvent::Resized(phys_size) => {
+                        renderer.resize(phys_size);
+                        apply_resize_event(&renderer.size, &mut ui_events);
+                    },
+                    glutin::event::WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        renderer.resize(*new_inner_size);
+                        apply_resize_event(&renderer.size, &mut ui_events);
+                    },
+                    event => handle_window_event(event, |event| renderer.observe_window_event(event), &mut modifiers, &mut ui_events),
+                }
+            }
+        });
+
+        outcome
+    }
+
+    /// Start the application with a closure in place of a `UIController`,
+    /// which is convenient for quick sketches. The closure is called every
+    /// frame with the events that arrived since the previous frame, and
+    /// returns the next frame, or `None` to keep displaying the last one.
+    pub fn launch_fn<F>(blueprint: UIBlueprint, render: F) -> Result<(), OpenUiError>
+    where F: 'static + FnMut(&[UIEvent]) -> Option<RgbaImage> {
+        UI::launch(ClosureController { blueprint, render, events: vec![], frame: None })
+    }
+
+    /// Open a window that is drawn entirely by a Shadertoy-style fragment
+    /// shader, with no controller. See the `shadertoy` module for the
+    /// inputs that the shader receives. Returns an error if the blueprint
+    /// is invalid, or if the shader doesn't compile.
+    pub fn launch_shadertoy(blueprint: UIBlueprint, source: &str) -> Result<(), OpenUiError> {
+        let blueprint = blueprint.with_overrides();
+        blueprint.validate()?;
+        let mut event_loop = create_event_loop(&blueprint);
+        let mut renderer = Renderer::new(&blueprint, &event_loop)?;
+
+        let mut runner = ShadertoyRunner::new(&renderer.display, source, blueprint.frames_per_second)?;
+
+        let refresh_interval = frame_interval(blueprint.frames_per_second);
+        let mut next_frame = Instant::now();
+        let mut ui_events = vec![];
+        let mut modifiers = KeyboardModifiers::default();
+
+        event_loop.run_return(|event, _, control_flow| {
+            match event {
+                glutin::event::Event::WindowEvent { event, .. } => match event {
+                    glutin::event::WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                    glutin::event::WindowEvent::Resized(phys_size) => renderer.resize(phys_size),
+                    glutin::event::WindowEvent::ScaleFactorChanged { new_inner_size, .. } => renderer.resize(*new_inner_size),
+                    event => handle_window_event(event, |event| renderer.observe_window_event(event), &mut modifiers, &mut ui_events),
+                },
+
+                // Drawing frames at the blueprint's rate, without
+                // trying to catch up on frames that were missed
+                RedrawEventsCleared => {
+                    let now = Instant::now();
+                    if now >= next_frame {
+                        runner.draw(&renderer.display, &ui_events);
+                        ui_events.clear();
+                        next_frame = (next_frame + refresh_interval).max(now);
+                    }
+                    *control_flow = ControlFlow::WaitUntil(next_frame);
+                },
+                _ => {}
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Start the application like `UI::launch()`, but call the controller
+    /// from a separate thread than the one that displays frames, so that
+    /// slow frames in the controller don't delay the display of finished
+    /// frames, and vice versa. Frames are copied into one of two buffers,
+    /// so the controller can draw the next frame while the previous one
+    /// is being displayed.
+    pub fn launch_threaded<T: 'static + UIController + Send>(mut controller: T) -> Result<(), OpenUiError> {
+        let blueprint = controller.blueprint().with_overrides();
+        blueprint.validate()?;
+        let mut event_loop = create_event_loop(&blueprint);
+        let mut renderer = Renderer::new(&blueprint, &event_loop)?;
+        renderer.plugins = plugin::Plugins::new(controller.plugins());
+
+        // Setting up timekeeping
+        let mut fps = blueprint.frames_per_second;
+        if blueprint.sync_to_monitor {
+            fps = renderer.monitor_refresh_rate().unwrap_or(fps);
+        }
+        renderer.video_fps = fps;
+        let mut refresh_interval = frame_interval(fps);
+
+        // Events travel to the controller's thread along with the window's
+        // size and scale factor, the monitors whenever they've changed, and
+        // anything that went wrong while displaying frames. Finished frames
+        // and commands travel back, and frame buffers are
+        // returned once they've been displayed. A `None` frame means that
+        // the controller wants to terminate.
+        let (event_sender, event_receiver) = mpsc::channel::<(Vec<UIEvent>, (u32, u32), f64, Option<Vec<Monitor>>, Vec<OpenUiError>)>();
+        let (frame_sender, frame_receiver) = mpsc::channel::<Option<RgbaImage>>();
+        let (buffer_sender, buffer_receiver) = mpsc::channel::<RgbaImage>();
+        let (command_sender, command_receiver) = mpsc::channel::<UICommand>();
+
+        for _ in 0..2 {
+            buffer_sender.send(RgbaImage::new(0, 0)).unwrap();
+        }
+
+        let video_modes = renderer.video_modes.clone();
+        let monitors = renderer.monitors.clone();
+        let linux_backend = renderer.linux_backend;
+        let controller_thread = std::thread::spawn(move || {
+            let mut controller: Box<dyn UIController> = Box::new(controller);
+            let mut outcome = Ok(());
+            let launched = Instant::now();
+            let mut context = UIContext::new((0, 0), 1.0);
+            context.video_modes = video_modes;
+            context.monitors = monitors;
+            context.linux_backend = linux_backend;
+
+            while let Ok((mut ui_events, mut window_size, mut scale_factor, mut monitors, mut errors)) = event_receiver.recv() {
+
+                // Catching up on any events that arrived while the
+                // previous frame was being drawn
+                while let Ok((more_events, size, scale, more_monitors, more_errors)) = event_receiver.try_recv() {
+                    ui_events.extend(more_events);
+                    (window_size, scale_factor) = (size, scale);
+                    monitors = more_monitors.or(monitors);
+                    errors.extend(more_errors);
+                }
+
+                for error in errors {
+                    controller.render_error(error);
+                }
+
+                context.window_size = window_size;
+                context.scale_factor = scale_factor;
+                context.elapsed = launched.elapsed();
+                if let Some(monitors) = monitors {
+                    context.monitors = monitors;
+                }
+
+                if let Some(result) = exit_requested(controller.process_events(&ui_events, &mut context)) {
+                    outcome = result;
+                    break;
+                }
+
+                if let Some(pixels) = controller.next_frame(&mut context) {
+                    let mut buffer = match buffer_receiver.recv() {
+                        Ok(buffer) => buffer,
+                        Err(_) => break,
+                    };
+
+                    buffer.copy_region(&pixels);
+
+                    if frame_sender.send(Some(buffer)).is_err() {
+                        break;
+                    }
+                    context.frame += 1;
+                }
+
+                // Quitting from this thread, and leaving the rest to the window's
+                let commands = context.take_commands();
+                if quit_requested(&commands) {
+                    break;
+                }
+                replace_controller(&mut controller, &commands);
+                for command in commands {
+                    let _ = command_sender.send(command);
+                }
+            }
+
+            let _ = frame_sender.send(None);
+            outcome
+        });
+
+        let mut ui_events = vec![];
+        let mut modifiers = KeyboardModifiers::default();
+
+        // The time after which a request to close the window is
+        // granted, whether or not the controller has responded
+        let close_policy = blueprint.close_policy;
+        let mut close_deadline = None;
+        let mut visibility = Visibility::default();
+
+        let mut outcome = Ok(());
+        event_loop.run_return(|event, event_loop, control_flow| {
+
+            if matches!(close_deadline, Some(deadline) if Instant::now() >= deadline) {
+                return *control_flow = ControlFlow::Exit;
+            }
+
+            apply_visibility_event(&event, &mut visibility, &mut ui_events);
+
+            // Following the window onto monitors with different refresh rates
+            if let Some(new_fps) = monitor_change(&event, &renderer, fps, blueprint.sync_to_monitor) {
+                fps = new_fps;
+                refresh_interval = frame_interval(fps);
+                renderer.video_fps = fps;
+                ui_events.push(UIEvent::RefreshRate(RefreshRateEvent { frames_per_second: fps }));
+            }
+
+            if event == RedrawEventsCleared {
+                // Reopening the window if the graphics context was lost
+                if renderer.context_lost() {
+                    if let Err(error) = renderer.recover(event_loop) {
+                        outcome = Err(error);
+                        return *control_flow = ControlFlow::Exit;
+                    }
+                    ui_events.push(UIEvent::DisplayReset);
+                }
+
+                if menu::apply_menu_events(&mut ui_events) && request_close(close_policy, &mut close_deadline, &mut ui_events) {
+                    return *control_flow = ControlFlow::Exit;
+                }
+                tray::apply_tray_events(&mut ui_events);
+                renderer.gamepads.apply_gamepad_events(&mut ui_events);
+                renderer.apply_monitor_events(&mut ui_events);
+                renderer.apply_remote_events(&mut ui_events);
+                renderer.observe_events(&ui_events);
+                renderer.plugins.process_events(&mut ui_events);
+
+                renderer.apply_commands(command_receiver.try_iter().collect());
+                let (window_size, scale_factor) = (renderer.context_size(), renderer.scale_factor());
+                let monitors = ui_events.iter()
+                    .any(|event| matches!(event, UIEvent::Monitor(MonitorEvent {
+                        action: MonitorAction::Connected | MonitorAction::Disconnected, ..
+                    })))
+                    .then(|| renderer.monitors.clone());
+
+                // Sleeping while frames can't be displayed, which also
+                // pauses the controller's thread once it has caught up
+                if visibility.paused() {
+                    if !ui_events.is_empty() {
+                        let _ = event_sender.send((std::mem::take(&mut ui_events), window_size, scale_factor, monitors, renderer.take_errors()));
+                    }
+                    return *control_flow = close_deadline.map_or(ControlFlow::Wait, ControlFlow::WaitUntil);
+                }
+
+                // Handing off events that have been collected
+                // during the previous frame
+                let _ = event_sender.send((std::mem::take(&mut ui_events), window_size, scale_factor, monitors, renderer.take_errors()));
+
+                // Finding the newest finished frame, and recycling the others
+                let mut newest_frame = None;
+                loop {
+                    match frame_receiver.try_recv() {
+                        Ok(Some(frame)) => {
+                            if let Some(older_frame) = newest_frame.replace(frame) {
+                                let _ = buffer_sender.send(older_frame);
+                            }
+                        },
+                        Ok(None) | Err(mpsc::TryRecvError::Disconnected) => {
+                            return *control_flow = ControlFlow::Exit;
+                        },
+                        Err(mpsc::TryRecvError::Empty) => break,
+                    }
+                }
+
+                if let Some(frame) = newest_frame {
+                    renderer.present(&frame.as_region());
+                    let _ = buffer_sender.send(frame);
+                }
+
+                // Waiting until the next frame
+                let next_frame_time = Instant::now() + refresh_interval;
+                *control_flow = ControlFlow::WaitUntil(next_frame_time);
+            }
+
+            // Responding to UI events
+            if let glutin::event::Event::WindowEvent { event, .. } = event {
+                match event {
+                    glutin::event::WindowEvent::CloseRequested => {
+                        if request_close(close_policy, &mut close_deadline, &mut ui_events) {
+                            *control_flow = ControlFlow::Exit;
+                        }
+                    },
+                    glutin::event::WindowEvent::Resized(phys_size) => {
+                        renderer.resize(phys_size);
+                        apply_resize_event(&renderer.size, &mut ui_events);
+                    },
+                    glutin::event::WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        renderer.resize(*new_inner_size);
+                        apply_resize_event(&renderer.size, &mut ui_events);
+                    },
+                    event => handle_window_event(event, |event| renderer.observe_window_event(event), &mut modifiers, &mut ui_events),
+                }
+            }
+        });
+
+        // Letting the controller's thread finish, which it does once
+        // it finds that the window has stopped sending it events
+        drop((event_sender, buffer_sender));
+        let controller_outcome = controller_thread.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+        outcome.and(controller_outcome)
+    }
+
+    /// Open a window without handing control of the main loop to OpenUI.
+    /// This is an alternative to `UI::launch()` for applications that need
+    /// to run their own loop, which should repeatedly call
+    /// `UIHandle::poll_events()` and `UIHandle::present()`.
+    pub fn open(blueprint: UIBlueprint) -> Result<UIHandle, OpenUiError> {
+        let blueprint = blueprint.with_overrides();
+        blueprint.validate()?;
+        let event_loop = create_event_loop(&blueprint);
+        let renderer = Renderer::new(&blueprint, &event_loop)?;
+
+        Ok(UIHandle {
+            event_loop,
+            renderer,
+            modifiers: KeyboardModifiers::default(),
+            close_requested: false,
+            close_deadline: None,
+            visibility: Visibility::default(),
+            converted: RgbaImage::new(0, 0),
+        })
+    }
+}
+
+/// An open window, created by `UI::open()`, whose main loop is owned by
+/// the caller. The window closes when the handle is dropped.
+pub struct UIHandle {
+    event_loop: glutin::event_loop::EventLoop<()>,
+    renderer: Renderer,
+    modifiers: KeyboardModifiers,
+    close_requested: bool,
+    close_deadline: Option<Instant>,
+    visibility: Visibility,
+    // A reusable buffer for frames in other pixel formats
+    converted: RgbaImage,
+}
+
+impl UIHandle {
+    /// Collect every event that has occurred since the last call,
+    /// without waiting for new ones.
+    pub fn poll_events(&mut self) -> Vec<UIEvent> {
+        let mut ui_events = vec![];
+        let renderer = &mut self.renderer;
+        let modifiers = &mut self.modifiers;
+        let close_requested = &mut self.close_requested;
+        let close_deadline = &mut self.close_deadline;
+        let close_policy = renderer.blueprint.close_policy;
+        let visibility = &mut self.visibility;
+
+        self.event_loop.run_return(|event, event_loop, control_flow| {
+            apply_visibility_event(&event, visibility, &mut ui_events);
+
+            match event {
+                glutin::event::Event::WindowEvent { event, .. } => match event {
+                    glutin::event::WindowEvent::CloseRequested => {
+                        *close_requested |= request_close(close_policy, close_deadline, &mut ui_events);
+                    },
+                    glutin::event::WindowEvent::Resized(phys_size) => {
+                        renderer.resize(phys_size);
+                        apply_resize_event(&renderer.size, &mut ui_events);
+                    },
+                    glutin::event::WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        renderer.resize(*new_inner_size);
+                        apply_resize_event(&renderer.size, &mut ui_events);
+                    },
+                    event => handle_window_event(event, |event| renderer.observe_window_event(event), modifiers, &mut ui_events),
+                },
+
+                // Returning once all pending events have been handled
+                glutin::event::Event::MainEventsCleared => {
+                    if renderer.context_lost() {
+                        match renderer.recover(event_loop) {
+                            Ok(()) => ui_events.push(UIEvent::DisplayReset),
+                            Err(error) => {
+                                renderer.report_error(error);
+                                *close_requested = true;
+                                ui_events.push(UIEvent::CloseRequested);
+                            },
+                        }
+                    }
+                    if menu::apply_menu_events(&mut ui_events) {
+                        *close_requested |= request_close(close_policy, close_deadline, &mut ui_events);
+                    }
+                    if matches!(*close_deadline, Some(deadline) if Instant::now() >= deadline) {
+                        *close_requested = true;
+                    }
+                    tray::apply_tray_events(&mut ui_events);
+                    renderer.gamepads.apply_gamepad_events(&mut ui_events);
+                    renderer.apply_monitor_events(&mut ui_events);
+                    renderer.apply_remote_events(&mut ui_events);
+                    renderer.observe_events(&ui_events);
+                    renderer.plugins.process_events(&mut ui_events);
+                    *control_flow = ControlFlow::Exit;
+                },
+                _ => {}
+            }
+        });
+
+        ui_events
+    }
+
+    /// Display a frame in the window. Returns the first thing that went wrong
+    /// since the last frame, such as the GPU running out of memory, or a
+    /// screenshot failing to save. Later frames are still displayed either way.
+    pub fn present(&mut self, pixels: &RgbaImageRegion) -> Result<(), OpenUiError> {
+        self.renderer.present(pixels);
+        self.renderer.take_errors().into_iter().next().map_or(Ok(()), Err)
+    }
+
+    /// Display a frame in any pixel format, after converting it to 8-bit RGBA.
+    pub fn present_image<P: Pixel>(&mut self, image: &Image<P>) -> Result<(), OpenUiError> {
+        image.write_rgba(&mut self.converted);
+        self.renderer.present(&self.converted.as_region());
+        self.renderer.take_errors().into_iter().next().map_or(Ok(()), Err)
+    }
+
+    /// Apply effects on the GPU to every frame presented from now on.
+    /// See the `gpu` module for details.
+    #[cfg(feature = "gpu")]
+    pub fn set_gpu_effects(&mut self, effects: Vec<gpu::GpuEffect>) {
+        self.renderer.gpu_effects = effects;
+    }
+
+    /// Set a uniform declared by the blueprint's custom fragment shader,
+    /// for every frame presented from now on.
+    pub fn set_uniform(&mut self, name: &str, value: impl Into<ShaderValue>) {
+        shader::set_uniform(&mut self.renderer.uniforms, name, value.into());
+    }
+
+    /// Add a plugin, which sees events before they're returned by
+    /// `poll_events()`, and draws over frames. See the `plugin` module.
+    pub fn add_plugin(&mut self, plugin: impl plugin::UIPlugin + 'static) {
+        self.renderer.plugins.push(Box::new(plugin));
+    }
+
+    /// Every video mode of the blueprint's monitor, like `UIContext::video_modes()`.
+    pub fn video_modes(&self) -> &[VideoMode] {
+        &self.renderer.video_modes
+    }
+
+    /// Every connected monitor, like `UIContext::monitors()`.
+    pub fn monitors(&self) -> &[Monitor] {
+        &self.renderer.monitors
+    }
+
+    /// Whether the window was opened with X11 or Wayland,
+    /// like `UIContext::linux_backend()`.
+    pub fn linux_backend(&self) -> Option<LinuxBackend> {
+        self.renderer.linux_backend
+    }
+
+    /// Switch the window between windowed and fullscreen modes,
+    /// like `UIContext::set_fullscreen()`.
+    pub fn set_fullscreen(&mut self, fullscreen: FullscreenMode) {
+        self.renderer.apply_commands(vec![UICommand::SetFullscreen(fullscreen)]);
+    }
+
+    /// Move the window onto another monitor, like `UIContext::set_monitor()`.
+    pub fn set_monitor(&mut self, monitor: MonitorChoice) {
+        self.renderer.apply_commands(vec![UICommand::SetMonitor(monitor)]);
+    }
+
+    /// Vibrate a gamepad, like `UIContext::set_rumble()`.
+    pub fn set_rumble(&mut self, gamepad_id: u64, strong: f32, weak: f32, duration: Duration) {
+        self.renderer.gamepads.set_rumble(gamepad::Rumble { gamepad_id, strong, weak, duration });
+    }
+
+    /// Declare the regions that move and resize the window, like
+    /// `UIContext::set_hit_regions()`.
+    pub fn set_hit_regions(&mut self, regions: Vec<HitRegion>) {
+        self.renderer.hit_regions = regions;
+    }
+
+    /// Whether the window should close, because the user tried to close it
+    /// under `ClosePolicy::Exit`, or because the timeout of `ClosePolicy::Ask`
+    /// has passed. Under `ClosePolicy::Ask`, attempts to close the window are
+    /// returned by `poll_events()` as `UIEvent::CloseRequested` instead.
+    pub fn close_requested(&self) -> bool {
+        self.close_requested
+    }
+}
+
+// A texture, and the vertices that place it in the window, for each
+// viewport or tile of a frame, reused from frame to frame
+struct TexturedQuad {
+    texture: Option<glium::texture::Texture2d>,
+    vertex_buffer: glium::VertexBuffer<Vertex>,
+}
+
+// The window, and everything needed to draw frames into it
+struct Renderer {
+    display: glium::Display,
+    indices: glium::IndexBuffer<u16>,
+    program: glium::Program,
+    vertex_buffer: glium::VertexBuffer<Vertex>,
+    draw_params: glium::DrawParameters<'static>,
+    preserve_aspect_ratio: bool,
+    lock_aspect_ratio: bool,
+    transparent: bool,
+    size: LogicalSize<f32>,
+    // Keeping track of sizes, for aspect ratio locking
+    window_size: glutin::dpi::PhysicalSize<u32>,
+    frame_dimensions: Option<(u32, u32)>,
+    _menu_bar: Option<menu::NativeMenuBar>,
+    _tray_icon: Option<tray::NativeTrayIcon>,
+    gamepads: gamepad::Gamepads,
+    // Recording frames into a video, which starts with the first frame at
+    // the current pacing rate. ffmpeg's input rate can't change mid-stream,
+    // so a video that's already being recorded keeps its initial rate
+    video_path: Option<std::path::PathBuf>,
+    video_fps: u32,
+    exporter: Option<export::VideoExporter>,
+    frame_hashes: replay::FrameHashLog,
+    screenshot_key: Option<KeyboardKey>,
+    screenshot_directory: std::path::PathBuf,
+    // Whether the next frame should be saved as a screenshot
+    screenshot_requested: bool,
+    // How long the latest frame took to upload and display, for frame reports
+    upload_time: Duration,
+    present_time: Duration,
+    // Reused from frame to frame, to avoid allocating every frame
+    texture: Option<glium::texture::Texture2d>,
+    staging: Vec<u8>,
+    mipmaps: bool,
+    multi_viewport: bool,
+    quads: Vec<TexturedQuad>,
+    // Frames larger than this in either dimension are split into tiles
+    max_texture_size: u32,
+    // An image larger than the window, which frames are drawn onto
+    // before being shrunk onto the window, when supersampling
+    supersampling: u32,
+    supersampled: Option<glium::texture::Texture2d>,
+    // The program that shrinks the supersampled image, and a quad covering the window
+    downsample: Option<(glium::Program, glium::VertexBuffer<Vertex>)>,
+    // What's needed to fill in the uniforms of custom shaders
+    launched: Instant,
+    frames_presented: u64,
+    cursor: [f32; 2],
+    uniforms: Vec<(String, ShaderValue)>,
+    // Moving and resizing windows that draw their own title bar and borders
+    hit_regions: Vec<HitRegion>,
+    hit_cursor: glutin::dpi::PhysicalPosition<f64>,
+    resizing: Option<ResizeEdge>,
+    // The video modes of the blueprint's monitor, for exclusive fullscreen
+    video_modes: Vec<VideoMode>,
+    // The connected monitors, and the one the window is on, as of the
+    // last time they were checked, or `None` to check them right away
+    monitors: Vec<Monitor>,
+    current_monitor: Option<u32>,
+    monitors_checked: Option<Instant>,
+    next_monitor_id: u32,
+    // The refresh rate of each monitor, by name and resolution, since
+    // listing a monitor's video modes can be slow
+    refresh_rates: std::cell::RefCell<RefreshRates>,
+    // Whether the window was opened with X11 or Wayland
+    linux_backend: Option<LinuxBackend>,
+    // Where the window is while it isn't maximized, which is saved
+    // when it closes, if the blueprint asks for it
+    geometry: Option<(String, geometry::WindowGeometry)>,
+    #[cfg(feature = "gpu")]
+    effect_chain: gpu::EffectChain,
+    #[cfg(feature = "gpu")]
+    gpu_effects: Vec<gpu::GpuEffect>,
+    // Where frames are streamed to, and remote input comes from
+    remote: Option<remote::RemoteServer>,
+    // What the window was opened with, kept up to date by commands,
+    // for reopening it after the graphics context is lost
+    blueprint: UIBlueprint,
+    context_lost: std::cell::Cell<bool>,
+    // What went wrong while displaying frames, until it's handed to the application
+    errors: std::cell::RefCell<Vec<OpenUiError>>,
+    plugins: plugin::Plugins,
+}
+
+impl Renderer {
+    fn new(blueprint: &UIBlueprint, event_loop: &glutin::event_loop::EventLoopWindowTarget<()>) -> Result<Renderer, OpenUiError> {
+        let (width, height) = blueprint.dimensions;
+        let size = LogicalSize::new(width as f32, height as f32);
+        let preserve_aspect_ratio = blueprint.preserve_aspect_ratio;
+        let lock_aspect_ratio = preserve_aspect_ratio && blueprint.lock_aspect_ratio;
+
+        // Choosing how the window covers the screen
+        let mut next_monitor_id = 0;
+        let listed = list_monitors(event_loop.available_monitors(), event_loop.primary_monitor());
+        let monitors = identify_monitors(&[], listed, &mut next_monitor_id);
+        let monitor = choose_monitor(&blueprint.monitor, &monitors, event_loop.available_monitors(), event_loop.primary_monitor());
+        let video_modes = monitor.as_ref().map_or(vec![], list_video_modes);
+        let fullscreen = native_fullscreen(blueprint.fullscreen, monitor);
+
+        // Reopening where the window was left last time, unless it's fullscreen,
+        // or the monitor that it was on has since been disconnected
+        let saved_geometry = match (&blueprint.remember_geometry, blueprint.fullscreen) {
+            (Some(app_name), FullscreenMode::Windowed) => geometry::load(app_name),
+            _ => None,
+        };
+        let size = saved_geometry.as_ref().map_or(size, |saved| LogicalSize::new(saved.size.0 as f32, saved.size.1 as f32));
+        let maximized = saved_geometry.as_ref().map_or(blueprint.maximized, |saved| saved.maximized);
+        let position = saved_geometry.as_ref().and_then(|saved| {
+            let monitor_connected = event_loop.available_monitors().any(|monitor| monitor.name() == saved.monitor);
+            saved.position.filter(|_| monitor_connected)
+        });
+
+        let wb = glutin::window::WindowBuilder::new()
+            .with_title(&blueprint.title)
+            .with_inner_size(size)
+            .with_maximized(maximized)
+            .with_resizable(blueprint.resizeable)
+            .with_decorations(blueprint.decorations)
+            .with_fullscreen(fullscreen)
+            .with_transparent(blueprint.transparent);
+
+        let wb = match position {
+            Some((x, y)) => wb.with_position(glutin::dpi::PhysicalPosition::new(x, y)),
+            None => wb,
+        };
+
+        #[cfg(target_os = "windows")]
+        let wb = {
+            use glium::glutin::platform::windows::WindowBuilderExtWindows;
+            wb.with_theme(blueprint.theme.map(native_theme))
+        };
+
+        // Asking to be told when the context is lost, rather than
+        // having every call into the driver fail silently afterwards
+        let context_builder = || {
+            let cb = glutin::ContextBuilder::new()
+                .with_vsync(blueprint.vsync)
+                .with_gl_robustness(glutin::Robustness::TryRobustLoseContextOnReset);
+            let cb = if blueprint.opengl_es {
+                cb.with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGlEs, (3, 0)))
+            } else {
+                cb
+            };
+            if blueprint.software_rendering {
+                cb.with_hardware_acceleration(Some(false))
+            } else {
+                cb
+            }
+        };
+
+        let mut cb = context_builder();
+        if blueprint.multisampling > 0 {
+            cb = cb.with_multisampling(blueprint.multisampling);
+        }
+
+        // Falling back to a context without multisampling,
+        // since not every graphics driver supports it
+        let display = create_display(wb.clone(), cb, event_loop)
+            .or_else(|error| {
+                if blueprint.multisampling == 0 {
+                    return Err(error);
+                }
+                log_warning!("unable to enable multisampling: {}", error);
+                create_display(wb, context_builder(), event_loop)
+            })?;
+        display.gl_window().window().set_cursor_visible(blueprint.cursor_visible);
+        let current_monitor = display.gl_window().window().current_monitor()
+            .and_then(|current| monitors.iter().find(|monitor| same_monitor(monitor, &monitor_info(&current, None))))
+            .map(|monitor| monitor.id);
+        let menu_bar = menu::install(&blueprint.menus, &blueprint.title, display.gl_window().window());
+        let tray_icon = blueprint.tray_icon.as_ref().and_then(tray::install);
+        if let Some(description) = &blueprint.accessible_description {
+            accessibility::announce(&format!("{}. {}", blueprint.title, description), accessibility::Politeness::Polite);
+        }
+        let remote = blueprint.remote_address.as_ref().and_then(|address| remote::RemoteServer::bind(address.as_str())
+            .map_err(|error| log_error!("unable to stream frames from {}: {}", address, error))
+            .ok());
+
+        let indices: [u16; 6] = [0,1,2,2,3,0];
+        let indices = glium::IndexBuffer::new(
+            &display,
+            glium::index::PrimitiveType::TrianglesList,
+            &indices
+        )?;
+    
+        let default_program = || shader::program(&display, VERTEX_SHADER_SRC, FRAGMENT_SHADER_SRC);
+
+        // Falling back to the default shader if the custom one is broken
+        let program = match &blueprint.fragment_shader {
+            Some(source) => shader::program(&display, VERTEX_SHADER_SRC, source)
+                .or_else(|error| {
+                    log_error!("unable to compile fragment shader: {}", error);
+                    default_program()
+                })?,
+            None => default_program()?,
+        };
+
+        let shape = vec![
+            Vertex { dest: [-1.0, -1.0 ], src: [0.0, 1.0] },
+            Vertex { dest: [ 1.0, -1.0 ], src: [1.0, 1.0] },
+            Vertex { dest: [ 1.0,  1.0 ], src: [1.0, 0.0] },
+            Vertex { dest: [-1.0,  1.0 ], src: [0.0, 0.0] },
+        ];
+
+        let vertex_buffer = glium::VertexBuffer::new(&display, &shape)?;
+
+        // The extra parameters that will be used when drawing frames.
+        // Blend is important because it allows the alpha channel of
+        // RGBA to work.
+        let draw_params = glium::DrawParameters {
+            blend: Blend::alpha_blending(),
+            .. Default::default()
+        };
+
+        let window_size = display.gl_window().window().inner_size();
+
+        let max_texture_size = display.get_capabilities().max_texture_size.max(1) as u32;
+        let supersampled = supersampled_image(&display, window_size, blueprint.supersampling, max_texture_size);
+        let downsample = if blueprint.supersampling > 1 {
+            Some((
+                shader::program(&display, VERTEX_SHADER_SRC, DOWNSAMPLE_SHADER_SRC)?,
+                glium::VertexBuffer::new(&display, &[
+                    Vertex { dest: [-1.0, -1.0], src: [0.0, 0.0] },
+                    Vertex { dest: [ 1.0, -1.0], src: [1.0, 0.0] },
+                    Vertex { dest: [ 1.0,  1.0], src: [1.0, 1.0] },
+                    Vertex { dest: [-1.0,  1.0], src: [0.0, 1.0] },
+                ])?,
+            ))
+        } else {
+            None
+        };
+
+        #[cfg(feature = "gpu")]
+        let effect_chain = gpu::EffectChain::new(&display)?;
+
+        Ok(Renderer {
+            display,
+            indices,
+            program,
+            vertex_buffer,
+            draw_params,
+            preserve_aspect_ratio,
+            lock_aspect_ratio,
+            transparent: blueprint.transparent,
+            size: window_size.to_logical(1.0),
+            window_size,
+            frame_dimensions: None,
+            _menu_bar: menu_bar,
+            _tray_icon: tray_icon,
+            gamepads: gamepad::Gamepads::new(&blueprint.gamepad_mappings, blueprint.raw_joysticks),
+            video_path: blueprint.video_export.clone(),
+            video_fps: blueprint.frames_per_second,
+            exporter: None,
+            frame_hashes: replay::FrameHashLog::new(blueprint.frame_hash_log.as_deref()),
+            screenshot_key: blueprint.screenshot_key,
+            screenshot_directory: blueprint.screenshot_directory.clone(),
+            screenshot_requested: false,
+            upload_time: Duration::ZERO,
+            texture: None,
+            staging: Vec::new(),
+            mipmaps: blueprint.mipmaps,
+            multi_viewport: blueprint.multi_viewport,
+            quads: vec![],
+            max_texture_size,
+            supersampling: blueprint.supersampling,
+            supersampled,
+            downsample,
+            launched: Instant::now(),
+            frames_presented: 0,
+            cursor: [0.0, 0.0],
+            uniforms: vec![],
+            hit_regions: vec![],
+            hit_cursor: glutin::dpi::PhysicalPosition::new(0.0, 0.0),
+            resizing: None,
+            video_modes,
+            monitors,
+            current_monitor,
+            monitors_checked: Some(Instant::now()),
+            next_monitor_id,
+            refresh_rates: std::cell::RefCell::default(),
+            linux_backend: active_linux_backend(event_loop),
+            geometry: blueprint.remember_geometry.clone().map(|app_name| (app_name, geometry::WindowGeometry {
+                size: (size.width as u32, size.height as u32),
+                position,
+                monitor: None,
+                maximized,
+            })),
+            #[cfg(feature = "gpu")]
+            effect_chain,
+            #[cfg(feature = "gpu")]
+            gpu_effects: vec![],
+            present_time: Duration::ZERO,
+            remote,
+            blueprint: blueprint.clone(),
+            context_lost: std::cell::Cell::new(false),
+            errors: std::cell::RefCell::default(),
+            plugins: plugin::Plugins::default(),
+        })
+    }
+
+    // Whether the graphics context was lost, either when the latest
+    // frame was displayed, or as reported by the driver since
+    fn context_lost(&self) -> bool {
+        self.context_lost.get() || self.display.is_context_lost()
+    }
+
+    // Replacing the window, and everything on the GPU, after the graphics
+    // context is lost, while keeping the state that outlives a context
+    fn recover(&mut self, event_loop: &glutin::event_loop::EventLoopWindowTarget<()>) -> Result<(), OpenUiError> {
+        // Reopening the window at the same size and place, where blueprints
+        // measure sizes in logical pixels, rather than the physical pixels
+        // that the window reports
+        let (size, maximized, position) = {
+            let gl_window = self.display.gl_window();
+            let window = gl_window.window();
+            let size = self.window_size.to_logical::<f64>(window.scale_factor());
+            ((size.width.round() as u32, size.height.round() as u32), window.is_maximized(), window.outer_position().ok())
+        };
+
+        let blueprint = UIBlueprint {
+            dimensions: size,
+            maximized,
+            remote_address: None,
+            remember_geometry: None,
+            accessible_description: None,
+            frame_hash_log: None,
+            // Finding the same monitor by name, since identifiers start over
+            monitor: match &self.blueprint.monitor {
+                MonitorChoice::Id(id) => self.monitors.iter()
+                    .find(|monitor| monitor.id == *id)
+                    .and_then(|monitor| monitor.name.clone())
+                    .map_or(MonitorChoice::Primary, MonitorChoice::Name),
+                choice => choice.clone(),
+            },
+            ..self.blueprint.clone()
+        };
+
+        let mut lost = std::mem::replace(self, Renderer::new(&blueprint, event_loop)?);
+        if let (Some(position), false, FullscreenMode::Windowed) = (position, maximized, blueprint.fullscreen) {
+            self.display.gl_window().window().set_outer_position(position);
+        }
+
+        // Keeping the original blueprint, to reopen the same way next time
+        std::mem::swap(&mut self.blueprint, &mut lost.blueprint);
+        std::mem::swap(&mut self.gamepads, &mut lost.gamepads);
+        self.remote = lost.remote.take();
+        self.exporter = lost.exporter.take();
+        self.frame_hashes = std::mem::take(&mut lost.frame_hashes);
+        self.geometry = lost.geometry.take();
+        self.plugins = std::mem::take(&mut lost.plugins);
+        self.errors = std::mem::take(&mut lost.errors);
+        self.hit_regions = std::mem::take(&mut lost.hit_regions);
+        self.uniforms = std::mem::take(&mut lost.uniforms);
+        self.monitors = std::mem::take(&mut lost.monitors);
+        self.current_monitor = lost.current_monitor;
+        self.next_monitor_id = lost.next_monitor_id;
+        self.monitors_checked = None;
+        #[cfg(feature = "gpu")]
+        {
+            self.gpu_effects = std::mem::take(&mut lost.gpu_effects);
+        }
+        self.launched = lost.launched;
+        self.frames_presented = lost.frames_presented;
+        self.cursor = lost.cursor;
+        self.screenshot_requested = lost.screenshot_requested;
+        Ok(())
+    }
+
+    // The refresh rate of the monitor that the window is on. winit doesn't
+    // report a monitor's current video mode, so this is only known when
+    // every mode at the monitor's current resolution has the same rate.
+    fn monitor_refresh_rate(&self) -> Option<u32> {
+        let monitor = self.display.gl_window().window().current_monitor()?;
+        let size = monitor.size();
+
+        *self.refresh_rates.borrow_mut()
+            .entry((monitor.name(), (size.width, size.height)))
+            .or_insert_with(|| refresh_rate_at(&list_video_modes(&monitor), (size.width, size.height)))
+    }
+
+    // The window size reported to controllers, which matches `ResizeEvent`
+    fn context_size(&self) -> (u32, u32) {
+        (self.size.width as u32, self.size.height as u32)
+    }
+
+    fn scale_factor(&self) -> f64 {
+        self.display.gl_window().window().scale_factor()
+    }
+
+    // Bringing a controller's context up to date before calling it
+    fn refresh_context(&self, context: &mut UIContext, launched: Instant) {
+        context.window_size = self.context_size();
+        context.scale_factor = self.scale_factor();
+        context.elapsed = launched.elapsed();
+        context.video_modes.clone_from(&self.video_modes);
+        context.monitors.clone_from(&self.monitors);
+        context.linux_backend = self.linux_backend;
+    }
+
+    // Carrying out the commands that a controller has queued,
+    // returning true if one of them ends the application
+    fn apply_commands(&mut self, commands: Vec<UICommand>) -> bool {
+        let gl_window = self.display.gl_window();
+        let window = gl_window.window();
+
+        for command in &commands {
+            match command {
+                UICommand::SetTitle(title) => {
+                    window.set_title(title);
+                    self.blueprint.title = title.clone();
+                },
+                UICommand::SetCursorVisible(visible) => {
+                    window.set_cursor_visible(*visible);
+                    self.blueprint.cursor_visible = *visible;
+                },
+                UICommand::SetHitRegions(regions) => self.hit_regions = regions.clone(),
+                UICommand::SetFullscreen(fullscreen) => {
+                    let monitor = choose_monitor(&self.blueprint.monitor, &self.monitors, window.available_monitors(), window.primary_monitor());
+                    window.set_fullscreen(native_fullscreen(*fullscreen, monitor));
+                    self.blueprint.fullscreen = *fullscreen;
+                },
+                UICommand::SetMonitor(choice) => {
+                    let monitor = choose_monitor(choice, &self.monitors, window.available_monitors(), window.primary_monitor());
+                    self.video_modes = monitor.as_ref().map_or(vec![], list_video_modes);
+
+                    // Moving windowed windows into the monitor's top left corner
+                    match (self.blueprint.fullscreen, monitor) {
+                        (FullscreenMode::Windowed, Some(monitor)) => window.set_outer_position(monitor.position()),
+                        (FullscreenMode::Windowed, None) => {},
+                        (fullscreen, monitor) => window.set_fullscreen(native_fullscreen(fullscreen, monitor)),
+                    }
+                    self.blueprint.monitor = choice.clone();
+                },
+                UICommand::SetRumble(rumble) => self.gamepads.set_rumble(*rumble),
+                UICommand::Quit | UICommand::ReplaceController(_) => {},
+            }
+        }
+
+        quit_requested(&commands)
+    }
+
+    // Keeping track of the window's geometry and monitor, and moving or
+    // resizing it from hit regions, before the event reaches the controller.
+    // Every event loop passes its window events through here.
+    fn observe_window_event(&mut self, event: &glutin::event::WindowEvent) {
+        if let glutin::event::WindowEvent::Moved(_) = event {
+            self.track_geometry();
+            // Checking which monitor the window is on at the end of this
+            // batch of events, rather than up to a second later
+            self.monitors_checked = None;
+        }
+        self.apply_hit_regions(event);
+    }
+
+    // Noticing monitors being connected and disconnected, which the OS
+    // doesn't report, by listing them about once a second, and noticing
+    // the window moving onto another monitor
+    fn apply_monitor_events(&mut self, ui_events: &mut Vec<UIEvent>) {
+        if matches!(self.monitors_checked, Some(checked) if checked.elapsed() < Duration::from_secs(1)) {
+            return;
+        }
+        self.monitors_checked = Some(Instant::now());
+
+        let gl_window = self.display.gl_window();
+        let window = gl_window.window();
+        let listed = list_monitors(window.available_monitors(), window.primary_monitor());
+        let monitors = identify_monitors(&self.monitors, listed, &mut self.next_monitor_id);
+        monitor_changes(&self.monitors, &monitors, ui_events);
+        self.monitors = monitors;
+
+        let current_monitor = window.current_monitor()
+            .and_then(|current| self.monitors.iter().find(|monitor| same_monitor(monitor, &monitor_info(&current, None))))
+            .map(|monitor| monitor.id);
+        if let Some(monitor_id) = current_monitor.filter(|&id| self.current_monitor != Some(id)) {
+            ui_events.push(UIEvent::Monitor(MonitorEvent { monitor_id, action: MonitorAction::WindowEntered }));
+        }
+        self.current_monitor = current_monitor;
+    }
+
+    // Recording the window's size and position, unless it's maximized or
+    // fullscreen, so that it reopens at the size it'll be restored to
+    fn track_geometry(&mut self) {
+        let gl_window = self.display.gl_window();
+        let window = gl_window.window();
+
+        if let Some((_, geometry)) = &mut self.geometry {
+            if window.is_maximized() || window.fullscreen().is_some() {
+                return;
+            }
+
+            let size = window.inner_size().to_logical::<f64>(window.scale_factor());
+            geometry.size = (size.width.round() as u32, size.height.round() as u32);
+            geometry.position = window.outer_position().ok().map(|position| (position.x, position.y));
+        }
+    }
+
+    // Moving the window when its title bar region is dragged, and
+    // resizing it while one of its border regions is dragged
+    fn apply_hit_regions(&mut self, event: &glutin::event::WindowEvent) {
+        use glutin::event::{ElementState, WindowEvent};
+
+        if self.hit_regions.is_empty() {
+            return;
+        }
+
+        let gl_window = self.display.gl_window();
+        let window = gl_window.window();
+
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.hit_cursor = *position;
+
+                let edge = match self.resizing {
+                    Some(edge) => edge,
+                    None => {
+                        let icon = match hit_test(&self.hit_regions, *position) {
+                            Some(HitTest::Resize(edge)) => resize_cursor_icon(edge),
+                            _ => glutin::window::CursorIcon::Default,
+                        };
+                        return window.set_cursor_icon(icon);
+                    },
+                };
+
+                // Windows can't be moved on some platforms, so
+                // only their bottom and right edges follow the cursor
+                let size = window.inner_size();
+                let outer = window.outer_position().ok().map(|outer| (outer.x, outer.y));
+                let cursor = (position.x.round() as i32, position.y.round() as i32);
+                let (moved, resized) = resized_bounds(edge, outer.unwrap_or((0, 0)), (size.width, size.height), cursor);
+
+                if outer.is_some() {
+                    window.set_outer_position(glutin::dpi::PhysicalPosition::new(moved.0, moved.1));
+                }
+                window.set_inner_size(glutin::dpi::PhysicalSize::new(resized.0, resized.1));
+            },
+            WindowEvent::MouseInput { state, button: glutin::event::MouseButton::Left, .. } => match state {
+                ElementState::Pressed => match hit_test(&self.hit_regions, self.hit_cursor) {
+                    Some(HitTest::TitleBar) => {
+                        if let Err(error) = window.drag_window() {
+                            log_warning!("unable to move window: {}", error);
+                        }
+                    },
+                    Some(HitTest::Resize(edge)) => self.resizing = Some(edge),
+                    None => {},
+                },
+                ElementState::Released => self.resizing = None,
+            },
+            _ => {},
+        }
+    }
+
+    fn resize(&mut self, phys_size: glutin::dpi::PhysicalSize<u32>) {
+        if let Some(dimensions) = self.frame_dimensions {
+            if let Some(corrected) = aspect_ratio_correction(self.window_size, phys_size, dimensions) {
+                self.display.gl_window().window().set_inner_size(corrected);
+            }
+        }
+
+        self.window_size = phys_size;
+        self.size = phys_size.to_logical(1.0);
+        self.track_geometry();
+        self.supersampled = supersampled_image(&self.display, phys_size, self.supersampling, self.max_texture_size);
+    }
+
+    // Displaying several frames, each in its own rectangle of the window.
+    // Unlike single frames, these aren't recorded, saved or streamed.
+    fn present_viewports(&mut self, viewports: &[Viewport]) {
+        let started = Instant::now();
+        let uploaded = {
+            trace_span!("upload");
+            self.upload_viewports(viewports)
+        };
+        self.upload_time = started.elapsed();
+
+        match uploaded {
+            Ok(()) => self.draw_quads(viewports.len()),
+            Err(error) => self.report_error(error),
+        }
+    }
+
+    fn upload_viewports(&mut self, viewports: &[Viewport]) -> Result<(), OpenUiError> {
+        self.prepare_quads(viewports.len())?;
+
+        for (quad, viewport) in self.quads.iter_mut().zip(viewports) {
+            upload_frame(&self.display, &mut quad.texture, &mut self.staging, &viewport.frame, self.mipmaps)?;
+            let shape = viewport_vertices(&self.size, viewport, self.preserve_aspect_ratio);
+            quad.vertex_buffer.write(&shape);
+        }
+
+        Ok(())
+    }
+
+    // Displaying a frame that's too large for a single texture, by
+    // splitting it into tiles. GPU effects aren't applied to tiled frames.
+    fn present_tiles(&mut self, pixels: &RgbaImageRegion) {
+        let started = Instant::now();
+        let tiles = tile_rects((pixels.width, pixels.height), self.max_texture_size);
+        let uploaded = {
+            trace_span!("upload");
+            self.upload_tiles(pixels, &tiles)
+        };
+        self.upload_time = started.elapsed();
+
+        match uploaded {
+            Ok(()) => self.draw_quads(tiles.len()),
+            Err(error) => self.report_error(error),
+        }
+    }
+
+    fn upload_tiles(&mut self, pixels: &RgbaImageRegion, tiles: &[Rect]) -> Result<(), OpenUiError> {
+        let magnitude = if self.preserve_aspect_ratio { frame_magnitude(&self.size, pixels) } else { (1.0, 1.0) };
+        self.prepare_quads(tiles.len())?;
+
+        for (quad, &tile) in self.quads.iter_mut().zip(tiles) {
+            upload_frame(&self.display, &mut quad.texture, &mut self.staging, &pixels.region(tile).unwrap(), self.mipmaps)?;
+            quad.vertex_buffer.write(&tile_vertices(magnitude, (pixels.width, pixels.height), tile));
+        }
+
+        Ok(())
+    }
+
+    // Making sure there are at least `count` quads to draw with
+    fn prepare_quads(&mut self, count: usize) -> Result<(), OpenUiError> {
+        while self.quads.len() < count {
+            let vertex_buffer = glium::VertexBuffer::dynamic(&self.display, &[Vertex { dest: [0.0; 2], src: [0.0; 2] }; 4])?;
+            self.quads.push(TexturedQuad { texture: None, vertex_buffer });
+        }
+        Ok(())
+    }
+
+    // Drawing the first `count` quads, which have already been uploaded
+    fn draw_quads(&mut self, count: usize) {
+        let started = Instant::now();
+        {
+            trace_span!("present");
+            let quads = self.quads[..count].iter()
+                .map(|quad| (&quad.vertex_buffer, quad.texture.as_ref().unwrap()));
+            self.draw_to_window(quads);
+        }
+        self.present_time = started.elapsed();
+        self.frames_presented += 1;
+    }
+
+    // Keeping an error for the application, and noticing if it happened because
+    // the graphics context was lost, so the window is recreated before the next frame
+    fn report_error(&self, error: impl Into<OpenUiError>) {
+        if self.display.is_context_lost() {
+            self.context_lost.set(true);
+        }
+        self.errors.borrow_mut().push(error.into());
+    }
+
+    // Handing over everything that went wrong since the last call
+    fn take_errors(&self) -> Vec<OpenUiError> {
+        self.errors.take()
+    }
+
+    // Drawing textured quads onto the window, by way of
+    // the supersampled image if supersampling is enabled
+    fn draw_to_window<'q, I>(&self, quads: I)
+    where I: IntoIterator<Item = (&'q glium::VertexBuffer<Vertex>, &'q glium::texture::Texture2d)> {
+        let mut frame = self.display.draw();
+
+        match &self.supersampled {
+            Some(image) => match SimpleFrameBuffer::new(&self.display, image) {
+                Ok(mut target) => {
+                    self.draw_quads_onto(&mut target, quads);
+
+                    // Shrinking the image onto the window
+                    if let Some((program, vertex_buffer)) = &self.downsample {
+                        let uniforms = glium::uniform! {
+                            sampler: image.sampled()
+                                .magnify_filter(MagnifySamplerFilter::Nearest)
+                                .minify_filter(MinifySamplerFilter::Nearest),
+                            factor: self.supersampling as i32,
+                        };
+                        if let Err(error) = frame.draw(vertex_buffer, &self.indices, program, &uniforms, &Default::default()) {
+                            self.report_error(error);
+                        }
+                    }
+                },
+                // Drawing straight onto the window instead
+                Err(error) => {
+                    self.report_error(error);
+                    self.draw_quads_onto(&mut frame, quads);
+                },
+            },
+            None => self.draw_quads_onto(&mut frame, quads),
+        }
+
+        // Committing the drawn frame, and noticing if the context was
+        // lost, so the window can be recreated before the next one
+        match frame.finish() {
+            Ok(()) => (),
+            Err(glium::SwapBuffersError::ContextLost) => self.context_lost.set(true),
+            Err(error) => self.report_error(error),
+        }
+    }
+
+    fn draw_quads_onto<'q, S, I>(&self, surface: &mut S, quads: I)
+    where S: Surface, I: IntoIterator<Item = (&'q glium::VertexBuffer<Vertex>, &'q glium::texture::Texture2d)> {
+        // Erasing the previous frame
+        // Leaving the background see-through for transparent windows
+        let background_alpha = if self.transparent { 0.0 } else { 1.0 };
+        surface.clear_color(0.0, 0.0, 0.0, background_alpha);
+
+        for (vertex_buffer, texture) in quads {
+            let uniforms = FrameUniforms {
+                sampler: frame_sampler(texture),
+                time: self.launched.elapsed().as_secs_f32(),
+                frame: self.frames_presented as i32,
+                canvas_size: [texture.width() as f32, texture.height() as f32],
+                window_size: [self.size.width, self.size.height],
+                mouse: self.cursor,
+                custom: &self.uniforms,
+            };
+            if let Err(error) = surface.draw(vertex_buffer, &self.indices, &self.program, &uniforms, &self.draw_params) {
+                self.report_error(error);
+            }
+        }
+    }
+
+    // Noticing presses of the screenshot key, so that the next frame
+    // to be displayed gets saved, and following the cursor for shaders
+    fn observe_events(&mut self, ui_events: &[UIEvent]) {
+        if let Some(key) = self.screenshot_key {
+            self.screenshot_requested |= ui_events.iter().any(|event| matches!(event,
+                UIEvent::Keyboard(event) if event.key == key && event.action == KeyboardAction::Press
+            ));
+        }
+
+        for event in ui_events {
+            if let UIEvent::CursorMovement(movement) = event {
+                self.cursor = [movement.x as f32, movement.y as f32];
+            }
+        }
+    }
+
+    fn save_screenshot(&mut self, pixels: &RgbaImageRegion) {
+        if !std::mem::take(&mut self.screenshot_requested) {
+            return;
+        }
+
+        let path = self.screenshot_directory.join(screenshot_file_name(std::time::SystemTime::now()));
+        let result = assets::encode_png(pixels).and_then(|bytes| Ok(std::fs::write(&path, bytes)?));
+        if let Err(error) = result {
+            self.report_error(error);
+        }
+    }
+
+    // Appending a frame to the video being exported, if any,
+    // and giving up on the export if it fails
+    fn record(&mut self, pixels: &RgbaImageRegion) {
+        let path = match &self.video_path {
+            Some(path) => path,
+            None => return,
+        };
+
+        if self.exporter.is_none() {
+            match export::VideoExporter::new(path, (pixels.width, pixels.height), self.video_fps) {
+                Ok(exporter) => self.exporter = Some(exporter),
+                Err(error) => {
+                    self.report_error(error);
+                    self.video_path = None;
+                    return;
+                },
+            }
+        }
+
+        if let Some(Err(error)) = self.exporter.as_mut().map(|exporter| exporter.write_frame(pixels)) {
+            self.report_error(error);
+            self.video_path = None;
+            self.exporter = None;
+        }
+    }
+
+    fn present(&mut self, pixels: &RgbaImageRegion) {
+        let mut plugins = std::mem::take(&mut self.plugins);
+        match plugins.overlay(pixels) {
+            Some(overlay) => self.present_frame(&overlay),
+            None => self.present_frame(pixels),
+        }
+        self.plugins = plugins;
+    }
+
+    // Displaying a frame once plugins have drawn over it
+    fn present_frame(&mut self, pixels: &RgbaImageRegion) {
+
+        // Fitting the window to the first frame, and any
+        // frame whose dimensions are different from the last
+        if self.lock_aspect_ratio && self.frame_dimensions != Some((pixels.width, pixels.height)) {
+            self.frame_dimensions = Some((pixels.width, pixels.height));
+            let corrected = aspect_ratio_correction(self.window_size, self.window_size, (pixels.width, pixels.height));
+            if let Some(corrected) = corrected {
+                self.display.gl_window().window().set_inner_size(corrected);
+            }
+        }
+
+        // Splitting frames that are too large for a single texture into tiles
+        if pixels.width > self.max_texture_size || pixels.height > self.max_texture_size {
+            self.present_tiles(pixels);
+        } else {
+            self.present_texture(pixels);
+        }
+
+        self.record(pixels);
+        self.save_screenshot(pixels);
+        self.frame_hashes.log(pixels);
+
+        if let Some(remote) = &self.remote {
+            remote.send_frame(pixels);
+        }
+    }
+
+    // Displaying a frame using a single texture
+    fn present_texture(&mut self, pixels: &RgbaImageRegion) {
+        let started = Instant::now();
+        let uploaded = {
+            trace_span!("upload");
+            upload_frame(&self.display, &mut self.texture, &mut self.staging, pixels, self.mipmaps)
+        };
+        if let Err(error) = uploaded {
+            self.upload_time = started.elapsed();
+            return self.report_error(error);
+        }
+        #[cfg(feature = "gpu")]
+        if let Err(error) = self.effect_chain.apply(&self.display, self.texture.as_ref().unwrap(), &self.gpu_effects) {
+            self.report_error(error);
+        }
+        self.upload_time = started.elapsed();
+
+        // If the aspect ratio of the UI doesn't match that of `image`
+        // imposing letterboxing to leave the aspect ratio of `image` unchanged.
+        if self.preserve_aspect_ratio {
+            let shape = calculate_vertices(&self.size, pixels);
+            self.vertex_buffer.write(&shape);
+        }
+
+        let texture = self.texture.as_ref().unwrap();
+        #[cfg(feature = "gpu")]
+        let texture = self.effect_chain.result(texture);
+
+        let started = Instant::now();
+        {
+            trace_span!("present");
+            self.draw_to_window([(&self.vertex_buffer, texture)]);
+        }
+        self.present_time = started.elapsed();
+        self.frames_presented += 1;
+    }
+
+    // Collecting input from the remote viewer, if there is one
+    fn apply_remote_events(&self, ui_events: &mut Vec<UIEvent>) {
+        if let Some(remote) = &self.remote {
+            remote.poll_events(ui_events);
+        }
+    }
+}
+
+// Opening the event loop with the display server that the blueprint asks
+// for, falling back to the usual choice if it isn't available
+#[allow(unused_variables)]
+fn create_event_loop(blueprint: &UIBlueprint) -> glutin::event_loop::EventLoop<()> {
+    #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))]
+    if let Some(backend) = blueprint.linux_backend {
+        use glium::glutin::platform::unix::EventLoopExtUnix;
+
+        match backend {
+            LinuxBackend::X11 => match glutin::event_loop::EventLoop::new_x11() {
+                Ok(event_loop) => return event_loop,
+                Err(error) => log_warning!("can't use x11: {}", error),
+            },
+            // Checking for a display server first, since winit
+            // panics if there isn't a Wayland one
+            LinuxBackend::Wayland => match std::env::var_os("WAYLAND_DISPLAY") {
+                Some(_) => return glutin::event_loop::EventLoop::new_wayland(),
+                None => log_warning!("can't use wayland, since WAYLAND_DISPLAY isn't set"),
+            },
+        }
+    }
+
+    glutin::event_loop::EventLoop::new()
+}
+
+#[allow(unused_variables)]
+fn active_linux_backend(event_loop: &glutin::event_loop::EventLoopWindowTarget<()>) -> Option<LinuxBackend> {
+    #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))]
+    {
+        use glium::glutin::platform::unix::EventLoopWindowTargetExtUnix;
+        Some(if event_loop.is_wayland() { LinuxBackend::Wayland } else { LinuxBackend::X11 })
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd")))]
+    None
+}
+
+// Opening a window with an OpenGL context, like `glium::Display::new()`,
+// but from within the event loop, so that it can be done again
+fn create_display(
+    wb: glutin::window::WindowBuilder,
+    cb: glutin::ContextBuilder<glutin::NotCurrent>,
+    event_loop: &glutin::event_loop::EventLoopWindowTarget<()>,
+) -> Result<glium::Display, glium::backend::glutin::DisplayCreationError> {
+    let gl_window = cb.build_windowed(wb, event_loop)?;
+    Ok(glium::Display::from_gl_window(gl_window)?)
+}
+
+// Remembering where the window was left, for the next run
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        if let Some((app_name, geometry)) = &mut self.geometry {
+            let gl_window = self.display.gl_window();
+            let window = gl_window.window();
+
+            geometry.maximized = window.is_maximized();
+            geometry.monitor = window.current_monitor().and_then(|monitor| monitor.name());
+            geometry::save(app_name, geometry);
+        }
+    }
+}
+
+// Finding the monitor that the blueprint asks for, falling back
+// to the primary monitor if it isn't connected
+fn choose_monitor(
+    choice: &MonitorChoice,
+    monitors: &[Monitor],
+    mut available: impl Iterator<Item = glutin::monitor::MonitorHandle>,
+    primary: Option<glutin::monitor::MonitorHandle>,
+) -> Option<glutin::monitor::MonitorHandle> {
+    let chosen = match choice {
+        MonitorChoice::Primary => return primary,
+        MonitorChoice::Id(id) => monitors.iter().find(|monitor| monitor.id == *id).and_then(|wanted| {
+            available.find(|monitor| same_monitor(&monitor_info(monitor, None), wanted))
+        }),
+        MonitorChoice::Name(name) => available.find(|monitor| monitor.name().as_ref() == Some(name)),
+    };
+    if chosen.is_none() {
+        log_warning!("no monitor matches {:?}, using the primary monitor", choice);
+    }
+    chosen.or(primary)
+}
+
+// Describing a monitor, before it's been given an identifier
+fn monitor_info(monitor: &glutin::monitor::MonitorHandle, primary: Option<glutin::monitor::MonitorHandle>) -> Monitor {
+    let position = monitor.position();
+    let size = monitor.size();
+
+    Monitor {
+        id: 0,
+        name: monitor.name(),
+        position: (position.x, position.y),
+        size: (size.width, size.height),
+        scale_factor: monitor.scale_factor(),
+        primary: primary.as_ref() == Some(monitor),
+    }
+}
+
+fn list_monitors(
+    available: impl Iterator<Item = glutin::monitor::MonitorHandle>,
+    primary: Option<glutin::monitor::MonitorHandle>,
+) -> Vec<Monitor> {
+    available.map(|monitor| monitor_info(&monitor, primary.clone())).collect()
+}
+
+// Monitors are told apart by name, or by position if they have none,
+// so that changing a monitor's resolution doesn't look like replacing it
+fn same_monitor(a: &Monitor, b: &Monitor) -> bool {
+    match (&a.name, &b.name) {
+        (Some(a), Some(b)) => a == b,
+        _ => a.position == b.position,
+    }
+}
+
+// Giving newly listed monitors the identifiers they had before,
+// or new ones if they've only just been connected
+fn identify_monitors(previous: &[Monitor], listed: Vec<Monitor>, next_id: &mut u32) -> Vec<Monitor> {
+    listed.into_iter().map(|monitor| {
+        let id = match previous.iter().find(|other| same_monitor(other, &monitor)) {
+            Some(other) => other.id,
+            None => {
+                *next_id += 1;
+                *next_id - 1
+            },
+        };
+        Monitor { id, ..monitor }
+    }).collect()
+}
+
+// Reporting the monitors that were connected or disconnected
+// between two listings
+fn monitor_changes(before: &[Monitor], after: &[Monitor], ui_events: &mut Vec<UIEvent>) {
+    let missing_from = |monitors: &[Monitor], monitor: &Monitor| !monitors.iter().any(|other| other.id == monitor.id);
+
+    for monitor in before.iter().filter(|monitor| missing_from(after, monitor)) {
+        ui_events.push(UIEvent::Monitor(MonitorEvent { monitor_id: monitor.id, action: MonitorAction::Disconnected }));
+    }
+    for monitor in after.iter().filter(|monitor| missing_from(before, monitor)) {
+        ui_events.push(UIEvent::Monitor(MonitorEvent { monitor_id: monitor.id, action: MonitorAction::Connected }));
+    }
+}
+
+#[test]
+fn _monitor_changes() {
+    let monitor = |name: &str, x| Monitor {
+        id: 0,
+        name: Some(name.to_string()),
+        position: (x, 0),
+        size: (1920, 1080),
+        scale_factor: 1.0,
+        primary: x == 0,
+    };
+    let mut next_id = 0;
+    let before = identify_monitors(&[], vec![monitor("eDP-1", 0), monitor("HDMI-1", 1920)], &mut next_id);
+    let listed = vec![Monitor { size: (1280, 720), ..monitor("HDMI-1", 1920) }, monitor("DP-2", 3200)];
+    let after = identify_monitors(&before, listed, &mut next_id);
+    assert_eq!(after.iter().map(|monitor| monitor.id).collect::<Vec<_>>(), vec![1, 2]);
+
+    let mut ui_events = vec![];
+    monitor_changes(&before, &after, &mut ui_events);
+    assert_eq!(ui_events, vec![
+        UIEvent::Monitor(MonitorEvent { monitor_id: 0, action: MonitorAction::Disconnected }),
+        UIEvent::Monitor(MonitorEvent { monitor_id: 2, action: MonitorAction::Connected }),
+    ]);
+}
+
+// Converting a `FullscreenMode` into the form glutin expects, falling
+// back to borderless fullscreen if no video mode can be chosen
+fn native_fullscreen(fullscreen: FullscreenMode, monitor: Option<glutin::monitor::MonitorHandle>) -> Option<Fullscreen> {
+    let chosen_mode = |wanted: Option<VideoMode>| monitor.as_ref().and_then(|monitor| {
+        let modes = list_video_modes(monitor);
+        let mode = match wanted {
+            Some(wanted) => closest_video_mode(&modes, wanted)?,
+            None => *modes.first()?,
+        };
+        monitor.video_modes().find(|native| video_mode(native) == mode)
+    });
+
+    let exclusive = match fullscreen {
+        FullscreenMode::Windowed => return None,
+        FullscreenMode::Borderless => None,
+        FullscreenMode::Exclusive => chosen_mode(None),
+        FullscreenMode::ExclusiveMode(wanted) => chosen_mode(Some(wanted)),
+    };
+
+    match exclusive {
+        Some(mode) => Some(Fullscreen::Exclusive(mode)),
+        None => Some(Fullscreen::Borderless(monitor)),
+    }
+}
+
+fn video_mode(mode: &glutin::monitor::VideoMode) -> VideoMode {
+    let size = mode.size();
+    VideoMode {
+        width: size.width,
+        height: size.height,
+        refresh_rate: mode.refresh_rate() as u32,
+        bit_depth: mode.bit_depth(),
+    }
+}
+
+// Every distinct video mode of a monitor, largest first
+fn list_video_modes(monitor: &glutin::monitor::MonitorHandle) -> Vec<VideoMode> {
+    let mut modes: Vec<VideoMode> = monitor.video_modes().map(|mode| video_mode(&mode)).collect();
+    modes.sort_by_key(|mode| std::cmp::Reverse((mode.width * mode.height, mode.refresh_rate, mode.bit_depth)));
+    modes.dedup();
+    modes
+}
+
+// Refresh rates by monitor name and resolution
+type RefreshRates = std::collections::HashMap<(Option<String>, (u32, u32)), Option<u32>>;
+
+// The refresh rate shared by every mode at the given resolution, ignoring
+// modes without one, or `None` if they disagree, since there's no telling
+// which of them the monitor is using
+fn refresh_rate_at(modes: &[VideoMode], (width, height): (u32, u32)) -> Option<u32> {
+    let mut rates = modes.iter()
+        .filter(|mode| (mode.width, mode.height) == (width, height) && mode.refresh_rate > 0)
+        .map(|mode| mode.refresh_rate);
+
+    let rate = rates.next()?;
+    rates.all(|other| other == rate).then_some(rate)
+}
+
+#[test]
+fn _refresh_rate_at() {
+    let mode = |width, height, refresh_rate, bit_depth| VideoMode { width, height, refresh_rate, bit_depth };
+
+    let modes = [mode(1920, 1080, 60, 32), mode(1920, 1080, 60, 24), mode(1280, 720, 75, 32), mode(1280, 720, 0, 32)];
+    assert_eq!(refresh_rate_at(&modes, (1920, 1080)), Some(60));
+    assert_eq!(refresh_rate_at(&modes, (1280, 720)), Some(75));
+    assert_eq!(refresh_rate_at(&modes, (640, 480)), None);
+
+    let modes = [mode(2560, 1440, 144, 32), mode(2560, 1440, 120, 32), mode(2560, 1440, 60, 32)];
+    assert_eq!(refresh_rate_at(&modes, (2560, 1440)), None);
+}
+
+// The mode that most closely matches the wanted resolution, then
+// refresh rate, then bit depth, preferring larger modes on ties
+fn closest_video_mode(modes: &[VideoMode], wanted: VideoMode) -> Option<VideoMode> {
+    modes.iter().copied().min_by_key(|mode| (
+        mode.width.abs_diff(wanted.width) + mode.height.abs_diff(wanted.height),
+        mode.refresh_rate.abs_diff(wanted.refresh_rate),
+        mode.bit_depth.abs_diff(wanted.bit_depth),
+    ))
+}
+
+#[test]
+fn _closest_video_mode() {
+    let mode = |width, height, refresh_rate| VideoMode { width, height, refresh_rate, bit_depth: 32 };
+    let modes = [mode(1920, 1080, 144), mode(1920, 1080, 60), mode(1280, 720, 60), mode(640, 480, 60)];
+
+    assert_eq!(closest_video_mode(&modes, mode(1920, 1080, 60)), Some(mode(1920, 1080, 60)));
+    assert_eq!(closest_video_mode(&modes, mode(1920, 1080, 120)), Some(mode(1920, 1080, 144)));
+    assert_eq!(closest_video_mode(&modes, mode(320, 240, 60)), Some(mode(640, 480, 60)));
+    assert_eq!(closest_video_mode(&[], mode(320, 240, 60)), None);
+}
+
+// The first region under the cursor, if any
+fn hit_test(regions: &[HitRegion], cursor: glutin::dpi::PhysicalPosition<f64>) -> Option<HitTest> {
+    if cursor.x < 0.0 || cursor.y < 0.0 {
+        return None;
+    }
+
+    regions.iter()
+        .find(|region| region.rect.contains(cursor.x as u32, cursor.y as u32))
+        .map(|region| region.hit_test)
+}
+
+fn resize_cursor_icon(edge: ResizeEdge) -> glutin::window::CursorIcon {
+    match edge {
+        ResizeEdge::Top | ResizeEdge::Bottom => glutin::window::CursorIcon::NsResize,
+        ResizeEdge::Left | ResizeEdge::Right => glutin::window::CursorIcon::EwResize,
+        ResizeEdge::TopLeft | ResizeEdge::BottomRight => glutin::window::CursorIcon::NwseResize,
+        ResizeEdge::TopRight | ResizeEdge::BottomLeft => glutin::window::CursorIcon::NeswResize,
+    }
+}
+
+// The position and size of a window after dragging one of its edges
+// to the cursor, which is measured from the window's top-left corner
+fn resized_bounds(edge: ResizeEdge, position: (i32, i32), size: (u32, u32), cursor: (i32, i32)) -> ((i32, i32), (u32, u32)) {
+    let (mut x, mut y) = position;
+    let (mut width, mut height) = (size.0 as i32, size.1 as i32);
+
+    let left = matches!(edge, ResizeEdge::Left | ResizeEdge::TopLeft | ResizeEdge::BottomLeft);
+    let right = matches!(edge, ResizeEdge::Right | ResizeEdge::TopRight | ResizeEdge::BottomRight);
+    let top = matches!(edge, ResizeEdge::Top | ResizeEdge::TopLeft | ResizeEdge::TopRight);
+    let bottom = matches!(edge, ResizeEdge::Bottom | ResizeEdge::BottomLeft | ResizeEdge::BottomRight);
+
+    // Keeping the opposite edge in place, and at least a pixel away
+    if left {
+        let moved = cursor.0.min(width - 1);
+        x += moved;
+        width -= moved;
+    }
+    if top {
+        let moved = cursor.1.min(height - 1);
+        y += moved;
+        height -= moved;
+    }
+    if right {
+        width = cursor.0;
+    }
+    if bottom {
+        height = cursor.1;
+    }
+
+    ((x, y), (width.max(1) as u32, height.max(1) as u32))
+}
+
+#[test]
+fn _resized_bounds() {
+    assert_eq!(resized_bounds(ResizeEdge::BottomRight, (100, 100), (400, 300), (450, 320)), ((100, 100), (450, 320)));
+    assert_eq!(resized_bounds(ResizeEdge::Left, (100, 100), (400, 300), (-50, 10)), ((50, 100), (450, 300)));
+    assert_eq!(resized_bounds(ResizeEdge::TopRight, (100, 100), (400, 300), (380, 20)), ((100, 120), (380, 280)));
+
+    // Windows never shrink below a pixel
+    assert_eq!(resized_bounds(ResizeEdge::Left, (100, 100), (400, 300), (500, 0)), ((499, 100), (1, 300)));
+    assert_eq!(resized_bounds(ResizeEdge::Bottom, (100, 100), (400, 300), (0, -20)), ((100, 100), (400, 1)));
+}
+
+// Converting glutin window events into `UIEvent`s, for every
+// kind of event that doesn't affect the window itself
+// Letting the renderer see a window event first, so that it can follow
+// hit regions and the window's position, then passing it to the controller
+fn handle_window_event<'a>(
+    event: glutin::event::WindowEvent<'a>,
+    observe: impl FnOnce(&glutin::event::WindowEvent<'a>),
+    modifiers: &mut KeyboardModifiers,
+    ui_events: &mut Vec<UIEvent>,
+) {
+    observe(&event);
+    apply_window_event(event, modifiers, ui_events);
+}
+
+fn apply_window_event(
+    event: glutin::event::WindowEvent,
+    modifiers: &mut KeyboardModifiers,
+    ui_events: &mut Vec<UIEvent>,
+) {
+    match event {
+        glutin::event::WindowEvent::KeyboardInput { device_id, input, .. } => {
+            apply_keyboard_event(&device_id, &input, modifiers, ui_events);
+        },
+        glutin::event::WindowEvent::ModifiersChanged(state) => {
+            apply_modifiers_event(&state, modifiers);
+        },
+        glutin::event::WindowEvent::ReceivedCharacter(character) => {
+            apply_received_character(character, ui_events);
+        },
+        glutin::event::WindowEvent::MouseInput { device_id, state, button, .. } => {
+            apply_mouse_button_event(&device_id, &state, &button, ui_events);
+        },
+        glutin::event::WindowEvent::CursorMoved { device_id, position, .. } => {
+            apply_cursor_movement_event(&device_id, &position, ui_events);
+        },
+        glutin::event::WindowEvent::MouseWheel { device_id, delta, .. } => {
+            apply_scroll_event(&device_id, &delta, ui_events);
+        },
+        glutin::event::WindowEvent::Touch(touch) => {
+            apply_pen_event(&touch, ui_events);
+        },
+        glutin::event::WindowEvent::ThemeChanged(theme) => {
+            apply_theme_event(&theme, ui_events);
+        },
+        _ => {},
+    }
+}
+
+#[test]
+fn _handle_window_event() {
+    use glutin::event::WindowEvent;
+
+    let mut observed = vec![];
+    let mut modifiers = KeyboardModifiers::default();
+    let mut ui_events = vec![];
+
+    // The renderer sees every event that reaches the controller
+    let event = WindowEvent::ReceivedCharacter('x');
+    handle_window_event(event, |event| observed.push(format!("{:?}", event)), &mut modifiers, &mut ui_events);
+    assert_eq!(observed, vec!["ReceivedCharacter('x')"]);
+    assert_eq!(ui_events, vec![UIEvent::Text(TextEvent { character: 'x' })]);
+
+    // Moving a window without resizing it reaches the renderer, which
+    // remembers the new position, even though the controller isn't told
+    let moved = WindowEvent::Moved(glutin::dpi::PhysicalPosition::new(40, 30));
+    handle_window_event(moved, |event| observed.push(format!("{:?}", event)), &mut modifiers, &mut ui_events);
+    assert_eq!(observed[1], "Moved(PhysicalPosition { x: 40, y: 30 })");
+    assert_eq!(ui_events.len(), 1);
+}
+
+fn apply_theme_event(
+    theme: &glutin::window::Theme,
+    ui_events: &mut Vec<UIEvent>,
+) {
+    ui_events.push(UIEvent::Theme(ThemeEvent { theme: window_theme(theme) }));
+}
+
+fn window_theme(theme: &glutin::window::Theme) -> WindowTheme {
+    match theme {
+        glutin::window::Theme::Light => WindowTheme::Light,
+        glutin::window::Theme::Dark => WindowTheme::Dark,
+    }
+}
+
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn native_theme(theme: WindowTheme) -> glutin::window::Theme {
+    match theme {
+        WindowTheme::Light => glutin::window::Theme::Light,
+        WindowTheme::Dark => glutin::window::Theme::Dark,
+    }
+}
+
+#[test]
+fn _window_theme() {
+    for theme in [WindowTheme::Light, WindowTheme::Dark] {
+        assert_eq!(window_theme(&native_theme(theme)), theme);
+    }
+
+    let mut ui_events = vec![];
+    apply_theme_event(&glutin::window::Theme::Dark, &mut ui_events);
+    assert_eq!(ui_events, vec![UIEvent::Theme(ThemeEvent { theme: WindowTheme::Dark })]);
+}
+
+fn hash<T: Hash>(value: T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Handling events and drawing the next frame, if the controller has one,
+// while measuring how long each stage takes. Returns what the launch
+// function should return, once the controller has asked to exit.
+fn run_frame(
+    controller: &mut Box<dyn UIController>,
+    renderer: &mut Renderer,
+    context: &mut UIContext,
+    console: &mut Option<console::Console>,
+    tape: &mut replay::InputTape,
+    ui_events: &mut Vec<UIEvent>,
+    report: &mut FrameReport,
+) -> Option<Result<(), OpenUiError>> {
+    let started = Instant::now();
+    {
+        trace_span!("process_events");
+        tape.apply(ui_events);
+        renderer.plugins.process_events(ui_events);
+        if let Some(outcome) = exit_requested(controller.process_events(ui_events, context)) {
+            return Some(outcome);
+        }
+        ui_events.clear();
+    }
+    report.events += started.elapsed();
+
+    let started = Instant::now();
+    if renderer.multi_viewport {
+        let viewports = {
+            trace_span!("next_frame");
+            controller.next_viewports(context)
+        };
+        report.build = started.elapsed();
+
+        if !viewports.is_empty() {
+            renderer.uniforms.clone_from(&context.uniforms);
+            renderer.present_viewports(&viewports);
+            report.upload = renderer.upload_time;
+            report.present = renderer.present_time;
+        }
+    } else {
+        let pixels = {
+            trace_span!("next_frame");
+            controller.next_frame(context)
+        };
+        report.build = started.elapsed();
+
+        if let Some(pixels) = pixels {
+            renderer.uniforms.clone_from(&context.uniforms);
+            #[cfg(feature = "gpu")]
+            renderer.gpu_effects.clone_from(&context.gpu_effects);
+            present_with_console(renderer, console, &pixels);
+            report.upload = renderer.upload_time;
+            report.present = renderer.present_time;
+        }
+    }
+
+    for error in renderer.take_errors() {
+        controller.render_error(error);
+    }
+
+    let commands = context.take_commands();
+    replace_controller(controller, &commands);
+    renderer.apply_commands(commands).then_some(Ok(()))
+}
+
+// Copying a frame into a texture, reusing the texture if its dimensions
+// match. Rows are uploaded top to bottom, which the texture coordinates
+// of the vertices account for, so the rows needn't be reversed.
+fn upload_frame(
+    display: &glium::Display,
+    texture: &mut Option<glium::texture::Texture2d>,
+    staging: &mut Vec<u8>,
+    pixels: &RgbaImageRegion,
+    mipmaps: bool,
+) -> Result<(), OpenUiError> {
+    let (width, height) = (pixels.width, pixels.height);
+    let image = glium::texture::RawImage2d {
+        data: std::borrow::Cow::Borrowed(pixels.contiguous_bytes_into(staging)),
+        width,
+        height,
+        format: glium::texture::ClientFormat::U8U8U8U8,
+    };
+
+    match texture {
+        Some(texture) if (texture.width(), texture.height()) == (width, height) => {
+            texture.write(glium::Rect { left: 0, bottom: 0, width, height }, image);
+
+            // Writing only replaces the full-size level, so the smaller
+            // levels are generated again from it. This is sound because
+            // the texture was created with room for every level.
+            if mipmaps {
+                unsafe { texture.generate_mipmaps() };
+            }
+        },
+        _ => {
+            let option = if mipmaps { MipmapsOption::AutoGeneratedMipmaps } else { MipmapsOption::NoMipmap };
+            *texture = Some(glium::texture::Texture2d::with_mipmaps(display, image, option)?);
+        },
+    }
+
+    Ok(())
+}
+
+// The uniforms passed to the shader that draws frames onto the window,
+// which are described in the `shader` module
+struct FrameUniforms<'a> {
+    sampler: Sampler<'a, glium::texture::Texture2d>,
+    time: f32,
+    frame: i32,
+    canvas_size: [f32; 2],
+    window_size: [f32; 2],
+    mouse: [f32; 2],
+    custom: &'a [(String, ShaderValue)],
+}
+
+impl glium::uniforms::Uniforms for FrameUniforms<'_> {
+    fn visit_values<'b, F: FnMut(&str, UniformValue<'b>)>(&'b self, mut visit: F) {
+        visit("sampler", UniformValue::Texture2d(self.sampler.0, Some(self.sampler.1)));
+        visit("u_time", UniformValue::Float(self.time));
+        visit("u_frame", UniformValue::SignedInt(self.frame));
+        visit("u_canvas_size", UniformValue::Vec2(self.canvas_size));
+        visit("u_window_size", UniformValue::Vec2(self.window_size));
+        visit("u_mouse", UniformValue::Vec2(self.mouse));
+
+        for (name, value) in self.custom {
+            visit(name, match *value {
+                ShaderValue::Int(value) => UniformValue::SignedInt(value),
+                ShaderValue::Float(value) => UniformValue::Float(value),
+                ShaderValue::Vec2(value) => UniformValue::Vec2(value),
+                ShaderValue::Vec3(value) => UniformValue::Vec3(value),
+                ShaderValue::Vec4(value) => UniformValue::Vec4(value),
+            });
+        }
+    }
+}
+
+// The image that frames are drawn onto when supersampling, which is
+// `factor` times the size of the window, within the texture size limit
+fn supersampled_image(
+    display: &glium::Display,
+    window_size: glutin::dpi::PhysicalSize<u32>,
+    factor: u32,
+    max_texture_size: u32,
+) -> Option<glium::texture::Texture2d> {
+    if factor <= 1 {
+        return None;
+    }
+
+    let width = (window_size.width.max(1) * factor).min(max_texture_size);
+    let height = (window_size.height.max(1) * factor).min(max_texture_size);
+    glium::texture::Texture2d::empty_with_format(display, UncompressedFloatFormat::U8U8U8U8, MipmapsOption::NoMipmap, width, height).ok()
+}
+
+// Sampling frames without smoothing, except when they're shrunk
+// and have mipmaps, which are blended between to avoid aliasing
+fn frame_sampler(texture: &glium::texture::Texture2d) -> Sampler<'_, glium::texture::Texture2d> {
+    let minify_filter = if texture.get_mipmap_levels() > 1 {
+        MinifySamplerFilter::LinearMipmapLinear
+    } else {
+        MinifySamplerFilter::Nearest
+    };
+
+    texture.sampled()
+        .magnify_filter(MagnifySamplerFilter::Nearest)
+        .minify_filter(minify_filter)
+}
+
+// Displaying a frame, with the debug console on top if it's open
+fn present_with_console(renderer: &mut Renderer, console: &mut Option<console::Console>, pixels: &RgbaImageRegion) {
+    match console {
+        Some(console) if console.is_open() => renderer.present(&console.overlay(pixels)),
+        _ => renderer.present(pixels),
+    }
+}
+
+// Naming screenshots after the UTC date and time they were taken,
+// such as `screenshot-2023-11-14-221320-000.png`
+fn screenshot_file_name(time: std::time::SystemTime) -> String {
+    let since_epoch = time.duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (hour, minute, second) = (seconds / 3600 % 24, seconds / 60 % 60, seconds % 60);
+    let (year, month, day) = civil_date(seconds / 86400);
+
+    format!(
+        "screenshot-{:04}-{:02}-{:02}-{:02}{:02}{:02}-{:03}.png",
+        year, month, day, hour, minute, second, since_epoch.subsec_millis(),
+    )
+}
+
+// Converting days since the epoch into a year, month and day
+pub(crate) fn civil_date(days: u64) -> (u64, u64, u64) {
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[test]
+fn _screenshot_file_name() {
+    let time = std::time::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+    assert_eq!(screenshot_file_name(time), "screenshot-2023-11-14-221320-123.png");
+
+    let time = std::time::UNIX_EPOCH + Duration::from_secs(951_782_400);
+    assert_eq!(screenshot_file_name(time), "screenshot-2000-02-29-000000-000.png");
+}
+
+fn frame_interval(frames_per_second: u32) -> Duration {
+    Duration::from_nanos(1_000_000_000 / frames_per_second.max(1) as u64)
+}
+
+// Checking whether the window has moved onto a monitor with a different
+// refresh rate, returning the new rate if frames should follow it
+fn monitor_change(event: &glutin::event::Event<()>, renderer: &Renderer, fps: u32, sync_to_monitor: bool) -> Option<u32> {
+    use glutin::event::{Event, WindowEvent};
+
+    match event {
+        Event::WindowEvent { event: WindowEvent::Moved(_) | WindowEvent::ScaleFactorChanged { .. }, .. } if sync_to_monitor => {
+            renderer.monitor_refresh_rate().filter(|&rate| rate != fps)
+        },
+        _ => None,
+    }
+}
+
+// Whether frames can be displayed, which they can't be while the
+// application is suspended, or while its window is minimized
+#[derive(Default)]
+struct Visibility {
+    suspended: bool,
+    occluded: bool,
+}
+
+impl Visibility {
+    fn paused(&self) -> bool {
+        self.suspended || self.occluded
+    }
+}
+
+fn apply_visibility_event(
+    event: &glutin::event::Event<()>,
+    visibility: &mut Visibility,
+    ui_events: &mut Vec<UIEvent>,
+) {
+    match event {
+        glutin::event::Event::Suspended => {
+            visibility.suspended = true;
+            ui_events.push(UIEvent::Suspended);
+        },
+        glutin::event::Event::Resumed => {
+            visibility.suspended = false;
+            ui_events.push(UIEvent::Resumed);
+        },
+        glutin::event::Event::WindowEvent { event: glutin::event::WindowEvent::Resized(size), .. } => {
+            // Windows are resized to nothing while minimized
+            let occluded = size.width == 0 || size.height == 0;
+            if occluded != visibility.occluded {
+                visibility.occluded = occluded;
+                ui_events.push(UIEvent::Occlusion(OcclusionEvent { occluded }));
+            }
+        },
+        _ => {},
+    }
+}
+
+fn apply_resize_event(
+    size: &glutin::dpi::LogicalSize<f32>,
+    ui_events: &mut Vec<UIEvent>,
+) {
+    ui_events.push(UIEvent::Resize(ResizeEvent {
+        width: size.width as u32,
+        height: size.height as u32,
+    }));
+}
+
+// Responding to the user trying to close the window, from its close button
+// or the menu bar, returning whether the application should exit right away
+fn request_close(
+    close_policy: ClosePolicy,
+    close_deadline: &mut Option<Instant>,
+    ui_events: &mut Vec<UIEvent>,
+) -> bool {
+    match close_policy {
+        ClosePolicy::Exit => true,
+        ClosePolicy::Ask { timeout } => {
+            ui_events.push(UIEvent::CloseRequested);
+            if close_deadline.is_none() {
+                *close_deadline = timeout.map(|timeout| Instant::now() + timeout);
+            }
+            false
+        },
+    }
+}
+
+fn apply_cursor_movement_event(
+    device_id: &glutin::event::DeviceId,
+    position:  &glutin::dpi::PhysicalPosition<f64>,
+    ui_events: &mut Vec<UIEvent>,
+) {
+    let position = position.to_logical::<f32>(1.0);
+
+    ui_events.push(UIEvent::CursorMovement(CursorMovementEvent {
+        device_id: hash(device_id),
+        x: position.x as u32,
+        y: position.y as u32,
+    }));
+}
+
+
+fn apply_keyboard_event(
+    device_id: &glutin::event::DeviceId,
+    input: &glutin::event::KeyboardInput,
+    modifiers: &KeyboardModifiers,
+    ui_events: &mut Vec<UIEvent>
+) {
+    let device_id = hash(device_id);
+
+    let action = match input.state {
+        glutin::event::ElementState::Pressed => KeyboardAction::Press,
+        glutin::event::ElementState::Released => KeyboardAction::Release,
+    };
+
+    let key = match input.virtual_keycode.and_then(translate_virtual_keycode) {
+        Some(key) => key,
+        None => return,
+    };
+
+    let keyboard_event = KeyboardEvent {
+        device_id,
+        action,
+        key,
+        modifiers: *modifiers,
+        character: None,
+    };
+
+    ui_events.push(UIEvent::Keyboard(keyboard_event));
+}
+
+fn apply_modifiers_event(
+    state: &glutin::event::ModifiersState,
+    modifiers: &mut KeyboardModifiers,
+) {
+    *modifiers = KeyboardModifiers {
+        shift: state.shift(),
+        ctrl: state.ctrl(),
+        alt: state.alt(),
+        logo: state.logo(),
+    };
+}
+
+// Glutin reports the character produced by a key press separately,
+// immediately after the press itself, so it's attached to that press.
+// Some presses produce more than one character, such as a dead key
+// followed by a key it can't be combined with, and the extra
+// characters are reported by themselves.
+fn apply_received_character(character: char, ui_events: &mut Vec<UIEvent>) {
+    if character.is_control() { return }
+
+    if let Some(UIEvent::Keyboard(event)) = ui_events.last_mut() {
+        if event.action == KeyboardAction::Press && event.character.is_none() {
+            event.character = Some(character);
+            return;
+        }
+    }
+
+    ui_events.push(UIEvent::Text(TextEvent { character }));
+}
+
+#[test]
+fn _apply_received_character() {
+    let press = |key| UIEvent::Keyboard(KeyboardEvent {
+        device_id: 0,
+        key,
+        action: KeyboardAction::Press,
+        modifiers: KeyboardModifiers::default(),
+        character: None,
+    });
+
+    // A dead accent, which can't be combined with the key after it
+    let mut ui_events = vec![press(KeyboardKey::Apostrophe), press(KeyboardKey::X)];
+    apply_received_character('´', &mut ui_events);
+    apply_received_character('x', &mut ui_events);
+
+    assert_eq!(ui_events[0], press(KeyboardKey::Apostrophe));
+    assert!(matches!(ui_events[1], UIEvent::Keyboard(KeyboardEvent { character: Some('´'), .. })));
+    assert_eq!(ui_events[2], UIEvent::Text(TextEvent { character: 'x' }));
+}
+
+fn apply_scroll_event(
+    device_id: &glutin::event::DeviceId,
+    delta: &glutin::event::MouseScrollDelta,
+    ui_events: &mut Vec<UIEvent>,
+) {
+    ui_events.push(UIEvent::Scroll(ScrollEvent {
+        device_id: hash(device_id),
+        delta: scroll_delta(delta),
+    }));
+}
+
+// Keeping winit's signs, where positive values scroll up and right
+fn scroll_delta(delta: &glutin::event::MouseScrollDelta) -> ScrollDelta {
+    match delta {
+        glutin::event::MouseScrollDelta::LineDelta(x, y) => {
+            ScrollDelta::Lines { x: *x, y: *y }
+        },
+        glutin::event::MouseScrollDelta::PixelDelta(position) => {
+            let position = position.to_logical::<f32>(1.0);
+            ScrollDelta::Pixels { x: position.x, y: position.y }
+        },
+    }
+}
+
+#[test]
+fn _scroll_delta() {
+    use glutin::event::MouseScrollDelta;
+
+    // Rolling a mouse wheel away from the user, which scrolls up
+    assert_eq!(scroll_delta(&MouseScrollDelta::LineDelta(0.0, 1.0)), ScrollDelta::Lines { x: 0.0, y: 1.0 });
+    assert_eq!(scroll_delta(&MouseScrollDelta::LineDelta(-2.0, 0.0)), ScrollDelta::Lines { x: -2.0, y: 0.0 });
+
+    let position = glutin::dpi::PhysicalPosition::new(3.5, -12.25);
+    assert_eq!(scroll_delta(&MouseScrollDelta::PixelDelta(position)), ScrollDelta::Pixels { x: 3.5, y: -12.25 });
+}
+
+// Converting glutin touch events, which include pen and stylus
+// contacts, to native pen events
+fn apply_pen_event(
+    touch: &glutin::event::Touch,
+    ui_events: &mut Vec<UIEvent>,
+) {
+    let position = touch.location.to_logical::<f32>(1.0);
+
+    let phase = match touch.phase {
+        glutin::event::TouchPhase::Started => PenPhase::Down,
+        glutin::event::TouchPhase::Moved => PenPhase::Move,
+        glutin::event::TouchPhase::Ended => PenPhase::Up,
+        glutin::event::TouchPhase::Cancelled => PenPhase::Cancel,
+    };
+
+    let tilt = match touch.force {
+        Some(glutin::event::Force::Calibrated { altitude_angle, .. }) => altitude_angle,
+        _ => None,
+    };
+
+    ui_events.push(UIEvent::Pen(PenEvent {
+        device_id: hash(touch.device_id),
+        contact_id: touch.id,
+        phase,
+        x: position.x,
+        y: position.y,
+        pressure: touch.force.map(|force| force.normalized() as f32),
+        tilt: tilt.map(|tilt| tilt as f32),
+    }));
+}
+
+// Converting glutin mouse events to native mouse button events
+fn apply_mouse_button_event(
+    device_id: &glutin::event::DeviceId,
+    state: &glutin::event::ElementState,
+    button: &glutin::event::MouseButton,
+    ui_events: &mut Vec<UIEvent>,
+) {
+    let device_id = hash(device_id);
+
+    // Determining button pressed/released
+    let button = match button {
+        glutin::event::MouseButton::Left => MouseButton::Left,
+        glutin::event::MouseButton::Right => MouseButton::Right,
+        glutin::event::MouseButton::Middle => MouseButton::Middle,
+        glutin::event::MouseButton::Other(num) => MouseButton::Other(*num),
+    };
+
+    let action = match state {
+        glutin::event::ElementState::Pressed => MouseButtonAction::Press,
+        glutin::event::ElementState::Released => MouseButtonAction::Release,
+    };
+
+    let event = MouseButtonEvent {
+        device_id,
+        button,
+        action,
+    };
+
+    ui_events.push(UIEvent::MouseButton(event));
+}